@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use analytics_service::repository::AnalyticsStore;
+use async_trait::async_trait;
+use shortener_core::AppError;
+use shortener_service::analytics::ClickCounter;
+use tracing::instrument;
+
+/// Reads click counts from the analytics store of the same process.
+pub struct LocalClickCounter {
+    store: Arc<dyn AnalyticsStore>,
+}
+
+impl LocalClickCounter {
+    #[must_use]
+    pub fn new(store: Arc<dyn AnalyticsStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl ClickCounter for LocalClickCounter {
+    #[instrument(skip(self))]
+    async fn click_count(&self, code: &str) -> Result<i64, AppError> {
+        let analytics = self.store.get(code).await?;
+        Ok(analytics.map_or(0, |analytics| analytics.access_count))
+    }
+}
@@ -0,0 +1,67 @@
+//! shortener-service and analytics-service in one process.
+//!
+//! Both services are mounted on one router and exchange events through an
+//! [`InProcessBroker`] instead of `RabbitMQ`; shortener-service reads click
+//! counts straight from the analytics store.
+
+mod click_counter;
+
+use std::sync::Arc;
+
+use axum::Router;
+use shortener_core::{Broker, broker::InProcessBroker, openapi::docs_routes};
+use utoipa::openapi::OpenApi;
+
+pub use click_counter::LocalClickCounter;
+
+/// Builds both services, starts their background tasks and returns the
+/// combined router.
+///
+/// The `MESSAGE_BROKER` and `ANALYTICS_SERVICE_URL` settings are ignored.
+/// Redis is only needed when `analytics_config` selects the `redis`
+/// analytics store.
+/// Serve the router with `into_make_service_with_connect_info::<SocketAddr>()`.
+///
+/// # Errors
+///
+/// Returns an error if either service cannot be built.
+pub async fn build(
+    shortener_config: &shortener_service::Config,
+    analytics_config: &analytics_service::Config,
+) -> anyhow::Result<Router> {
+    let broker: Arc<dyn Broker> = Arc::new(InProcessBroker::default());
+
+    let analytics = analytics_service::connect(analytics_config).await?;
+    analytics_service::spawn_consumers(&analytics, analytics_config, Arc::clone(&broker)).await?;
+
+    let click_counter = Arc::new(LocalClickCounter::new(Arc::clone(
+        &analytics.analytics_store,
+    )));
+    let shortener = shortener_service::build(shortener_config, broker, Some(click_counter)).await?;
+
+    Ok(router(shortener, analytics))
+}
+
+/// Mounts the HTTP APIs of both services on one router, with the health
+/// probes of shortener-service and the combined [`openapi`] document.
+pub fn router(
+    shortener: shortener_service::AppState,
+    analytics: analytics_service::AppState,
+) -> Router {
+    shortener_service::service_routes()
+        .with_state(shortener)
+        .merge(analytics_service::api_routes().with_state(analytics))
+        .merge(docs_routes(openapi()))
+}
+
+/// `OpenAPI` document of both services. Where both define a path or schema,
+/// as with the health probes, shortener-service's is kept.
+#[must_use]
+pub fn openapi() -> OpenApi {
+    let mut spec = shortener_service::openapi();
+    spec.merge(analytics_service::openapi());
+    spec.info.title = env!("CARGO_PKG_NAME").to_string();
+    spec.info.description =
+        Some("shortener-service and analytics-service in one process".to_string());
+    spec
+}
@@ -0,0 +1,38 @@
+use std::{env, net::SocketAddr};
+
+use shortener_core::telemetry;
+use tower_http::trace::TraceLayer;
+use tracing::info;
+
+const ANALYTICS_STORE_VAR: &str = "ANALYTICS_STORE";
+const DEFAULT_ANALYTICS_STORE: &str = "memory";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    dotenvy::dotenv().ok();
+
+    let shortener_config = shortener_service::Config::from_env()?;
+    let mut analytics_config = analytics_service::Config::from_env()?;
+    // Counters need not be shared with other processes, so they are kept in
+    // memory unless a store is chosen.
+    if env::var_os(ANALYTICS_STORE_VAR).is_none() {
+        analytics_config.analytics_store = DEFAULT_ANALYTICS_STORE.to_string();
+    }
+    let _guard = telemetry::init_tracing(&shortener_config.observability_config(), "all-in-one")?;
+
+    let app = all_in_one::build(&shortener_config, &analytics_config)
+        .await?
+        .layer(TraceLayer::new_for_http());
+
+    let addr = shortener_config.server_addr();
+    info!("Starting all-in-one on {}", addr);
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! The all-in-one binary served with only its defaults and an in-memory
+//! `SQLite` database: no Postgres, `RabbitMQ` or Redis.
+
+use std::{net::TcpListener, process::Stdio, time::Duration};
+
+use reqwest::{Client, StatusCode, redirect};
+use serde_json::{Value, json};
+use tokio::process::{Child, Command};
+
+/// Returns a command running the binary on `port` with `vars` as its only
+/// environment. The working directory has no `.env` to add to it.
+fn all_in_one(port: u16, vars: &[(&str, &str)]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_all-in-one"));
+    command
+        .env_clear()
+        .current_dir(std::env::temp_dir())
+        .env("DATABASE_URL", "sqlite::memory:")
+        .env("OG_FETCH_ENABLED", "false")
+        .env("SERVER_HOST", "127.0.0.1")
+        .env("SERVER_PORT", port.to_string())
+        .envs(vars.iter().copied())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+    command
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+struct Server {
+    base_url: String,
+    client: Client,
+    _child: Child,
+}
+
+impl Server {
+    /// Starts the binary on a free port and waits until it is healthy.
+    async fn start() -> Self {
+        let port = free_port();
+        let server = Self {
+            base_url: format!("http://127.0.0.1:{port}"),
+            client: Client::builder()
+                .redirect(redirect::Policy::none())
+                .build()
+                .unwrap(),
+            _child: all_in_one(port, &[]).spawn().unwrap(),
+        };
+
+        for _ in 0..300 {
+            if let Ok(response) = server.client.get(server.url("/health")).send().await
+                && response.status().is_success()
+            {
+                return server;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("all-in-one did not become healthy");
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base_url)
+    }
+}
+
+#[tokio::test]
+async fn runs_without_redis() {
+    let server = Server::start().await;
+
+    let response = server
+        .client
+        .post(server.url("/api/v1/urls"))
+        .json(&json!({ "url": "https://example.com/landing" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body: Value = response.json().await.unwrap();
+    let code = body["code"].as_str().unwrap();
+
+    let response = server
+        .client
+        .get(server.url(&format!("/{code}")))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+
+    let analytics_url = server.url(&format!("/api/v1/analytics/{code}"));
+    for _ in 0..100 {
+        let response = server.client.get(&analytics_url).send().await.unwrap();
+        if response.status().is_success() {
+            let body: Value = response.json().await.unwrap();
+            if body["access_count"] == 1 {
+                return;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("the redirect was not counted");
+}
+
+#[tokio::test]
+async fn redis_store_needs_redis_url() {
+    let status = tokio::time::timeout(
+        Duration::from_secs(30),
+        all_in_one(free_port(), &[("ANALYTICS_STORE", "redis")]).status(),
+    )
+    .await
+    .expect("all-in-one kept running without REDIS_URL")
+    .unwrap();
+
+    assert!(!status.success());
+}
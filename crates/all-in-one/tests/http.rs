@@ -0,0 +1,502 @@
+//! End-to-end requests against the combined router, with in-memory stores.
+//!
+//! The shortener's events go to a `MockEventPublisher`, which hands them to
+//! the test. Forwarding them over an `InProcessBroker` to the analytics
+//! consumers completes the create → redirect → event → analytics flow.
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use analytics_service::{
+    consumer::{AccessEventConsumer, EventConsumer, LinkEventConsumer},
+    repository::InMemoryAnalyticsStore,
+};
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    extract::ConnectInfo,
+    http::{Method, Request, StatusCode, header},
+};
+use chrono::{TimeDelta, Utc};
+use serde_json::{Value, json};
+use shortener_core::{
+    Broker,
+    broker::InProcessBroker,
+    messaging::{AccessEvent, Codec, LinkEvent, UrlPurged},
+};
+use shortener_service::{
+    publisher::{AccessEventPublisher, EventPublisher, MockEventPublisher},
+    repository::{Stores, UrlStore},
+};
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+
+const ACCESS_QUEUE: &str = "access_events";
+const ACCESS_ROUTING_KEY: &str = "access.event";
+const LINK_EVENTS_QUEUE: &str = "analytics_link_events";
+
+struct TestApp {
+    router: Router,
+    /// Links of the shortener, for changes no endpoint makes.
+    url_store: Arc<dyn UrlStore>,
+    /// Access events published by the shortener.
+    access_events: mpsc::UnboundedReceiver<AccessEvent>,
+    /// Link events published by the shortener.
+    link_events: mpsc::UnboundedReceiver<LinkEvent>,
+    /// Delivers events to the analytics consumers.
+    broker_publisher: AccessEventPublisher,
+}
+
+impl TestApp {
+    async fn new() -> Self {
+        let (access_tx, access_events) = mpsc::unbounded_channel();
+        let (link_tx, link_events) = mpsc::unbounded_channel();
+
+        let mut event_publisher = MockEventPublisher::new();
+        event_publisher.expect_publish().returning(move |event| {
+            access_tx.send(event).unwrap();
+            Ok(())
+        });
+        event_publisher
+            .expect_publish_link_event()
+            .returning(move |event| {
+                link_tx.send(event).unwrap();
+                Ok(())
+            });
+
+        let stores = Stores::in_memory();
+        let url_store = Arc::clone(&stores.urls);
+        let shortener = shortener_service::AppState::new(stores, Arc::new(event_publisher));
+        let analytics =
+            analytics_service::AppState::new(Arc::new(InMemoryAnalyticsStore::new()), 16);
+
+        let broker: Arc<dyn Broker> = Arc::new(InProcessBroker::default());
+        let access_consumer = AccessEventConsumer::new(
+            Arc::clone(&broker),
+            ACCESS_QUEUE,
+            ACCESS_ROUTING_KEY,
+            Arc::clone(&analytics.analytics_store),
+            None,
+            Vec::new(),
+            Codec::Json,
+        )
+        .await
+        .unwrap();
+        analytics
+            .click_stream
+            .follow(broker.as_ref())
+            .await
+            .unwrap();
+        let link_consumer = LinkEventConsumer::new(
+            Arc::clone(&broker),
+            LINK_EVENTS_QUEUE,
+            Arc::clone(&analytics.analytics_store),
+            None,
+        )
+        .await
+        .unwrap();
+        tokio::spawn(async move { access_consumer.start_consuming().await });
+        tokio::spawn(async move { link_consumer.start_consuming().await });
+
+        let broker_publisher =
+            AccessEventPublisher::new(broker, ACCESS_QUEUE, ACCESS_ROUTING_KEY, Codec::Json)
+                .await
+                .unwrap();
+
+        Self {
+            router: all_in_one::router(shortener, analytics),
+            url_store,
+            access_events,
+            link_events,
+            broker_publisher,
+        }
+    }
+
+    async fn request(&self, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        let response = self.send(method, uri, body).await;
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, body)
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        uri: &str,
+        body: Option<Value>,
+    ) -> axum::response::Response {
+        let mut request = Request::builder().method(method).uri(uri);
+        let body = match body {
+            Some(body) => {
+                request = request.header(header::CONTENT_TYPE, "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+        self.send_request(request.body(body).unwrap()).await
+    }
+
+    async fn send_request(&self, mut request: Request<Body>) -> axum::response::Response {
+        // Redirects read the client address, which `serve` would provide.
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([192, 0, 2, 1], 40000))));
+
+        self.router.clone().oneshot(request).await.unwrap()
+    }
+
+    async fn create(&self, url: &str) -> String {
+        let (status, body) = self
+            .request(Method::POST, "/api/v1/urls", Some(json!({ "url": url })))
+            .await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        body["code"].as_str().unwrap().to_string()
+    }
+
+    /// Forwards the next access event the shortener published to analytics.
+    async fn forward_access_event(&mut self) -> AccessEvent {
+        let event = next(&mut self.access_events).await;
+        self.broker_publisher.publish(event.clone()).await.unwrap();
+        event
+    }
+
+    /// Forwards the next link event the shortener published to analytics.
+    async fn forward_link_event(&mut self) -> LinkEvent {
+        let event = next(&mut self.link_events).await;
+        self.broker_publisher
+            .publish_link_event(event.clone())
+            .await
+            .unwrap();
+        event
+    }
+
+    /// Polls the analytics of `code` until `done` accepts the response.
+    async fn wait_for_analytics(&self, code: &str, done: impl Fn(StatusCode, &Value) -> bool) {
+        let uri = format!("/api/v1/analytics/{code}");
+        for _ in 0..100 {
+            let (status, body) = self.request(Method::GET, &uri, None).await;
+            if done(status, &body) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("analytics of {code} did not reach the expected state");
+    }
+}
+
+async fn next<T>(events: &mut mpsc::UnboundedReceiver<T>) -> T {
+    tokio::time::timeout(Duration::from_secs(1), events.recv())
+        .await
+        .expect("no event published")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn redirect_is_counted_in_analytics() {
+    let mut app = TestApp::new().await;
+
+    let code = app.create("https://example.com/landing").await;
+    assert!(matches!(
+        app.forward_link_event().await,
+        LinkEvent::Created(_)
+    ));
+
+    let response = app.send(Method::GET, &format!("/{code}"), None).await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(
+        response.headers()[header::LOCATION],
+        "https://example.com/landing"
+    );
+
+    let event = app.forward_access_event().await;
+    assert_eq!(event.code, code);
+    assert_eq!(event.ip_address.as_deref(), Some("192.0.2.1"));
+
+    app.wait_for_analytics(&code, |status, body| {
+        status == StatusCode::OK && body["access_count"] == 1
+    })
+    .await;
+
+    let (status, body) = app
+        .request(Method::GET, "/api/v1/analytics/top?window=all", None)
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["items"][0]["code"], code);
+}
+
+#[tokio::test]
+async fn deleting_a_link_keeps_analytics_until_it_is_purged() {
+    let mut app = TestApp::new().await;
+
+    let code = app.create("https://example.com/").await;
+    app.forward_link_event().await;
+    app.send(Method::GET, &format!("/{code}"), None).await;
+    app.forward_access_event().await;
+    app.wait_for_analytics(&code, |status, _| status == StatusCode::OK)
+        .await;
+
+    let response = app
+        .send(Method::DELETE, &format!("/api/v1/urls/{code}"), None)
+        .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(matches!(
+        app.forward_link_event().await,
+        LinkEvent::Deleted(_)
+    ));
+
+    let response = app.send(Method::GET, &format!("/{code}"), None).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let (status, body) = app
+        .request(Method::GET, &format!("/api/v1/analytics/{code}"), None)
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["access_count"], 1);
+
+    let purged = app
+        .url_store
+        .purge_deleted(Utc::now() + TimeDelta::seconds(1), true)
+        .await
+        .unwrap();
+    assert_eq!(purged, vec![code.clone()]);
+    app.broker_publisher
+        .publish_link_event(LinkEvent::Purged(UrlPurged::new(code.clone())))
+        .await
+        .unwrap();
+
+    app.wait_for_analytics(&code, |status, _| status == StatusCode::NOT_FOUND)
+        .await;
+}
+
+#[tokio::test]
+async fn updated_destination_is_used_for_redirects() {
+    let mut app = TestApp::new().await;
+
+    let code = app.create("https://example.com/old").await;
+    app.forward_link_event().await;
+
+    let (status, body) = app
+        .request(
+            Method::PUT,
+            &format!("/api/v1/urls/{code}"),
+            Some(json!({ "url": "https://example.com/new" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(matches!(
+        app.forward_link_event().await,
+        LinkEvent::Updated(_)
+    ));
+
+    let response = app.send(Method::GET, &format!("/{code}"), None).await;
+    assert_eq!(
+        response.headers()[header::LOCATION],
+        "https://example.com/new"
+    );
+}
+
+#[tokio::test]
+async fn interstitial_is_passed_only_through_its_form() {
+    let mut app = TestApp::new().await;
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/api/v1/urls",
+            Some(json!({ "url": "https://example.com/flagged", "always_interstitial": true })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let code = body["code"].as_str().unwrap();
+
+    for uri in [format!("/{code}"), format!("/{code}?confirm")] {
+        let response = app.send(Method::GET, &uri, None).await;
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        let html = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let html = String::from_utf8(html.to_vec()).unwrap();
+        assert!(
+            html.contains(&format!(r#"<form method="post" action="/{code}"#)),
+            "{html}"
+        );
+    }
+
+    let cross_site = Request::builder()
+        .method(Method::POST)
+        .uri(format!("/{code}"))
+        .header("sec-fetch-site", "cross-site")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(app.send_request(cross_site).await.status(), StatusCode::OK);
+
+    let from_preview = Request::builder()
+        .method(Method::POST)
+        .uri(format!("/{code}?ref=mail"))
+        .header("sec-fetch-site", "same-origin")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send_request(from_preview).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(
+        response.headers()[header::LOCATION],
+        "https://example.com/flagged"
+    );
+
+    assert_eq!(app.forward_access_event().await.code, code);
+    assert!(app.access_events.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn unknown_code_is_not_found() {
+    let app = TestApp::new().await;
+
+    let (status, body) = app.request(Method::GET, "/missing", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"]["code"], "NOT_FOUND");
+
+    let (status, _) = app
+        .request(Method::GET, "/api/v1/analytics/missing", None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn invalid_destination_is_rejected() {
+    let app = TestApp::new().await;
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/v1/urls",
+            Some(json!({ "url": "ftp://example.com/file" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = app.request(Method::GET, "/api/v1/urls", None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["items"], json!([]));
+}
+
+#[tokio::test]
+async fn blocked_domains_are_rejected() {
+    let app = TestApp::new().await;
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/api/v1/admin/blocklist",
+            Some(json!({ "domain": "example.org" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/v1/urls",
+            Some(json!({ "url": "https://www.example.org/" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+/// Follows `next_cursor` from `uri` with `limit` items per page, returning
+/// the `field` of every item.
+async fn collect_pages(app: &TestApp, uri: &str, limit: usize, field: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let page_uri = match &cursor {
+            Some(cursor) => format!("{uri}?limit={limit}&cursor={cursor}"),
+            None => format!("{uri}?limit={limit}"),
+        };
+        let (status, body) = app.request(Method::GET, &page_uri, None).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+
+        let page = body["items"].as_array().unwrap();
+        assert!(page.len() <= limit, "{body}");
+        items.extend(
+            page.iter()
+                .map(|item| item[field].as_str().unwrap().to_string()),
+        );
+
+        match body["next_cursor"].as_str() {
+            Some(next) => cursor = Some(next.to_string()),
+            None => return items,
+        }
+    }
+}
+
+#[tokio::test]
+async fn url_list_cursor_walks_every_link_once_newest_first() {
+    let app = TestApp::new().await;
+    let mut codes = Vec::new();
+    for i in 0..5 {
+        codes.push(app.create(&format!("https://example.com/{i}")).await);
+        // Keeps creation times apart, which order the list.
+        tokio::time::sleep(Duration::from_millis(2)).await;
+    }
+    codes.reverse();
+
+    assert_eq!(collect_pages(&app, "/api/v1/urls", 2, "code").await, codes);
+    assert_eq!(collect_pages(&app, "/api/v1/urls", 5, "code").await, codes);
+}
+
+#[tokio::test]
+async fn analytics_list_cursor_walks_every_code_once_in_code_order() {
+    let app = TestApp::new().await;
+    let mut codes = vec!["b2", "a1", "B3", "c4", "a10"];
+    // The busiest code comes last in code order.
+    for (clicks, code) in [(1, "b2"), (1, "a1"), (1, "B3"), (3, "c4"), (1, "a10")] {
+        for _ in 0..clicks {
+            let event = AccessEvent::new(code.to_string(), None, None, None);
+            app.broker_publisher.publish(event).await.unwrap();
+        }
+    }
+    app.wait_for_analytics("c4", |status, body| {
+        status == StatusCode::OK && body["access_count"] == 3
+    })
+    .await;
+    app.wait_for_analytics("a10", |status, _| status == StatusCode::OK)
+        .await;
+    codes.sort_unstable();
+
+    assert_eq!(
+        collect_pages(&app, "/api/v1/analytics", 2, "code").await,
+        codes
+    );
+    assert_eq!(
+        collect_pages(&app, "/api/v1/analytics", 5, "code").await,
+        codes
+    );
+}
+
+#[tokio::test]
+async fn invalid_cursors_are_rejected() {
+    let app = TestApp::new().await;
+
+    for uri in [
+        "/api/v1/urls?cursor=not-a-cursor",
+        "/api/v1/urls?cursor=ImFiYzEyMyI",
+        "/api/v1/analytics?cursor=not-a-cursor",
+        "/api/v1/analytics?cursor=WzEsMl0",
+    ] {
+        let (status, body) = app.request(Method::GET, uri, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}: {body}");
+        assert_eq!(body["error"]["message"], "Invalid cursor", "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn openapi_document_covers_both_services() {
+    let app = TestApp::new().await;
+
+    let (status, body) = app.request(Method::GET, "/api/openapi.json", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["openapi"], "3.1.0");
+    assert!(body["paths"]["/api/v1/urls"]["post"].is_object());
+    assert!(body["paths"]["/api/v1/analytics/top"]["get"].is_object());
+
+    let response = app.send(Method::GET, "/api/docs/", None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
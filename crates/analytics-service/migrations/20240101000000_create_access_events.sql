@@ -0,0 +1,14 @@
+-- Raw access events, partitioned by month. Partitions are created by
+-- analytics-service before the first event of a month is stored.
+CREATE TABLE access_events (
+    event_id UUID NOT NULL,
+    code VARCHAR(10) NOT NULL,
+    accessed_at TIMESTAMPTZ NOT NULL,
+    user_agent TEXT,
+    ip_address TEXT,
+    referer TEXT,
+    PRIMARY KEY (event_id, accessed_at)
+) PARTITION BY RANGE (accessed_at);
+
+CREATE INDEX idx_access_events_code_accessed_at ON access_events(code, accessed_at);
+CREATE INDEX idx_access_events_accessed_at ON access_events(accessed_at);
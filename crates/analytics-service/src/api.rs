@@ -0,0 +1,10 @@
+//! Request and response bodies of the HTTP API, defined in shortener-types
+//! so that clients do not depend on the service.
+
+pub use crate::export::ExportFormat;
+pub use crate::repository::{Analytics, RankWindow, RankedCode};
+pub use crate::routes::{
+    AnalyticsListResponse, ExportQuery, HealthResponse, ListAnalyticsQuery, ReadyResponse,
+    TopAnalyticsQuery, TopAnalyticsResponse,
+};
+pub use crate::stream::{Click, StreamMessage};
@@ -2,35 +2,81 @@
 
 use saferet::SecretString;
 use serviceconf::ServiceConf;
-use shortener_core::config::{ObservabilityConfig, RabbitMQConfig, RedisConfig};
+use shortener_core::config::{
+    BrokerConfig, DatabaseConfig, ObservabilityConfig, RabbitMQConfig, RedisConfig,
+    RedisStreamsConfig,
+};
 
 /// Configuration for analytics-service.
 #[derive(Debug, Clone, ServiceConf)]
 pub struct Config {
-    /// Redis connection URL.
+    /// Where click counts are kept: `redis`, or `memory` to keep them in
+    /// process memory until the service stops.
+    #[conf(default = "redis".to_string())]
+    pub analytics_store: String,
+
+    /// Redis connection URL (required by the `redis` analytics store and
+    /// broker).
+    #[conf(from_file)]
+    pub redis_url: Option<SecretString>,
+
+    /// Postgres connection URL for durable event storage (optional).
     #[conf(from_file)]
-    pub redis_url: SecretString,
+    pub analytics_database_url: Option<SecretString>,
 
-    /// `RabbitMQ` connection URL.
+    /// Maximum number of event storage connections.
+    #[conf(default = 5)]
+    pub analytics_database_max_connections: u32,
+
+    /// Message broker: `rabbitmq`, `redis` (Redis Streams) or `in_process`.
+    #[conf(default = "rabbitmq".to_string())]
+    pub message_broker: String,
+
+    /// `RabbitMQ` connection URL (required by the `rabbitmq` broker).
     #[conf(from_file)]
-    pub rabbitmq_url: SecretString,
+    pub rabbitmq_url: Option<SecretString>,
 
     /// `RabbitMQ` exchange name.
     #[conf(default = "url_shortener".to_string())]
     pub rabbitmq_exchange: String,
 
-    /// `RabbitMQ` queue name.
+    /// Prefix of the Redis stream keys used by the `redis` broker.
+    #[conf(default = "url_shortener".to_string())]
+    pub redis_stream_prefix: String,
+
+    /// Approximate number of entries kept per Redis stream.
+    #[conf(default = 100_000)]
+    pub redis_stream_max_len: usize,
+
+    /// Queue access events are delivered to.
     #[conf(default = "access_events".to_string())]
     pub rabbitmq_queue: String,
 
-    /// `RabbitMQ` routing key.
+    /// Routing key access events are published with.
     #[conf(default = "access.event".to_string())]
     pub rabbitmq_routing_key: String,
 
+    /// Queue the link lifecycle events are read from.
+    #[conf(default = "analytics_link_events".to_string())]
+    pub link_events_queue: String,
+
     /// OTEL exporter endpoint (optional).
     #[conf(from_file)]
     pub otel_exporter_endpoint: Option<SecretString>,
 
+    /// Comma-separated all-time click counts that publish a threshold event
+    /// when a link reaches them.
+    #[conf(default = "100,1000,10000".to_string())]
+    pub click_thresholds: String,
+
+    /// Encoding of published events: `json` or `msgpack`.
+    #[conf(default = "json".to_string())]
+    pub event_codec: String,
+
+    /// Clicks buffered per real-time subscriber before the oldest are dropped.
+    #[conf(default = 1024)]
+    pub stream_buffer_size: usize,
+
     /// Server host address.
     #[conf(default = "0.0.0.0".to_string())]
     pub server_host: String,
@@ -40,23 +86,72 @@ pub struct Config {
     pub server_port: u16,
 }
 
+/// Backend of the click counters.
+#[derive(Debug, Clone)]
+pub enum AnalyticsStoreConfig {
+    Redis(RedisConfig),
+    InMemory,
+}
+
 impl Config {
-    /// Returns the Redis configuration.
-    #[must_use]
-    pub fn redis_config(&self) -> RedisConfig {
-        RedisConfig {
-            url: self.redis_url.clone(),
+    /// Returns the analytics store configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store is unknown or its URL is not set.
+    pub fn analytics_store_config(&self) -> anyhow::Result<AnalyticsStoreConfig> {
+        match self.analytics_store.as_str() {
+            "redis" => Ok(AnalyticsStoreConfig::Redis(RedisConfig {
+                url: self.redis_url()?,
+            })),
+            "memory" => Ok(AnalyticsStoreConfig::InMemory),
+            other => anyhow::bail!("Unknown analytics store '{other}'"),
         }
     }
 
-    /// Returns the `RabbitMQ` configuration.
+    fn redis_url(&self) -> anyhow::Result<SecretString> {
+        let Some(url) = &self.redis_url else {
+            anyhow::bail!(
+                "REDIS_URL must be set to use the redis analytics store or the redis broker"
+            );
+        };
+        Ok(url.clone())
+    }
+
+    /// Returns the event storage database configuration, if configured.
     #[must_use]
-    pub fn rabbitmq_config(&self) -> RabbitMQConfig {
-        RabbitMQConfig {
-            url: self.rabbitmq_url.clone(),
-            exchange: self.rabbitmq_exchange.clone(),
-            queue: self.rabbitmq_queue.clone(),
-            routing_key: self.rabbitmq_routing_key.clone(),
+    pub fn database_config(&self) -> Option<DatabaseConfig> {
+        self.analytics_database_url
+            .as_ref()
+            .map(|url| DatabaseConfig {
+                url: url.clone(),
+                max_connections: self.analytics_database_max_connections,
+            })
+    }
+
+    /// Returns the message broker configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the broker is unknown or its URL is not set.
+    pub fn broker_config(&self) -> anyhow::Result<BrokerConfig> {
+        match self.message_broker.as_str() {
+            "rabbitmq" => {
+                let Some(url) = &self.rabbitmq_url else {
+                    anyhow::bail!("RABBITMQ_URL must be set to use the rabbitmq broker");
+                };
+                Ok(BrokerConfig::RabbitMQ(RabbitMQConfig {
+                    url: url.clone(),
+                    exchange: self.rabbitmq_exchange.clone(),
+                }))
+            }
+            "redis" => Ok(BrokerConfig::RedisStreams(RedisStreamsConfig {
+                url: self.redis_url()?,
+                prefix: self.redis_stream_prefix.clone(),
+                max_len: self.redis_stream_max_len,
+            })),
+            "in_process" => Ok(BrokerConfig::InProcess),
+            other => anyhow::bail!("Unknown message broker '{other}'"),
         }
     }
 
@@ -68,6 +163,23 @@ impl Config {
         }
     }
 
+    /// Returns the click counts listed in `click_thresholds`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry is not a positive integer.
+    pub fn click_thresholds(&self) -> anyhow::Result<Vec<i64>> {
+        self.click_thresholds
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(|t| match t.parse::<i64>() {
+                Ok(threshold) if threshold > 0 => Ok(threshold),
+                _ => Err(anyhow::anyhow!("Invalid click threshold '{t}'")),
+            })
+            .collect()
+    }
+
     /// Returns the server address.
     #[must_use]
     pub fn server_addr(&self) -> String {
@@ -1,145 +1,431 @@
-use std::collections::HashMap;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use futures_lite::stream::StreamExt;
-use lapin::{
-    options::{BasicAckOptions, BasicConsumeOptions},
-    types::{AMQPValue, FieldTable},
+use opentelemetry::trace::SpanKind;
+use shortener_core::{
+    AppError, Broker,
+    messaging::{
+        AccessEvent, ClickCounted, ClickThresholdReached, Codec, LinkEvent, envelope, routing_keys,
+    },
 };
-use opentelemetry::{global, propagation::Extractor, trace::SpanKind};
-use shortener_core::{RabbitMQChannel, config::RabbitMQConfig, messaging::AccessEvent};
 use tracing::{Instrument, error, info, info_span, instrument, warn};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
 use super::EventConsumer;
-use crate::repository::AnalyticsRepository;
+use crate::repository::{AnalyticsStore, EventStore};
 
-pub struct AccessEventConsumer {
-    rabbitmq: RabbitMQChannel,
-    repository: Arc<AnalyticsRepository>,
-}
-
-/// Carrier for extracting trace context from message headers.
-struct HeaderExtractor(HashMap<String, String>);
-
-impl HeaderExtractor {
-    fn from_field_table(headers: &FieldTable) -> Self {
-        let map = headers
-            .inner()
-            .iter()
-            .filter_map(|(k, v)| {
-                if let AMQPValue::LongString(s) = v {
-                    Some((k.to_string(), s.to_string()))
-                } else {
-                    None
-                }
-            })
-            .collect();
-        Self(map)
-    }
-}
-
-impl Extractor for HeaderExtractor {
-    fn get(&self, key: &str) -> Option<&str> {
-        self.0.get(key).map(String::as_str)
-    }
+const PRODUCER: &str = env!("CARGO_PKG_NAME");
 
-    fn keys(&self) -> Vec<&str> {
-        self.0.keys().map(String::as_str).collect()
-    }
+pub struct AccessEventConsumer {
+    broker: Arc<dyn Broker>,
+    queue: String,
+    routing_key: String,
+    repository: Arc<dyn AnalyticsStore>,
+    event_store: Option<Arc<EventStore>>,
+    click_thresholds: Vec<i64>,
+    /// Encoding of the events this consumer publishes.
+    codec: Codec,
 }
 
 impl AccessEventConsumer {
-    #[instrument(skip(config, repository))]
+    /// Declares `queue` bound to the access event `routing_key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the queue cannot be declared.
+    #[instrument(skip(broker, repository, event_store))]
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
-        config: &RabbitMQConfig,
-        repository: Arc<AnalyticsRepository>,
+        broker: Arc<dyn Broker>,
+        queue: &str,
+        routing_key: &str,
+        repository: Arc<dyn AnalyticsStore>,
+        event_store: Option<Arc<EventStore>>,
+        click_thresholds: Vec<i64>,
+        codec: Codec,
     ) -> anyhow::Result<Self> {
-        let rabbitmq = RabbitMQChannel::try_new(config).await?;
+        broker.declare_queue(queue, &[routing_key]).await?;
         Ok(Self {
-            rabbitmq,
+            broker,
+            queue: queue.to_string(),
+            routing_key: routing_key.to_string(),
             repository,
+            event_store,
+            click_thresholds,
+            codec,
         })
     }
+
+    /// Persists `event` and updates the counters.
+    ///
+    /// Fails if the event could not be persisted or counted, in which case it
+    /// should be redelivered. Already persisted events are not counted again,
+    /// so an event that could not be counted is deleted from the event store
+    /// first.
+    async fn handle(&self, event: &AccessEvent) -> Result<(), AppError> {
+        if let Some(event_store) = &self.event_store
+            && !event_store.insert(event).await?
+        {
+            info!(event_id = %event.event_id, "Skipping duplicate access event");
+            return Ok(());
+        }
+
+        let access_count = match self
+            .repository
+            .increment(&event.code, event.accessed_at)
+            .await
+        {
+            Ok(access_count) => access_count,
+            Err(e) => {
+                self.forget(event).await;
+                return Err(e);
+            }
+        };
+
+        self.publish_click_counted(event, access_count).await;
+
+        if self.click_thresholds.contains(&access_count) {
+            self.publish_threshold_reached(&event.code, access_count)
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the persisted `event` after counting it failed, so its
+    /// redelivery is counted instead of skipped as a duplicate.
+    async fn forget(&self, event: &AccessEvent) {
+        let Some(event_store) = &self.event_store else {
+            return;
+        };
+        if let Err(e) = event_store.delete(event.event_id, event.accessed_at).await {
+            // The redelivery will be skipped, so the counters stay behind the
+            // event store until they are rebuilt.
+            error!(
+                event_id = %event.event_id,
+                code = %event.code,
+                "Counters missed a stored access event: {:?}",
+                e
+            );
+        }
+    }
+
+    /// Announces the counted `event` to the click streams of every replica.
+    async fn publish_click_counted(&self, event: &AccessEvent, access_count: i64) {
+        let counted = ClickCounted {
+            event_id: event.event_id,
+            code: event.code.clone(),
+            accessed_at: event.accessed_at,
+            access_count,
+        };
+
+        let result = match envelope::encode(self.codec, PRODUCER, &counted) {
+            Ok(payload) => {
+                self.broker
+                    .publish(
+                        routing_keys::CLICK_COUNTED,
+                        &counted.event_id.to_string(),
+                        self.codec,
+                        &payload,
+                    )
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        // The click is counted either way; only real-time subscribers miss it.
+        if let Err(e) = result {
+            warn!("Failed to publish counted click: {:?}", e);
+        }
+    }
+
+    async fn publish_threshold_reached(&self, code: &str, threshold: i64) {
+        let event = LinkEvent::ClickThresholdReached(ClickThresholdReached::new(
+            code.to_string(),
+            threshold,
+        ));
+
+        let result = match event.encode(self.codec, PRODUCER) {
+            Ok(payload) => {
+                self.broker
+                    .publish(
+                        event.routing_key(),
+                        &event.event_id().to_string(),
+                        self.codec,
+                        &payload,
+                    )
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => info!(code, threshold, "Click threshold event published"),
+            Err(e) => warn!("Failed to publish click threshold event: {:?}", e),
+        }
+    }
 }
 
 #[async_trait]
 impl EventConsumer for AccessEventConsumer {
     async fn start_consuming(&self) -> anyhow::Result<()> {
-        let mut consumer = self
-            .rabbitmq
-            .channel
-            .basic_consume(
-                &self.rabbitmq.queue,
-                "analytics-consumer",
-                BasicConsumeOptions::default(),
-                FieldTable::default(),
-            )
+        let mut subscription = self
+            .broker
+            .subscribe(&self.queue, &[self.routing_key.as_str()])
             .await?;
 
-        info!(queue = %self.rabbitmq.queue, "Started consuming access events");
-
-        while let Some(delivery_result) = consumer.next().await {
-            match delivery_result {
-                Ok(delivery) => {
-                    // Extract trace context from headers
-                    let parent_context = delivery
-                        .properties
-                        .headers()
-                        .as_ref()
-                        .map(|headers| {
-                            let extractor = HeaderExtractor::from_field_table(headers);
-                            global::get_text_map_propagator(|propagator| {
-                                propagator.extract(&extractor)
-                            })
-                        })
-                        .unwrap_or_default();
-
-                    // Create a new span linked to the parent context
-                    let span = info_span!(
-                        "process_access_event",
-                        otel.kind = ?SpanKind::Consumer,
-                        messaging.system = "rabbitmq",
-                        messaging.destination = %self.rabbitmq.queue,
-                    );
-                    span.set_parent(parent_context);
-
-                    async {
-                        match serde_json::from_slice::<AccessEvent>(&delivery.data) {
-                            Ok(event) => {
-                                info!(
-                                    event_id = %event.event_id,
-                                    code = %event.code,
-                                    "Processing access event"
-                                );
-
-                                if let Err(e) = self
-                                    .repository
-                                    .increment(&event.code, event.accessed_at)
-                                    .await
-                                {
-                                    error!("Failed to store event: {:?}", e);
-                                }
-                            }
-                            Err(e) => {
-                                warn!("Failed to deserialize event: {:?}", e);
+        info!(queue = %self.queue, "Started consuming access events");
+
+        while let Some(delivery) = subscription.next().await {
+            // Create a new span linked to the publisher's trace
+            let span = info_span!(
+                "process_access_event",
+                otel.kind = ?SpanKind::Consumer,
+                messaging.system = self.broker.system(),
+                messaging.destination = %self.queue,
+            );
+            span.set_parent(delivery.trace_context());
+
+            async {
+                let event = delivery
+                    .codec()
+                    .and_then(|codec| envelope::decode::<AccessEvent>(codec, &delivery.payload));
+                match event {
+                    Ok(event) => {
+                        info!(
+                            event_id = %event.event_id,
+                            code = %event.code,
+                            "Processing access event"
+                        );
+
+                        if let Err(e) = self.handle(&event).await {
+                            error!("Failed to handle event: {:?}", e);
+
+                            if let Err(e) = delivery.nack(true).await {
+                                error!("Failed to nack message: {:?}", e);
                             }
+                            return;
                         }
+                    }
+                    Err(e) => {
+                        error!("Failed to deserialize event, dead-lettering it: {:?}", e);
 
-                        if let Err(e) = delivery.ack(BasicAckOptions::default()).await {
-                            error!("Failed to ack message: {:?}", e);
+                        if let Err(e) = delivery.nack(false).await {
+                            error!("Failed to nack message: {:?}", e);
                         }
+                        return;
                     }
-                    .instrument(span)
-                    .await;
                 }
-                Err(e) => {
-                    error!("Consumer error: {:?}", e);
+
+                if let Err(e) = delivery.ack().await {
+                    error!("Failed to ack message: {:?}", e);
                 }
             }
+            .instrument(span)
+            .await;
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{pin::pin, time::Duration};
+
+    use futures_lite::StreamExt;
+    use mockall::Sequence;
+    use shortener_core::{
+        broker::{InProcessBroker, dead_letter_queue},
+        config::{DatabaseConfig, SecretString},
+    };
+
+    use super::*;
+    use crate::{
+        repository::{InMemoryAnalyticsStore, MockAnalyticsStore},
+        stream::{Click, ClickStream, StreamMessage},
+    };
+
+    const QUEUE: &str = "access_events";
+    const ROUTING_KEY: &str = "access.event";
+
+    /// Returns a store whose first increment fails.
+    fn flaky_store() -> MockAnalyticsStore {
+        let mut store = MockAnalyticsStore::new();
+        let mut sequence = Sequence::new();
+        store
+            .expect_increment()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _| Err(AppError::Redis("Connection refused".to_string())));
+        store
+            .expect_increment()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _| Ok(1));
+        store
+    }
+
+    /// Publishes `event` to a consumer counting in a flaky store and returns
+    /// the first click it streams.
+    async fn first_click(event_store: Option<Arc<EventStore>>, event: &AccessEvent) -> Click {
+        let broker: Arc<dyn Broker> = Arc::new(InProcessBroker::default());
+        let click_stream = ClickStream::new(16);
+        click_stream.follow(broker.as_ref()).await.unwrap();
+        let mut clicks = pin!(click_stream.subscribe(None));
+        let consumer = AccessEventConsumer::new(
+            Arc::clone(&broker),
+            QUEUE,
+            ROUTING_KEY,
+            Arc::new(flaky_store()),
+            event_store,
+            Vec::new(),
+            Codec::Json,
+        )
+        .await
+        .unwrap();
+        tokio::spawn(async move { consumer.start_consuming().await });
+
+        let payload = envelope::encode(Codec::Json, PRODUCER, event).unwrap();
+        broker
+            .publish(
+                ROUTING_KEY,
+                &event.event_id.to_string(),
+                Codec::Json,
+                &payload,
+            )
+            .await
+            .unwrap();
+
+        match tokio::time::timeout(Duration::from_secs(5), clicks.next()).await {
+            Ok(Some(StreamMessage::Click(click))) => click,
+            other => panic!("expected a click, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_count_is_retried() {
+        let event = AccessEvent::new("abc123".to_string(), None, None, None);
+
+        let click = first_click(None, &event).await;
+
+        assert_eq!(click.event_id, event.event_id);
+        assert_eq!(click.access_count, 1);
+    }
+
+    #[tokio::test]
+    async fn failed_count_of_stored_event_is_retried() {
+        let Ok(url) = std::env::var("ANALYTICS_DATABASE_URL") else {
+            return;
+        };
+        let event_store = crate::connect_event_store(&DatabaseConfig {
+            url: SecretString::new(url),
+            max_connections: 2,
+        })
+        .await
+        .unwrap();
+        let event_store = Arc::new(event_store);
+        let event = AccessEvent::new("retried".to_string(), None, None, None);
+
+        let click = first_click(Some(Arc::clone(&event_store)), &event).await;
+
+        // The redelivery was stored and counted rather than skipped.
+        assert_eq!(click.event_id, event.event_id);
+        assert_eq!(click.access_count, 1);
+        assert!(
+            event_store
+                .delete(event.event_id, event.accessed_at)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn undecodable_event_is_dead_lettered() {
+        let broker: Arc<dyn Broker> = Arc::new(InProcessBroker::default());
+        let consumer = AccessEventConsumer::new(
+            Arc::clone(&broker),
+            QUEUE,
+            ROUTING_KEY,
+            Arc::new(MockAnalyticsStore::new()),
+            None,
+            Vec::new(),
+            Codec::Json,
+        )
+        .await
+        .unwrap();
+        let mut dead_letters = broker
+            .subscribe(&dead_letter_queue(QUEUE), &[])
+            .await
+            .unwrap();
+        tokio::spawn(async move { consumer.start_consuming().await });
+
+        broker
+            .publish(ROUTING_KEY, "garbled", Codec::Json, b"{\"code\":")
+            .await
+            .unwrap();
+
+        let delivery = tokio::time::timeout(Duration::from_secs(5), dead_letters.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(delivery.routing_key, ROUTING_KEY);
+        assert_eq!(delivery.message_id.as_deref(), Some("garbled"));
+        assert_eq!(delivery.payload, b"{\"code\":");
+    }
+
+    #[tokio::test]
+    async fn every_replica_streams_every_click() {
+        let broker: Arc<dyn Broker> = Arc::new(InProcessBroker::default());
+        let store: Arc<dyn AnalyticsStore> = Arc::new(InMemoryAnalyticsStore::new());
+
+        // Two replicas compete for the access events of one queue.
+        let mut replicas = Vec::new();
+        for _ in 0..2 {
+            let click_stream = ClickStream::new(16);
+            click_stream.follow(broker.as_ref()).await.unwrap();
+            let consumer = AccessEventConsumer::new(
+                Arc::clone(&broker),
+                QUEUE,
+                ROUTING_KEY,
+                Arc::clone(&store),
+                None,
+                Vec::new(),
+                Codec::Json,
+            )
+            .await
+            .unwrap();
+            replicas.push(Box::pin(click_stream.subscribe(None)));
+            tokio::spawn(async move { consumer.start_consuming().await });
+        }
+
+        let mut published = Vec::new();
+        for _ in 0..4 {
+            let event = AccessEvent::new("abc123".to_string(), None, None, None);
+            let payload = envelope::encode(Codec::Json, PRODUCER, &event).unwrap();
+            broker
+                .publish(
+                    ROUTING_KEY,
+                    &event.event_id.to_string(),
+                    Codec::Json,
+                    &payload,
+                )
+                .await
+                .unwrap();
+            published.push(event.event_id);
+        }
+        published.sort();
+
+        for clicks in &mut replicas {
+            let mut streamed = Vec::new();
+            for _ in 0..published.len() {
+                match tokio::time::timeout(Duration::from_secs(5), clicks.next()).await {
+                    Ok(Some(StreamMessage::Click(click))) => streamed.push(click.event_id),
+                    other => panic!("expected a click, got {other:?}"),
+                }
+            }
+            streamed.sort();
+            assert_eq!(streamed, published);
+        }
+    }
+}
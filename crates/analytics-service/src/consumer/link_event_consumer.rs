@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use opentelemetry::trace::SpanKind;
+use shortener_core::{
+    AppError, Broker,
+    messaging::{LinkEvent, routing_keys},
+};
+use tracing::{Instrument, error, info, info_span, instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use super::EventConsumer;
+use crate::repository::{AnalyticsStore, EventStore};
+
+/// Clears the analytics of links purged from the trash in shortener-service.
+pub struct LinkEventConsumer {
+    broker: Arc<dyn Broker>,
+    queue: String,
+    repository: Arc<dyn AnalyticsStore>,
+    event_store: Option<Arc<EventStore>>,
+}
+
+impl LinkEventConsumer {
+    /// Declares `queue` bound to the `url.purged` routing key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the queue cannot be declared.
+    #[instrument(skip(broker, repository, event_store))]
+    pub async fn new(
+        broker: Arc<dyn Broker>,
+        queue: &str,
+        repository: Arc<dyn AnalyticsStore>,
+        event_store: Option<Arc<EventStore>>,
+    ) -> anyhow::Result<Self> {
+        broker
+            .declare_queue(queue, &[routing_keys::URL_PURGED])
+            .await?;
+
+        Ok(Self {
+            broker,
+            queue: queue.to_string(),
+            repository,
+            event_store,
+        })
+    }
+
+    async fn handle(&self, event: &LinkEvent) -> Result<(), AppError> {
+        let LinkEvent::Purged(purged) = event else {
+            return Ok(());
+        };
+
+        self.repository.remove(&purged.code).await?;
+
+        let deleted_events = match &self.event_store {
+            Some(event_store) => event_store.delete_code(&purged.code).await?,
+            None => 0,
+        };
+
+        info!(
+            code = %purged.code,
+            deleted_events,
+            "Cleared analytics of purged link"
+        );
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventConsumer for LinkEventConsumer {
+    async fn start_consuming(&self) -> anyhow::Result<()> {
+        let mut subscription = self
+            .broker
+            .subscribe(&self.queue, &[routing_keys::URL_PURGED])
+            .await?;
+
+        info!(queue = %self.queue, "Started consuming link events");
+
+        while let Some(delivery) = subscription.next().await {
+            let span = info_span!(
+                "process_link_event",
+                otel.kind = ?SpanKind::Consumer,
+                messaging.system = self.broker.system(),
+                messaging.destination = %self.queue,
+                routing_key = %delivery.routing_key,
+            );
+            span.set_parent(delivery.trace_context());
+
+            async {
+                let event = delivery.codec().and_then(|codec| {
+                    LinkEvent::decode(codec, &delivery.routing_key, &delivery.payload)
+                });
+                match event {
+                    Ok(Some(event)) => {
+                        if let Err(e) = self.handle(&event).await {
+                            error!("Failed to handle link event: {:?}", e);
+
+                            if let Err(e) = delivery.nack(true).await {
+                                error!("Failed to nack message: {:?}", e);
+                            }
+                            return;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        error!(
+                            "Failed to deserialize link event, dead-lettering it: {:?}",
+                            e
+                        );
+
+                        if let Err(e) = delivery.nack(false).await {
+                            error!("Failed to nack message: {:?}", e);
+                        }
+                        return;
+                    }
+                }
+
+                if let Err(e) = delivery.ack().await {
+                    error!("Failed to ack message: {:?}", e);
+                }
+            }
+            .instrument(span)
+            .await;
+        }
+
+        Ok(())
+    }
+}
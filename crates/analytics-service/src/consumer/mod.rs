@@ -1,13 +1,21 @@
+//! Consumers of the events published by shortener-service.
+
 mod access_event_consumer;
+mod link_event_consumer;
 
 use async_trait::async_trait;
 
 pub use access_event_consumer::AccessEventConsumer;
+pub use link_event_consumer::LinkEventConsumer;
 
 /// Trait for consuming events.
 #[cfg_attr(test, mockall::automock)]
 #[async_trait]
 pub trait EventConsumer: Send + Sync {
     /// Start consuming events from the queue.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the queue cannot be subscribed to.
     async fn start_consuming(&self) -> anyhow::Result<()>;
 }
@@ -0,0 +1,385 @@
+//! Streaming export of stored access events.
+
+use std::{io, mem, pin::pin, sync::Arc};
+
+use arrow_array::{ArrayRef, RecordBatch, StringArray, TimestampMicrosecondArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use axum::body::{Body, Bytes};
+use chrono::{DateTime, Utc};
+use futures_lite::{StreamExt, stream};
+use parquet::arrow::ArrowWriter;
+use shortener_core::AppError;
+use tokio::sync::mpsc;
+use tracing::{Instrument, Span, error};
+
+use crate::repository::{EventStore, StoredEvent};
+
+pub use shortener_types::analytics::ExportFormat;
+
+/// Encoded bytes are sent to the client once this much has accumulated.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks buffered ahead of a slow client before reading from the database
+/// pauses.
+const CHANNEL_CAPACITY: usize = 8;
+
+/// Events per Parquet row group.
+const ROW_GROUP_SIZE: usize = 8192;
+
+const CSV_HEADER: &str = "event_id,code,accessed_at,user_agent,ip_address,referer\n";
+
+/// Returns a response body streaming the events of `code` (every code when
+/// `None`) accessed in `[from, to)` as `format`.
+///
+/// Events are read and encoded in a background task that pauses while the
+/// client is not keeping up. Failures after the response has started abort
+/// the body.
+pub fn export_body(
+    event_store: Arc<EventStore>,
+    code: Option<String>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    format: ExportFormat,
+) -> Body {
+    let (tx, rx) = mpsc::channel::<io::Result<Bytes>>(CHANNEL_CAPACITY);
+
+    tokio::spawn(
+        async move {
+            let result = write_events(&event_store, code.as_deref(), from, to, format, &tx).await;
+            if let Err(e) = result {
+                error!("Failed to export events: {:?}", e);
+                let _ = tx.send(Err(io::Error::other(e.to_string()))).await;
+            }
+        }
+        .instrument(Span::current()),
+    );
+
+    Body::from_stream(stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    }))
+}
+
+async fn write_events(
+    event_store: &EventStore,
+    code: Option<&str>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    format: ExportFormat,
+    tx: &mpsc::Sender<io::Result<Bytes>>,
+) -> Result<(), AppError> {
+    let mut encoder = Encoder::new(format)?;
+    let mut out = Vec::with_capacity(CHUNK_SIZE);
+    encoder.start(&mut out);
+
+    let mut events = pin!(event_store.events(code, from, to));
+    while let Some(event) = events.next().await {
+        encoder.encode(event?, &mut out)?;
+
+        if out.len() >= CHUNK_SIZE && tx.send(Ok(Bytes::from(mem::take(&mut out)))).await.is_err() {
+            // The client went away.
+            return Ok(());
+        }
+    }
+
+    encoder.finish(&mut out)?;
+    if !out.is_empty() {
+        let _ = tx.send(Ok(Bytes::from(out))).await;
+    }
+
+    Ok(())
+}
+
+enum Encoder {
+    Csv,
+    Ndjson,
+    Parquet {
+        writer: Box<ArrowWriter<Vec<u8>>>,
+        pending: Vec<StoredEvent>,
+    },
+}
+
+impl Encoder {
+    fn new(format: ExportFormat) -> Result<Self, AppError> {
+        Ok(match format {
+            ExportFormat::Csv => Self::Csv,
+            ExportFormat::Ndjson => Self::Ndjson,
+            ExportFormat::Parquet => Self::Parquet {
+                writer: Box::new(
+                    ArrowWriter::try_new(Vec::new(), parquet_schema(), None)
+                        .map_err(|e| AppError::Serialization(e.to_string()))?,
+                ),
+                pending: Vec::with_capacity(ROW_GROUP_SIZE),
+            },
+        })
+    }
+
+    fn start(&self, out: &mut Vec<u8>) {
+        if matches!(self, Self::Csv) {
+            out.extend_from_slice(CSV_HEADER.as_bytes());
+        }
+    }
+
+    fn encode(&mut self, event: StoredEvent, out: &mut Vec<u8>) -> Result<(), AppError> {
+        match self {
+            Self::Csv => {
+                let accessed_at = event.accessed_at.to_rfc3339();
+                let fields = [
+                    Some(event.event_id.to_string()),
+                    Some(event.code),
+                    Some(accessed_at),
+                    event.user_agent,
+                    event.ip_address,
+                    event.referer,
+                ];
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(b',');
+                    }
+                    if let Some(field) = field {
+                        write_csv_field(field, out);
+                    }
+                }
+                out.push(b'\n');
+            }
+            Self::Ndjson => {
+                serde_json::to_writer(&mut *out, &event)
+                    .map_err(|e| AppError::Serialization(e.to_string()))?;
+                out.push(b'\n');
+            }
+            Self::Parquet { writer, pending } => {
+                pending.push(event);
+                if pending.len() >= ROW_GROUP_SIZE {
+                    write_row_group(writer, pending)?;
+                    writer
+                        .flush()
+                        .map_err(|e| AppError::Serialization(e.to_string()))?;
+                    // Completed row groups can be sent before the footer is written.
+                    out.append(writer.inner_mut());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self, out: &mut Vec<u8>) -> Result<(), AppError> {
+        if let Self::Parquet {
+            mut writer,
+            mut pending,
+        } = self
+        {
+            if !pending.is_empty() {
+                write_row_group(&mut writer, &mut pending)?;
+            }
+            let mut rest = writer
+                .into_inner()
+                .map_err(|e| AppError::Serialization(e.to_string()))?;
+            out.append(&mut rest);
+        }
+        Ok(())
+    }
+}
+
+/// Writes `field` quoting it if needed, as described in RFC 4180.
+fn write_csv_field(field: &str, out: &mut Vec<u8>) {
+    if field.contains([',', '"', '\n', '\r']) {
+        out.push(b'"');
+        out.extend_from_slice(field.replace('"', "\"\"").as_bytes());
+        out.push(b'"');
+    } else {
+        out.extend_from_slice(field.as_bytes());
+    }
+}
+
+fn parquet_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("event_id", DataType::Utf8, false),
+        Field::new("code", DataType::Utf8, false),
+        Field::new(
+            "accessed_at",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            false,
+        ),
+        Field::new("user_agent", DataType::Utf8, true),
+        Field::new("ip_address", DataType::Utf8, true),
+        Field::new("referer", DataType::Utf8, true),
+    ]))
+}
+
+fn write_row_group(
+    writer: &mut ArrowWriter<Vec<u8>>,
+    pending: &mut Vec<StoredEvent>,
+) -> Result<(), AppError> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            pending.iter().map(|e| e.event_id.to_string()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            pending.iter().map(|e| e.code.as_str()),
+        )),
+        Arc::new(
+            TimestampMicrosecondArray::from_iter_values(
+                pending.iter().map(|e| e.accessed_at.timestamp_micros()),
+            )
+            .with_timezone("UTC"),
+        ),
+        Arc::new(
+            pending
+                .iter()
+                .map(|e| e.user_agent.as_deref())
+                .collect::<StringArray>(),
+        ),
+        Arc::new(
+            pending
+                .iter()
+                .map(|e| e.ip_address.as_deref())
+                .collect::<StringArray>(),
+        ),
+        Arc::new(
+            pending
+                .iter()
+                .map(|e| e.referer.as_deref())
+                .collect::<StringArray>(),
+        ),
+    ];
+
+    let batch = RecordBatch::try_new(parquet_schema(), columns)
+        .map_err(|e| AppError::Serialization(e.to_string()))?;
+    writer
+        .write(&batch)
+        .map_err(|e| AppError::Serialization(e.to_string()))?;
+
+    pending.clear();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::Array;
+    use parquet::{
+        arrow::arrow_reader::ParquetRecordBatchReaderBuilder, file::metadata::RowGroupMetaData,
+    };
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn event(i: usize, user_agent: Option<&str>) -> StoredEvent {
+        StoredEvent {
+            event_id: Uuid::new_v4(),
+            code: format!("c{i}"),
+            accessed_at: DateTime::from_timestamp_micros(
+                1_700_000_000_000_000 + i64::try_from(i).unwrap(),
+            )
+            .unwrap(),
+            user_agent: user_agent.map(str::to_string),
+            ip_address: Some("192.0.2.1".to_string()),
+            referer: None,
+        }
+    }
+
+    fn encode(format: ExportFormat, events: &[StoredEvent]) -> Vec<u8> {
+        let mut encoder = Encoder::new(format).unwrap();
+        let mut out = Vec::new();
+        encoder.start(&mut out);
+        for event in events {
+            encoder.encode(event.clone(), &mut out).unwrap();
+        }
+        encoder.finish(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn csv_has_a_header_and_quotes_special_fields() {
+        let events = [
+            event(0, Some("Mozilla/5.0")),
+            event(1, Some("a, \"quoted\"\nagent")),
+            event(2, None),
+        ];
+
+        let csv = String::from_utf8(encode(ExportFormat::Csv, &events)).unwrap();
+
+        let expected = format!(
+            "{CSV_HEADER}\
+             {},c0,{},Mozilla/5.0,192.0.2.1,\n\
+             {},c1,{},\"a, \"\"quoted\"\"\nagent\",192.0.2.1,\n\
+             {},c2,{},,192.0.2.1,\n",
+            events[0].event_id,
+            events[0].accessed_at.to_rfc3339(),
+            events[1].event_id,
+            events[1].accessed_at.to_rfc3339(),
+            events[2].event_id,
+            events[2].accessed_at.to_rfc3339(),
+        );
+        assert_eq!(csv, expected);
+    }
+
+    #[test]
+    fn csv_of_no_events_is_only_the_header() {
+        assert_eq!(encode(ExportFormat::Csv, &[]), CSV_HEADER.as_bytes());
+    }
+
+    #[test]
+    fn ndjson_has_one_event_per_line() {
+        let events = [event(0, Some("line\nbreak")), event(1, None)];
+
+        let ndjson = String::from_utf8(encode(ExportFormat::Ndjson, &events)).unwrap();
+
+        let lines: Vec<&str> = ndjson.lines().collect();
+        assert_eq!(lines.len(), events.len());
+        for (line, event) in lines.iter().zip(&events) {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value, serde_json::to_value(event).unwrap());
+        }
+        assert!(ndjson.ends_with('\n'));
+    }
+
+    #[test]
+    fn parquet_round_trips_in_row_groups() {
+        let events: Vec<StoredEvent> = (0..=ROW_GROUP_SIZE)
+            .map(|i| event(i, (i % 2 == 0).then_some("Mozilla/5.0")))
+            .collect();
+
+        let bytes = Bytes::from(encode(ExportFormat::Parquet, &events));
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(bytes).unwrap();
+        assert_eq!(builder.schema().fields(), parquet_schema().fields());
+        let row_groups: Vec<i64> = builder
+            .metadata()
+            .row_groups()
+            .iter()
+            .map(RowGroupMetaData::num_rows)
+            .collect();
+        assert_eq!(row_groups, [i64::try_from(ROW_GROUP_SIZE).unwrap(), 1]);
+
+        let batches: Vec<RecordBatch> = builder.build().unwrap().collect::<Result<_, _>>().unwrap();
+        let rows: usize = batches.iter().map(RecordBatch::num_rows).sum();
+        assert_eq!(rows, events.len());
+
+        let user_agents = batches[0].column_by_name("user_agent").unwrap();
+        assert!(user_agents.is_valid(0) && user_agents.is_null(1));
+
+        let last = batches.last().unwrap();
+        let row = last.num_rows() - 1;
+        let expected = events.last().unwrap();
+        let column = |name: &str| {
+            last.column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(column("event_id").value(row), expected.event_id.to_string());
+        assert_eq!(column("code").value(row), expected.code);
+        assert_eq!(column("user_agent").value(row), "Mozilla/5.0");
+        assert!(column("referer").is_null(row));
+        let accessed_at = last
+            .column_by_name("accessed_at")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap()
+            .value(row);
+        assert_eq!(accessed_at, expected.accessed_at.timestamp_micros());
+    }
+}
@@ -0,0 +1,199 @@
+//! Click analytics service.
+//!
+//! The `analytics-service` binary serves [`router`] on its own; the
+//! all-in-one binary mounts it next to shortener-service in one process.
+//! [`api`] holds the request and response bodies for clients.
+
+pub mod api;
+pub mod config;
+pub mod consumer;
+mod export;
+pub mod rebuild;
+pub mod repository;
+mod routes;
+pub mod stream;
+
+use std::sync::Arc;
+
+use axum::{Router, routing::get};
+use shortener_core::{
+    Broker,
+    config::{DatabaseConfig, RedisConfig},
+    openapi::docs_routes,
+};
+use sqlx::postgres::PgPoolOptions;
+use tracing::{error, info};
+use utoipa::OpenApi;
+
+pub use config::{AnalyticsStoreConfig, Config};
+use consumer::{AccessEventConsumer, EventConsumer, LinkEventConsumer};
+use repository::{AnalyticsStore, EventStore, InMemoryAnalyticsStore, RedisAnalyticsStore};
+use stream::ClickStream;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub analytics_store: Arc<dyn AnalyticsStore>,
+    pub event_store: Option<Arc<EventStore>>,
+    pub click_stream: ClickStream,
+}
+
+impl AppState {
+    /// State over `analytics_store` without an event store, buffering
+    /// `stream_buffer_size` clicks per real-time subscriber.
+    #[must_use]
+    pub fn new(analytics_store: Arc<dyn AnalyticsStore>, stream_buffer_size: usize) -> Self {
+        Self {
+            analytics_store,
+            event_store: None,
+            click_stream: ClickStream::new(stream_buffer_size),
+        }
+    }
+}
+
+/// Connects to the configured analytics store and, when configured, the
+/// event store, and migrates both.
+///
+/// # Errors
+///
+/// Returns an error if the configuration is invalid or a store cannot be
+/// reached or migrated.
+pub async fn connect(config: &Config) -> anyhow::Result<AppState> {
+    let analytics_store: Arc<dyn AnalyticsStore> = match config.analytics_store_config()? {
+        AnalyticsStoreConfig::Redis(redis_config) => {
+            Arc::new(connect_analytics_store(&redis_config).await?)
+        }
+        AnalyticsStoreConfig::InMemory => Arc::new(InMemoryAnalyticsStore::new()),
+    };
+
+    let event_store = match config.database_config() {
+        Some(db_config) => Some(Arc::new(connect_event_store(&db_config).await?)),
+        None => None,
+    };
+
+    Ok(AppState {
+        event_store,
+        ..AppState::new(analytics_store, config.stream_buffer_size)
+    })
+}
+
+/// Connects to Redis and migrates the analytics kept there to the current
+/// layout.
+///
+/// # Errors
+///
+/// Returns an error if Redis cannot be reached or migrated.
+pub async fn connect_analytics_store(config: &RedisConfig) -> anyhow::Result<RedisAnalyticsStore> {
+    let redis_client = redis::Client::open(config.url.expose())?;
+    let redis_store = RedisAnalyticsStore::new(redis_client);
+
+    let migrated = redis_store.migrate_code_index().await?;
+    if migrated > 0 {
+        info!(migrated, "Migrated analytics codes into the sorted index");
+    }
+
+    let ranked = redis_store.backfill_rankings().await?;
+    if ranked > 0 {
+        info!(ranked, "Backfilled all-time click ranking");
+    }
+
+    Ok(redis_store)
+}
+
+/// Connects to the Postgres event store and applies its migrations.
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be reached or migrated.
+pub async fn connect_event_store(config: &DatabaseConfig) -> anyhow::Result<EventStore> {
+    let db_pool = PgPoolOptions::new()
+        .max_connections(config.max_connections)
+        .connect(config.url.expose())
+        .await?;
+
+    sqlx::migrate!("./migrations").run(&db_pool).await?;
+
+    Ok(EventStore::new(db_pool))
+}
+
+/// Starts consuming access events and link events from `broker`, and
+/// following the clicks counted by every replica.
+///
+/// # Errors
+///
+/// Returns an error if the configuration is invalid or the queues cannot be
+/// declared or subscribed to.
+pub async fn spawn_consumers(
+    state: &AppState,
+    config: &Config,
+    broker: Arc<dyn Broker>,
+) -> anyhow::Result<()> {
+    let consumer = AccessEventConsumer::new(
+        Arc::clone(&broker),
+        &config.rabbitmq_queue,
+        &config.rabbitmq_routing_key,
+        Arc::clone(&state.analytics_store),
+        state.event_store.clone(),
+        config.click_thresholds()?,
+        config.event_codec.parse()?,
+    )
+    .await?;
+
+    state.click_stream.follow(broker.as_ref()).await?;
+
+    tokio::spawn(async move {
+        if let Err(e) = consumer.start_consuming().await {
+            error!("Consumer error: {:?}", e);
+        }
+    });
+
+    let link_consumer = LinkEventConsumer::new(
+        broker,
+        &config.link_events_queue,
+        Arc::clone(&state.analytics_store),
+        state.event_store.clone(),
+    )
+    .await?;
+
+    tokio::spawn(async move {
+        if let Err(e) = link_consumer.start_consuming().await {
+            error!("Link event consumer error: {:?}", e);
+        }
+    });
+
+    Ok(())
+}
+
+/// Routes of the HTTP API, without the health probes.
+pub fn api_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/v1/analytics", get(routes::list_analytics))
+        .route("/api/v1/analytics/top", get(routes::top_analytics))
+        .route("/api/v1/analytics/stream", get(routes::stream_all))
+        .route("/api/v1/analytics/ws", get(routes::stream_ws))
+        .route("/api/v1/analytics/{code}", get(routes::get_analytics))
+        .route("/api/v1/analytics/{code}/stream", get(routes::stream_code))
+        .route(
+            "/api/v1/analytics/{code}/events/export",
+            get(routes::export_events),
+        )
+        .route(
+            "/api/v1/admin/analytics/events/export",
+            get(routes::export_all_events),
+        )
+}
+
+/// The HTTP API together with the health probes, their [`openapi`] document
+/// and a Swagger UI.
+pub fn router(state: AppState) -> Router {
+    api_routes()
+        .route("/health", get(routes::health))
+        .route("/ready", get(routes::ready))
+        .merge(docs_routes(openapi()))
+        .with_state(state)
+}
+
+/// `OpenAPI` document of the HTTP API and the health probes.
+#[must_use]
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    routes::ApiDoc::openapi()
+}
@@ -1,24 +1,8 @@
-mod config;
-mod consumer;
-mod repository;
-mod routes;
-
-use std::sync::Arc;
-
-use axum::{Router, routing::get};
-use shortener_core::telemetry;
+use analytics_service::{Config, rebuild};
+use shortener_core::{broker, telemetry};
 use tower_http::trace::TraceLayer;
 use tracing::info;
 
-use config::Config;
-use consumer::{AccessEventConsumer, EventConsumer};
-use repository::AnalyticsRepository;
-
-#[derive(Clone)]
-pub struct AppState {
-    pub analytics_repository: Arc<AnalyticsRepository>,
-}
-
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenvy::dotenv().ok();
@@ -26,31 +10,20 @@ async fn main() -> anyhow::Result<()> {
     let config = Config::from_env()?;
     let _guard = telemetry::init_tracing(&config.observability_config(), "analytics-service")?;
 
-    let redis_config = config.redis_config();
-    let redis_client = redis::Client::open(redis_config.url.expose())?;
-    let analytics_repository = Arc::new(AnalyticsRepository::new(redis_client));
-
-    let consumer =
-        AccessEventConsumer::new(&config.rabbitmq_config(), Arc::clone(&analytics_repository))
-            .await?;
-
-    tokio::spawn(async move {
-        if let Err(e) = consumer.start_consuming().await {
-            tracing::error!("Consumer error: {:?}", e);
-        }
-    });
-
-    let state = AppState {
-        analytics_repository,
-    };
-
-    let app = Router::new()
-        .route("/health", get(routes::health))
-        .route("/ready", get(routes::ready))
-        .route("/api/v1/analytics", get(routes::list_analytics))
-        .route("/api/v1/analytics/{code}", get(routes::get_analytics))
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+    let state = analytics_service::connect(&config).await?;
+
+    if std::env::args().nth(1).as_deref() == Some("rebuild") {
+        let Some(event_store) = &state.event_store else {
+            anyhow::bail!("ANALYTICS_DATABASE_URL must be set to rebuild analytics");
+        };
+        rebuild::rebuild(event_store, state.analytics_store.as_ref()).await?;
+        return Ok(());
+    }
+
+    let broker = broker::connect(&config.broker_config()?).await?;
+    analytics_service::spawn_consumers(&state, &config, broker).await?;
+
+    let app = analytics_service::router(state).layer(TraceLayer::new_for_http());
 
     let addr = config.server_addr();
     info!("Starting analytics-service on {}", addr);
@@ -0,0 +1,63 @@
+//! Repopulates the analytics store from the Postgres event store.
+
+use std::pin::pin;
+
+use chrono::{TimeDelta, Utc};
+use futures_lite::StreamExt;
+use shortener_core::AppError;
+use tracing::{info, instrument};
+
+use crate::repository::{AnalyticsStore, EventStore};
+
+/// Codes whose totals are written to the analytics store at once.
+const BATCH_SIZE: usize = 1000;
+
+/// Hours of events replayed into the hourly ranking buckets. Older buckets
+/// have already expired.
+const HOURLY_LOOKBACK_HOURS: i64 = 24 * 8;
+
+/// Overwrites the per-code counters, the code index and the rankings in the
+/// analytics store with the totals computed from stored events.
+///
+/// Counters of codes without stored events are left untouched. Events
+/// consumed while the rebuild runs may be counted twice or not at all, so the
+/// consumer should be stopped first.
+///
+/// # Errors
+///
+/// Returns an error if reading events or writing analytics fails.
+#[instrument(skip(store, analytics))]
+pub async fn rebuild(store: &EventStore, analytics: &dyn AnalyticsStore) -> Result<(), AppError> {
+    let mut codes = 0;
+    let mut totals = pin!(store.totals());
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    while let Some(total) = totals.next().await {
+        batch.push(total?);
+        if batch.len() == BATCH_SIZE {
+            analytics.restore_totals(&batch).await?;
+            codes += batch.len();
+            batch.clear();
+        }
+    }
+    if !batch.is_empty() {
+        analytics.restore_totals(&batch).await?;
+        codes += batch.len();
+    }
+
+    let hourly = store
+        .hourly_counts(Utc::now() - TimeDelta::hours(HOURLY_LOOKBACK_HOURS))
+        .await?;
+    let mut hours = 0;
+    for bucket in hourly.chunk_by(|a, b| a.hour == b.hour) {
+        let counts: Vec<(String, i64)> = bucket
+            .iter()
+            .map(|c| (c.code.clone(), c.access_count))
+            .collect();
+        analytics.restore_hour(bucket[0].hour, &counts).await?;
+        hours += 1;
+    }
+
+    info!(codes, hours, "Rebuilt analytics from stored events");
+
+    Ok(())
+}
@@ -0,0 +1,231 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Datelike, Months, TimeZone, Utc};
+use futures_lite::{Stream, StreamExt};
+use serde::Serialize;
+use shortener_core::{AppError, messaging::AccessEvent};
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use super::Analytics;
+
+/// An access event as stored.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct StoredEvent {
+    pub event_id: Uuid,
+    pub code: String,
+    pub accessed_at: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub referer: Option<String>,
+}
+
+/// Number of clicks on a code during one UTC hour.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct HourlyCount {
+    pub hour: DateTime<Utc>,
+    pub code: String,
+    pub access_count: i64,
+}
+
+/// Durable store of raw access events in Postgres.
+pub struct EventStore {
+    pool: PgPool,
+    /// Monthly partitions known to exist.
+    partitions: Mutex<HashSet<String>>,
+}
+
+impl EventStore {
+    #[must_use]
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            partitions: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Creates the monthly partition holding `at` if it does not exist yet.
+    async fn ensure_partition(&self, at: DateTime<Utc>) -> Result<(), AppError> {
+        let start = Utc
+            .with_ymd_and_hms(at.year(), at.month(), 1, 0, 0, 0)
+            .single()
+            .ok_or_else(|| AppError::Internal(format!("Invalid event time {at}")))?;
+        let end = start
+            .checked_add_months(Months::new(1))
+            .ok_or_else(|| AppError::Internal(format!("Invalid event time {at}")))?;
+        let name = format!("access_events_{}", start.format("%Y%m"));
+
+        let mut partitions = self.partitions.lock().await;
+        if partitions.contains(&name) {
+            return Ok(());
+        }
+
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {name} PARTITION OF access_events \
+             FOR VALUES FROM ('{}') TO ('{}')",
+            start.to_rfc3339(),
+            end.to_rfc3339()
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        info!(partition = %name, "Ensured access event partition");
+        partitions.insert(name);
+
+        Ok(())
+    }
+
+    /// Stores `event`, returning `false` if it was already stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Database` if the query fails.
+    #[instrument(skip(self, event), fields(event_id = %event.event_id))]
+    pub async fn insert(&self, event: &AccessEvent) -> Result<bool, AppError> {
+        self.ensure_partition(event.accessed_at).await?;
+
+        let result = sqlx::query(
+            r"
+            INSERT INTO access_events (event_id, code, accessed_at, user_agent, ip_address, referer)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT DO NOTHING
+            ",
+        )
+        .bind(event.event_id)
+        .bind(&event.code)
+        .bind(event.accessed_at)
+        .bind(&event.user_agent)
+        .bind(&event.ip_address)
+        .bind(&event.referer)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Streams events accessed in `[from, to)`, oldest first, for `code` or
+    /// for every code when `code` is `None`.
+    pub fn events<'a>(
+        &'a self,
+        code: Option<&'a str>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Stream<Item = Result<StoredEvent, AppError>> + Send + 'a {
+        let query = match code {
+            Some(code) => sqlx::query_as::<_, StoredEvent>(
+                r"
+                SELECT event_id, code, accessed_at, user_agent, ip_address, referer
+                FROM access_events
+                WHERE code = $1 AND accessed_at >= $2 AND accessed_at < $3
+                ORDER BY accessed_at, event_id
+                ",
+            )
+            .bind(code),
+            None => sqlx::query_as::<_, StoredEvent>(
+                r"
+                SELECT event_id, code, accessed_at, user_agent, ip_address, referer
+                FROM access_events
+                WHERE accessed_at >= $1 AND accessed_at < $2
+                ORDER BY accessed_at, event_id
+                ",
+            ),
+        };
+
+        query
+            .bind(from)
+            .bind(to)
+            .fetch(&self.pool)
+            .map(|row| row.map_err(|e| AppError::Database(e.to_string())))
+    }
+
+    /// Deletes the stored event `event_id` accessed at `accessed_at`,
+    /// returning `false` if it was not stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Database` if the query fails.
+    #[instrument(skip(self))]
+    pub async fn delete(
+        &self,
+        event_id: Uuid,
+        accessed_at: DateTime<Utc>,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r"
+            DELETE FROM access_events
+            WHERE event_id = $1 AND accessed_at = $2
+            ",
+        )
+        .bind(event_id)
+        .bind(accessed_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Deletes every stored event of `code`, returning how many were deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Database` if the query fails.
+    #[instrument(skip(self))]
+    pub async fn delete_code(&self, code: &str) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r"
+            DELETE FROM access_events
+            WHERE code = $1
+            ",
+        )
+        .bind(code)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Streams the click totals of every code, ordered by code.
+    ///
+    /// The totals come from a single aggregation over the table, read as it
+    /// is consumed.
+    pub fn totals(&self) -> impl Stream<Item = Result<Analytics, AppError>> + Send + '_ {
+        sqlx::query_as::<_, Analytics>(
+            r"
+            SELECT code, COUNT(*) AS access_count, MAX(accessed_at) AS last_accessed_at
+            FROM access_events
+            GROUP BY code
+            ORDER BY code
+            ",
+        )
+        .fetch(&self.pool)
+        .map(|row| row.map_err(|e| AppError::Database(e.to_string())))
+    }
+
+    /// Returns per-hour click counts since `since`, ordered by hour.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Database` if the query fails.
+    #[instrument(skip(self))]
+    pub async fn hourly_counts(&self, since: DateTime<Utc>) -> Result<Vec<HourlyCount>, AppError> {
+        sqlx::query_as::<_, HourlyCount>(
+            r"
+            SELECT date_trunc('hour', accessed_at, 'UTC') AS hour, code, COUNT(*) AS access_count
+            FROM access_events
+            WHERE accessed_at >= $1
+            GROUP BY 1, 2
+            ORDER BY 1
+            ",
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, HashMap};
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
+use shortener_core::AppError;
+use tokio::sync::Mutex;
+
+use super::{
+    Analytics, AnalyticsStore, RANK_HOUR_RETENTION_HOURS, RankWindow, RankedCode, hour_start,
+    window_hours,
+};
+
+#[derive(Default)]
+struct State {
+    /// All-time counters by code.
+    totals: BTreeMap<String, Analytics>,
+    /// Click counts by code, bucketed by the start of their UTC hour.
+    hours: BTreeMap<DateTime<Utc>, HashMap<String, i64>>,
+}
+
+/// Analytics kept in process memory, for tests and single-process setups.
+///
+/// Nothing survives a restart.
+#[derive(Default)]
+pub struct InMemoryAnalyticsStore {
+    state: Mutex<State>,
+}
+
+impl InMemoryAnalyticsStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Returns whether the bucket of the hour starting at `hour` is past its retention.
+fn is_expired(hour: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    hour + TimeDelta::hours(1 + RANK_HOUR_RETENTION_HOURS) <= now
+}
+
+#[async_trait]
+impl AnalyticsStore for InMemoryAnalyticsStore {
+    async fn ping(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    async fn increment(&self, code: &str, accessed_at: DateTime<Utc>) -> Result<i64, AppError> {
+        let mut state = self.state.lock().await;
+
+        let analytics = state
+            .totals
+            .entry(code.to_string())
+            .or_insert_with(|| Analytics {
+                code: code.to_string(),
+                access_count: 0,
+                last_accessed_at: None,
+            });
+        analytics.access_count += 1;
+        analytics.last_accessed_at = Some(accessed_at);
+        let access_count = analytics.access_count;
+
+        *state
+            .hours
+            .entry(hour_start(accessed_at))
+            .or_default()
+            .entry(code.to_string())
+            .or_default() += 1;
+
+        let now = Utc::now();
+        state.hours.retain(|hour, _| !is_expired(*hour, now));
+
+        Ok(access_count)
+    }
+
+    async fn get(&self, code: &str) -> Result<Option<Analytics>, AppError> {
+        Ok(self.state.lock().await.totals.get(code).cloned())
+    }
+
+    async fn remove(&self, code: &str) -> Result<(), AppError> {
+        let mut state = self.state.lock().await;
+
+        state.totals.remove(code);
+        for counts in state.hours.values_mut() {
+            counts.remove(code);
+        }
+
+        Ok(())
+    }
+
+    async fn restore_totals(&self, totals: &[Analytics]) -> Result<(), AppError> {
+        let mut state = self.state.lock().await;
+
+        for analytics in totals {
+            let entry = state
+                .totals
+                .entry(analytics.code.clone())
+                .or_insert_with(|| analytics.clone());
+            entry.access_count = analytics.access_count;
+            if analytics.last_accessed_at.is_some() {
+                entry.last_accessed_at = analytics.last_accessed_at;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn restore_hour(
+        &self,
+        hour: DateTime<Utc>,
+        counts: &[(String, i64)],
+    ) -> Result<(), AppError> {
+        if is_expired(hour, Utc::now()) {
+            return Ok(());
+        }
+
+        let mut state = self.state.lock().await;
+        if counts.is_empty() {
+            state.hours.remove(&hour);
+        } else {
+            state.hours.insert(hour, counts.iter().cloned().collect());
+        }
+
+        Ok(())
+    }
+
+    async fn top(&self, window: RankWindow, limit: usize) -> Result<Vec<RankedCode>, AppError> {
+        let state = self.state.lock().await;
+
+        let mut counts: Vec<(String, i64)> = match window_hours(window) {
+            None => state
+                .totals
+                .values()
+                .map(|analytics| (analytics.code.clone(), analytics.access_count))
+                .collect(),
+            Some(hours) => {
+                let current = hour_start(Utc::now());
+                let mut sums: HashMap<String, i64> = HashMap::new();
+                for (_, bucket) in state
+                    .hours
+                    .range(current - TimeDelta::hours(hours - 1)..=current)
+                {
+                    for (code, count) in bucket {
+                        *sums.entry(code.clone()).or_default() += count;
+                    }
+                }
+                sums.into_iter().collect()
+            }
+        };
+
+        // Ties are ordered by code, descending, as in a Redis sorted set.
+        counts.sort_by(|(a_code, a_count), (b_code, b_count)| {
+            b_count.cmp(a_count).then_with(|| b_code.cmp(a_code))
+        });
+
+        Ok(counts
+            .into_iter()
+            .take(limit)
+            .enumerate()
+            .map(|(i, (code, access_count))| RankedCode {
+                rank: i + 1,
+                code,
+                access_count,
+            })
+            .collect())
+    }
+
+    async fn list(
+        &self,
+        after: Option<String>,
+        limit: usize,
+    ) -> Result<(Vec<Analytics>, usize), AppError> {
+        let state = self.state.lock().await;
+
+        let analytics = state
+            .totals
+            .values()
+            .filter(|analytics| {
+                after
+                    .as_deref()
+                    .is_none_or(|after| analytics.code.as_str() > after)
+            })
+            .take(limit)
+            .cloned()
+            .collect();
+
+        Ok((analytics, state.totals.len()))
+    }
+}
@@ -1,3 +1,81 @@
-mod analytics_repository;
+//! Storage of click analytics and raw access events.
+//!
+//! Counters and rankings live behind [`AnalyticsStore`], kept in Redis by
+//! [`RedisAnalyticsStore`] or in process memory by [`InMemoryAnalyticsStore`].
 
-pub use analytics_repository::{Analytics, AnalyticsRepository};
+mod event_store;
+mod memory;
+mod redis_store;
+
+use async_trait::async_trait;
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use shortener_core::AppError;
+
+pub use event_store::{EventStore, StoredEvent};
+pub use memory::InMemoryAnalyticsStore;
+pub use redis_store::RedisAnalyticsStore;
+pub use shortener_types::analytics::{Analytics, RankWindow, RankedCode};
+
+/// How many hours hourly buckets are kept after their hour ends.
+const RANK_HOUR_RETENTION_HOURS: i64 = 24 * 7;
+
+/// Hours of hourly buckets `window` sums, or `None` for the all-time ranking.
+fn window_hours(window: RankWindow) -> Option<i64> {
+    match window {
+        RankWindow::Day => Some(24),
+        RankWindow::Week => Some(24 * 7),
+        RankWindow::All => None,
+    }
+}
+
+fn window_name(window: RankWindow) -> &'static str {
+    match window {
+        RankWindow::Day => "24h",
+        RankWindow::Week => "7d",
+        RankWindow::All => "all",
+    }
+}
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait AnalyticsStore: Send + Sync {
+    /// Checks that the store can be reached.
+    async fn ping(&self) -> Result<(), AppError>;
+
+    /// Records a click on `code`, returning its new all-time count.
+    async fn increment(&self, code: &str, accessed_at: DateTime<Utc>) -> Result<i64, AppError>;
+
+    async fn get(&self, code: &str) -> Result<Option<Analytics>, AppError>;
+
+    /// Removes the counters of `code` and its entries in every ranking.
+    async fn remove(&self, code: &str) -> Result<(), AppError>;
+
+    /// Overwrites the counters and all-time ranking of the given codes.
+    async fn restore_totals(&self, totals: &[Analytics]) -> Result<(), AppError>;
+
+    /// Replaces the ranking bucket of the hour starting at `hour` with
+    /// `counts`. Buckets past their retention are skipped.
+    async fn restore_hour(
+        &self,
+        hour: DateTime<Utc>,
+        counts: &[(String, i64)],
+    ) -> Result<(), AppError>;
+
+    /// Returns the `limit` most clicked codes in `window`, most clicked first.
+    ///
+    /// Windowed rankings are built from hourly buckets, so they start at the
+    /// beginning of an hour.
+    async fn top(&self, window: RankWindow, limit: usize) -> Result<Vec<RankedCode>, AppError>;
+
+    /// Returns up to `limit` entries ordered by code, starting after the code
+    /// `after`, together with the total number of codes.
+    async fn list(
+        &self,
+        after: Option<String>,
+        limit: usize,
+    ) -> Result<(Vec<Analytics>, usize), AppError>;
+}
+
+fn hour_start(at: DateTime<Utc>) -> DateTime<Utc> {
+    at.duration_trunc(TimeDelta::hours(1)).unwrap_or(at)
+}
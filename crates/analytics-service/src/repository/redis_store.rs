@@ -0,0 +1,451 @@
+use async_trait::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
+use redis::{AsyncCommands, Client};
+use shortener_core::AppError;
+use tokio::sync::Mutex;
+use tracing::instrument;
+
+use super::{
+    Analytics, AnalyticsStore, RANK_HOUR_RETENTION_HOURS, RankWindow, RankedCode, hour_start,
+    window_hours, window_name,
+};
+
+const KEY_PREFIX_COUNT: &str = "access:count:";
+const KEY_PREFIX_LAST: &str = "access:last:";
+/// Set of accessed codes written by earlier versions, migrated into
+/// `KEY_CODE_INDEX` on startup.
+const KEY_LEGACY_CODES: &str = "access:codes";
+/// Accessed codes in a sorted set with equal scores, so members are ordered
+/// lexicographically and can be paged with `ZRANGEBYLEX`.
+const KEY_CODE_INDEX: &str = "access:code_index";
+/// All-time click counts by code.
+const KEY_RANK_ALL: &str = "access:rank:all";
+/// Click counts by code for one UTC hour, suffixed with `%Y%m%d%H`.
+const KEY_PREFIX_RANK_HOUR: &str = "access:rank:hour:";
+/// Cached union of the hourly buckets of a window, suffixed with the window.
+const KEY_PREFIX_RANK_WINDOW: &str = "access:rank:window:";
+
+/// How long a computed window ranking is reused.
+const RANK_WINDOW_CACHE_SECS: i64 = 60;
+const BACKFILL_BATCH_SIZE: isize = 1000;
+
+/// Analytics kept in Redis counters and sorted sets.
+pub struct RedisAnalyticsStore {
+    client: Client,
+    conn: Mutex<Option<redis::aio::MultiplexedConnection>>,
+}
+
+impl RedisAnalyticsStore {
+    #[must_use]
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            conn: Mutex::new(None),
+        }
+    }
+
+    async fn get_conn(&self) -> Result<redis::aio::MultiplexedConnection, AppError> {
+        let mut guard = self.conn.lock().await;
+        if guard.is_none() {
+            let conn = self
+                .client
+                .get_multiplexed_async_connection()
+                .await
+                .map_err(|e| AppError::Redis(e.to_string()))?;
+            *guard = Some(conn);
+        }
+        Ok(guard.clone().unwrap())
+    }
+
+    /// Moves codes from the legacy unordered set into the sorted code index.
+    ///
+    /// Returns the number of codes migrated.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Redis` if Redis cannot be reached.
+    #[instrument(skip(self))]
+    pub async fn migrate_code_index(&self) -> Result<usize, AppError> {
+        let mut conn = self.get_conn().await?;
+
+        let codes: Vec<String> = conn
+            .smembers(KEY_LEGACY_CODES)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+
+        if codes.is_empty() {
+            return Ok(0);
+        }
+
+        let members: Vec<(i64, &str)> = codes.iter().map(|code| (0, code.as_str())).collect();
+
+        redis::pipe()
+            .atomic()
+            .zadd_multiple(KEY_CODE_INDEX, &members)
+            .del(KEY_LEGACY_CODES)
+            .exec_async(&mut conn)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+
+        Ok(codes.len())
+    }
+
+    /// Builds the all-time ranking from the per-code counters if it does not
+    /// exist yet. Hourly buckets are not backfilled.
+    ///
+    /// Returns the number of codes ranked.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Redis` if Redis cannot be reached.
+    #[instrument(skip(self))]
+    pub async fn backfill_rankings(&self) -> Result<usize, AppError> {
+        let mut conn = self.get_conn().await?;
+
+        let exists: bool = conn
+            .exists(KEY_RANK_ALL)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+        if exists {
+            return Ok(0);
+        }
+
+        let mut ranked = 0;
+        let mut min = "-".to_string();
+        loop {
+            let codes: Vec<String> = conn
+                .zrangebylex_limit(KEY_CODE_INDEX, &min, "+", 0, BACKFILL_BATCH_SIZE)
+                .await
+                .map_err(|e| AppError::Redis(e.to_string()))?;
+            let Some(last) = codes.last() else {
+                break;
+            };
+            min = format!("({last}");
+
+            let count_keys: Vec<String> = codes
+                .iter()
+                .map(|code| format!("{KEY_PREFIX_COUNT}{code}"))
+                .collect();
+            let counts: Vec<Option<i64>> = redis::cmd("MGET")
+                .arg(&count_keys)
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| AppError::Redis(e.to_string()))?;
+
+            let members: Vec<(i64, &str)> = counts
+                .into_iter()
+                .zip(&codes)
+                .filter_map(|(count, code)| Some((count?, code.as_str())))
+                .collect();
+            if !members.is_empty() {
+                conn.zadd_multiple::<_, _, _, ()>(KEY_RANK_ALL, &members)
+                    .await
+                    .map_err(|e| AppError::Redis(e.to_string()))?;
+                ranked += members.len();
+            }
+        }
+
+        Ok(ranked)
+    }
+}
+
+#[async_trait]
+impl AnalyticsStore for RedisAnalyticsStore {
+    #[instrument(skip(self))]
+    async fn ping(&self) -> Result<(), AppError> {
+        let mut conn = self.get_conn().await?;
+        redis::cmd("PING")
+            .query_async::<String>(&mut conn)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn increment(&self, code: &str, accessed_at: DateTime<Utc>) -> Result<i64, AppError> {
+        let mut conn = self.get_conn().await?;
+
+        let count_key = format!("{KEY_PREFIX_COUNT}{code}");
+        let last_key = format!("{KEY_PREFIX_LAST}{code}");
+        let hour = hour_start(accessed_at);
+        let hour_key = rank_hour_key(hour);
+        let hour_expires_at = rank_hour_expires_at(hour);
+
+        let (access_count,): (i64,) = redis::pipe()
+            .atomic()
+            .incr(&count_key, 1i64)
+            .set(&last_key, accessed_at.to_rfc3339())
+            .ignore()
+            .zadd(KEY_CODE_INDEX, code, 0)
+            .ignore()
+            .zincr(KEY_RANK_ALL, code, 1i64)
+            .ignore()
+            .zincr(&hour_key, code, 1i64)
+            .ignore()
+            .expire_at(&hour_key, hour_expires_at)
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+
+        Ok(access_count)
+    }
+
+    #[instrument(skip(self))]
+    async fn get(&self, code: &str) -> Result<Option<Analytics>, AppError> {
+        let mut conn = self.get_conn().await?;
+
+        let count_key = format!("{KEY_PREFIX_COUNT}{code}");
+        let last_key = format!("{KEY_PREFIX_LAST}{code}");
+
+        let count: Option<i64> = conn
+            .get(&count_key)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+
+        let Some(access_count) = count else {
+            return Ok(None);
+        };
+
+        let last: Option<String> = conn
+            .get(&last_key)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+
+        Ok(Some(Analytics {
+            code: code.to_string(),
+            access_count,
+            last_accessed_at: parse_timestamp(last),
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn remove(&self, code: &str) -> Result<(), AppError> {
+        let mut conn = self.get_conn().await?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .del(&[
+                format!("{KEY_PREFIX_COUNT}{code}"),
+                format!("{KEY_PREFIX_LAST}{code}"),
+            ])
+            .zrem(KEY_CODE_INDEX, code)
+            .zrem(KEY_RANK_ALL, code);
+        for window in [RankWindow::Day, RankWindow::Week] {
+            pipe.zrem(
+                format!("{KEY_PREFIX_RANK_WINDOW}{}", window_name(window)),
+                code,
+            );
+        }
+        let current = hour_start(Utc::now());
+        for i in 0..=RANK_HOUR_RETENTION_HOURS {
+            pipe.zrem(rank_hour_key(current - TimeDelta::hours(i)), code);
+        }
+
+        pipe.exec_async(&mut conn)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))
+    }
+
+    #[instrument(skip(self, totals), fields(codes = totals.len()))]
+    async fn restore_totals(&self, totals: &[Analytics]) -> Result<(), AppError> {
+        let mut conn = self.get_conn().await?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for analytics in totals {
+            let code = &analytics.code;
+            pipe.set(format!("{KEY_PREFIX_COUNT}{code}"), analytics.access_count)
+                .zadd(KEY_CODE_INDEX, code, 0)
+                .zadd(KEY_RANK_ALL, code, analytics.access_count);
+            if let Some(last) = analytics.last_accessed_at {
+                pipe.set(format!("{KEY_PREFIX_LAST}{code}"), last.to_rfc3339());
+            }
+        }
+
+        pipe.exec_async(&mut conn)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))
+    }
+
+    #[instrument(skip(self, counts), fields(codes = counts.len()))]
+    async fn restore_hour(
+        &self,
+        hour: DateTime<Utc>,
+        counts: &[(String, i64)],
+    ) -> Result<(), AppError> {
+        let expires_at = rank_hour_expires_at(hour);
+        if expires_at <= Utc::now().timestamp() {
+            return Ok(());
+        }
+
+        let mut conn = self.get_conn().await?;
+
+        let key = rank_hour_key(hour);
+        let members: Vec<(i64, &str)> = counts
+            .iter()
+            .map(|(code, count)| (*count, code.as_str()))
+            .collect();
+
+        let mut pipe = redis::pipe();
+        pipe.atomic().del(&key);
+        if !members.is_empty() {
+            pipe.zadd_multiple(&key, &members)
+                .expire_at(&key, expires_at);
+        }
+
+        pipe.exec_async(&mut conn)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))
+    }
+
+    #[instrument(skip(self))]
+    async fn top(&self, window: RankWindow, limit: usize) -> Result<Vec<RankedCode>, AppError> {
+        let mut conn = self.get_conn().await?;
+
+        let key = match window_hours(window) {
+            None => KEY_RANK_ALL.to_string(),
+            // Windowed rankings are cached for a minute.
+            Some(hours) => {
+                let key = format!("{KEY_PREFIX_RANK_WINDOW}{}", window_name(window));
+                let cached: bool = conn
+                    .exists(&key)
+                    .await
+                    .map_err(|e| AppError::Redis(e.to_string()))?;
+
+                if !cached {
+                    let current = hour_start(Utc::now());
+                    let buckets: Vec<String> = (0..hours)
+                        .map(|i| rank_hour_key(current - TimeDelta::hours(i)))
+                        .collect();
+
+                    redis::pipe()
+                        .atomic()
+                        .zunionstore(&key, &buckets)
+                        .expire(&key, RANK_WINDOW_CACHE_SECS)
+                        .exec_async(&mut conn)
+                        .await
+                        .map_err(|e| AppError::Redis(e.to_string()))?;
+                }
+                key
+            }
+        };
+
+        let stop = isize::try_from(limit).unwrap_or(isize::MAX) - 1;
+        let entries: Vec<(String, i64)> = conn
+            .zrevrange_withscores(&key, 0, stop)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+
+        Ok(entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, (code, access_count))| RankedCode {
+                rank: i + 1,
+                code,
+                access_count,
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn list(
+        &self,
+        after: Option<String>,
+        limit: usize,
+    ) -> Result<(Vec<Analytics>, usize), AppError> {
+        let mut conn = self.get_conn().await?;
+
+        let min = after.map_or_else(|| "-".to_string(), |code| format!("({code}"));
+        let count = isize::try_from(limit).unwrap_or(isize::MAX);
+
+        let (codes, total): (Vec<String>, usize) = redis::pipe()
+            .zrangebylex_limit(KEY_CODE_INDEX, min, "+", 0, count)
+            .zcard(KEY_CODE_INDEX)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+
+        if codes.is_empty() {
+            return Ok((Vec::new(), total));
+        }
+
+        let count_keys: Vec<String> = codes
+            .iter()
+            .map(|code| format!("{KEY_PREFIX_COUNT}{code}"))
+            .collect();
+        let last_keys: Vec<String> = codes
+            .iter()
+            .map(|code| format!("{KEY_PREFIX_LAST}{code}"))
+            .collect();
+
+        let (counts, lasts): (Vec<Option<i64>>, Vec<Option<String>>) = redis::pipe()
+            .cmd("MGET")
+            .arg(&count_keys)
+            .cmd("MGET")
+            .arg(&last_keys)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::Redis(e.to_string()))?;
+
+        Ok((page_rows(codes, counts, lasts), total))
+    }
+}
+
+/// Pairs a page of indexed codes with their counters.
+///
+/// A code whose count key is gone (cleared between the two round trips)
+/// still gets a row with a zero count: dropping it would cut the page short
+/// and move the next cursor back before codes already returned.
+fn page_rows(
+    codes: Vec<String>,
+    counts: Vec<Option<i64>>,
+    lasts: Vec<Option<String>>,
+) -> Vec<Analytics> {
+    codes
+        .into_iter()
+        .zip(counts.into_iter().zip(lasts))
+        .map(|(code, (count, last))| Analytics {
+            code,
+            access_count: count.unwrap_or(0),
+            last_accessed_at: parse_timestamp(last),
+        })
+        .collect()
+}
+
+/// Returns when the ranking bucket of the hour starting at `hour` expires,
+/// as a Unix timestamp.
+fn rank_hour_expires_at(hour: DateTime<Utc>) -> i64 {
+    (hour + TimeDelta::hours(1 + RANK_HOUR_RETENTION_HOURS)).timestamp()
+}
+
+fn rank_hour_key(hour: DateTime<Utc>) -> String {
+    format!("{KEY_PREFIX_RANK_HOUR}{}", hour.format("%Y%m%d%H"))
+}
+
+fn parse_timestamp(value: Option<String>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_rows_keep_codes_without_counts() {
+        let rows = page_rows(
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            vec![Some(3), None, Some(1)],
+            vec![Some("2026-01-02T03:04:05Z".to_string()), None, None],
+        );
+
+        let codes: Vec<_> = rows.iter().map(|row| row.code.as_str()).collect();
+        assert_eq!(codes, ["a", "b", "c"]);
+        let counts: Vec<_> = rows.iter().map(|row| row.access_count).collect();
+        assert_eq!(counts, [3, 0, 1]);
+        assert!(rows[0].last_accessed_at.is_some());
+        assert!(rows[1].last_accessed_at.is_none());
+    }
+}
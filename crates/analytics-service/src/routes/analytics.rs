@@ -2,37 +2,37 @@ use axum::{
     Json,
     extract::{Path, Query, State},
 };
-use serde::{Deserialize, Serialize};
-use shortener_core::AppError;
+use shortener_core::{
+    AppError, Page,
+    error::ErrorResponse,
+    pagination::{clamp_limit, decode_cursor},
+};
 use tracing::instrument;
 
 use crate::{AppState, repository::Analytics};
 
-#[derive(Debug, Deserialize)]
-pub struct ListAnalyticsQuery {
-    #[serde(default = "default_limit")]
-    pub limit: usize,
-    #[serde(default)]
-    pub offset: usize,
-}
-
-fn default_limit() -> usize {
-    20
-}
-
-#[derive(Debug, Serialize)]
-pub struct AnalyticsListResponse {
-    pub items: Vec<Analytics>,
-    pub total: usize,
-}
+pub use shortener_types::analytics::{
+    AnalyticsListResponse, ListAnalyticsQuery, TopAnalyticsQuery, TopAnalyticsResponse,
+};
 
+/// Returns the click counters of a code.
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics/{code}",
+    tag = "analytics",
+    params(("code" = String, Path, description = "Short code of the link")),
+    responses(
+        (status = 200, description = "Counters of the code", body = Analytics),
+        (status = 404, description = "The code has no recorded clicks", body = ErrorResponse),
+    )
+)]
 #[instrument(skip(state))]
 pub async fn get_analytics(
     State(state): State<AppState>,
     Path(code): Path<String>,
 ) -> Result<Json<Analytics>, AppError> {
     let analytics = state
-        .analytics_repository
+        .analytics_store
         .get(&code)
         .await?
         .ok_or_else(|| AppError::NotFound(format!("Analytics for code '{code}' not found")))?;
@@ -40,15 +40,54 @@ pub async fn get_analytics(
     Ok(Json(analytics))
 }
 
+/// Lists the click counters of every code, ordered by code.
+///
+/// Codes are compared byte by byte, so the order does not follow click
+/// counts; `GET /api/v1/analytics/top` ranks codes by clicks.
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics",
+    tag = "analytics",
+    params(ListAnalyticsQuery),
+    responses(
+        (status = 200, description = "A page of counters", body = AnalyticsListResponse),
+        (status = 400, description = "The cursor is invalid", body = ErrorResponse),
+    )
+)]
 #[instrument(skip(state))]
 pub async fn list_analytics(
     State(state): State<AppState>,
     Query(query): Query<ListAnalyticsQuery>,
 ) -> Result<Json<AnalyticsListResponse>, AppError> {
-    let (items, total) = state
-        .analytics_repository
-        .list(query.limit, query.offset)
+    let after: Option<String> = query.cursor.as_deref().map(decode_cursor).transpose()?;
+    let limit = clamp_limit(query.limit);
+
+    let (items, total) = state.analytics_store.list(after, limit + 1).await?;
+    let page = Page::from_overfetch(items, limit, |a| a.code.clone());
+
+    Ok(Json(AnalyticsListResponse { page, total }))
+}
+
+/// Returns the most clicked codes in a period, most clicked first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics/top",
+    tag = "analytics",
+    params(TopAnalyticsQuery),
+    responses((status = 200, description = "The ranking", body = TopAnalyticsResponse))
+)]
+#[instrument(skip(state))]
+pub async fn top_analytics(
+    State(state): State<AppState>,
+    Query(query): Query<TopAnalyticsQuery>,
+) -> Result<Json<TopAnalyticsResponse>, AppError> {
+    let items = state
+        .analytics_store
+        .top(query.window, clamp_limit(query.limit))
         .await?;
 
-    Ok(Json(AnalyticsListResponse { items, total }))
+    Ok(Json(TopAnalyticsResponse {
+        window: query.window,
+        items,
+    }))
 }
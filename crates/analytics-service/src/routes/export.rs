@@ -0,0 +1,163 @@
+use std::fmt::Write;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use shortener_core::{AppError, error::ErrorResponse};
+use tracing::instrument;
+
+use crate::{AppState, export::export_body};
+
+pub use shortener_types::analytics::ExportQuery;
+
+/// Downloads the stored access events of a code.
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics/{code}/events/export",
+    tag = "export",
+    params(("code" = String, Path, description = "Short code of the link"), ExportQuery),
+    responses(
+        (status = 200, description = "The events as an attachment in the requested format",
+            content(
+                (String = "text/csv"),
+                (String = "application/x-ndjson"),
+                (String = "application/vnd.apache.parquet"),
+            )),
+        (status = 400, description = "The range or format is invalid", body = ErrorResponse),
+        (status = 503, description = "Event storage is not configured", body = ErrorResponse),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn export_events(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, AppError> {
+    let filename = format!("{code}-events.{}", query.format.extension());
+    export(&state, Some(code), &query, &filename)
+}
+
+/// Downloads the stored access events of every code.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/analytics/events/export",
+    tag = "export",
+    params(ExportQuery),
+    responses(
+        (status = 200, description = "The events as an attachment in the requested format",
+            content(
+                (String = "text/csv"),
+                (String = "application/x-ndjson"),
+                (String = "application/vnd.apache.parquet"),
+            )),
+        (status = 400, description = "The range or format is invalid", body = ErrorResponse),
+        (status = 503, description = "Event storage is not configured", body = ErrorResponse),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn export_all_events(
+    State(state): State<AppState>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, AppError> {
+    let filename = format!("events.{}", query.format.extension());
+    export(&state, None, &query, &filename)
+}
+
+fn export(
+    state: &AppState,
+    code: Option<String>,
+    query: &ExportQuery,
+    filename: &str,
+) -> Result<Response, AppError> {
+    let event_store = state.event_store.clone().ok_or_else(|| {
+        AppError::ServiceUnavailable("Event storage is not configured".to_string())
+    })?;
+
+    let from = query.from.unwrap_or(DateTime::UNIX_EPOCH);
+    let to = query.to.unwrap_or_else(Utc::now);
+    if from >= to {
+        return Err(AppError::BadRequest(
+            "'from' must be earlier than 'to'".to_string(),
+        ));
+    }
+
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                query.format.content_type().to_string(),
+            ),
+            (header::CONTENT_DISPOSITION, content_disposition(filename)),
+        ],
+        export_body(event_store, code, from, to, query.format),
+    )
+        .into_response())
+}
+
+/// Returns an attachment `Content-Disposition` for `filename`.
+///
+/// The path code may hold any character, so `filename` carries a copy with
+/// everything but `[A-Za-z0-9._-]` replaced by `_`, and `filename*` the exact
+/// name percent-encoded as described in RFC 8187.
+fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let mut encoded = String::with_capacity(filename.len());
+    for byte in filename.bytes() {
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+
+    format!("attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_disposition_escapes_the_filename() {
+        for (filename, expected) in [
+            (
+                "abc123-events.csv",
+                "attachment; filename=\"abc123-events.csv\"; filename*=UTF-8''abc123-events.csv",
+            ),
+            (
+                "a\"b\\c;d-events.csv",
+                "attachment; filename=\"a_b_c_d-events.csv\"; \
+                 filename*=UTF-8''a%22b%5Cc%3Bd-events.csv",
+            ),
+            (
+                "a\r\nX-Injected: 1-events.csv",
+                "attachment; filename=\"a__X-Injected__1-events.csv\"; \
+                 filename*=UTF-8''a%0D%0AX-Injected%3A%201-events.csv",
+            ),
+            (
+                "café-events.csv",
+                "attachment; filename=\"caf_-events.csv\"; filename*=UTF-8''caf%C3%A9-events.csv",
+            ),
+        ] {
+            let header = content_disposition(filename);
+            assert_eq!(header, expected, "{filename:?}");
+            assert!(
+                header::HeaderValue::from_str(&header).is_ok(),
+                "{filename:?}"
+            );
+        }
+    }
+}
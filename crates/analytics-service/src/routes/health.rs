@@ -1,51 +1,54 @@
 use axum::{Json, extract::State, http::StatusCode};
-use serde::Serialize;
 use tracing::instrument;
 
 use crate::AppState;
 
-#[derive(Serialize)]
-pub struct HealthResponse {
-    status: &'static str,
-    service_name: &'static str,
-}
-
-#[derive(Serialize)]
-pub struct ReadyResponse {
-    status: &'static str,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    redis: Option<&'static str>,
-}
+pub use shortener_types::analytics::{HealthResponse, ReadyResponse};
 
 /// Liveness probe - プロセスが生きているか確認
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses((status = 200, description = "The process is running", body = HealthResponse))
+)]
 #[instrument]
 pub async fn health() -> Json<HealthResponse> {
     Json(HealthResponse {
-        status: "ok",
-        service_name: env!("CARGO_PKG_NAME"),
+        status: "ok".to_string(),
+        service_name: env!("CARGO_PKG_NAME").to_string(),
     })
 }
 
 /// Readiness probe - トラフィックを受け入れられるか確認
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "Redis is reachable", body = ReadyResponse),
+        (status = 503, description = "Redis is unreachable", body = ReadyResponse),
+    )
+)]
 #[instrument(skip(state))]
 pub async fn ready(State(state): State<AppState>) -> (StatusCode, Json<ReadyResponse>) {
     // Check Redis connection
-    let redis_ok = state.analytics_repository.ping().await.is_ok();
+    let redis_ok = state.analytics_store.ping().await.is_ok();
 
     if redis_ok {
         (
             StatusCode::OK,
             Json(ReadyResponse {
-                status: "ready",
-                redis: Some("ok"),
+                status: "ready".to_string(),
+                redis: Some("ok".to_string()),
             }),
         )
     } else {
         (
             StatusCode::SERVICE_UNAVAILABLE,
             Json(ReadyResponse {
-                status: "not ready",
-                redis: Some("unavailable"),
+                status: "not ready".to_string(),
+                redis: Some("unavailable".to_string()),
             }),
         )
     }
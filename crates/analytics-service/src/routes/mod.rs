@@ -1,5 +1,14 @@
 mod analytics;
+mod export;
 mod health;
+mod openapi;
+mod stream;
 
-pub use analytics::{get_analytics, list_analytics};
-pub use health::{health, ready};
+pub use analytics::{
+    AnalyticsListResponse, ListAnalyticsQuery, TopAnalyticsQuery, TopAnalyticsResponse,
+    get_analytics, list_analytics, top_analytics,
+};
+pub use export::{ExportQuery, export_all_events, export_events};
+pub use health::{HealthResponse, ReadyResponse, health, ready};
+pub use openapi::ApiDoc;
+pub use stream::{stream_all, stream_code, stream_ws};
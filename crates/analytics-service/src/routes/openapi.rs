@@ -0,0 +1,30 @@
+use utoipa::OpenApi;
+
+use super::{analytics, export, health, stream};
+use crate::stream::{Click, StreamMessage};
+
+/// `OpenAPI` document of the HTTP API and the health probes.
+#[derive(OpenApi)]
+#[openapi(
+    info(description = "Click analytics service"),
+    paths(
+        analytics::list_analytics,
+        analytics::top_analytics,
+        analytics::get_analytics,
+        stream::stream_all,
+        stream::stream_code,
+        stream::stream_ws,
+        export::export_events,
+        export::export_all_events,
+        health::health,
+        health::ready,
+    ),
+    components(schemas(Click, StreamMessage)),
+    tags(
+        (name = "analytics", description = "Click counters and rankings"),
+        (name = "stream", description = "Real-time clicks"),
+        (name = "export", description = "Stored access events"),
+        (name = "health", description = "Liveness and readiness probes"),
+    )
+)]
+pub struct ApiDoc;
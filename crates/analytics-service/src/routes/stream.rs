@@ -0,0 +1,123 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{
+        Path, Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures_lite::{Stream, StreamExt};
+use serde::Deserialize;
+use tracing::{instrument, warn};
+use utoipa::IntoParams;
+
+use crate::{AppState, stream::StreamMessage};
+
+/// Response description of the Server-Sent Events routes.
+const SSE_DESCRIPTION: &str =
+    "`click` events carrying a `Click` and `lagged` events carrying a `StreamMessage`";
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StreamQuery {
+    /// Only clicks on this code; every code when omitted.
+    pub code: Option<String>,
+}
+
+/// Server-Sent Events stream of clicks on `code`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics/{code}/stream",
+    tag = "stream",
+    params(("code" = String, Path, description = "Short code of the link")),
+    responses((status = 200, description = SSE_DESCRIPTION, content_type = "text/event-stream", body = String))
+)]
+#[instrument(skip(state))]
+pub async fn stream_code(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    sse(state.click_stream.subscribe(Some(code)))
+}
+
+/// Server-Sent Events stream of clicks on every code.
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics/stream",
+    tag = "stream",
+    responses((status = 200, description = SSE_DESCRIPTION, content_type = "text/event-stream", body = String))
+)]
+#[instrument(skip(state))]
+pub async fn stream_all(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    sse(state.click_stream.subscribe(None))
+}
+
+/// WebSocket stream of clicks, optionally limited to one code.
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics/ws",
+    tag = "stream",
+    params(StreamQuery),
+    responses((status = 101, description = "Switched to a WebSocket that sends each `StreamMessage` as a JSON text frame"))
+)]
+#[instrument(skip(state, ws))]
+pub async fn stream_ws(
+    State(state): State<AppState>,
+    Query(query): Query<StreamQuery>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let messages = state.click_stream.subscribe(query.code);
+    ws.on_upgrade(move |socket| forward(socket, messages))
+}
+
+fn sse(
+    messages: impl Stream<Item = StreamMessage> + Send + 'static,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events = messages.map(|message| {
+        let event = match &message {
+            StreamMessage::Click(click) => Event::default().event("click").json_data(click),
+            StreamMessage::Lagged { .. } => Event::default().event("lagged").json_data(&message),
+        };
+        Ok(event.unwrap_or_else(|_| Event::default().comment("unserializable message")))
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Sends `messages` to `socket` as JSON text frames until either side closes.
+async fn forward(mut socket: WebSocket, messages: impl Stream<Item = StreamMessage> + Send) {
+    let mut messages = std::pin::pin!(messages);
+
+    loop {
+        tokio::select! {
+            message = messages.next() => {
+                let Some(message) = message else {
+                    break;
+                };
+                let text = match serde_json::to_string(&message) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        warn!("Failed to serialize stream message: {:?}", e);
+                        continue;
+                    }
+                };
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => {
+                // Incoming messages are ignored; pings are answered by axum.
+                match incoming {
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Fan-out of processed clicks to real-time subscribers.
+
+use futures_lite::{Stream, stream};
+use shortener_core::{
+    AppError, Broker,
+    messaging::{ClickCounted, envelope, routing_keys},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{error, warn};
+
+pub use shortener_types::analytics::{Click, StreamMessage};
+
+/// Broadcasts clicks to subscribers of a single code or of every code.
+///
+/// Clicks are counted by whichever replica consumes them, so each replica
+/// [follows](Self::follow) the clicks counted by all of them.
+///
+/// Each subscriber has a bounded buffer. Subscribers that do not keep up lose
+/// the oldest clicks and are told how many were skipped, so a slow client
+/// never holds up the consumer.
+#[derive(Debug, Clone)]
+pub struct ClickStream {
+    sender: broadcast::Sender<Click>,
+}
+
+impl ClickStream {
+    /// Creates a stream buffering up to `capacity` clicks per subscriber.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// Sends `click` to the current subscribers.
+    pub fn publish(&self, click: Click) {
+        // Having no subscribers is not an error.
+        let _ = self.sender.send(click);
+    }
+
+    /// Starts publishing every click counted by any replica, as announced
+    /// on `broker`, through a subscription of this replica's own.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the subscription cannot be set up.
+    pub async fn follow(&self, broker: &dyn Broker) -> Result<(), AppError> {
+        let mut subscription = broker
+            .subscribe_broadcast(&[routing_keys::CLICK_COUNTED])
+            .await?;
+
+        let click_stream = self.clone();
+        tokio::spawn(async move {
+            while let Some(delivery) = subscription.next().await {
+                let counted = delivery
+                    .codec()
+                    .and_then(|codec| envelope::decode::<ClickCounted>(codec, &delivery.payload));
+                match counted {
+                    Ok(counted) => click_stream.publish(Click {
+                        event_id: counted.event_id,
+                        code: counted.code,
+                        accessed_at: counted.accessed_at,
+                        access_count: counted.access_count,
+                    }),
+                    // Clicks are only announced, so a lost one is not worth
+                    // keeping around.
+                    Err(e) => warn!("Failed to deserialize counted click: {:?}", e),
+                }
+
+                if let Err(e) = delivery.ack().await {
+                    error!("Failed to ack counted click: {:?}", e);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Returns the messages for clicks on `code`, or on every code if `None`.
+    pub fn subscribe(
+        &self,
+        code: Option<String>,
+    ) -> impl Stream<Item = StreamMessage> + Send + use<> {
+        let receiver = self.sender.subscribe();
+
+        stream::unfold((receiver, code), |(mut receiver, code)| async move {
+            loop {
+                let message = match receiver.recv().await {
+                    Ok(click) if code.as_ref().is_none_or(|c| *c == click.code) => {
+                        StreamMessage::Click(click)
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => StreamMessage::Lagged { skipped },
+                    Err(RecvError::Closed) => return None,
+                };
+                return Some((message, (receiver, code)));
+            }
+        })
+    }
+}
@@ -0,0 +1,205 @@
+//! Storage of access events in Postgres and rebuilding counters from them.
+//!
+//! Needs a Postgres database in `ANALYTICS_DATABASE_URL`, and is skipped
+//! without one. Other runs may have stored events too, so each test uses
+//! codes of its own.
+
+use std::{pin::pin, sync::Arc, time::Duration};
+
+use analytics_service::{
+    connect_event_store,
+    consumer::{AccessEventConsumer, EventConsumer},
+    rebuild::rebuild,
+    repository::{AnalyticsStore, EventStore, InMemoryAnalyticsStore, RankWindow},
+    stream::{ClickStream, StreamMessage},
+};
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use futures_lite::StreamExt;
+use shortener_core::{
+    Broker,
+    broker::InProcessBroker,
+    config::{DatabaseConfig, SecretString},
+    messaging::{AccessEvent, Codec, envelope},
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Returns the event store and a pool to inspect it, or `None` without a
+/// database.
+async fn event_store() -> Option<(EventStore, PgPool)> {
+    let url = std::env::var("ANALYTICS_DATABASE_URL").ok()?;
+    let event_store = connect_event_store(&DatabaseConfig {
+        url: SecretString::new(url.as_str()),
+        max_connections: 2,
+    })
+    .await
+    .unwrap();
+    let pool = PgPool::connect(&url).await.unwrap();
+    Some((event_store, pool))
+}
+
+/// Returns a code no other test uses.
+fn unique_code() -> String {
+    Uuid::new_v4().simple().to_string()[..10].to_string()
+}
+
+fn event(code: &str, accessed_at: DateTime<Utc>) -> AccessEvent {
+    AccessEvent {
+        accessed_at,
+        ..AccessEvent::new(code.to_string(), None, None, None)
+    }
+}
+
+/// Returns the partition `event_id` is stored in.
+async fn partition_of(pool: &PgPool, event_id: Uuid) -> String {
+    sqlx::query_scalar("SELECT tableoid::regclass::text FROM access_events WHERE event_id = $1")
+        .bind(event_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn events_are_stored_in_the_partition_of_their_month() {
+    let Some((store, pool)) = event_store().await else {
+        return;
+    };
+    let code = unique_code();
+    let month_start = Utc.with_ymd_and_hms(2091, 2, 1, 0, 0, 0).unwrap();
+
+    for (accessed_at, partition) in [
+        (
+            month_start - TimeDelta::microseconds(1),
+            "access_events_209101",
+        ),
+        (month_start, "access_events_209102"),
+        (
+            Utc.with_ymd_and_hms(2091, 12, 31, 23, 59, 59).unwrap(),
+            "access_events_209112",
+        ),
+    ] {
+        let event = event(&code, accessed_at);
+        assert!(store.insert(&event).await.unwrap());
+        assert_eq!(partition_of(&pool, event.event_id).await, partition);
+    }
+
+    // A new store does not know the partitions exist yet.
+    let (store, _) = event_store().await.unwrap();
+    assert!(store.insert(&event(&code, month_start)).await.unwrap());
+
+    assert_eq!(store.delete_code(&code).await.unwrap(), 4);
+}
+
+#[tokio::test]
+async fn duplicate_events_are_stored_and_counted_once() {
+    let Some((store, _)) = event_store().await else {
+        return;
+    };
+    let store = Arc::new(store);
+    let event = event(&unique_code(), Utc::now());
+
+    assert!(store.insert(&event).await.unwrap());
+    assert!(!store.insert(&event).await.unwrap());
+    assert!(
+        store
+            .delete(event.event_id, event.accessed_at)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !store
+            .delete(event.event_id, event.accessed_at)
+            .await
+            .unwrap()
+    );
+
+    let broker: Arc<dyn Broker> = Arc::new(InProcessBroker::default());
+    let analytics = Arc::new(InMemoryAnalyticsStore::new());
+    let click_stream = ClickStream::new(16);
+    click_stream.follow(broker.as_ref()).await.unwrap();
+    let mut clicks = pin!(click_stream.subscribe(None));
+    let consumer = AccessEventConsumer::new(
+        Arc::clone(&broker),
+        "access_events",
+        "access.event",
+        Arc::clone(&analytics) as Arc<dyn AnalyticsStore>,
+        Some(Arc::clone(&store)),
+        Vec::new(),
+        Codec::Json,
+    )
+    .await
+    .unwrap();
+    tokio::spawn(async move { consumer.start_consuming().await });
+
+    let payload = envelope::encode(Codec::Json, "test", &event).unwrap();
+    let message_id = event.event_id.to_string();
+    for _ in 0..2 {
+        broker
+            .publish("access.event", &message_id, Codec::Json, &payload)
+            .await
+            .unwrap();
+    }
+    let marker = self::event(&event.code, Utc::now());
+    let payload = envelope::encode(Codec::Json, "test", &marker).unwrap();
+    broker
+        .publish("access.event", "marker", Codec::Json, &payload)
+        .await
+        .unwrap();
+
+    // The duplicate is skipped, so the marker is the second click.
+    let mut counts = Vec::new();
+    for _ in 0..2 {
+        match tokio::time::timeout(Duration::from_secs(5), clicks.next()).await {
+            Ok(Some(StreamMessage::Click(click))) => {
+                counts.push((click.event_id, click.access_count));
+            }
+            other => panic!("expected a click, got {other:?}"),
+        }
+    }
+    assert_eq!(counts, [(event.event_id, 1), (marker.event_id, 2)]);
+
+    store.delete_code(&event.code).await.unwrap();
+}
+
+#[tokio::test]
+async fn rebuild_restores_totals_and_recent_rankings() {
+    let Some((store, _)) = event_store().await else {
+        return;
+    };
+    let busy = unique_code();
+    let quiet = unique_code();
+    let now = Utc::now();
+    let last_busy_click = now - TimeDelta::minutes(1);
+    for event in [
+        event(&busy, now - TimeDelta::days(400)),
+        event(&busy, now - TimeDelta::hours(2)),
+        event(&busy, last_busy_click),
+        event(&quiet, now - TimeDelta::days(30)),
+    ] {
+        store.insert(&event).await.unwrap();
+    }
+
+    let analytics = InMemoryAnalyticsStore::new();
+    // Stale counters are overwritten.
+    analytics.increment(&quiet, now).await.unwrap();
+    analytics.increment(&quiet, now).await.unwrap();
+
+    rebuild(&store, &analytics).await.unwrap();
+
+    let busy_totals = analytics.get(&busy).await.unwrap().unwrap();
+    assert_eq!(busy_totals.access_count, 3);
+    assert_eq!(
+        busy_totals.last_accessed_at.map(|at| at.timestamp_micros()),
+        Some(last_busy_click.timestamp_micros())
+    );
+    let quiet_totals = analytics.get(&quiet).await.unwrap().unwrap();
+    assert_eq!(quiet_totals.access_count, 1);
+
+    let day = analytics.top(RankWindow::Day, 1000).await.unwrap();
+    let busy_day = day.iter().find(|ranked| ranked.code == busy).unwrap();
+    assert_eq!(busy_day.access_count, 2);
+    assert!(day.iter().all(|ranked| ranked.code != quiet));
+
+    store.delete_code(&busy).await.unwrap();
+    store.delete_code(&quiet).await.unwrap();
+}
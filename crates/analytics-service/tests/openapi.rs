@@ -0,0 +1,25 @@
+//! Keeps the committed `openapi.json` in step with the handlers and DTOs.
+//!
+//! After changing the API, regenerate the document with
+//! `UPDATE_OPENAPI=1 cargo test -p analytics-service --test openapi`.
+
+use std::{env, fs, path::Path};
+
+#[test]
+fn committed_spec_matches_the_code() {
+    let generated = analytics_service::openapi().to_pretty_json().unwrap() + "\n";
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("openapi.json");
+
+    if env::var_os("UPDATE_OPENAPI").is_some() {
+        fs::write(&path, &generated).unwrap();
+        return;
+    }
+
+    let committed = fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        committed == generated,
+        "{} is out of date; regenerate it with \
+         `UPDATE_OPENAPI=1 cargo test -p analytics-service --test openapi`",
+        path.display()
+    );
+}
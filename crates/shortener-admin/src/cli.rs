@@ -0,0 +1,58 @@
+//! Command-line arguments.
+
+use clap::{Parser, Subcommand};
+use uuid::Uuid;
+
+/// Maintenance of the data behind shortener-service and analytics-service.
+///
+/// Connection settings are read from the environment and `.env`, with the
+/// same names the services use.
+#[derive(Debug, Parser)]
+#[command(name = "shortener-admin", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Apply the migrations of the shortener database and, when configured,
+    /// the event store and the Redis analytics.
+    Migrate,
+
+    /// Permanently remove links deleted more than `--older-than-days` ago,
+    /// together with their analytics when `REDIS_URL` is set. Their codes
+    /// are retired unless `REUSE_PURGED_CODES` is set.
+    PurgeDeleted {
+        #[arg(long, default_value_t = 30)]
+        older_than_days: u32,
+    },
+
+    /// Overwrite the Redis counters and rankings with the totals of the
+    /// event store. Stop analytics-service first.
+    RebuildCounters,
+
+    /// Remove the analytics of codes that have no link, active or in the
+    /// trash.
+    ReconcileAnalytics {
+        /// Only list the codes.
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Move the messages dead-lettered from each of `queues` back onto
+    /// it, once the consumer has been fixed to handle them.
+    ReplayDeadLetters {
+        /// Queues to replay, such as `access_events`.
+        #[arg(required = true)]
+        queues: Vec<String>,
+    },
+
+    /// Requeue the webhook deliveries that were given up after
+    /// `WEBHOOK_MAX_ATTEMPTS` attempts.
+    ReplayWebhooks {
+        /// Only the deliveries of this subscription.
+        #[arg(long)]
+        subscription: Option<Uuid>,
+    },
+}
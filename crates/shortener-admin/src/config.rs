@@ -0,0 +1,138 @@
+//! Configuration for shortener-admin.
+//!
+//! The settings have the same names as those of the services, so the admin
+//! tool runs against the `.env` file or secrets of a deployment unchanged.
+
+use saferet::SecretString;
+use serviceconf::ServiceConf;
+use shortener_core::config::{
+    BrokerConfig, DatabaseConfig, ObservabilityConfig, RabbitMQConfig, RedisConfig,
+    RedisStreamsConfig,
+};
+
+/// Configuration for shortener-admin.
+#[derive(Debug, Clone, ServiceConf)]
+pub struct Config {
+    /// Database URL of shortener-service: `postgres://...` or `sqlite:...`.
+    #[conf(from_file)]
+    pub database_url: SecretString,
+
+    /// Maximum number of shortener-service database connections.
+    #[conf(default = 10)]
+    pub database_max_connections: u32,
+
+    /// Whether the codes of purged links may be given to new links, as in
+    /// shortener-service.
+    #[conf(default = false)]
+    pub reuse_purged_codes: bool,
+
+    /// Redis URL of analytics-service (required by the commands that touch
+    /// click counters).
+    #[conf(from_file)]
+    pub redis_url: Option<SecretString>,
+
+    /// Postgres URL of the analytics event store (optional).
+    #[conf(from_file)]
+    pub analytics_database_url: Option<SecretString>,
+
+    /// Maximum number of event store connections.
+    #[conf(default = 5)]
+    pub analytics_database_max_connections: u32,
+
+    /// Message broker of the services: `rabbitmq` or `redis` (Redis
+    /// Streams). Needed to replay dead letters.
+    #[conf(default = "rabbitmq".to_string())]
+    pub message_broker: String,
+
+    /// `RabbitMQ` connection URL (required by the `rabbitmq` broker).
+    #[conf(from_file)]
+    pub rabbitmq_url: Option<SecretString>,
+
+    /// `RabbitMQ` exchange name.
+    #[conf(default = "url_shortener".to_string())]
+    pub rabbitmq_exchange: String,
+
+    /// Prefix of the Redis stream keys used by the `redis` broker.
+    #[conf(default = "url_shortener".to_string())]
+    pub redis_stream_prefix: String,
+
+    /// Approximate number of entries kept per Redis stream.
+    #[conf(default = 100_000)]
+    pub redis_stream_max_len: usize,
+
+    /// OTEL exporter endpoint (optional).
+    #[conf(from_file)]
+    pub otel_exporter_endpoint: Option<SecretString>,
+}
+
+impl Config {
+    /// Returns the shortener-service database configuration.
+    #[must_use]
+    pub fn database_config(&self) -> DatabaseConfig {
+        DatabaseConfig {
+            url: self.database_url.clone(),
+            max_connections: self.database_max_connections,
+        }
+    }
+
+    /// Returns the Redis configuration of analytics-service, if configured.
+    #[must_use]
+    pub fn redis_config(&self) -> Option<RedisConfig> {
+        self.redis_url
+            .as_ref()
+            .map(|url| RedisConfig { url: url.clone() })
+    }
+
+    /// Returns the event store configuration, if configured.
+    #[must_use]
+    pub fn analytics_database_config(&self) -> Option<DatabaseConfig> {
+        self.analytics_database_url
+            .as_ref()
+            .map(|url| DatabaseConfig {
+                url: url.clone(),
+                max_connections: self.analytics_database_max_connections,
+            })
+    }
+
+    /// Returns the message broker configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the broker is unknown, has no queues outside the
+    /// services' process, or its URL is not set.
+    pub fn broker_config(&self) -> anyhow::Result<BrokerConfig> {
+        match self.message_broker.as_str() {
+            "rabbitmq" => {
+                let Some(url) = &self.rabbitmq_url else {
+                    anyhow::bail!("RABBITMQ_URL must be set to use the rabbitmq broker");
+                };
+                Ok(BrokerConfig::RabbitMQ(RabbitMQConfig {
+                    url: url.clone(),
+                    exchange: self.rabbitmq_exchange.clone(),
+                }))
+            }
+            "redis" => {
+                let Some(url) = &self.redis_url else {
+                    anyhow::bail!("REDIS_URL must be set to use the redis broker");
+                };
+                Ok(BrokerConfig::RedisStreams(RedisStreamsConfig {
+                    url: url.clone(),
+                    prefix: self.redis_stream_prefix.clone(),
+                    max_len: self.redis_stream_max_len,
+                }))
+            }
+            "in_process" => {
+                anyhow::bail!("The in_process broker cannot be reached from shortener-admin")
+            }
+            other => anyhow::bail!("Unknown message broker '{other}'"),
+        }
+    }
+
+    /// Returns the observability configuration.
+    #[must_use]
+    pub fn observability_config(&self) -> ObservabilityConfig {
+        ObservabilityConfig {
+            otlp_endpoint: self.otel_exporter_endpoint.clone(),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Maintenance commands run directly against the databases and the message
+//! broker of shortener-service and analytics-service.
+//!
+//! The `shortener-admin` binary parses a [`Cli`] and hands its command to
+//! [`run`], which connects only to the stores the command needs.
+
+pub mod cli;
+pub mod config;
+mod reconcile;
+
+use analytics_service::rebuild;
+use anyhow::Context;
+use chrono::{TimeDelta, Utc};
+use shortener_core::{Broker, config::RedisConfig};
+use tracing::{info, warn};
+
+pub use cli::{Cli, Command};
+pub use config::Config;
+pub use reconcile::{reconcile_analytics, remove_analytics};
+
+/// Runs `command` against the stores configured in `config`.
+///
+/// # Errors
+///
+/// Returns an error if a setting the command needs is missing, a store
+/// cannot be reached, or the command fails.
+pub async fn run(command: Command, config: &Config) -> anyhow::Result<()> {
+    match command {
+        Command::Migrate => {
+            shortener_service::repository::connect(&config.database_config()).await?;
+            info!("Migrated the shortener database");
+
+            if let Some(db_config) = config.analytics_database_config() {
+                analytics_service::connect_event_store(&db_config).await?;
+                info!("Migrated the event store");
+            }
+            if let Some(redis_config) = config.redis_config() {
+                analytics_service::connect_analytics_store(&redis_config).await?;
+                info!("Migrated the Redis analytics");
+            }
+        }
+        Command::PurgeDeleted { older_than_days } => {
+            let stores = shortener_service::repository::connect(&config.database_config()).await?;
+            let deleted_before = Utc::now() - TimeDelta::days(i64::from(older_than_days));
+            let purged = stores
+                .urls
+                .purge_deleted(deleted_before, !config.reuse_purged_codes)
+                .await?;
+            info!(purged = purged.len(), %deleted_before, "Purged deleted links");
+
+            match config.redis_config() {
+                Some(redis_config) => {
+                    let analytics =
+                        analytics_service::connect_analytics_store(&redis_config).await?;
+                    let event_store = match config.analytics_database_config() {
+                        Some(db_config) => {
+                            Some(analytics_service::connect_event_store(&db_config).await?)
+                        }
+                        None => None,
+                    };
+                    remove_analytics(&analytics, event_store.as_ref(), &purged).await?;
+                    info!("Cleared the analytics of the purged links");
+                }
+                None if !purged.is_empty() => {
+                    warn!("REDIS_URL is not set; run reconcile-analytics to clear their analytics");
+                }
+                None => {}
+            }
+        }
+        Command::RebuildCounters => {
+            let db_config = config
+                .analytics_database_config()
+                .context("ANALYTICS_DATABASE_URL must be set to rebuild counters")?;
+            let event_store = analytics_service::connect_event_store(&db_config).await?;
+            let analytics =
+                analytics_service::connect_analytics_store(&redis_config(config)?).await?;
+            rebuild::rebuild(&event_store, &analytics).await?;
+        }
+        Command::ReconcileAnalytics { dry_run } => {
+            let stores = shortener_service::repository::connect(&config.database_config()).await?;
+            let analytics =
+                analytics_service::connect_analytics_store(&redis_config(config)?).await?;
+            let event_store = match config.analytics_database_config() {
+                Some(db_config) => Some(analytics_service::connect_event_store(&db_config).await?),
+                None => None,
+            };
+
+            let orphaned = reconcile_analytics(
+                stores.urls.as_ref(),
+                &analytics,
+                event_store.as_ref(),
+                dry_run,
+            )
+            .await?;
+            for code in orphaned {
+                info!(%code, "Analytics without a link");
+            }
+        }
+        Command::ReplayDeadLetters { queues } => {
+            let broker = shortener_core::broker::connect(&config.broker_config()?).await?;
+            replay_dead_letters(broker.as_ref(), &queues).await?;
+        }
+        Command::ReplayWebhooks { subscription } => {
+            let stores = shortener_service::repository::connect(&config.database_config()).await?;
+            let requeued = stores.webhooks.retry_failed(subscription).await?;
+            info!(requeued, "Requeued failed webhook deliveries");
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves the dead letters of each of `queues` back onto it. Returns how
+/// many messages were moved.
+///
+/// # Errors
+///
+/// Returns an error if the broker fails, after logging the queues already
+/// replayed.
+pub async fn replay_dead_letters(broker: &dyn Broker, queues: &[String]) -> anyhow::Result<usize> {
+    let mut total = 0;
+    for queue in queues {
+        let replayed = broker
+            .replay_dead_letters(queue)
+            .await
+            .with_context(|| format!("Failed to replay the dead letters of '{queue}'"))?;
+        info!(%queue, replayed, "Replayed dead letters");
+        total += replayed;
+    }
+    Ok(total)
+}
+
+fn redis_config(config: &Config) -> anyhow::Result<RedisConfig> {
+    config
+        .redis_config()
+        .context("REDIS_URL must be set to reach the click counters")
+}
@@ -0,0 +1,14 @@
+use clap::Parser;
+use shortener_admin::{Cli, Config};
+use shortener_core::telemetry;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    dotenvy::dotenv().ok();
+
+    let cli = Cli::parse();
+    let config = Config::from_env()?;
+    let _guard = telemetry::init_tracing(&config.observability_config(), "shortener-admin")?;
+
+    shortener_admin::run(cli.command, &config).await
+}
@@ -0,0 +1,78 @@
+//! Clears analytics left behind by links that no longer exist.
+
+use analytics_service::repository::{AnalyticsStore, EventStore};
+use shortener_core::AppError;
+use shortener_service::repository::UrlStore;
+use tracing::{info, instrument};
+
+const BATCH_SIZE: usize = 1000;
+
+/// Finds the codes in `analytics` without a link in `urls`, active or in the
+/// trash, and, unless `dry_run` is set, removes their counters and stored
+/// events.
+///
+/// Analytics are normally cleared when analytics-service consumes the
+/// `url.purged` event; this catches codes whose event was lost. Returns the
+/// codes found.
+///
+/// # Errors
+///
+/// Returns an error if reading links or analytics, or removing analytics
+/// fails.
+#[instrument(skip(urls, analytics, event_store))]
+pub async fn reconcile_analytics(
+    urls: &dyn UrlStore,
+    analytics: &dyn AnalyticsStore,
+    event_store: Option<&EventStore>,
+    dry_run: bool,
+) -> Result<Vec<String>, AppError> {
+    let mut orphaned = Vec::new();
+    let mut after = None;
+    loop {
+        let (page, _) = analytics.list(after.take(), BATCH_SIZE).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        after = Some(last.code.clone());
+
+        for entry in page {
+            if urls.find_by_code(&entry.code).await?.is_none()
+                && urls.find_deleted(&entry.code).await?.is_none()
+            {
+                orphaned.push(entry.code);
+            }
+        }
+    }
+
+    if !dry_run {
+        remove_analytics(analytics, event_store, &orphaned).await?;
+    }
+
+    info!(
+        orphaned = orphaned.len(),
+        dry_run, "Reconciled analytics with links"
+    );
+
+    Ok(orphaned)
+}
+
+/// Removes the counters and, when `event_store` is given, the stored events
+/// of `codes`.
+///
+/// # Errors
+///
+/// Returns an error if removing the analytics of a code fails.
+pub async fn remove_analytics(
+    analytics: &dyn AnalyticsStore,
+    event_store: Option<&EventStore>,
+    codes: &[String],
+) -> Result<(), AppError> {
+    for code in codes {
+        analytics.remove(code).await?;
+        if let Some(event_store) = event_store {
+            event_store.delete_code(code).await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! Maintenance operations run against the in-memory stores and broker.
+
+use analytics_service::repository::{AnalyticsStore, InMemoryAnalyticsStore};
+use chrono::{TimeDelta, Utc};
+use shortener_admin::{reconcile_analytics, replay_dead_letters};
+use shortener_core::{Broker, broker::InProcessBroker, messaging::Codec};
+use shortener_service::repository::{NewUrl, Stores};
+use uuid::Uuid;
+
+async fn create_link(stores: &Stores) -> String {
+    let new_url = NewUrl {
+        original_url: "https://example.com".to_string(),
+        ..NewUrl::default()
+    };
+    stores.urls.create(&new_url).await.unwrap().code
+}
+
+#[tokio::test]
+async fn reconcile_removes_analytics_of_missing_links() {
+    let stores = Stores::in_memory();
+    let kept = create_link(&stores).await;
+    let purged = create_link(&stores).await;
+    stores.urls.delete(&purged).await.unwrap();
+    stores
+        .urls
+        .purge_deleted(Utc::now() + TimeDelta::seconds(1), true)
+        .await
+        .unwrap();
+
+    let analytics = InMemoryAnalyticsStore::new();
+    for code in [kept.as_str(), purged.as_str(), "unknown"] {
+        analytics.increment(code, Utc::now()).await.unwrap();
+    }
+
+    let mut orphaned = reconcile_analytics(stores.urls.as_ref(), &analytics, None, true)
+        .await
+        .unwrap();
+    orphaned.sort();
+    let mut expected = vec![purged.clone(), "unknown".to_string()];
+    expected.sort();
+    assert_eq!(orphaned, expected);
+    assert!(analytics.get(&purged).await.unwrap().is_some());
+
+    reconcile_analytics(stores.urls.as_ref(), &analytics, None, false)
+        .await
+        .unwrap();
+    assert!(analytics.get(&kept).await.unwrap().is_some());
+    assert!(analytics.get(&purged).await.unwrap().is_none());
+    assert!(analytics.get("unknown").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn reconcile_keeps_analytics_of_links_in_the_trash() {
+    let stores = Stores::in_memory();
+    let trashed = create_link(&stores).await;
+    stores.urls.delete(&trashed).await.unwrap();
+
+    let analytics = InMemoryAnalyticsStore::new();
+    analytics.increment(&trashed, Utc::now()).await.unwrap();
+
+    let orphaned = reconcile_analytics(stores.urls.as_ref(), &analytics, None, false)
+        .await
+        .unwrap();
+    assert!(orphaned.is_empty());
+    assert!(analytics.get(&trashed).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn purge_removes_only_deleted_links_past_the_cutoff() {
+    let stores = Stores::in_memory();
+    let active = create_link(&stores).await;
+    let deleted = create_link(&stores).await;
+    stores.urls.delete(&deleted).await.unwrap();
+
+    let purged = stores
+        .urls
+        .purge_deleted(Utc::now() - TimeDelta::days(1), true)
+        .await
+        .unwrap();
+    assert!(purged.is_empty());
+
+    let purged = stores
+        .urls
+        .purge_deleted(Utc::now() + TimeDelta::seconds(1), true)
+        .await
+        .unwrap();
+    assert_eq!(purged, vec![deleted.clone()]);
+    assert!(stores.urls.find_by_code(&active).await.unwrap().is_some());
+    assert!(stores.urls.restore(&deleted).await.is_err());
+}
+
+#[tokio::test]
+async fn replay_requeues_given_up_deliveries() {
+    let stores = Stores::in_memory();
+    let failing = stores
+        .webhooks
+        .create("https://hooks.example/a", "secret", &[])
+        .await
+        .unwrap();
+    let other = stores
+        .webhooks
+        .create("https://hooks.example/b", "secret", &[])
+        .await
+        .unwrap();
+
+    let payload = serde_json::json!({});
+    stores
+        .webhooks
+        .enqueue(Uuid::new_v4(), "url.created", &payload)
+        .await
+        .unwrap();
+    let lease_until = Utc::now() + TimeDelta::minutes(1);
+    for due in stores.webhooks.claim_due(10, lease_until).await.unwrap() {
+        stores
+            .webhooks
+            .record_failure(due.id, Some(500), "Internal Server Error", None)
+            .await
+            .unwrap();
+    }
+
+    let requeued = stores
+        .webhooks
+        .retry_failed(Some(failing.id))
+        .await
+        .unwrap();
+    assert_eq!(requeued, 1);
+
+    let deliveries = stores
+        .webhooks
+        .deliveries(failing.id, None, 10)
+        .await
+        .unwrap();
+    assert_eq!(deliveries[0].status, "pending");
+    assert_eq!(deliveries[0].attempts, 0);
+
+    let deliveries = stores
+        .webhooks
+        .deliveries(other.id, None, 10)
+        .await
+        .unwrap();
+    assert_eq!(deliveries[0].status, "failed");
+    assert_eq!(stores.webhooks.retry_failed(None).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn replay_moves_dead_letters_back_onto_their_queues() {
+    let broker = InProcessBroker::default();
+    let mut subscription = broker
+        .subscribe("access_events", &["access.event"])
+        .await
+        .unwrap();
+    for id in ["1", "2"] {
+        broker
+            .publish("access.event", id, Codec::Json, b"{}")
+            .await
+            .unwrap();
+        subscription
+            .next()
+            .await
+            .unwrap()
+            .nack(false)
+            .await
+            .unwrap();
+    }
+
+    let queues = ["access_events".to_string()];
+    assert_eq!(replay_dead_letters(&broker, &queues).await.unwrap(), 2);
+
+    for id in ["1", "2"] {
+        let delivery = subscription.next().await.unwrap();
+        assert_eq!(delivery.message_id.as_deref(), Some(id));
+        delivery.ack().await.unwrap();
+    }
+    assert_eq!(replay_dead_letters(&broker, &queues).await.unwrap(), 0);
+}
@@ -0,0 +1,95 @@
+//! `analytics` subcommands.
+
+use std::io::Write;
+
+use shortener_client::{
+    AnalyticsClient,
+    analytics_api::{Analytics, AnalyticsListResponse, ListAnalyticsQuery, TopAnalyticsQuery},
+};
+
+use crate::{
+    cli::{AnalyticsCommand, OutputFormat, PageArgs},
+    output::{self, fields, optional_time, print, table},
+};
+
+pub(crate) async fn run(
+    command: AnalyticsCommand,
+    client: &AnalyticsClient,
+    format: OutputFormat,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    match command {
+        AnalyticsCommand::Get { code } => {
+            let analytics = client.get_analytics(&code).await?;
+            print(out, format, &analytics, |analytics| {
+                fields([
+                    ("code", analytics.code.clone()),
+                    ("access_count", analytics.access_count.to_string()),
+                    (
+                        "last_accessed_at",
+                        optional_time(analytics.last_accessed_at),
+                    ),
+                ])
+            })
+        }
+        AnalyticsCommand::List(args) => {
+            let list = list(client, args).await?;
+            print(out, format, &list, |list| analytics_table(&list.page.items))?;
+            if format == OutputFormat::Table {
+                writeln!(out, "Codes with analytics: {}", list.total)?;
+                output::next_page(out, list.page.next_cursor.as_deref())?;
+            }
+            Ok(())
+        }
+        AnalyticsCommand::Top { window, limit } => {
+            let top = client
+                .top_analytics(&TopAnalyticsQuery { window, limit })
+                .await?;
+            print(out, format, &top, |top| {
+                table(
+                    ["RANK", "CODE", "CLICKS"],
+                    top.items.iter().map(|ranked| {
+                        [
+                            ranked.rank.to_string(),
+                            ranked.code.clone(),
+                            ranked.access_count.to_string(),
+                        ]
+                    }),
+                )
+            })
+        }
+    }
+}
+
+/// Fetches one page, or every page with `--all`.
+async fn list(client: &AnalyticsClient, args: PageArgs) -> anyhow::Result<AnalyticsListResponse> {
+    let mut query = ListAnalyticsQuery {
+        limit: args.limit,
+        cursor: args.cursor,
+    };
+
+    let mut list = client.list_analytics(&query).await?;
+    if args.all {
+        while let Some(cursor) = list.page.next_cursor.take() {
+            query.cursor = Some(cursor);
+            let next = client.list_analytics(&query).await?;
+            list.page.items.extend(next.page.items);
+            list.page.next_cursor = next.page.next_cursor;
+        }
+    }
+
+    Ok(list)
+}
+
+fn analytics_table(items: &[Analytics]) -> comfy_table::Table {
+    table(
+        ["CODE", "CLICKS", "LAST ACCESSED"],
+        items.iter().map(|analytics| {
+            [
+                analytics.code.clone(),
+                analytics.access_count.to_string(),
+                optional_time(analytics.last_accessed_at),
+            ]
+        }),
+    )
+}
@@ -0,0 +1,241 @@
+//! Command-line arguments.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, de::DeserializeOwned};
+use shortener_client::{analytics_api::RankWindow, shortener_api::RedirectType};
+
+/// Manage short links and read their analytics.
+#[derive(Debug, Parser)]
+#[command(name = "shortener-cli", version)]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Connection and output settings. Unset values fall back to the config
+/// file, then to the defaults.
+#[derive(Debug, Args)]
+pub struct GlobalArgs {
+    /// TOML config file [default: `$XDG_CONFIG_HOME/shortener/config.toml`]
+    #[arg(long, global = true, env = "SHORTENER_CLI_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Base URL of shortener-service [default: `http://localhost:8080`]
+    #[arg(long, global = true, env = "SHORTENER_URL")]
+    pub shortener_url: Option<String>,
+
+    /// Base URL of analytics-service [default: `http://localhost:8081`]
+    #[arg(long, global = true, env = "ANALYTICS_URL")]
+    pub analytics_url: Option<String>,
+
+    /// Sent as `Authorization: Bearer <key>` with every request.
+    #[arg(long, global = true, env = "SHORTENER_API_KEY", hide_env_values = true)]
+    pub api_key: Option<String>,
+
+    /// Output format [default: table]
+    #[arg(short, long, global = true, env = "SHORTENER_CLI_OUTPUT")]
+    pub output: Option<OutputFormat>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Create, list, update, delete and import links.
+    #[command(subcommand)]
+    Links(LinksCommand),
+
+    /// Read click analytics.
+    #[command(subcommand)]
+    Analytics(AnalyticsCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum LinksCommand {
+    /// Create a short link.
+    Create(CreateArgs),
+
+    /// List active links, or deleted ones with `--deleted`, newest first.
+    List(ListArgs),
+
+    /// Show a link.
+    Get { code: String },
+
+    /// Change the destination or settings of a link; omitted settings are kept.
+    Update(UpdateArgs),
+
+    /// Move a link to the trash.
+    Delete { code: String },
+
+    /// Take a deleted link out of the trash.
+    Restore { code: String },
+
+    /// Create one link per record of a CSV or NDJSON file.
+    Import(ImportArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct CreateArgs {
+    /// Destination URL.
+    pub url: String,
+
+    #[arg(long)]
+    pub title: Option<String>,
+
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// May be repeated.
+    #[arg(long = "tag")]
+    pub tags: Vec<String>,
+
+    /// RFC 3339 time after which the link stops redirecting.
+    #[arg(long)]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// `moved_permanently`, `found`, `temporary` or `permanent`.
+    #[arg(long, value_parser = parse_serde::<RedirectType>)]
+    pub redirect_type: Option<RedirectType>,
+
+    /// Append the query string of each visit to the destination.
+    #[arg(long)]
+    pub forward_query: bool,
+
+    /// Always show the interstitial warning before redirecting.
+    #[arg(long)]
+    pub always_interstitial: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    /// List the links in the trash instead.
+    #[arg(long)]
+    pub deleted: bool,
+
+    /// Only links with this tag.
+    #[arg(long)]
+    pub tag: Option<String>,
+
+    /// Only links to this host or its subdomains.
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Words that must all appear in the title, description or destination.
+    #[arg(short, long)]
+    pub query: Option<String>,
+
+    /// Only links created at or after this RFC 3339 time.
+    #[arg(long)]
+    pub created_from: Option<DateTime<Utc>>,
+
+    /// Only links created before this RFC 3339 time.
+    #[arg(long)]
+    pub created_to: Option<DateTime<Utc>>,
+
+    #[command(flatten)]
+    pub page: PageArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct PageArgs {
+    /// Page size, at most 100.
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+
+    /// `next_cursor` of the previous page.
+    #[arg(long, conflicts_with = "all")]
+    pub cursor: Option<String>,
+
+    /// Follow the cursors and print every page.
+    #[arg(long)]
+    pub all: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct UpdateArgs {
+    pub code: String,
+
+    /// New destination URL.
+    #[arg(long)]
+    pub url: Option<String>,
+
+    #[arg(long)]
+    pub title: Option<String>,
+
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// Replaces the tags of the link; may be repeated.
+    #[arg(long = "tag")]
+    pub tags: Option<Vec<String>>,
+
+    /// RFC 3339 time after which the link stops redirecting.
+    #[arg(long)]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// `moved_permanently`, `found`, `temporary` or `permanent`.
+    #[arg(long, value_parser = parse_serde::<RedirectType>)]
+    pub redirect_type: Option<RedirectType>,
+
+    #[arg(long)]
+    pub forward_query: Option<bool>,
+
+    #[arg(long)]
+    pub always_interstitial: Option<bool>,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    pub file: PathBuf,
+
+    /// Format of `file`; guessed from its extension when omitted.
+    #[arg(long)]
+    pub format: Option<ImportFormat>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    /// Header row, then `url,title,description,tags,expires_at,redirect_type`
+    /// columns; only `url` is required and `tags` is comma-separated.
+    Csv,
+    /// One `POST /api/v1/urls` request body per line.
+    Ndjson,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AnalyticsCommand {
+    /// Show the click counters of a code.
+    Get { code: String },
+
+    /// List the click counters of every code, ordered by code.
+    List(PageArgs),
+
+    /// Rank codes by clicks.
+    Top {
+        /// `24h`, `7d` or `all`.
+        #[arg(long, default_value = "all", value_parser = parse_serde::<RankWindow>)]
+        window: RankWindow,
+
+        /// Number of codes, at most 100.
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+}
+
+/// Parses `value` as the JSON string of an API enum, so arguments use the
+/// same names as the API.
+fn parse_serde<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string())).map_err(|e| e.to_string())
+}
@@ -0,0 +1,98 @@
+//! Settings from the arguments, the environment and the config file.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::cli::{GlobalArgs, OutputFormat};
+
+const DEFAULT_SHORTENER_URL: &str = "http://localhost:8080";
+const DEFAULT_ANALYTICS_URL: &str = "http://localhost:8081";
+
+/// Contents of the config file; every setting is optional.
+///
+/// ```toml
+/// shortener_url = "https://sho.rt"
+/// analytics_url = "https://analytics.sho.rt"
+/// api_key = "..."
+/// output = "json"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub shortener_url: Option<String>,
+    pub analytics_url: Option<String>,
+    pub api_key: Option<String>,
+    pub output: Option<OutputFormat>,
+}
+
+impl FileConfig {
+    /// Reads the config file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+}
+
+/// Settings of one invocation.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub shortener_url: String,
+    pub analytics_url: String,
+    pub api_key: Option<String>,
+    pub output: OutputFormat,
+}
+
+impl Config {
+    /// Resolves each setting from `args` (flags or environment variables),
+    /// then the config file, then the defaults.
+    ///
+    /// The config file is `args.config` or, when that is unset, the default
+    /// path if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config file cannot be read or parsed.
+    pub fn resolve(args: &GlobalArgs) -> anyhow::Result<Self> {
+        let path = args
+            .config
+            .clone()
+            .or_else(|| default_path().filter(|path| path.exists()));
+        let file = match path {
+            Some(path) => FileConfig::load(&path)?,
+            None => FileConfig::default(),
+        };
+
+        Ok(Self {
+            shortener_url: args
+                .shortener_url
+                .clone()
+                .or(file.shortener_url)
+                .unwrap_or_else(|| DEFAULT_SHORTENER_URL.to_string()),
+            analytics_url: args
+                .analytics_url
+                .clone()
+                .or(file.analytics_url)
+                .unwrap_or_else(|| DEFAULT_ANALYTICS_URL.to_string()),
+            api_key: args.api_key.clone().or(file.api_key),
+            output: args.output.or(file.output).unwrap_or_default(),
+        })
+    }
+}
+
+/// `$XDG_CONFIG_HOME/shortener/config.toml`, or under `$HOME/.config`.
+fn default_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("shortener").join("config.toml"))
+}
@@ -0,0 +1,182 @@
+//! `links import`: one link per record of a CSV or NDJSON file.
+
+use std::{fs, io::Write, path::Path};
+
+use anyhow::{Context, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shortener_client::{
+    ShortenerClient,
+    shortener_api::{CreateUrlRequest, RedirectType},
+};
+
+use crate::{
+    cli::{ImportArgs, ImportFormat},
+    config::Config,
+    links::short_url,
+    output::{print, table},
+};
+
+/// A row of a CSV import.
+#[derive(Debug, Deserialize)]
+struct CsvRecord {
+    url: String,
+    title: Option<String>,
+    description: Option<String>,
+    /// Comma-separated.
+    tags: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    redirect_type: Option<RedirectType>,
+}
+
+impl From<CsvRecord> for CreateUrlRequest {
+    fn from(record: CsvRecord) -> Self {
+        Self {
+            url: record.url,
+            title: record.title,
+            description: record.description,
+            tags: record
+                .tags
+                .map(|tags| tags.split(',').map(str::to_string).collect())
+                .unwrap_or_default(),
+            expires_at: record.expires_at,
+            redirect_type: record.redirect_type.unwrap_or_default(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Outcome of one record.
+#[derive(Debug, Serialize)]
+struct Imported {
+    /// Line of the record in the file.
+    line: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    short_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Creates a link per record of `args.file`, then reports each outcome.
+///
+/// Invalid records and rejected links do not stop the import, but make it
+/// fail once every record was tried.
+pub(crate) async fn run(
+    args: &ImportArgs,
+    client: &ShortenerClient,
+    config: &Config,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let format = match args.format {
+        Some(format) => format,
+        None => guess_format(&args.file)?,
+    };
+    let contents = fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read {}", args.file.display()))?;
+    let records = match format {
+        ImportFormat::Csv => read_csv(&contents)?,
+        ImportFormat::Ndjson => read_ndjson(&contents),
+    };
+
+    let mut results = Vec::with_capacity(records.len());
+    for (line, record) in records {
+        let created = match record {
+            Ok(req) => client.create_url(&req).await.map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        results.push(match created {
+            Ok(created) => Imported {
+                line,
+                code: Some(created.code),
+                short_url: Some(created.short_url),
+                error: None,
+            },
+            Err(error) => Imported {
+                line,
+                code: None,
+                short_url: None,
+                error: Some(error),
+            },
+        });
+    }
+
+    print(out, config.output, &results, |results| {
+        table(
+            ["LINE", "CODE", "SHORT URL", "ERROR"],
+            results.iter().map(|imported| {
+                [
+                    imported.line.to_string(),
+                    imported.code.clone().unwrap_or_default(),
+                    imported
+                        .short_url
+                        .as_deref()
+                        .map(|path| short_url(config, path))
+                        .unwrap_or_default(),
+                    imported.error.clone().unwrap_or_default(),
+                ]
+            }),
+        )
+    })?;
+
+    let failed = results
+        .iter()
+        .filter(|imported| imported.error.is_some())
+        .count();
+    if failed > 0 {
+        bail!("{failed} of {} records failed to import", results.len());
+    }
+    Ok(())
+}
+
+fn guess_format(path: &Path) -> anyhow::Result<ImportFormat> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("csv") => Ok(ImportFormat::Csv),
+        Some("ndjson" | "jsonl") => Ok(ImportFormat::Ndjson),
+        _ => bail!(
+            "Cannot tell the format of {}; pass --format",
+            path.display()
+        ),
+    }
+}
+
+/// Parses the records of a CSV file with a header row. A malformed header
+/// fails the whole import; malformed records only fail themselves.
+fn read_csv(contents: &str) -> anyhow::Result<Vec<(u64, Result<CreateUrlRequest, String>)>> {
+    let mut reader = csv::Reader::from_reader(contents.as_bytes());
+    let headers = reader.headers().context("Invalid CSV header")?.clone();
+
+    Ok(reader
+        .records()
+        .map(|record| match record {
+            Ok(record) => {
+                let line = record.position().map_or(0, csv::Position::line);
+                let req = record
+                    .deserialize::<CsvRecord>(Some(&headers))
+                    .map(CreateUrlRequest::from)
+                    .map_err(|e| e.to_string());
+                (line, req)
+            }
+            Err(e) => (
+                e.position().map_or(0, csv::Position::line),
+                Err(e.to_string()),
+            ),
+        })
+        .collect())
+}
+
+/// Parses one request body per non-blank line.
+fn read_ndjson(contents: &str) -> Vec<(u64, Result<CreateUrlRequest, String>)> {
+    contents
+        .lines()
+        .zip(1..)
+        .filter(|(line, _)| !line.trim().is_empty())
+        .map(|(line, number)| {
+            (
+                number,
+                serde_json::from_str(line).map_err(|e| e.to_string()),
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,63 @@
+//! Command-line client of shortener-service and analytics-service.
+//!
+//! The `shortener-cli` binary parses a [`Cli`] and hands it to [`run`],
+//! which talks to the services through `shortener-client`.
+
+mod analytics;
+pub mod cli;
+pub mod config;
+mod import;
+mod links;
+mod output;
+
+use std::io::Write;
+
+use anyhow::Context;
+use reqwest::{
+    Client,
+    header::{self, HeaderMap, HeaderValue},
+    redirect,
+};
+use shortener_client::{AnalyticsClient, ShortenerClient};
+
+pub use cli::Cli;
+use cli::Command;
+pub use config::Config;
+
+/// Runs `cli`, writing its results to `out`.
+///
+/// # Errors
+///
+/// Returns an error if the configuration is invalid, a service rejects a
+/// request or cannot be reached, or an import has failed records.
+pub async fn run(cli: Cli, out: &mut dyn Write) -> anyhow::Result<()> {
+    let config = Config::resolve(&cli.global)?;
+    let http = http_client(config.api_key.as_deref())?;
+
+    match cli.command {
+        Command::Links(command) => {
+            let client = ShortenerClient::with_client(http, &config.shortener_url)?;
+            links::run(command, &client, &config, out).await
+        }
+        Command::Analytics(command) => {
+            let client = AnalyticsClient::with_client(http, &config.analytics_url)?;
+            analytics::run(command, &client, config.output, out).await
+        }
+    }
+}
+
+/// HTTP client sending `api_key` as a bearer token with every request.
+fn http_client(api_key: Option<&str>) -> anyhow::Result<Client> {
+    let mut headers = HeaderMap::new();
+    if let Some(api_key) = api_key {
+        let mut value = HeaderValue::from_str(&format!("Bearer {api_key}"))
+            .context("The API key is not a valid header value")?;
+        value.set_sensitive(true);
+        headers.insert(header::AUTHORIZATION, value);
+    }
+
+    Ok(Client::builder()
+        .default_headers(headers)
+        .redirect(redirect::Policy::none())
+        .build()?)
+}
@@ -0,0 +1,189 @@
+//! `links` subcommands.
+
+use std::io::Write;
+
+use comfy_table::Table;
+use serde::Serialize;
+use shortener_client::{
+    Page, ShortenerClient,
+    shortener_api::{CreateUrlRequest, ListUrlsQuery, UpdateUrlRequest, Url, UrlState},
+};
+
+use crate::{
+    cli::{CreateArgs, LinksCommand, ListArgs, OutputFormat, UpdateArgs},
+    config::Config,
+    import,
+    output::{self, fields, optional_time, print, table, time},
+};
+
+pub(crate) async fn run(
+    command: LinksCommand,
+    client: &ShortenerClient,
+    config: &Config,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let format = config.output;
+
+    match command {
+        LinksCommand::Create(args) => {
+            let created = client.create_url(&create_request(args)).await?;
+            print(out, format, &created, |created| {
+                fields([
+                    ("code", created.code.clone()),
+                    ("short_url", short_url(config, &created.short_url)),
+                    ("url", created.original_url.clone()),
+                ])
+            })
+        }
+        LinksCommand::List(args) => {
+            let page = list(client, args).await?;
+            print(out, format, &page, |page| urls_table(&page.items))?;
+            if format == OutputFormat::Table {
+                output::next_page(out, page.next_cursor.as_deref())?;
+            }
+            Ok(())
+        }
+        LinksCommand::Get { code } => {
+            let url = client.get_url(&code).await?;
+            print(out, format, &url, |url| url_fields(config, url))
+        }
+        LinksCommand::Update(args) => {
+            let url = update(client, args).await?;
+            print(out, format, &url, |url| url_fields(config, url))
+        }
+        LinksCommand::Delete { code } => {
+            client.delete_url(&code).await?;
+            print(out, format, &Deleted { code: &code }, |deleted| {
+                table(["DELETED"], [[deleted.code.to_string()]])
+            })
+        }
+        LinksCommand::Restore { code } => {
+            let url = client.restore_url(&code).await?;
+            print(out, format, &url, |url| url_fields(config, url))
+        }
+        LinksCommand::Import(args) => import::run(&args, client, config, out).await,
+    }
+}
+
+#[derive(Serialize)]
+struct Deleted<'a> {
+    code: &'a str,
+}
+
+fn create_request(args: CreateArgs) -> CreateUrlRequest {
+    CreateUrlRequest {
+        url: args.url,
+        forward_query: args.forward_query,
+        redirect_type: args.redirect_type.unwrap_or_default(),
+        always_interstitial: args.always_interstitial,
+        title: args.title,
+        description: args.description,
+        tags: args.tags,
+        expires_at: args.expires_at,
+        ..CreateUrlRequest::default()
+    }
+}
+
+/// Fetches one page, or every page with `--all`.
+async fn list(client: &ShortenerClient, args: ListArgs) -> anyhow::Result<Page<Url>> {
+    let mut query = ListUrlsQuery {
+        limit: args.page.limit,
+        cursor: args.page.cursor,
+        state: if args.deleted {
+            UrlState::Deleted
+        } else {
+            UrlState::Active
+        },
+        tag: args.tag,
+        domain: args.domain,
+        created_from: args.created_from,
+        created_to: args.created_to,
+        q: args.query,
+    };
+
+    let mut page = client.list_urls(&query).await?;
+    if args.page.all {
+        while let Some(cursor) = page.next_cursor.take() {
+            query.cursor = Some(cursor);
+            let next = client.list_urls(&query).await?;
+            page.items.extend(next.items);
+            page.next_cursor = next.next_cursor;
+        }
+    }
+
+    Ok(page)
+}
+
+/// Sends the changes in `args`, keeping the current destination unless
+/// `--url` is given.
+async fn update(client: &ShortenerClient, args: UpdateArgs) -> anyhow::Result<Url> {
+    let url = match args.url {
+        Some(url) => url,
+        None => client.get_url(&args.code).await?.original_url,
+    };
+
+    let req = UpdateUrlRequest {
+        url,
+        forward_query: args.forward_query,
+        redirect_type: args.redirect_type,
+        always_interstitial: args.always_interstitial,
+        title: args.title,
+        description: args.description,
+        tags: args.tags,
+        expires_at: args.expires_at,
+        ..UpdateUrlRequest::default()
+    };
+
+    Ok(client.update_url(&args.code, &req).await?)
+}
+
+/// `short_url` of the API is a path; tables show it below the base URL.
+pub(crate) fn short_url(config: &Config, path: &str) -> String {
+    format!("{}{path}", config.shortener_url.trim_end_matches('/'))
+}
+
+fn urls_table(urls: &[Url]) -> Table {
+    table(
+        ["CODE", "URL", "TITLE", "TAGS", "CREATED", "EXPIRES"],
+        urls.iter().map(|url| {
+            [
+                url.code.clone(),
+                url.original_url.clone(),
+                url.title.clone().unwrap_or_default(),
+                url.tags.join(","),
+                time(url.created_at),
+                optional_time(url.expires_at),
+            ]
+        }),
+    )
+}
+
+fn url_fields(config: &Config, url: &Url) -> Table {
+    fields([
+        ("code", url.code.clone()),
+        ("short_url", short_url(config, &format!("/{}", url.code))),
+        ("url", url.original_url.clone()),
+        ("title", url.title.clone().unwrap_or_default()),
+        ("description", url.description.clone().unwrap_or_default()),
+        ("tags", url.tags.join(",")),
+        ("redirect_type", api_name(&url.redirect_type)),
+        ("forward_query", url.forward_query.to_string()),
+        ("always_interstitial", url.always_interstitial.to_string()),
+        ("created_at", time(url.created_at)),
+        ("updated_at", time(url.updated_at)),
+        ("expires_at", optional_time(url.expires_at)),
+        ("disabled_at", optional_time(url.disabled_at)),
+        (
+            "disabled_reason",
+            url.disabled_reason.clone().unwrap_or_default(),
+        ),
+    ])
+}
+
+/// Name of an API enum value, as in its JSON.
+fn api_name(value: &impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,12 @@
+use std::io;
+
+use clap::Parser;
+use shortener_cli::Cli;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    dotenvy::dotenv().ok();
+
+    let cli = Cli::parse();
+    shortener_cli::run(cli, &mut io::stdout().lock()).await
+}
@@ -0,0 +1,64 @@
+//! Rendering of results as tables or JSON.
+
+use std::io::Write;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use comfy_table::Table;
+use serde::Serialize;
+
+use crate::cli::OutputFormat;
+
+/// Writes `value` as pretty-printed JSON, or as the table `table` builds
+/// from it.
+pub(crate) fn print<T: Serialize>(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    value: &T,
+    table: impl FnOnce(&T) -> Table,
+) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, value)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Table => writeln!(out, "{}", table(value))?,
+    }
+    Ok(())
+}
+
+/// A table with `header` and one row per item of `rows`.
+pub(crate) fn table<const N: usize>(
+    header: [&str; N],
+    rows: impl IntoIterator<Item = [String; N]>,
+) -> Table {
+    let mut table = Table::new();
+    table.set_header(header);
+    for row in rows {
+        table.add_row(row);
+    }
+    table
+}
+
+/// Field names and values of a single item.
+pub(crate) fn fields<const N: usize>(fields: [(&str, String); N]) -> Table {
+    table(
+        ["FIELD", "VALUE"],
+        fields.map(|(name, value)| [name.to_string(), value]),
+    )
+}
+
+pub(crate) fn time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+pub(crate) fn optional_time(time: Option<DateTime<Utc>>) -> String {
+    time.map(self::time).unwrap_or_default()
+}
+
+/// Hint for fetching the page after `next_cursor`, printed below tables.
+pub(crate) fn next_page(out: &mut dyn Write, next_cursor: Option<&str>) -> anyhow::Result<()> {
+    if let Some(cursor) = next_cursor {
+        writeln!(out, "More results: --cursor {cursor}")?;
+    }
+    Ok(())
+}
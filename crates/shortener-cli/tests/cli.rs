@@ -0,0 +1,270 @@
+//! Commands run against both services served over HTTP with in-memory
+//! stores. The services sit behind a check of the API key.
+
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use analytics_service::repository::InMemoryAnalyticsStore;
+use axum::{
+    Router,
+    extract::Request,
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::Response,
+};
+use clap::Parser;
+use serde_json::Value;
+use shortener_cli::Cli;
+use shortener_service::{publisher::MockEventPublisher, repository::Stores};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+const API_KEY: &str = "test-key";
+
+async fn require_api_key(request: Request, next: Next) -> Result<Response, StatusCode> {
+    let expected = format!("Bearer {API_KEY}");
+    match request.headers().get(header::AUTHORIZATION) {
+        Some(value) if value == expected.as_str() => Ok(next.run(request).await),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Serves `router` on a free local port and returns its base URL.
+async fn serve(router: Router) -> String {
+    let router = router.layer(middleware::from_fn(require_api_key));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    format!("http://{addr}")
+}
+
+struct TestApp {
+    shortener_url: String,
+    analytics_url: String,
+    analytics: analytics_service::AppState,
+}
+
+impl TestApp {
+    async fn new() -> Self {
+        let mut event_publisher = MockEventPublisher::new();
+        event_publisher.expect_publish().returning(|_| Ok(()));
+        event_publisher
+            .expect_publish_link_event()
+            .returning(|_| Ok(()));
+        let shortener =
+            shortener_service::AppState::new(Stores::in_memory(), Arc::new(event_publisher));
+        let analytics =
+            analytics_service::AppState::new(Arc::new(InMemoryAnalyticsStore::new()), 16);
+
+        Self {
+            shortener_url: serve(shortener_service::router(shortener)).await,
+            analytics_url: serve(analytics_service::router(analytics.clone())).await,
+            analytics,
+        }
+    }
+
+    /// Runs `shortener-cli <args>` with the service URLs and API key set.
+    async fn run(&self, args: &[&str]) -> anyhow::Result<String> {
+        let mut command = vec![
+            "shortener-cli",
+            "--shortener-url",
+            &self.shortener_url,
+            "--analytics-url",
+            &self.analytics_url,
+            "--api-key",
+            API_KEY,
+        ];
+        command.extend(args);
+        run(&command).await
+    }
+
+    /// Runs a command with JSON output and parses it.
+    async fn json(&self, args: &[&str]) -> Value {
+        let mut command = vec!["--output", "json"];
+        command.extend(args);
+        let output = self.run(&command).await.unwrap();
+        serde_json::from_str(&output).unwrap()
+    }
+}
+
+async fn run(argv: &[&str]) -> anyhow::Result<String> {
+    let mut out = Vec::new();
+    shortener_cli::run(Cli::try_parse_from(argv)?, &mut out).await?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+/// Writes `contents` to a new file in the temporary directory.
+fn temp_file(extension: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("shortener-cli-{}.{extension}", Uuid::new_v4()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn links_round_trip() {
+    let app = TestApp::new().await;
+
+    let created = app
+        .json(&[
+            "links",
+            "create",
+            "https://example.com/docs",
+            "--title",
+            "Docs",
+            "--tag",
+            "docs",
+            "--redirect-type",
+            "permanent",
+        ])
+        .await;
+    let code = created["code"].as_str().unwrap();
+
+    let page = app.json(&["links", "list", "--tag", "docs"]).await;
+    assert_eq!(page["items"][0]["code"], code);
+    assert_eq!(page["items"][0]["redirect_type"], "permanent");
+
+    let updated = app
+        .json(&["links", "update", code, "--title", "Guide"])
+        .await;
+    assert_eq!(updated["title"], "Guide");
+    assert_eq!(updated["original_url"], "https://example.com/docs");
+
+    let table = app.run(&["links", "get", code]).await.unwrap();
+    assert!(table.contains("Guide"), "{table}");
+    assert!(
+        table.contains(&format!("{}/{code}", app.shortener_url)),
+        "{table}"
+    );
+
+    app.run(&["links", "delete", code]).await.unwrap();
+    let error = app.run(&["links", "get", code]).await.unwrap_err();
+    assert!(error.to_string().contains("404"), "{error}");
+
+    let trash = app.json(&["links", "list", "--deleted"]).await;
+    assert_eq!(trash["items"][0]["code"], code);
+    let restored = app.json(&["links", "restore", code]).await;
+    assert_eq!(restored["title"], "Guide");
+    app.run(&["links", "get", code]).await.unwrap();
+}
+
+#[tokio::test]
+async fn list_all_follows_cursors() {
+    let app = TestApp::new().await;
+    for i in 0..3 {
+        app.run(&["links", "create", &format!("https://example.com/{i}")])
+            .await
+            .unwrap();
+    }
+
+    let page = app.json(&["links", "list", "--limit", "2"]).await;
+    assert_eq!(page["items"].as_array().unwrap().len(), 2);
+    assert!(page["next_cursor"].is_string());
+
+    let all = app.json(&["links", "list", "--limit", "2", "--all"]).await;
+    assert_eq!(all["items"].as_array().unwrap().len(), 3);
+    assert!(all["next_cursor"].is_null());
+}
+
+#[tokio::test]
+async fn csv_import_reports_each_record() {
+    let app = TestApp::new().await;
+    let file = temp_file(
+        "csv",
+        "url,title,tags\n\
+         https://example.com/a,First,\"x,y\"\n\
+         ftp://example.com/b,Rejected,\n\
+         https://example.com/c,,\n",
+    );
+
+    let error = app
+        .run(&["links", "import", file.to_str().unwrap()])
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("1 of 3"), "{error}");
+
+    let page = app.json(&["links", "list", "--tag", "y"]).await;
+    assert_eq!(page["items"][0]["title"], "First");
+    let all = app.json(&["links", "list"]).await;
+    assert_eq!(all["items"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn ndjson_import_creates_links() {
+    let app = TestApp::new().await;
+    let file = temp_file(
+        "ndjson",
+        "{\"url\": \"https://example.com/a\", \"forward_query\": true}\n\n\
+         {\"url\": \"https://example.com/b\"}\n",
+    );
+
+    let results = app.json(&["links", "import", file.to_str().unwrap()]).await;
+    assert_eq!(results[0]["line"], 1);
+    assert_eq!(results[1]["line"], 3);
+    assert!(results[1]["code"].is_string());
+}
+
+#[tokio::test]
+async fn analytics_commands() {
+    let app = TestApp::new().await;
+    for code in ["aaa", "bbb", "bbb"] {
+        app.analytics
+            .analytics_store
+            .increment(code, chrono::Utc::now())
+            .await
+            .unwrap();
+    }
+
+    let analytics = app.json(&["analytics", "get", "bbb"]).await;
+    assert_eq!(analytics["access_count"], 2);
+
+    let list = app
+        .json(&["analytics", "list", "--all", "--limit", "1"])
+        .await;
+    assert_eq!(list["total"], 2);
+    assert_eq!(list["items"].as_array().unwrap().len(), 2);
+
+    let top = app
+        .run(&["analytics", "top", "--window", "24h"])
+        .await
+        .unwrap();
+    assert!(top.contains("bbb"), "{top}");
+}
+
+#[tokio::test]
+async fn config_file_fills_unset_settings() {
+    let app = TestApp::new().await;
+    let config = temp_file(
+        "toml",
+        &format!(
+            "shortener_url = \"{}\"\napi_key = \"{API_KEY}\"\noutput = \"json\"\n",
+            app.shortener_url
+        ),
+    );
+    let config = config.to_str().unwrap();
+
+    let output = run(&["shortener-cli", "--config", config, "links", "list"])
+        .await
+        .unwrap();
+    let page: Value = serde_json::from_str(&output).unwrap();
+    assert!(page["items"].is_array());
+
+    // Flags take precedence over the file.
+    let error = run(&[
+        "shortener-cli",
+        "--config",
+        config,
+        "--api-key",
+        "wrong",
+        "links",
+        "list",
+    ])
+    .await
+    .unwrap_err();
+    assert!(error.to_string().contains("401"), "{error}");
+}
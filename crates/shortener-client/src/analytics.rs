@@ -0,0 +1,191 @@
+use bytes::Bytes;
+use eventsource_stream::{Event, EventStreamError, Eventsource};
+use futures_lite::{Stream, StreamExt};
+use reqwest::{Client, Method};
+use shortener_types::analytics::{
+    Analytics, AnalyticsListResponse, ExportQuery, HealthResponse, ListAnalyticsQuery,
+    ReadyResponse, StreamMessage, TopAnalyticsQuery, TopAnalyticsResponse,
+};
+
+use crate::{
+    Error, Result,
+    http::{self, Http},
+};
+
+/// Client of the analytics-service HTTP API.
+#[derive(Debug, Clone)]
+pub struct AnalyticsClient {
+    http: Http,
+}
+
+impl AnalyticsClient {
+    /// Client of the service at `base_url`, such as `http://localhost:8081`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidBaseUrl` if `base_url` is not an HTTP URL.
+    pub fn new(base_url: &str) -> Result<Self> {
+        Self::with_client(Client::new(), base_url)
+    }
+
+    /// Client sending its requests through `client`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidBaseUrl` if `base_url` is not an HTTP URL.
+    pub fn with_client(client: Client, base_url: &str) -> Result<Self> {
+        Ok(Self {
+            http: Http::new(client, base_url)?,
+        })
+    }
+
+    /// Returns the click counters of a code.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::NotFound` inside `Error::Api` if the code has no
+    /// clicks.
+    pub async fn get_analytics(&self, code: &str) -> Result<Analytics> {
+        http::json(
+            self.http
+                .request(Method::GET, &["api", "v1", "analytics", code]),
+        )
+        .await
+    }
+
+    /// Lists the click counters of every code, ordered by code.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::BadRequest` inside `Error::Api` if the cursor is
+    /// malformed.
+    pub async fn list_analytics(
+        &self,
+        query: &ListAnalyticsQuery,
+    ) -> Result<AnalyticsListResponse> {
+        http::json(
+            self.http
+                .request(Method::GET, &["api", "v1", "analytics"])
+                .query(query),
+        )
+        .await
+    }
+
+    /// Ranks codes by clicks within a window.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub async fn top_analytics(&self, query: &TopAnalyticsQuery) -> Result<TopAnalyticsResponse> {
+        http::json(
+            self.http
+                .request(Method::GET, &["api", "v1", "analytics", "top"])
+                .query(query),
+        )
+        .await
+    }
+
+    /// Subscribes to clicks on `code`, or on every code if `None`.
+    ///
+    /// Reads the Server-Sent Events routes; the WebSocket route carries the
+    /// same messages. The stream ends when the connection closes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the subscription cannot be opened. Items fail if
+    /// the connection breaks or an event does not match the API.
+    pub async fn stream_clicks(
+        &self,
+        code: Option<&str>,
+    ) -> Result<impl Stream<Item = Result<StreamMessage>> + Send + use<>> {
+        let segments: &[&str] = match code {
+            Some(code) => &["api", "v1", "analytics", code, "stream"],
+            None => &["api", "v1", "analytics", "stream"],
+        };
+        let response = http::send(self.http.request(Method::GET, segments)).await?;
+
+        Ok(response
+            .bytes_stream()
+            .eventsource()
+            .map(|event| match event {
+                Ok(event) => stream_message(&event),
+                Err(EventStreamError::Transport(e)) => Err(Error::Request(e)),
+                Err(e) => Err(Error::InvalidEvent(e.to_string())),
+            }))
+    }
+
+    /// Downloads the stored access events of a code.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::BadRequest` or `AppError::ServiceUnavailable`
+    /// inside `Error::Api` if the range is invalid or event storage is not
+    /// configured. Chunks fail if the connection breaks.
+    pub async fn export_events(
+        &self,
+        code: &str,
+        query: &ExportQuery,
+    ) -> Result<impl Stream<Item = Result<Bytes>> + Send + use<>> {
+        self.export(&["api", "v1", "analytics", code, "events", "export"], query)
+            .await
+    }
+
+    /// Downloads the stored access events of every code.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::BadRequest` or `AppError::ServiceUnavailable`
+    /// inside `Error::Api` if the range is invalid or event storage is not
+    /// configured. Chunks fail if the connection breaks.
+    pub async fn export_all_events(
+        &self,
+        query: &ExportQuery,
+    ) -> Result<impl Stream<Item = Result<Bytes>> + Send + use<>> {
+        self.export(
+            &["api", "v1", "admin", "analytics", "events", "export"],
+            query,
+        )
+        .await
+    }
+
+    async fn export(
+        &self,
+        segments: &[&str],
+        query: &ExportQuery,
+    ) -> Result<impl Stream<Item = Result<Bytes>> + Send + use<>> {
+        let response = http::send(self.http.request(Method::GET, segments).query(query)).await?;
+        Ok(response
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(Error::from)))
+    }
+
+    /// Liveness probe.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the service cannot be reached.
+    pub async fn health(&self) -> Result<HealthResponse> {
+        http::json(self.http.request(Method::GET, &["health"])).await
+    }
+
+    /// Readiness probe. A service that is not ready answers with a report
+    /// too, so this only fails if it cannot be reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the service cannot be reached.
+    pub async fn ready(&self) -> Result<ReadyResponse> {
+        let response = self.http.request(Method::GET, &["ready"]).send().await?;
+        http::decode(response).await
+    }
+}
+
+/// Decodes an event of the Server-Sent Events routes.
+fn stream_message(event: &Event) -> Result<StreamMessage> {
+    let message = match event.event.as_str() {
+        "click" => serde_json::from_str(&event.data).map(StreamMessage::Click),
+        "lagged" => serde_json::from_str(&event.data),
+        other => return Err(Error::InvalidEvent(format!("unknown event '{other}'"))),
+    };
+    message.map_err(|e| Error::InvalidEvent(e.to_string()))
+}
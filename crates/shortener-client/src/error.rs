@@ -0,0 +1,36 @@
+use reqwest::StatusCode;
+use shortener_types::AppError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The service answered with an error response.
+    #[error("{status}: {error}")]
+    Api { status: StatusCode, error: AppError },
+
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("Invalid base URL: {0}")]
+    InvalidBaseUrl(String),
+
+    /// The response body does not match the API.
+    #[error("Unexpected response ({status}): {body}")]
+    UnexpectedResponse { status: StatusCode, body: String },
+
+    #[error("Invalid stream event: {0}")]
+    InvalidEvent(String),
+}
+
+impl Error {
+    /// The error the service reported, if it answered with one.
+    #[must_use]
+    pub fn app_error(&self) -> Option<&AppError> {
+        match self {
+            Self::Api { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,74 @@
+//! Request plumbing shared by the service clients.
+
+use reqwest::{Client, Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use shortener_types::error::ErrorResponse;
+use url::Url;
+
+use crate::{Error, Result};
+
+/// A `reqwest` client bound to the base URL of one service.
+#[derive(Debug, Clone)]
+pub(crate) struct Http {
+    client: Client,
+    base_url: Url,
+}
+
+impl Http {
+    pub(crate) fn new(client: Client, base_url: &str) -> Result<Self> {
+        let base_url = Url::parse(base_url).map_err(|e| Error::InvalidBaseUrl(e.to_string()))?;
+        if base_url.cannot_be_a_base() {
+            return Err(Error::InvalidBaseUrl(format!(
+                "'{base_url}' cannot have a path"
+            )));
+        }
+
+        Ok(Self { client, base_url })
+    }
+
+    /// Starts a request to the path of `segments` below the base URL. Each
+    /// segment is percent-encoded.
+    pub(crate) fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let mut url = self.base_url.clone();
+        // `new` rejected URLs that cannot have a path.
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty().extend(segments);
+        }
+
+        self.client.request(method, url)
+    }
+}
+
+/// Sends `request`, turning error responses into [`Error::Api`].
+pub(crate) async fn send(request: RequestBuilder) -> Result<Response> {
+    let response = request.send().await?;
+    let status = response.status();
+    if status.is_success() || status.is_redirection() {
+        return Ok(response);
+    }
+
+    let body = response.text().await?;
+    Err(match serde_json::from_str::<ErrorResponse>(&body) {
+        Ok(response) => Error::Api {
+            status,
+            error: response.error.into(),
+        },
+        Err(_) => Error::UnexpectedResponse { status, body },
+    })
+}
+
+/// Sends `request` and decodes the JSON body of its response.
+pub(crate) async fn json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+    decode(send(request).await?).await
+}
+
+/// Decodes the JSON body of `response`, whatever its status.
+pub(crate) async fn decode<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let status = response.status();
+    let body = response.bytes().await?;
+
+    serde_json::from_slice(&body).map_err(|_| Error::UnexpectedResponse {
+        status,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
@@ -0,0 +1,17 @@
+//! Typed HTTP client of shortener-service and analytics-service.
+//!
+//! [`ShortenerClient`] and [`AnalyticsClient`] have one method per endpoint
+//! and exchange the services' own request and response types from
+//! shortener-types, re-exported as [`shortener_api`] and [`analytics_api`],
+//! so using the client does not build either service. Error responses are returned
+//! as [`Error::Api`] carrying the [`AppError`] the service reported.
+
+mod analytics;
+mod error;
+mod http;
+mod shortener;
+
+pub use analytics::AnalyticsClient;
+pub use error::{Error, Result};
+pub use shortener::{Resolution, ShortenerClient};
+pub use shortener_types::{AppError, Page, analytics as analytics_api, shortener as shortener_api};
@@ -0,0 +1,308 @@
+use reqwest::{Client, Method, StatusCode, header, redirect};
+use shortener_types::{
+    Page,
+    shortener::{
+        BlockDomainRequest, BlockedDomain, CreateUrlRequest, CreateUrlResponse,
+        CreateWebhookRequest, CreateWebhookResponse, HealthResponse, ListDeliveriesQuery,
+        ListUrlsQuery, ReadyResponse, UpdateUrlRequest, Url, WebhookDelivery, WebhookSubscription,
+    },
+};
+use uuid::Uuid;
+
+use crate::{
+    Error, Result,
+    http::{self, Http},
+};
+
+/// What the redirect route answered for a short code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// A redirect to `location` with the link's redirect status.
+    Redirect {
+        status: StatusCode,
+        location: String,
+    },
+    /// An HTML page served instead of a redirect, such as the interstitial
+    /// warning.
+    Page(String),
+}
+
+/// Client of the shortener-service HTTP API.
+#[derive(Debug, Clone)]
+pub struct ShortenerClient {
+    http: Http,
+}
+
+impl ShortenerClient {
+    /// Client of the service at `base_url`, such as `http://localhost:8080`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidBaseUrl` if `base_url` is not an HTTP URL.
+    pub fn new(base_url: &str) -> Result<Self> {
+        let client = Client::builder()
+            .redirect(redirect::Policy::none())
+            .build()?;
+        Self::with_client(client, base_url)
+    }
+
+    /// Client sending its requests through `client`.
+    ///
+    /// [`resolve`](Self::resolve) needs `client` not to follow redirects.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidBaseUrl` if `base_url` is not an HTTP URL.
+    pub fn with_client(client: Client, base_url: &str) -> Result<Self> {
+        Ok(Self {
+            http: Http::new(client, base_url)?,
+        })
+    }
+
+    /// Creates a short URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::BadRequest` inside `Error::Api` if the destination
+    /// or a field is invalid.
+    pub async fn create_url(&self, req: &CreateUrlRequest) -> Result<CreateUrlResponse> {
+        http::json(
+            self.http
+                .request(Method::POST, &["api", "v1", "urls"])
+                .json(req),
+        )
+        .await
+    }
+
+    /// Lists active links, or deleted ones when `query.state` is
+    /// `UrlState::Deleted`, newest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::BadRequest` inside `Error::Api` if the cursor is
+    /// malformed.
+    pub async fn list_urls(&self, query: &ListUrlsQuery) -> Result<Page<Url>> {
+        http::json(
+            self.http
+                .request(Method::GET, &["api", "v1", "urls"])
+                .query(query),
+        )
+        .await
+    }
+
+    /// Returns the link with `code`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::NotFound` inside `Error::Api` if no active link
+    /// has this code.
+    pub async fn get_url(&self, code: &str) -> Result<Url> {
+        http::json(self.http.request(Method::GET, &["api", "v1", "urls", code])).await
+    }
+
+    /// Changes the destination and settings of a link.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::NotFound` or `AppError::BadRequest` inside
+    /// `Error::Api` if the link is missing or a field is invalid.
+    pub async fn update_url(&self, code: &str, req: &UpdateUrlRequest) -> Result<Url> {
+        http::json(
+            self.http
+                .request(Method::PUT, &["api", "v1", "urls", code])
+                .json(req),
+        )
+        .await
+    }
+
+    /// Moves a link to the trash.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::NotFound` inside `Error::Api` if no active link
+    /// has this code.
+    pub async fn delete_url(&self, code: &str) -> Result<()> {
+        http::send(
+            self.http
+                .request(Method::DELETE, &["api", "v1", "urls", code]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Takes a deleted link out of the trash.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::NotFound` inside `Error::Api` if no deleted link
+    /// has this code, for instance because it was purged.
+    pub async fn restore_url(&self, code: &str) -> Result<Url> {
+        http::json(
+            self.http
+                .request(Method::POST, &["api", "v1", "urls", code, "restore"]),
+        )
+        .await
+    }
+
+    /// Follows `code` once without leaving the service. The visit is counted
+    /// like any other.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::NotFound` or `AppError::Gone` inside `Error::Api`
+    /// if the link is missing, expired or disabled.
+    pub async fn resolve(&self, code: &str) -> Result<Resolution> {
+        let response = http::send(self.http.request(Method::GET, &[code])).await?;
+        let status = response.status();
+
+        if status.is_redirection() {
+            let location = response
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .map(str::to_string);
+            return match location {
+                Some(location) => Ok(Resolution::Redirect { status, location }),
+                None => Err(Error::UnexpectedResponse {
+                    status,
+                    body: "redirect without a Location header".to_string(),
+                }),
+            };
+        }
+
+        Ok(Resolution::Page(response.text().await?))
+    }
+
+    /// Lists the blocked destination domains.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub async fn list_blocked_domains(&self) -> Result<Vec<BlockedDomain>> {
+        http::json(
+            self.http
+                .request(Method::GET, &["api", "v1", "admin", "blocklist"]),
+        )
+        .await
+    }
+
+    /// Blocks a destination domain and its subdomains.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::BadRequest` inside `Error::Api` if the domain is
+    /// invalid.
+    pub async fn block_domain(&self, req: &BlockDomainRequest) -> Result<BlockedDomain> {
+        http::json(
+            self.http
+                .request(Method::POST, &["api", "v1", "admin", "blocklist"])
+                .json(req),
+        )
+        .await
+    }
+
+    /// Removes a domain from the blocklist.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::NotFound` or `AppError::BadRequest` inside
+    /// `Error::Api` if the domain is not blocked or invalid.
+    pub async fn unblock_domain(&self, domain: &str) -> Result<()> {
+        http::send(
+            self.http
+                .request(Method::DELETE, &["api", "v1", "admin", "blocklist", domain]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Lists the webhook subscriptions. Their secrets are left empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub async fn list_webhooks(&self) -> Result<Vec<WebhookSubscription>> {
+        http::json(
+            self.http
+                .request(Method::GET, &["api", "v1", "admin", "webhooks"]),
+        )
+        .await
+    }
+
+    /// Subscribes an endpoint to link events.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::BadRequest` inside `Error::Api` if the endpoint or
+    /// secret is invalid.
+    pub async fn create_webhook(
+        &self,
+        req: &CreateWebhookRequest,
+    ) -> Result<CreateWebhookResponse> {
+        http::json(
+            self.http
+                .request(Method::POST, &["api", "v1", "admin", "webhooks"])
+                .json(req),
+        )
+        .await
+    }
+
+    /// Deletes a webhook subscription and its deliveries.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::NotFound` inside `Error::Api` if there is no such
+    /// subscription.
+    pub async fn delete_webhook(&self, id: Uuid) -> Result<()> {
+        let id = id.to_string();
+        http::send(
+            self.http
+                .request(Method::DELETE, &["api", "v1", "admin", "webhooks", &id]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Lists the deliveries of a subscription, newest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::BadRequest` inside `Error::Api` if the cursor is
+    /// malformed.
+    pub async fn list_webhook_deliveries(
+        &self,
+        id: Uuid,
+        query: &ListDeliveriesQuery,
+    ) -> Result<Page<WebhookDelivery>> {
+        let id = id.to_string();
+        http::json(
+            self.http
+                .request(
+                    Method::GET,
+                    &["api", "v1", "admin", "webhooks", &id, "deliveries"],
+                )
+                .query(query),
+        )
+        .await
+    }
+
+    /// Liveness probe.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the service cannot be reached.
+    pub async fn health(&self) -> Result<HealthResponse> {
+        http::json(self.http.request(Method::GET, &["health"])).await
+    }
+
+    /// Readiness probe. A service that is not ready answers with a report
+    /// too, so this only fails if it cannot be reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the service cannot be reached.
+    pub async fn ready(&self) -> Result<ReadyResponse> {
+        let response = self.http.request(Method::GET, &["ready"]).send().await?;
+        http::decode(response).await
+    }
+}
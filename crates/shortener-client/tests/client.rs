@@ -0,0 +1,393 @@
+//! The clients against both services served over HTTP with in-memory stores.
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use analytics_service::repository::InMemoryAnalyticsStore;
+use axum::Router;
+use chrono::Utc;
+use futures_lite::StreamExt;
+use reqwest::StatusCode;
+use shortener_client::{
+    AnalyticsClient, AppError, Error, Resolution, ShortenerClient,
+    analytics_api::{
+        Click, ExportFormat, ExportQuery, ListAnalyticsQuery, StreamMessage, TopAnalyticsQuery,
+    },
+    shortener_api::{
+        BlockDomainRequest, CreateUrlRequest, CreateWebhookRequest, ListDeliveriesQuery,
+        ListUrlsQuery, RedirectType, UpdateUrlRequest, UrlState,
+    },
+};
+use shortener_service::{publisher::MockEventPublisher, repository::Stores};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+/// Serves `router` on a free local port and returns its base URL.
+async fn serve(router: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    format!("http://{addr}")
+}
+
+async fn shortener() -> ShortenerClient {
+    let mut event_publisher = MockEventPublisher::new();
+    event_publisher.expect_publish().returning(|_| Ok(()));
+    event_publisher
+        .expect_publish_link_event()
+        .returning(|_| Ok(()));
+
+    let state = shortener_service::AppState::new(Stores::in_memory(), Arc::new(event_publisher));
+    ShortenerClient::new(&serve(shortener_service::router(state)).await).unwrap()
+}
+
+async fn analytics() -> (AnalyticsClient, analytics_service::AppState) {
+    let state = analytics_service::AppState::new(Arc::new(InMemoryAnalyticsStore::new()), 16);
+    let base_url = serve(analytics_service::router(state.clone())).await;
+    (AnalyticsClient::new(&base_url).unwrap(), state)
+}
+
+fn create_request(url: &str) -> CreateUrlRequest {
+    CreateUrlRequest {
+        url: url.to_string(),
+        ..CreateUrlRequest::default()
+    }
+}
+
+#[tokio::test]
+async fn links_round_trip() {
+    let client = shortener().await;
+
+    let created = client
+        .create_url(&CreateUrlRequest {
+            tags: vec!["docs".to_string()],
+            redirect_type: RedirectType::Found,
+            ..create_request("https://example.com/old")
+        })
+        .await
+        .unwrap();
+    client
+        .create_url(&create_request("https://example.com/other"))
+        .await
+        .unwrap();
+
+    let page = client
+        .list_urls(&ListUrlsQuery {
+            tag: Some("docs".to_string()),
+            ..ListUrlsQuery::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].code, created.code);
+    assert_eq!(page.next_cursor, None);
+
+    let updated = client
+        .update_url(
+            &created.code,
+            &UpdateUrlRequest {
+                url: "https://example.com/new".to_string(),
+                title: Some("New".to_string()),
+                ..UpdateUrlRequest::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.original_url, "https://example.com/new");
+    assert_eq!(
+        client
+            .get_url(&created.code)
+            .await
+            .unwrap()
+            .title
+            .as_deref(),
+        Some("New")
+    );
+
+    assert_eq!(
+        client.resolve(&created.code).await.unwrap(),
+        Resolution::Redirect {
+            status: StatusCode::FOUND,
+            location: "https://example.com/new".to_string(),
+        }
+    );
+
+    client.delete_url(&created.code).await.unwrap();
+    let error = client.get_url(&created.code).await.unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::Api {
+                status: StatusCode::NOT_FOUND,
+                error: AppError::NotFound(_),
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[tokio::test]
+async fn deleted_links_are_listed_and_restored() {
+    let client = shortener().await;
+    let created = client
+        .create_url(&create_request("https://example.com"))
+        .await
+        .unwrap();
+    client.delete_url(&created.code).await.unwrap();
+
+    let deleted = ListUrlsQuery {
+        state: UrlState::Deleted,
+        ..ListUrlsQuery::default()
+    };
+    let page = client.list_urls(&deleted).await.unwrap();
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].code, created.code);
+    assert!(page.items[0].deleted_at.is_some());
+    assert!(
+        client
+            .list_urls(&ListUrlsQuery::default())
+            .await
+            .unwrap()
+            .items
+            .is_empty()
+    );
+
+    let restored = client.restore_url(&created.code).await.unwrap();
+    assert!(restored.is_active);
+    assert_eq!(restored.deleted_at, None);
+    assert!(client.list_urls(&deleted).await.unwrap().items.is_empty());
+    assert_eq!(
+        client.resolve(&created.code).await.unwrap(),
+        Resolution::Redirect {
+            status: StatusCode::TEMPORARY_REDIRECT,
+            location: "https://example.com".to_string(),
+        }
+    );
+
+    let error = client.restore_url(&created.code).await.unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::Api {
+                status: StatusCode::NOT_FOUND,
+                error: AppError::NotFound(_),
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[tokio::test]
+async fn error_responses_become_app_errors() {
+    let client = shortener().await;
+
+    let error = client
+        .create_url(&create_request("ftp://example.com/file"))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error.app_error(), Some(AppError::BadRequest(_))),
+        "{error:?}"
+    );
+
+    let error = client
+        .list_urls(&ListUrlsQuery {
+            cursor: Some("not a cursor".to_string()),
+            ..ListUrlsQuery::default()
+        })
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error.app_error(), Some(AppError::BadRequest(_))),
+        "{error:?}"
+    );
+
+    let error = client.resolve("missing").await.unwrap_err();
+    assert!(
+        matches!(error.app_error(), Some(AppError::NotFound(_))),
+        "{error:?}"
+    );
+}
+
+#[tokio::test]
+async fn blocklist_and_webhooks() {
+    let client = shortener().await;
+
+    let blocked = client
+        .block_domain(&BlockDomainRequest {
+            domain: "example.org".to_string(),
+            reason: Some("spam".to_string()),
+        })
+        .await
+        .unwrap();
+    assert_eq!(blocked.reason.as_deref(), Some("spam"));
+    assert_eq!(client.list_blocked_domains().await.unwrap().len(), 1);
+    let error = client
+        .create_url(&create_request("https://www.example.org/"))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error.app_error(), Some(AppError::BadRequest(_))),
+        "{error:?}"
+    );
+    client.unblock_domain("example.org").await.unwrap();
+    assert!(client.list_blocked_domains().await.unwrap().is_empty());
+
+    let created = client
+        .create_webhook(&CreateWebhookRequest {
+            url: "https://hooks.example.com/links".to_string(),
+            secret: None,
+            event_types: vec!["url.created".to_string()],
+        })
+        .await
+        .unwrap();
+    assert!(!created.secret.is_empty());
+
+    let subscriptions = client.list_webhooks().await.unwrap();
+    assert_eq!(subscriptions.len(), 1);
+    assert_eq!(subscriptions[0].id, created.subscription.id);
+    assert!(subscriptions[0].secret.is_empty());
+
+    let deliveries = client
+        .list_webhook_deliveries(created.subscription.id, &ListDeliveriesQuery::default())
+        .await
+        .unwrap();
+    assert!(deliveries.items.is_empty());
+
+    client
+        .delete_webhook(created.subscription.id)
+        .await
+        .unwrap();
+    let error = client
+        .delete_webhook(created.subscription.id)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error.app_error(), Some(AppError::NotFound(_))),
+        "{error:?}"
+    );
+}
+
+#[tokio::test]
+async fn analytics_counters() {
+    let (client, state) = analytics().await;
+    for code in ["aaa", "bbb", "bbb"] {
+        state
+            .analytics_store
+            .increment(code, Utc::now())
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(client.get_analytics("bbb").await.unwrap().access_count, 2);
+
+    let list = client
+        .list_analytics(&ListAnalyticsQuery {
+            limit: 1,
+            ..ListAnalyticsQuery::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(list.total, 2);
+    assert_eq!(list.page.items[0].code, "aaa");
+    let next = client
+        .list_analytics(&ListAnalyticsQuery {
+            limit: 1,
+            cursor: list.page.next_cursor,
+        })
+        .await
+        .unwrap();
+    assert_eq!(next.page.items[0].code, "bbb");
+
+    let top = client
+        .top_analytics(&TopAnalyticsQuery::default())
+        .await
+        .unwrap();
+    assert_eq!(top.items[0].code, "bbb");
+    assert_eq!(top.items[0].rank, 1);
+
+    let error = client.get_analytics("missing").await.unwrap_err();
+    assert!(
+        matches!(error.app_error(), Some(AppError::NotFound(_))),
+        "{error:?}"
+    );
+}
+
+#[tokio::test]
+async fn click_stream_delivers_published_clicks() {
+    let (client, state) = analytics().await;
+    let mut clicks = Box::pin(client.stream_clicks(Some("abc")).await.unwrap());
+
+    let event_id = Uuid::new_v4();
+    for code in ["other", "abc"] {
+        state.click_stream.publish(Click {
+            event_id: if code == "abc" {
+                event_id
+            } else {
+                Uuid::new_v4()
+            },
+            code: code.to_string(),
+            accessed_at: Utc::now(),
+            access_count: 1,
+        });
+    }
+
+    let message = tokio::time::timeout(Duration::from_secs(1), clicks.next())
+        .await
+        .expect("no click streamed")
+        .unwrap()
+        .unwrap();
+    assert!(
+        matches!(&message, StreamMessage::Click(click) if click.event_id == event_id),
+        "{message:?}"
+    );
+}
+
+#[tokio::test]
+async fn export_without_event_store_is_unavailable() {
+    let (client, _state) = analytics().await;
+
+    let query = ExportQuery {
+        format: ExportFormat::Csv,
+        from: None,
+        to: None,
+    };
+    let Err(error) = client.export_all_events(&query).await else {
+        panic!("export succeeded without an event store");
+    };
+    assert!(
+        matches!(error.app_error(), Some(AppError::ServiceUnavailable(_))),
+        "{error:?}"
+    );
+}
+
+#[tokio::test]
+async fn health_probes() {
+    let shortener = shortener().await;
+    let (analytics, _state) = analytics().await;
+
+    assert_eq!(
+        shortener.health().await.unwrap().service_name,
+        "shortener-service"
+    );
+    assert_eq!(shortener.ready().await.unwrap().status, "ready");
+    assert_eq!(
+        analytics.health().await.unwrap().service_name,
+        "analytics-service"
+    );
+    assert_eq!(analytics.ready().await.unwrap().status, "ready");
+}
+
+#[test]
+fn base_url_must_be_a_url() {
+    assert!(matches!(
+        ShortenerClient::new("localhost"),
+        Err(Error::InvalidBaseUrl(_))
+    ));
+}
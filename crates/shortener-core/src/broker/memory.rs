@@ -0,0 +1,224 @@
+//! In-process broker for single-binary deployments and tests.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use async_trait::async_trait;
+use tokio::sync::{Mutex as AsyncMutex, mpsc};
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::{Acker, Broker, Delivery, Subscription, dead_letter_queue, trace_headers};
+use crate::{AppError, messaging::Codec};
+
+/// Broker delivering messages between tasks of one process.
+///
+/// Clones share their queues, so every service of a process must be given
+/// a clone of the same broker. Messages are lost when the process exits.
+/// Dead letters go to a queue without routing keys, which can be
+/// subscribed to like any other.
+#[derive(Clone, Default)]
+pub struct InProcessBroker {
+    queues: Queues,
+}
+
+type Queues = Arc<Mutex<HashMap<String, Queue>>>;
+
+struct Queue {
+    routing_keys: HashSet<String>,
+    sender: mpsc::UnboundedSender<Message>,
+    receiver: Arc<AsyncMutex<mpsc::UnboundedReceiver<Message>>>,
+}
+
+impl Queue {
+    fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            routing_keys: HashSet::new(),
+            sender,
+            receiver: Arc::new(AsyncMutex::new(receiver)),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Message {
+    routing_key: String,
+    id: Option<String>,
+    content_type: Option<String>,
+    payload: Vec<u8>,
+    headers: HashMap<String, String>,
+}
+
+/// Where deliveries of a queue go when nacked without requeue.
+#[derive(Clone)]
+pub(super) struct DeadLetters {
+    queues: Queues,
+    queue: String,
+}
+
+impl DeadLetters {
+    pub(super) fn push(&self, delivery: Delivery) {
+        let message = Message {
+            routing_key: delivery.routing_key,
+            id: delivery.message_id,
+            content_type: delivery.content_type,
+            payload: delivery.payload,
+            headers: delivery.headers,
+        };
+
+        let mut queues = self.queues.lock().unwrap_or_else(PoisonError::into_inner);
+        let queue = queues
+            .entry(dead_letter_queue(&self.queue))
+            .or_insert_with(Queue::new);
+        // The receiver lives as long as the queue.
+        let _ = queue.sender.send(message);
+    }
+}
+
+#[async_trait]
+impl Broker for InProcessBroker {
+    fn system(&self) -> &'static str {
+        "in_process"
+    }
+
+    #[instrument(skip(self))]
+    async fn declare_queue(&self, queue: &str, routing_keys: &[&str]) -> Result<(), AppError> {
+        let mut queues = self.queues.lock().unwrap_or_else(PoisonError::into_inner);
+        let queue = queues.entry(queue.to_string()).or_insert_with(Queue::new);
+        queue
+            .routing_keys
+            .extend(routing_keys.iter().map(ToString::to_string));
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, payload))]
+    async fn publish(
+        &self,
+        routing_key: &str,
+        message_id: &str,
+        codec: Codec,
+        payload: &[u8],
+    ) -> Result<(), AppError> {
+        let message = Message {
+            routing_key: routing_key.to_string(),
+            id: Some(message_id.to_string()),
+            content_type: Some(codec.content_type().to_string()),
+            payload: payload.to_vec(),
+            headers: trace_headers(),
+        };
+
+        let queues = self.queues.lock().unwrap_or_else(PoisonError::into_inner);
+        for queue in queues.values() {
+            if queue.routing_keys.contains(routing_key) {
+                // The receiver lives as long as the queue.
+                let _ = queue.sender.send(message.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn subscribe(
+        &self,
+        queue: &str,
+        routing_keys: &[&str],
+    ) -> Result<Subscription, AppError> {
+        self.declare_queue(queue, routing_keys).await?;
+        Ok(self.consume(queue, false))
+    }
+
+    #[instrument(skip(self))]
+    async fn subscribe_broadcast(&self, routing_keys: &[&str]) -> Result<Subscription, AppError> {
+        let queue = format!("broadcast.{}", Uuid::new_v4());
+        self.declare_queue(&queue, routing_keys).await?;
+        Ok(self.consume(&queue, true))
+    }
+
+    #[instrument(skip(self))]
+    async fn replay_dead_letters(&self, queue: &str) -> Result<usize, AppError> {
+        let (dead_letters, sender) = {
+            let queues = self.queues.lock().unwrap_or_else(PoisonError::into_inner);
+            let (Some(dead_letters), Some(queue)) =
+                (queues.get(&dead_letter_queue(queue)), queues.get(queue))
+            else {
+                return Ok(0);
+            };
+            (Arc::clone(&dead_letters.receiver), queue.sender.clone())
+        };
+
+        let Ok(mut dead_letters) = dead_letters.try_lock() else {
+            return Err(AppError::MessageQueue(format!(
+                "The dead letters of '{queue}' are being consumed"
+            )));
+        };
+        let count = dead_letters.len();
+        for _ in 0..count {
+            let Ok(message) = dead_letters.try_recv() else {
+                break;
+            };
+            // The receiver lives as long as the queue.
+            let _ = sender.send(message);
+        }
+
+        Ok(count)
+    }
+}
+
+impl InProcessBroker {
+    /// Starts consuming the declared `queue`. An `exclusive` queue has no
+    /// dead letters and is deleted once the subscription is dropped.
+    fn consume(&self, queue: &str, exclusive: bool) -> Subscription {
+        let receiver = {
+            let queues = self.queues.lock().unwrap_or_else(PoisonError::into_inner);
+            Arc::clone(&queues[queue].receiver)
+        };
+
+        let (sender, subscription) = Subscription::channel();
+        let queues = Arc::clone(&self.queues);
+        let queue = queue.to_string();
+        tokio::spawn(async move {
+            loop {
+                // Competing subscriptions take turns receiving.
+                let message = tokio::select! {
+                    message = async { receiver.lock().await.recv().await } => message,
+                    () = sender.closed() => None,
+                };
+                let Some(message) = message else {
+                    break;
+                };
+
+                let delivery = Delivery {
+                    routing_key: message.routing_key,
+                    message_id: message.id,
+                    content_type: message.content_type,
+                    payload: message.payload,
+                    headers: message.headers,
+                    acker: Acker::Local {
+                        redeliver: sender.redeliver.clone(),
+                        dead_letters: (!exclusive).then(|| DeadLetters {
+                            queues: Arc::clone(&queues),
+                            queue: queue.clone(),
+                        }),
+                    },
+                };
+                if !sender.send(delivery).await {
+                    break;
+                }
+            }
+
+            if exclusive {
+                queues
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .remove(&queue);
+            }
+        });
+
+        subscription
+    }
+}
@@ -0,0 +1,255 @@
+//! Message brokers events are published and consumed through.
+//!
+//! Every backend delivers messages at least once: consumers ack a delivery
+//! after handling it, and a delivery nacked with `requeue` is delivered
+//! again. A delivery nacked without `requeue` is moved to the
+//! [dead letter queue](dead_letter_queue) of its queue instead, for an
+//! operator to inspect. The trace context of the publishing span travels in message
+//! headers, so consumer spans join the trace that produced the message.
+
+mod memory;
+mod rabbitmq;
+mod redis_streams;
+
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use opentelemetry::{
+    Context, global,
+    propagation::{Extractor, Injector},
+};
+use tokio::sync::mpsc;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{AppError, config::BrokerConfig, messaging::Codec};
+
+pub use memory::InProcessBroker;
+pub use rabbitmq::RabbitMQBroker;
+pub use redis_streams::RedisStreamsBroker;
+
+/// Deliveries buffered per subscription ahead of the consumer.
+const PREFETCH: usize = 32;
+
+/// Returns the name of the queue messages of `queue` are dead-lettered to.
+#[must_use]
+pub fn dead_letter_queue(queue: &str) -> String {
+    format!("{queue}.dead-letter")
+}
+
+/// A message broker with durable queues bound to routing keys.
+#[async_trait]
+pub trait Broker: Send + Sync {
+    /// Name reported as `messaging.system` on consumer spans.
+    fn system(&self) -> &'static str;
+
+    /// Declares the durable `queue`, which from then on receives every
+    /// message published with one of `routing_keys`, along with its dead
+    /// letter queue. Declaring an existing queue adds the bindings.
+    async fn declare_queue(&self, queue: &str, routing_keys: &[&str]) -> Result<(), AppError>;
+
+    /// Publishes a persistent message encoded with `codec`, carrying the
+    /// current trace context in its headers.
+    async fn publish(
+        &self,
+        routing_key: &str,
+        message_id: &str,
+        codec: Codec,
+        payload: &[u8],
+    ) -> Result<(), AppError>;
+
+    /// Declares `queue` as in [`Broker::declare_queue`] and starts consuming
+    /// it. Subscriptions to the same queue compete for its messages.
+    async fn subscribe(&self, queue: &str, routing_keys: &[&str])
+    -> Result<Subscription, AppError>;
+
+    /// Starts receiving a copy of every message published with one of
+    /// `routing_keys` from now on, through a queue of this subscription's
+    /// own that goes away with it. Unlike [`Broker::subscribe`], every
+    /// subscription sees every message, and messages published while none
+    /// is running are lost.
+    async fn subscribe_broadcast(&self, routing_keys: &[&str]) -> Result<Subscription, AppError>;
+
+    /// Moves the messages dead-lettered from `queue` so far back onto it,
+    /// and only it, keeping their routing keys. Messages dead-lettered
+    /// again meanwhile are left for the next replay. Returns how many
+    /// messages were moved.
+    async fn replay_dead_letters(&self, queue: &str) -> Result<usize, AppError>;
+}
+
+/// Connects to the broker selected by `config`.
+///
+/// # Errors
+///
+/// Returns `AppError::MessageQueue` if the broker cannot be reached.
+pub async fn connect(config: &BrokerConfig) -> Result<Arc<dyn Broker>, AppError> {
+    Ok(match config {
+        BrokerConfig::RabbitMQ(config) => Arc::new(RabbitMQBroker::try_new(config).await?),
+        BrokerConfig::RedisStreams(config) => Arc::new(RedisStreamsBroker::try_new(config).await?),
+        BrokerConfig::InProcess => Arc::new(InProcessBroker::default()),
+    })
+}
+
+/// A message received from a queue, to be acked once handled.
+pub struct Delivery {
+    pub routing_key: String,
+    pub message_id: Option<String>,
+    pub content_type: Option<String>,
+    pub payload: Vec<u8>,
+    headers: HashMap<String, String>,
+    acker: Acker,
+}
+
+/// How a delivery is settled with the backend it came from.
+enum Acker {
+    RabbitMQ(lapin::acker::Acker),
+    Redis {
+        entry: Box<redis_streams::PendingEntry>,
+        redeliver: mpsc::UnboundedSender<Delivery>,
+    },
+    /// Settled in this process: nothing to ack, and requeued deliveries are
+    /// handed to the same subscription again. Deliveries of broadcast
+    /// subscriptions have no dead letters.
+    Local {
+        redeliver: mpsc::UnboundedSender<Delivery>,
+        dead_letters: Option<memory::DeadLetters>,
+    },
+}
+
+impl Delivery {
+    /// Returns the codec the message was published with.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Serialization` for unsupported content types.
+    pub fn codec(&self) -> Result<Codec, AppError> {
+        Codec::from_content_type(self.content_type.as_deref())
+    }
+
+    /// Returns the trace context the publisher put in the message headers.
+    #[must_use]
+    pub fn trace_context(&self) -> Context {
+        let carrier = HeaderCarrier(self.headers.clone());
+        global::get_text_map_propagator(|propagator| propagator.extract(&carrier))
+    }
+
+    /// Removes the message from its queue.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::MessageQueue` if the broker rejects the ack.
+    pub async fn ack(self) -> Result<(), AppError> {
+        match &self.acker {
+            Acker::RabbitMQ(acker) => rabbitmq::ack(acker).await,
+            Acker::Redis { entry, .. } => entry.ack().await,
+            Acker::Local { .. } => Ok(()),
+        }
+    }
+
+    /// Gives the message up, delivering it again if `requeue` is set and
+    /// moving it to the dead letter queue otherwise. Messages of broadcast
+    /// subscriptions are dropped instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::MessageQueue` if the broker rejects the nack.
+    pub async fn nack(self, requeue: bool) -> Result<(), AppError> {
+        match &self.acker {
+            Acker::RabbitMQ(acker) => rabbitmq::nack(acker, requeue).await,
+            Acker::Redis { redeliver, .. } | Acker::Local { redeliver, .. } if requeue => {
+                let redeliver = redeliver.clone();
+                redeliver
+                    .send(self)
+                    .map_err(|_| AppError::MessageQueue("Subscription closed".to_string()))
+            }
+            Acker::Redis { entry, .. } => entry.dead_letter(&self).await,
+            Acker::Local { dead_letters, .. } => {
+                if let Some(dead_letters) = dead_letters.clone() {
+                    dead_letters.push(self);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A stream of deliveries from a queue.
+pub struct Subscription {
+    deliveries: mpsc::Receiver<Delivery>,
+    redeliveries: mpsc::UnboundedReceiver<Delivery>,
+}
+
+impl Subscription {
+    /// Creates a subscription with the sender its backend feeds it through.
+    fn channel() -> (DeliverySender, Self) {
+        let (sender, deliveries) = mpsc::channel(PREFETCH);
+        let (redeliver, redeliveries) = mpsc::unbounded_channel();
+        (
+            DeliverySender { sender, redeliver },
+            Self {
+                deliveries,
+                redeliveries,
+            },
+        )
+    }
+
+    /// Waits for the next delivery, preferring redeliveries. Returns `None`
+    /// once the backend stopped delivering to this subscription.
+    pub async fn next(&mut self) -> Option<Delivery> {
+        tokio::select! {
+            biased;
+            Some(delivery) = self.redeliveries.recv() => Some(delivery),
+            delivery = self.deliveries.recv() => delivery,
+        }
+    }
+}
+
+/// Backend side of a [`Subscription`].
+#[derive(Clone)]
+struct DeliverySender {
+    sender: mpsc::Sender<Delivery>,
+    /// Deliveries nacked with `requeue`, handed out before new ones.
+    redeliver: mpsc::UnboundedSender<Delivery>,
+}
+
+impl DeliverySender {
+    /// Hands `delivery` to the consumer. Returns `false` once the
+    /// subscription has been dropped.
+    async fn send(&self, delivery: Delivery) -> bool {
+        self.sender.send(delivery).await.is_ok()
+    }
+
+    /// Completes once the subscription has been dropped.
+    async fn closed(&self) {
+        self.sender.closed().await;
+    }
+}
+
+/// Carrier for trace context in message headers.
+struct HeaderCarrier(HashMap<String, String>);
+
+impl Injector for HeaderCarrier {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.insert(key.to_string(), value);
+    }
+}
+
+impl Extractor for HeaderCarrier {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).collect()
+    }
+}
+
+/// Message headers carrying the trace context of the current span.
+fn trace_headers() -> HashMap<String, String> {
+    let mut carrier = HeaderCarrier(HashMap::new());
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut carrier);
+    });
+    carrier.0
+}
@@ -0,0 +1,406 @@
+//! `RabbitMQ` broker backed by a topic exchange.
+//!
+//! Every queue dead-letters to a queue of its own through the direct
+//! exchange `{exchange}.dead-letter`, with the queue name as routing key.
+//! Replayed dead letters are published to their queue alone through the
+//! default exchange, with their original routing key in a header.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use futures_lite::stream::StreamExt;
+use lapin::{
+    BasicProperties, Channel, Connection, ConnectionProperties, ExchangeKind,
+    acker::Acker,
+    message::Delivery as LapinDelivery,
+    options::{
+        BasicAckOptions, BasicConsumeOptions, BasicGetOptions, BasicNackOptions,
+        BasicPublishOptions, ExchangeDeclareOptions, QueueBindOptions, QueueDeclareOptions,
+    },
+    types::{AMQPValue, FieldTable, LongString},
+};
+use tracing::{error, info, instrument};
+
+use super::{Broker, Delivery, Subscription, dead_letter_queue, trace_headers};
+use crate::{AppError, config::RabbitMQConfig, messaging::Codec};
+
+/// Header holding the original routing key of a replayed dead letter.
+const ROUTING_KEY_HEADER: &str = "x-routing-key";
+
+/// Broker publishing to a durable `RabbitMQ` topic exchange.
+pub struct RabbitMQBroker {
+    channel: Channel,
+    exchange: String,
+    dead_letter_exchange: String,
+}
+
+impl RabbitMQBroker {
+    /// Connect to `RabbitMQ` and declare the exchange.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::MessageQueue` if connection, channel creation or
+    /// exchange declaration fails.
+    #[instrument(skip(config))]
+    pub async fn try_new(config: &RabbitMQConfig) -> Result<Self, AppError> {
+        let conn = Connection::connect(config.url.expose(), ConnectionProperties::default())
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        let channel = conn
+            .create_channel()
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        channel
+            .exchange_declare(
+                &config.exchange,
+                ExchangeKind::Topic,
+                ExchangeDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        let dead_letter_exchange = format!("{}.dead-letter", config.exchange);
+        channel
+            .exchange_declare(
+                &dead_letter_exchange,
+                ExchangeKind::Direct,
+                ExchangeDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        info!(exchange = %config.exchange, "RabbitMQ channel initialized");
+
+        Ok(Self {
+            channel,
+            exchange: config.exchange.clone(),
+            dead_letter_exchange,
+        })
+    }
+}
+
+#[async_trait]
+impl Broker for RabbitMQBroker {
+    fn system(&self) -> &'static str {
+        "rabbitmq"
+    }
+
+    #[instrument(skip(self))]
+    async fn declare_queue(&self, queue: &str, routing_keys: &[&str]) -> Result<(), AppError> {
+        let dead_letter_queue = dead_letter_queue(queue);
+        self.channel
+            .queue_declare(
+                &dead_letter_queue,
+                QueueDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+        self.channel
+            .queue_bind(
+                &dead_letter_queue,
+                &self.dead_letter_exchange,
+                queue,
+                QueueBindOptions::default(),
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        // Arguments of an existing queue cannot change, so queues declared
+        // without them have to be deleted first.
+        let mut arguments = FieldTable::default();
+        arguments.insert(
+            "x-dead-letter-exchange".into(),
+            AMQPValue::LongString(self.dead_letter_exchange.as_str().into()),
+        );
+        arguments.insert(
+            "x-dead-letter-routing-key".into(),
+            AMQPValue::LongString(queue.into()),
+        );
+        self.channel
+            .queue_declare(
+                queue,
+                QueueDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                arguments,
+            )
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        self.bind(queue, routing_keys).await
+    }
+
+    #[instrument(skip(self, payload))]
+    async fn publish(
+        &self,
+        routing_key: &str,
+        message_id: &str,
+        codec: Codec,
+        payload: &[u8],
+    ) -> Result<(), AppError> {
+        let mut headers = FieldTable::default();
+        for (k, v) in trace_headers() {
+            headers.insert(k.into(), AMQPValue::LongString(LongString::from(v)));
+        }
+
+        let properties = BasicProperties::default()
+            .with_content_type(codec.content_type().into())
+            .with_delivery_mode(2)
+            .with_message_id(message_id.into())
+            .with_headers(headers);
+
+        self.channel
+            .basic_publish(
+                &self.exchange,
+                routing_key,
+                BasicPublishOptions::default(),
+                payload,
+                properties,
+            )
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn subscribe(
+        &self,
+        queue: &str,
+        routing_keys: &[&str],
+    ) -> Result<Subscription, AppError> {
+        self.declare_queue(queue, routing_keys).await?;
+        self.consume(queue).await
+    }
+
+    #[instrument(skip(self))]
+    async fn subscribe_broadcast(&self, routing_keys: &[&str]) -> Result<Subscription, AppError> {
+        // A server-named queue of this connection, deleted along with its
+        // consumer.
+        let queue = self
+            .channel
+            .queue_declare(
+                "",
+                QueueDeclareOptions {
+                    exclusive: true,
+                    auto_delete: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+        self.bind(queue.name().as_str(), routing_keys).await?;
+
+        self.consume(queue.name().as_str()).await
+    }
+
+    #[instrument(skip(self))]
+    async fn replay_dead_letters(&self, queue: &str) -> Result<usize, AppError> {
+        let dead_letter_queue = dead_letter_queue(queue);
+        let declared = self
+            .channel
+            .queue_declare(
+                &dead_letter_queue,
+                QueueDeclareOptions {
+                    passive: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        let mut replayed = 0;
+        for _ in 0..declared.message_count() {
+            let Some(message) = self
+                .channel
+                .basic_get(&dead_letter_queue, BasicGetOptions::default())
+                .await
+                .map_err(|e| AppError::MessageQueue(e.to_string()))?
+            else {
+                break;
+            };
+            let delivery = message.delivery;
+
+            let mut headers = delivery.properties.headers().clone().unwrap_or_default();
+            let routing_key =
+                original_routing_key(&headers).unwrap_or_else(|| delivery.routing_key.to_string());
+            headers.insert(
+                ROUTING_KEY_HEADER.into(),
+                AMQPValue::LongString(routing_key.into()),
+            );
+            let properties = delivery.properties.clone().with_headers(headers);
+
+            self.channel
+                .basic_publish(
+                    "",
+                    queue,
+                    BasicPublishOptions::default(),
+                    &delivery.data,
+                    properties,
+                )
+                .await
+                .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+            ack(&delivery.acker).await?;
+            replayed += 1;
+        }
+
+        Ok(replayed)
+    }
+}
+
+/// Returns the routing key a dead letter was first published with: that of
+/// an earlier replay, or the first one `RabbitMQ` recorded in `x-death`.
+fn original_routing_key(headers: &FieldTable) -> Option<String> {
+    let header = |name: &str| {
+        headers
+            .inner()
+            .iter()
+            .find(|(key, _)| key.as_str() == name)
+            .map(|(_, value)| value)
+    };
+
+    if let Some(AMQPValue::LongString(routing_key)) = header(ROUTING_KEY_HEADER) {
+        return Some(routing_key.to_string());
+    }
+
+    let Some(AMQPValue::FieldArray(deaths)) = header("x-death") else {
+        return None;
+    };
+    deaths.as_slice().iter().rev().find_map(|death| {
+        let AMQPValue::FieldTable(death) = death else {
+            return None;
+        };
+        let (_, AMQPValue::FieldArray(routing_keys)) = death
+            .inner()
+            .iter()
+            .find(|(key, _)| key.as_str() == "routing-keys")?
+        else {
+            return None;
+        };
+        match routing_keys.as_slice().first()? {
+            AMQPValue::LongString(routing_key) => Some(routing_key.to_string()),
+            _ => None,
+        }
+    })
+}
+
+impl RabbitMQBroker {
+    async fn bind(&self, queue: &str, routing_keys: &[&str]) -> Result<(), AppError> {
+        for routing_key in routing_keys {
+            self.channel
+                .queue_bind(
+                    queue,
+                    &self.exchange,
+                    routing_key,
+                    QueueBindOptions::default(),
+                    FieldTable::default(),
+                )
+                .await
+                .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    async fn consume(&self, queue: &str) -> Result<Subscription, AppError> {
+        let mut consumer = self
+            .channel
+            .basic_consume(
+                queue,
+                "",
+                BasicConsumeOptions::default(),
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        let (sender, subscription) = Subscription::channel();
+        tokio::spawn(async move {
+            while let Some(delivery_result) = consumer.next().await {
+                match delivery_result {
+                    Ok(delivery) => {
+                        if !sender.send(into_delivery(delivery)).await {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        error!("Consumer error: {:?}", e);
+                    }
+                }
+            }
+        });
+
+        Ok(subscription)
+    }
+}
+
+fn into_delivery(delivery: LapinDelivery) -> Delivery {
+    let properties = &delivery.properties;
+    let headers: HashMap<String, String> = properties
+        .headers()
+        .as_ref()
+        .map(|headers| {
+            headers
+                .inner()
+                .iter()
+                .filter_map(|(k, v)| {
+                    if let AMQPValue::LongString(s) = v {
+                        Some((k.to_string(), s.to_string()))
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Delivery {
+        routing_key: headers
+            .get(ROUTING_KEY_HEADER)
+            .cloned()
+            .unwrap_or_else(|| delivery.routing_key.to_string()),
+        message_id: properties.message_id().as_ref().map(ToString::to_string),
+        content_type: properties.content_type().as_ref().map(ToString::to_string),
+        headers,
+        payload: delivery.data,
+        acker: super::Acker::RabbitMQ(delivery.acker),
+    }
+}
+
+pub(super) async fn ack(acker: &Acker) -> Result<(), AppError> {
+    acker
+        .ack(BasicAckOptions::default())
+        .await
+        .map(|_| ())
+        .map_err(|e| AppError::MessageQueue(e.to_string()))
+}
+
+pub(super) async fn nack(acker: &Acker, requeue: bool) -> Result<(), AppError> {
+    let options = BasicNackOptions {
+        requeue,
+        ..BasicNackOptions::default()
+    };
+    acker
+        .nack(options)
+        .await
+        .map(|_| ())
+        .map_err(|e| AppError::MessageQueue(e.to_string()))
+}
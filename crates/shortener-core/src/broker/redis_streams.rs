@@ -0,0 +1,523 @@
+//! Redis Streams broker.
+//!
+//! Each routing key is a stream named `{prefix}:{routing_key}` and each queue
+//! a consumer group on the streams of its routing keys. Entries stay pending
+//! in the group until acked; entries left pending by a consumer that went
+//! away are claimed by another consumer of the queue after [`CLAIM_IDLE_MS`].
+//! Entries nacked without requeue are moved to the stream
+//! `{prefix}:{queue}.dead-letter`, and replayed from there into
+//! `{prefix}:{queue}.replay`, which only the group of the queue reads.
+//! Broadcast subscriptions read the streams without a group.
+
+use std::{collections::HashMap, time::Duration};
+
+use async_trait::async_trait;
+use redis::{
+    AsyncCommands, Client, FromRedisValue,
+    aio::{ConnectionManager, MultiplexedConnection},
+    streams::{
+        StreamAutoClaimOptions, StreamAutoClaimReply, StreamId, StreamMaxlen, StreamRangeReply,
+        StreamReadOptions, StreamReadReply,
+    },
+};
+use tracing::{error, info, instrument};
+use uuid::Uuid;
+
+use super::{
+    Acker, Broker, Delivery, DeliverySender, Subscription, dead_letter_queue, trace_headers,
+};
+use crate::{AppError, config::RedisStreamsConfig, messaging::Codec};
+
+/// Entries read per stream and request.
+const BATCH_SIZE: usize = 32;
+
+/// How long a read waits for new entries before checking for stale ones.
+const BLOCK_MS: usize = 5_000;
+
+/// How long an entry stays pending before another consumer may claim it.
+const CLAIM_IDLE_MS: u64 = 300_000;
+
+/// Field prefix of the message headers stored in an entry.
+const HEADER_PREFIX: &str = "header:";
+
+/// Field holding the routing key of a dead-lettered or replayed entry.
+const ROUTING_KEY_FIELD: &str = "routing_key";
+
+/// Broker backed by Redis Streams and consumer groups.
+pub struct RedisStreamsBroker {
+    client: Client,
+    connection: ConnectionManager,
+    prefix: String,
+    max_len: usize,
+}
+
+impl RedisStreamsBroker {
+    /// Connect to Redis.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::MessageQueue` if Redis cannot be reached.
+    #[instrument(skip(config))]
+    pub async fn try_new(config: &RedisStreamsConfig) -> Result<Self, AppError> {
+        let client =
+            Client::open(config.url.expose()).map_err(|e| AppError::MessageQueue(e.to_string()))?;
+        let connection = ConnectionManager::new(client.clone())
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        info!(prefix = %config.prefix, "Redis Streams broker initialized");
+
+        Ok(Self {
+            client,
+            connection,
+            prefix: config.prefix.clone(),
+            max_len: config.max_len,
+        })
+    }
+
+    fn stream_key(&self, routing_key: &str) -> String {
+        format!("{}:{routing_key}", self.prefix)
+    }
+}
+
+#[async_trait]
+impl Broker for RedisStreamsBroker {
+    fn system(&self) -> &'static str {
+        "redis"
+    }
+
+    #[instrument(skip(self))]
+    async fn declare_queue(&self, queue: &str, routing_keys: &[&str]) -> Result<(), AppError> {
+        let mut conn = self.connection.clone();
+
+        let mut streams = vec![self.stream_key(&replay_queue(queue))];
+        for routing_key in routing_keys {
+            check_routing_key(routing_key)?;
+            streams.push(self.stream_key(routing_key));
+        }
+        for stream in streams {
+            // Like a new queue, a new group only sees entries added from now on.
+            let created: redis::RedisResult<()> =
+                conn.xgroup_create_mkstream(stream, queue, "$").await;
+            match created {
+                Err(e) if e.code() != Some("BUSYGROUP") => {
+                    return Err(AppError::MessageQueue(e.to_string()));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, payload))]
+    async fn publish(
+        &self,
+        routing_key: &str,
+        message_id: &str,
+        codec: Codec,
+        payload: &[u8],
+    ) -> Result<(), AppError> {
+        let fields = entry_fields(
+            Some(message_id),
+            Some(codec.content_type()),
+            payload,
+            &trace_headers(),
+        );
+
+        let mut conn = self.connection.clone();
+        let _: String = conn
+            .xadd_maxlen(
+                self.stream_key(routing_key),
+                StreamMaxlen::Approx(self.max_len),
+                "*",
+                &fields,
+            )
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn subscribe(
+        &self,
+        queue: &str,
+        routing_keys: &[&str],
+    ) -> Result<Subscription, AppError> {
+        self.declare_queue(queue, routing_keys).await?;
+
+        // Blocking reads get a connection of their own.
+        let reader = self
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        let (sender, subscription) = Subscription::channel();
+        let consumer = ConsumerLoop {
+            reader,
+            connection: self.connection.clone(),
+            prefix: format!("{}:", self.prefix),
+            streams: routing_keys
+                .iter()
+                .map(|k| self.stream_key(k))
+                .chain([self.stream_key(&replay_queue(queue))])
+                .collect(),
+            group: queue.to_string(),
+            consumer: Uuid::new_v4().to_string(),
+            dead_letter_stream: self.stream_key(&dead_letter_queue(queue)),
+            sender,
+        };
+        tokio::spawn(consumer.run());
+
+        Ok(subscription)
+    }
+
+    #[instrument(skip(self))]
+    async fn subscribe_broadcast(&self, routing_keys: &[&str]) -> Result<Subscription, AppError> {
+        let mut reader = self
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+        // Reads without a consumer group, starting after the last entry of
+        // each stream, so nothing is left behind in Redis.
+        let mut streams = Vec::with_capacity(routing_keys.len());
+        let mut last_ids = Vec::with_capacity(routing_keys.len());
+        for routing_key in routing_keys {
+            check_routing_key(routing_key)?;
+            let stream = self.stream_key(routing_key);
+            let last: StreamRangeReply = reader
+                .xrevrange_count(&stream, "+", "-", 1)
+                .await
+                .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+            last_ids.push(
+                last.ids
+                    .first()
+                    .map_or_else(|| "0-0".to_string(), |entry| entry.id.clone()),
+            );
+            streams.push(stream);
+        }
+
+        let (sender, subscription) = Subscription::channel();
+        let reader = BroadcastLoop {
+            reader,
+            prefix: format!("{}:", self.prefix),
+            streams,
+            last_ids,
+            sender,
+        };
+        tokio::spawn(reader.run());
+
+        Ok(subscription)
+    }
+
+    #[instrument(skip(self))]
+    async fn replay_dead_letters(&self, queue: &str) -> Result<usize, AppError> {
+        let dead_letter_stream = self.stream_key(&dead_letter_queue(queue));
+        let replay_stream = self.stream_key(&replay_queue(queue));
+        let mut conn = self.connection.clone();
+
+        let last: StreamRangeReply = conn
+            .xrevrange_count(&dead_letter_stream, "+", "-", 1)
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+        let Some(end) = last.ids.first().map(|entry| entry.id.clone()) else {
+            return Ok(0);
+        };
+
+        let mut replayed = 0;
+        let mut start = "-".to_string();
+        loop {
+            let page: StreamRangeReply = conn
+                .xrange_count(&dead_letter_stream, &start, &end, BATCH_SIZE)
+                .await
+                .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+
+            for entry in &page.ids {
+                let fields: Vec<(&String, Vec<u8>)> = entry
+                    .map
+                    .iter()
+                    .filter_map(|(field, value)| Some((field, Vec::from_redis_value(value).ok()?)))
+                    .collect();
+                redis::pipe()
+                    .atomic()
+                    .xadd(&replay_stream, "*", &fields)
+                    .ignore()
+                    .xdel(&dead_letter_stream, &[&entry.id])
+                    .ignore()
+                    .query_async::<()>(&mut conn)
+                    .await
+                    .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+                replayed += 1;
+            }
+
+            match page.ids.last() {
+                Some(last) if page.ids.len() == BATCH_SIZE => start = format!("({}", last.id),
+                _ => return Ok(replayed),
+            }
+        }
+    }
+}
+
+/// Fields of the stream entry of a message.
+fn entry_fields(
+    message_id: Option<&str>,
+    content_type: Option<&str>,
+    payload: &[u8],
+    headers: &HashMap<String, String>,
+) -> Vec<(String, Vec<u8>)> {
+    let mut fields = Vec::with_capacity(3 + headers.len());
+    if let Some(message_id) = message_id {
+        fields.push(("message_id".to_string(), message_id.as_bytes().to_vec()));
+    }
+    if let Some(content_type) = content_type {
+        fields.push(("content_type".to_string(), content_type.as_bytes().to_vec()));
+    }
+    fields.push(("payload".to_string(), payload.to_vec()));
+    for (k, v) in headers {
+        fields.push((format!("{HEADER_PREFIX}{k}"), v.as_bytes().to_vec()));
+    }
+    fields
+}
+
+/// Returns the name of the stream replayed dead letters of `queue` are
+/// added to, which only its group reads.
+fn replay_queue(queue: &str) -> String {
+    format!("{queue}.replay")
+}
+
+fn check_routing_key(routing_key: &str) -> Result<(), AppError> {
+    if routing_key.contains(['*', '#']) {
+        return Err(AppError::MessageQueue(format!(
+            "Routing key patterns are not supported by Redis Streams: '{routing_key}'"
+        )));
+    }
+    Ok(())
+}
+
+/// An entry delivered to a consumer group and not yet acked.
+pub(super) struct PendingEntry {
+    connection: ConnectionManager,
+    stream: String,
+    group: String,
+    id: String,
+    /// Stream the entry is moved to when dead-lettered.
+    dead_letter_stream: String,
+}
+
+impl PendingEntry {
+    /// Adds `delivery` to the dead letter stream and acks the entry, in one
+    /// transaction. The original routing key is kept in a field.
+    pub(super) async fn dead_letter(&self, delivery: &Delivery) -> Result<(), AppError> {
+        let mut fields = entry_fields(
+            delivery.message_id.as_deref(),
+            delivery.content_type.as_deref(),
+            &delivery.payload,
+            &delivery.headers,
+        );
+        fields.push((
+            ROUTING_KEY_FIELD.to_string(),
+            delivery.routing_key.as_bytes().to_vec(),
+        ));
+
+        let mut conn = self.connection.clone();
+        redis::pipe()
+            .atomic()
+            .xadd(&self.dead_letter_stream, "*", &fields)
+            .ignore()
+            .xack(&self.stream, &self.group, &[&self.id])
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))
+    }
+
+    pub(super) async fn ack(&self) -> Result<(), AppError> {
+        let mut conn = self.connection.clone();
+        let _: i64 = conn
+            .xack(&self.stream, &self.group, &[&self.id])
+            .await
+            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Reads the entries of one subscription into its channel.
+struct ConsumerLoop {
+    reader: MultiplexedConnection,
+    connection: ConnectionManager,
+    prefix: String,
+    streams: Vec<String>,
+    group: String,
+    consumer: String,
+    dead_letter_stream: String,
+    sender: DeliverySender,
+}
+
+impl ConsumerLoop {
+    async fn run(mut self) {
+        loop {
+            let entries = match self.read().await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    error!(group = %self.group, "Consumer error: {:?}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+
+            for (stream, entry) in entries {
+                if !self.sender.send(self.delivery(&stream, &entry)).await {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Claims stale entries of the group, then waits for new ones.
+    async fn read(&mut self) -> redis::RedisResult<Vec<(String, StreamId)>> {
+        let mut entries = Vec::new();
+
+        for stream in &self.streams {
+            let claimed: StreamAutoClaimReply = self
+                .reader
+                .xautoclaim_options(
+                    stream,
+                    &self.group,
+                    &self.consumer,
+                    CLAIM_IDLE_MS,
+                    "0-0",
+                    StreamAutoClaimOptions::default().count(BATCH_SIZE),
+                )
+                .await?;
+            entries.extend(claimed.claimed.into_iter().map(|id| (stream.clone(), id)));
+        }
+        if !entries.is_empty() {
+            return Ok(entries);
+        }
+
+        let options = StreamReadOptions::default()
+            .group(&self.group, &self.consumer)
+            .block(BLOCK_MS)
+            .count(BATCH_SIZE);
+        let ids = vec![">"; self.streams.len()];
+        let reply: Option<StreamReadReply> = self
+            .reader
+            .xread_options(&self.streams, &ids, &options)
+            .await?;
+
+        Ok(reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| {
+                let stream = key.key;
+                key.ids.into_iter().map(move |id| (stream.clone(), id))
+            })
+            .collect())
+    }
+
+    fn delivery(&self, stream: &str, entry: &StreamId) -> Delivery {
+        let acker = Acker::Redis {
+            entry: Box::new(PendingEntry {
+                connection: self.connection.clone(),
+                stream: stream.to_string(),
+                group: self.group.clone(),
+                id: entry.id.clone(),
+                dead_letter_stream: self.dead_letter_stream.clone(),
+            }),
+            redeliver: self.sender.redeliver.clone(),
+        };
+        into_delivery(&self.prefix, stream, entry, acker)
+    }
+}
+
+/// Reads the entries added to the streams of a broadcast subscription into
+/// its channel.
+struct BroadcastLoop {
+    reader: MultiplexedConnection,
+    prefix: String,
+    streams: Vec<String>,
+    /// ID of the last entry read from each of `streams`.
+    last_ids: Vec<String>,
+    sender: DeliverySender,
+}
+
+impl BroadcastLoop {
+    async fn run(mut self) {
+        let sender = self.sender.clone();
+        loop {
+            let entries = tokio::select! {
+                entries = self.read() => entries,
+                () = sender.closed() => return,
+            };
+            let entries = match entries {
+                Ok(entries) => entries,
+                Err(e) => {
+                    error!("Broadcast reader error: {:?}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+
+            for (stream, entry) in entries {
+                let acker = Acker::Local {
+                    redeliver: self.sender.redeliver.clone(),
+                    dead_letters: None,
+                };
+                let delivery = into_delivery(&self.prefix, &stream, &entry, acker);
+                if !self.sender.send(delivery).await {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Waits for entries after the last ones read.
+    async fn read(&mut self) -> redis::RedisResult<Vec<(String, StreamId)>> {
+        let options = StreamReadOptions::default()
+            .block(BLOCK_MS)
+            .count(BATCH_SIZE);
+        let reply: Option<StreamReadReply> = self
+            .reader
+            .xread_options(&self.streams, &self.last_ids, &options)
+            .await?;
+
+        let mut entries = Vec::new();
+        for key in reply.into_iter().flat_map(|reply| reply.keys) {
+            if let Some(index) = self.streams.iter().position(|s| *s == key.key)
+                && let Some(last) = key.ids.last()
+            {
+                self.last_ids[index].clone_from(&last.id);
+            }
+            entries.extend(key.ids.into_iter().map(|id| (key.key.clone(), id)));
+        }
+        Ok(entries)
+    }
+}
+
+/// Converts the `entry` of `stream` into a delivery settled with `acker`.
+fn into_delivery(prefix: &str, stream: &str, entry: &StreamId, acker: Acker) -> Delivery {
+    let headers: HashMap<String, String> = entry
+        .map
+        .iter()
+        .filter_map(|(field, value)| {
+            let name = field.strip_prefix(HEADER_PREFIX)?;
+            let value = String::from_redis_value(value).ok()?;
+            Some((name.to_string(), value))
+        })
+        .collect();
+
+    Delivery {
+        routing_key: entry
+            .get(ROUTING_KEY_FIELD)
+            .unwrap_or_else(|| stream.strip_prefix(prefix).unwrap_or(stream).to_string()),
+        message_id: entry.get("message_id"),
+        content_type: entry.get("content_type"),
+        payload: entry.get("payload").unwrap_or_default(),
+        headers,
+        acker,
+    }
+}
@@ -16,13 +16,30 @@ pub struct RedisConfig {
     pub url: SecretString,
 }
 
+/// Message broker configuration.
+#[derive(Debug, Clone)]
+pub enum BrokerConfig {
+    RabbitMQ(RabbitMQConfig),
+    RedisStreams(RedisStreamsConfig),
+    /// Queues in process memory, shared by the services of one binary.
+    InProcess,
+}
+
 /// `RabbitMQ` configuration.
 #[derive(Debug, Clone)]
 pub struct RabbitMQConfig {
     pub url: SecretString,
     pub exchange: String,
-    pub queue: String,
-    pub routing_key: String,
+}
+
+/// Redis Streams broker configuration.
+#[derive(Debug, Clone)]
+pub struct RedisStreamsConfig {
+    pub url: SecretString,
+    /// Prefix of the stream keys, followed by the routing key.
+    pub prefix: String,
+    /// Approximate number of entries kept per stream.
+    pub max_len: usize,
 }
 
 /// Observability configuration.
@@ -1,110 +1 @@
-use axum::{
-    Json,
-    http::StatusCode,
-    response::{IntoResponse, Response},
-};
-use serde::Serialize;
-use thiserror::Error;
-
-#[derive(Debug, Error)]
-pub enum AppError {
-    #[error("Resource not found: {0}")]
-    NotFound(String),
-
-    #[error("Invalid input: {0}")]
-    BadRequest(String),
-
-    #[error("Conflict: {0}")]
-    Conflict(String),
-
-    #[error("Database error: {0}")]
-    Database(String),
-
-    #[error("Redis error: {0}")]
-    Redis(String),
-
-    #[error("Message queue error: {0}")]
-    MessageQueue(String),
-
-    #[error("Serialization error: {0}")]
-    Serialization(String),
-
-    #[error("URL parse error: {0}")]
-    UrlParse(String),
-
-    #[error("Internal server error: {0}")]
-    Internal(String),
-}
-
-#[derive(Serialize)]
-struct ErrorResponse {
-    error: ErrorBody,
-}
-
-#[derive(Serialize)]
-struct ErrorBody {
-    code: String,
-    message: String,
-}
-
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, code, message) = match &self {
-            Self::NotFound(msg) => (StatusCode::NOT_FOUND, "NOT_FOUND", msg.clone()),
-            Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, "BAD_REQUEST", msg.clone()),
-            Self::Conflict(msg) => (StatusCode::CONFLICT, "CONFLICT", msg.clone()),
-            Self::Database(e) => {
-                tracing::error!("Database error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "DATABASE_ERROR",
-                    "Database error occurred".to_string(),
-                )
-            }
-            Self::Redis(e) => {
-                tracing::error!("Redis error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "REDIS_ERROR",
-                    "Redis error occurred".to_string(),
-                )
-            }
-            Self::MessageQueue(e) => {
-                tracing::error!("Message queue error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "MQ_ERROR",
-                    "Message queue error occurred".to_string(),
-                )
-            }
-            Self::Serialization(e) => {
-                tracing::error!("Serialization error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "SERIALIZATION_ERROR",
-                    "Serialization error".to_string(),
-                )
-            }
-            Self::UrlParse(e) => (StatusCode::BAD_REQUEST, "INVALID_URL", e.clone()),
-            Self::Internal(e) => {
-                tracing::error!("Internal error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "INTERNAL_ERROR",
-                    "Internal server error".to_string(),
-                )
-            }
-        };
-
-        let body = Json(ErrorResponse {
-            error: ErrorBody {
-                code: code.to_string(),
-                message,
-            },
-        });
-
-        (status, body).into_response()
-    }
-}
-
-pub type Result<T> = std::result::Result<T, AppError>;
+pub use shortener_types::error::{AppError, ErrorBody, ErrorResponse, Result};
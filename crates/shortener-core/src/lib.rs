@@ -1,8 +1,11 @@
+pub mod broker;
 pub mod config;
 pub mod error;
 pub mod messaging;
-pub mod rabbitmq;
+pub mod openapi;
+pub mod pagination;
 pub mod telemetry;
 
+pub use broker::Broker;
 pub use error::{AppError, Result};
-pub use rabbitmq::RabbitMQChannel;
+pub use pagination::Page;
@@ -0,0 +1,112 @@
+//! Serialization formats of messages on the bus.
+
+use std::str::FromStr;
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::AppError;
+
+const JSON_CONTENT_TYPE: &str = "application/json";
+const MESSAGE_PACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// Format a message body is encoded in, announced by the message's
+/// `content_type` so consumers can read queues holding both formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Codec {
+    #[default]
+    Json,
+    /// `MessagePack` with the same shape as JSON: structs are maps keyed by
+    /// field name and UUIDs are strings, so payloads evolve and upcast as
+    /// JSON ones do. Compact payloads of earlier builds, which wrote
+    /// structs as arrays of their fields and UUIDs as bytes, still decode
+    /// as long as their schema version is the current one.
+    MessagePack,
+}
+
+impl Codec {
+    #[must_use]
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => JSON_CONTENT_TYPE,
+            Self::MessagePack => MESSAGE_PACK_CONTENT_TYPE,
+        }
+    }
+
+    /// Returns the codec of a message with `content_type`. Messages without
+    /// one predate the codec choice and are JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Serialization` for unsupported content types.
+    pub fn from_content_type(content_type: Option<&str>) -> Result<Self, AppError> {
+        let Some(content_type) = content_type else {
+            return Ok(Self::Json);
+        };
+
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        match essence.as_str() {
+            JSON_CONTENT_TYPE => Ok(Self::Json),
+            MESSAGE_PACK_CONTENT_TYPE | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Ok(Self::MessagePack)
+            }
+            _ => Err(AppError::Serialization(format!(
+                "Unsupported content type '{content_type}'"
+            ))),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns `AppError::Serialization` if `value` cannot be serialized.
+    pub fn encode<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<u8>, AppError> {
+        match self {
+            Self::Json => {
+                serde_json::to_vec(value).map_err(|e| AppError::Serialization(e.to_string()))
+            }
+            Self::MessagePack => {
+                let mut bytes = Vec::new();
+                let mut serializer = rmp_serde::Serializer::new(&mut bytes)
+                    .with_struct_map()
+                    .with_human_readable();
+                value
+                    .serialize(&mut serializer)
+                    .map_err(|e| AppError::Serialization(e.to_string()))?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns `AppError::Serialization` if `bytes` is not a valid `T`.
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, AppError> {
+        match self {
+            Self::Json => {
+                serde_json::from_slice(bytes).map_err(|e| AppError::Serialization(e.to_string()))
+            }
+            Self::MessagePack => {
+                let mut deserializer =
+                    rmp_serde::Deserializer::from_read_ref(bytes).with_human_readable();
+                T::deserialize(&mut deserializer)
+                    .map_err(|e| AppError::Serialization(e.to_string()))
+            }
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "msgpack" => Ok(Self::MessagePack),
+            other => Err(AppError::Internal(format!("Unknown event codec '{other}'"))),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Versioned wire format of the events on the message bus.
+//!
+//! Every event is published wrapped in an [`Envelope`] naming its type and
+//! schema version. Consumers upcast payloads written with an older schema
+//! to the current one, so consumers can be deployed ahead of producers as
+//! long as each schema change is covered by [`VersionedEvent::upcast`].
+//!
+//! The other way round only works for fields added to JSON events: a
+//! consumer decodes a newer schema version as the one it knows, and fails
+//! on anything else. Consumers dead-letter messages they cannot decode, so
+//! deploy consumers first, or replay the dead letters once they caught up.
+
+use chrono::{DateTime, Utc};
+use std::fmt::Display;
+
+use serde::{
+    Deserialize, Serialize,
+    de::{DeserializeOwned, IgnoredAny},
+};
+use serde_json::Value;
+
+use super::Codec;
+use crate::AppError;
+
+/// Schema version of events published before envelopes were introduced,
+/// which are bare JSON payloads.
+pub const UNVERSIONED_SCHEMA_VERSION: u32 = 1;
+
+/// An event together with the metadata needed to decode it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<T = Value> {
+    /// Event type, as in [`VersionedEvent::EVENT_TYPE`].
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// Schema version `payload` was written with.
+    pub schema_version: u32,
+    /// Name of the service that published the event.
+    pub producer: String,
+    /// When the event was published.
+    pub produced_at: DateTime<Utc>,
+    pub payload: T,
+}
+
+/// An event type with a versioned wire schema.
+pub trait VersionedEvent: Serialize + DeserializeOwned {
+    /// Type name written to the envelope.
+    const EVENT_TYPE: &'static str;
+
+    /// Schema version the type is serialized with. Bump it whenever a field
+    /// is renamed, removed or changes meaning, and teach [`Self::upcast`] to
+    /// convert the previous version.
+    const SCHEMA_VERSION: u32;
+
+    /// Converts a payload of schema `version` to schema `version + 1`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Serialization` if `version` cannot be upcast.
+    fn upcast(version: u32, _payload: Value) -> Result<Value, AppError> {
+        Err(AppError::Serialization(format!(
+            "No upcast from {} schema version {version}",
+            Self::EVENT_TYPE
+        )))
+    }
+}
+
+/// Serializes `event` with `codec` in an envelope naming `producer`.
+///
+/// # Errors
+///
+/// Returns `AppError::Serialization` if `event` cannot be serialized.
+pub fn encode<T: VersionedEvent>(
+    codec: Codec,
+    producer: &str,
+    event: &T,
+) -> Result<Vec<u8>, AppError> {
+    codec.encode(&Envelope::<&T> {
+        event_type: T::EVENT_TYPE.to_string(),
+        schema_version: T::SCHEMA_VERSION,
+        producer: producer.to_string(),
+        produced_at: Utc::now(),
+        payload: event,
+    })
+}
+
+/// Envelope fields of a message, with the payload skipped.
+///
+/// Bare payloads published before envelopes were introduced have no
+/// `schema_version` or `payload` field.
+#[derive(Deserialize)]
+struct Header {
+    #[serde(rename = "type", default)]
+    event_type: String,
+    #[serde(default)]
+    schema_version: Option<u32>,
+    // Skipped, but declared so compact `MessagePack` envelopes, which are
+    // arrays, line up with `Envelope`.
+    #[serde(rename = "producer", default)]
+    _producer: Option<IgnoredAny>,
+    #[serde(rename = "produced_at", default)]
+    _produced_at: Option<IgnoredAny>,
+    #[serde(default)]
+    payload: Option<IgnoredAny>,
+}
+
+impl Header {
+    fn read(codec: Codec, bytes: &[u8]) -> Result<Self, AppError> {
+        codec.decode(bytes)
+    }
+
+    fn is_envelope(&self) -> bool {
+        self.schema_version.is_some() && self.payload.is_some()
+    }
+
+    fn schema_version(&self) -> u32 {
+        self.schema_version
+            .filter(|_| self.is_envelope())
+            .unwrap_or(UNVERSIONED_SCHEMA_VERSION)
+    }
+
+    /// Decodes the message `header` was read from with a `T` payload.
+    fn open<T: DeserializeOwned>(
+        self,
+        codec: Codec,
+        bytes: &[u8],
+    ) -> Result<Envelope<T>, AppError> {
+        if self.is_envelope() {
+            return codec.decode(bytes);
+        }
+
+        Ok(Envelope {
+            event_type: String::new(),
+            schema_version: UNVERSIONED_SCHEMA_VERSION,
+            producer: String::new(),
+            produced_at: DateTime::UNIX_EPOCH,
+            payload: codec.decode(bytes)?,
+        })
+    }
+}
+
+/// Decodes the envelope of a message with a `T` payload. Use
+/// [`IgnoredAny`] as `T` to read the envelope fields only.
+///
+/// Bare payloads published before envelopes were introduced are returned
+/// in an envelope of [`UNVERSIONED_SCHEMA_VERSION`] with an empty type and
+/// producer.
+///
+/// # Errors
+///
+/// Returns `AppError::Serialization` if `bytes` is not valid for `codec` or
+/// its payload is not a `T`.
+pub fn open<T: DeserializeOwned>(codec: Codec, bytes: &[u8]) -> Result<Envelope<T>, AppError> {
+    Header::read(codec, bytes)?.open(codec, bytes)
+}
+
+/// Decodes a message holding a `T`, upcasting older schema versions.
+///
+/// Payloads of the current schema version are decoded straight into a `T`.
+/// Payloads of a newer schema version than this build knows are decoded
+/// as the current version, which succeeds for fields added to JSON events.
+///
+/// # Errors
+///
+/// Returns `AppError::Serialization` if the message holds another event
+/// type or its payload cannot be converted to a `T`.
+pub fn decode<T: VersionedEvent>(codec: Codec, bytes: &[u8]) -> Result<T, AppError> {
+    let header = Header::read(codec, bytes)?;
+    check_event_type::<T>(&header.event_type)?;
+
+    let schema_version = header.schema_version();
+    if schema_version < T::SCHEMA_VERSION {
+        return decode_envelope(header.open(codec, bytes)?);
+    }
+
+    header
+        .open(codec, bytes)
+        .map(|envelope| envelope.payload)
+        .map_err(|e| invalid_payload::<T>(schema_version, &e))
+}
+
+/// Decodes the payload of `envelope` as a `T`, upcasting older schema versions.
+///
+/// # Errors
+///
+/// Returns `AppError::Serialization` if `envelope` holds another event type
+/// or its payload cannot be converted to a `T`.
+pub fn decode_envelope<T: VersionedEvent>(envelope: Envelope) -> Result<T, AppError> {
+    check_event_type::<T>(&envelope.event_type)?;
+
+    let mut version = envelope.schema_version;
+    let mut payload = envelope.payload;
+    while version < T::SCHEMA_VERSION {
+        payload = T::upcast(version, payload)?;
+        version += 1;
+    }
+
+    serde_json::from_value(payload).map_err(|e| invalid_payload::<T>(envelope.schema_version, &e))
+}
+
+fn check_event_type<T: VersionedEvent>(event_type: &str) -> Result<(), AppError> {
+    if event_type.is_empty() || event_type == T::EVENT_TYPE {
+        return Ok(());
+    }
+
+    Err(AppError::Serialization(format!(
+        "Expected {} but got {event_type}",
+        T::EVENT_TYPE
+    )))
+}
+
+fn invalid_payload<T: VersionedEvent>(schema_version: u32, e: &dyn Display) -> AppError {
+    AppError::Serialization(format!(
+        "Invalid {} schema version {schema_version}: {e}",
+        T::EVENT_TYPE
+    ))
+}
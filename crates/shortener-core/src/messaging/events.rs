@@ -2,6 +2,13 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::{
+    Codec,
+    envelope::{self, VersionedEvent},
+};
+use crate::AppError;
+
+/// A click on a short URL.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccessEvent {
     pub event_id: Uuid,
@@ -30,3 +37,328 @@ impl AccessEvent {
         }
     }
 }
+
+impl VersionedEvent for AccessEvent {
+    const EVENT_TYPE: &'static str = "AccessEvent";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+/// An access event counted by analytics-service, published so every
+/// replica can stream it to its real-time subscribers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClickCounted {
+    /// ID of the counted [`AccessEvent`].
+    pub event_id: Uuid,
+    pub code: String,
+    pub accessed_at: DateTime<Utc>,
+    /// All-time count of the code including this click.
+    pub access_count: i64,
+}
+
+impl VersionedEvent for ClickCounted {
+    const EVENT_TYPE: &'static str = "ClickCounted";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+/// Routing keys of the link lifecycle events on the topic exchange.
+pub mod routing_keys {
+    pub const URL_CREATED: &str = "url.created";
+    pub const URL_UPDATED: &str = "url.updated";
+    pub const URL_DELETED: &str = "url.deleted";
+    pub const URL_RESTORED: &str = "url.restored";
+    pub const URL_PURGED: &str = "url.purged";
+    pub const URL_EXPIRED: &str = "url.expired";
+    pub const CLICK_THRESHOLD_REACHED: &str = "clicks.threshold_reached";
+    /// Routing key of [`super::ClickCounted`], which is not a link event.
+    pub const CLICK_COUNTED: &str = "clicks.counted";
+
+    /// Every link event routing key.
+    pub const LINK_EVENTS: &[&str] = &[
+        URL_CREATED,
+        URL_UPDATED,
+        URL_DELETED,
+        URL_RESTORED,
+        URL_PURGED,
+        URL_EXPIRED,
+        CLICK_THRESHOLD_REACHED,
+    ];
+}
+
+/// A short URL was created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlCreated {
+    pub event_id: Uuid,
+    pub code: String,
+    pub original_url: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl VersionedEvent for UrlCreated {
+    const EVENT_TYPE: &'static str = "UrlCreated";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl UrlCreated {
+    #[must_use]
+    pub fn new(code: String, original_url: String) -> Self {
+        Self {
+            event_id: Uuid::new_v4(),
+            code,
+            original_url,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// A short URL was changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlUpdated {
+    pub event_id: Uuid,
+    pub code: String,
+    pub original_url: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl VersionedEvent for UrlUpdated {
+    const EVENT_TYPE: &'static str = "UrlUpdated";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl UrlUpdated {
+    #[must_use]
+    pub fn new(code: String, original_url: String) -> Self {
+        Self {
+            event_id: Uuid::new_v4(),
+            code,
+            original_url,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// A short URL was deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlDeleted {
+    pub event_id: Uuid,
+    pub code: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl VersionedEvent for UrlDeleted {
+    const EVENT_TYPE: &'static str = "UrlDeleted";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl UrlDeleted {
+    #[must_use]
+    pub fn new(code: String) -> Self {
+        Self {
+            event_id: Uuid::new_v4(),
+            code,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// A deleted short URL was restored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlRestored {
+    pub event_id: Uuid,
+    pub code: String,
+    pub original_url: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl VersionedEvent for UrlRestored {
+    const EVENT_TYPE: &'static str = "UrlRestored";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl UrlRestored {
+    #[must_use]
+    pub fn new(code: String, original_url: String) -> Self {
+        Self {
+            event_id: Uuid::new_v4(),
+            code,
+            original_url,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// A deleted short URL was removed from the trash for good.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlPurged {
+    pub event_id: Uuid,
+    pub code: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl VersionedEvent for UrlPurged {
+    const EVENT_TYPE: &'static str = "UrlPurged";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl UrlPurged {
+    #[must_use]
+    pub fn new(code: String) -> Self {
+        Self {
+            event_id: Uuid::new_v4(),
+            code,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// A short URL passed its expiry time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlExpired {
+    pub event_id: Uuid,
+    pub code: String,
+    pub expires_at: DateTime<Utc>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl VersionedEvent for UrlExpired {
+    const EVENT_TYPE: &'static str = "UrlExpired";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl UrlExpired {
+    #[must_use]
+    pub fn new(code: String, expires_at: DateTime<Utc>) -> Self {
+        Self {
+            event_id: Uuid::new_v4(),
+            code,
+            expires_at,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// The all-time click count of a short URL reached `threshold`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClickThresholdReached {
+    pub event_id: Uuid,
+    pub code: String,
+    pub threshold: i64,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl VersionedEvent for ClickThresholdReached {
+    const EVENT_TYPE: &'static str = "ClickThresholdReached";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl ClickThresholdReached {
+    #[must_use]
+    pub fn new(code: String, threshold: i64) -> Self {
+        Self {
+            event_id: Uuid::new_v4(),
+            code,
+            threshold,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// Lifecycle event of a short URL.
+///
+/// Each variant is published in its own envelope under its own routing key.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum LinkEvent {
+    Created(UrlCreated),
+    Updated(UrlUpdated),
+    Deleted(UrlDeleted),
+    Restored(UrlRestored),
+    Purged(UrlPurged),
+    Expired(UrlExpired),
+    ClickThresholdReached(ClickThresholdReached),
+}
+
+impl LinkEvent {
+    #[must_use]
+    pub fn routing_key(&self) -> &'static str {
+        match self {
+            Self::Created(_) => routing_keys::URL_CREATED,
+            Self::Updated(_) => routing_keys::URL_UPDATED,
+            Self::Deleted(_) => routing_keys::URL_DELETED,
+            Self::Restored(_) => routing_keys::URL_RESTORED,
+            Self::Purged(_) => routing_keys::URL_PURGED,
+            Self::Expired(_) => routing_keys::URL_EXPIRED,
+            Self::ClickThresholdReached(_) => routing_keys::CLICK_THRESHOLD_REACHED,
+        }
+    }
+
+    #[must_use]
+    pub fn event_id(&self) -> Uuid {
+        match self {
+            Self::Created(e) => e.event_id,
+            Self::Updated(e) => e.event_id,
+            Self::Deleted(e) => e.event_id,
+            Self::Restored(e) => e.event_id,
+            Self::Purged(e) => e.event_id,
+            Self::Expired(e) => e.event_id,
+            Self::ClickThresholdReached(e) => e.event_id,
+        }
+    }
+
+    #[must_use]
+    pub fn code(&self) -> &str {
+        match self {
+            Self::Created(e) => &e.code,
+            Self::Updated(e) => &e.code,
+            Self::Deleted(e) => &e.code,
+            Self::Restored(e) => &e.code,
+            Self::Purged(e) => &e.code,
+            Self::Expired(e) => &e.code,
+            Self::ClickThresholdReached(e) => &e.code,
+        }
+    }
+
+    /// Serializes the event with `codec` in an envelope naming `producer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Serialization` if the event cannot be serialized.
+    pub fn encode(&self, codec: Codec, producer: &str) -> Result<Vec<u8>, AppError> {
+        match self {
+            Self::Created(e) => envelope::encode(codec, producer, e),
+            Self::Updated(e) => envelope::encode(codec, producer, e),
+            Self::Deleted(e) => envelope::encode(codec, producer, e),
+            Self::Restored(e) => envelope::encode(codec, producer, e),
+            Self::Purged(e) => envelope::encode(codec, producer, e),
+            Self::Expired(e) => envelope::encode(codec, producer, e),
+            Self::ClickThresholdReached(e) => envelope::encode(codec, producer, e),
+        }
+    }
+
+    /// Decodes a `codec` message published with `routing_key`.
+    ///
+    /// Returns `Ok(None)` if `routing_key` is not a link event.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Serialization` if `payload` does not match the event.
+    pub fn decode(
+        codec: Codec,
+        routing_key: &str,
+        payload: &[u8],
+    ) -> Result<Option<Self>, AppError> {
+        let event = match routing_key {
+            routing_keys::URL_CREATED => Self::Created(envelope::decode(codec, payload)?),
+            routing_keys::URL_UPDATED => Self::Updated(envelope::decode(codec, payload)?),
+            routing_keys::URL_DELETED => Self::Deleted(envelope::decode(codec, payload)?),
+            routing_keys::URL_RESTORED => Self::Restored(envelope::decode(codec, payload)?),
+            routing_keys::URL_PURGED => Self::Purged(envelope::decode(codec, payload)?),
+            routing_keys::URL_EXPIRED => Self::Expired(envelope::decode(codec, payload)?),
+            routing_keys::CLICK_THRESHOLD_REACHED => {
+                Self::ClickThresholdReached(envelope::decode(codec, payload)?)
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(event))
+    }
+}
@@ -1,3 +1,10 @@
+mod codec;
+pub mod envelope;
 mod events;
 
-pub use events::AccessEvent;
+pub use codec::Codec;
+pub use envelope::{Envelope, VersionedEvent};
+pub use events::{
+    AccessEvent, ClickCounted, ClickThresholdReached, LinkEvent, UrlCreated, UrlDeleted,
+    UrlExpired, UrlPurged, UrlRestored, UrlUpdated, routing_keys,
+};
@@ -0,0 +1,20 @@
+//! Serving of `OpenAPI` documents.
+
+use axum::Router;
+use utoipa::openapi::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+/// Path the `OpenAPI` document is served at.
+pub const SPEC_PATH: &str = "/api/openapi.json";
+
+/// Path of the Swagger UI.
+pub const DOCS_PATH: &str = "/api/docs";
+
+/// Routes serving `spec` at [`SPEC_PATH`] and a Swagger UI for it at
+/// [`DOCS_PATH`].
+pub fn docs_routes<S>(spec: OpenApi) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    SwaggerUi::new(DOCS_PATH).url(SPEC_PATH, spec).into()
+}
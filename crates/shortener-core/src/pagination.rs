@@ -0,0 +1,30 @@
+//! Keyset pagination with opaque cursors.
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::de::DeserializeOwned;
+
+use crate::AppError;
+
+pub use shortener_types::pagination::{Page, encode_cursor};
+
+/// Largest page size accepted by list endpoints.
+pub const MAX_PAGE_SIZE: usize = 100;
+
+/// Clamps a requested page size to `1..=MAX_PAGE_SIZE`.
+#[must_use]
+pub fn clamp_limit(limit: usize) -> usize {
+    limit.clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Decodes a cursor produced by [`encode_cursor`].
+///
+/// # Errors
+///
+/// Returns `AppError::BadRequest` if `cursor` is malformed.
+pub fn decode_cursor<K: DeserializeOwned>(cursor: &str) -> Result<K, AppError> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))
+}
@@ -0,0 +1,64 @@
+//! Every `AppError` survives the trip through its JSON response, so HTTP
+//! clients can match on the same variants as the services.
+
+use std::mem::discriminant;
+
+use axum::{body::to_bytes, http::StatusCode, response::IntoResponse};
+use shortener_core::{AppError, error::ErrorResponse};
+
+async fn round_trip(error: AppError) -> (StatusCode, AppError) {
+    let response = error.into_response();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: ErrorResponse = serde_json::from_slice(&bytes).unwrap();
+    (status, body.error.into())
+}
+
+#[tokio::test]
+async fn variants_are_rebuilt_from_their_response() {
+    let errors = [
+        AppError::NotFound("a".to_string()),
+        AppError::BadRequest("a".to_string()),
+        AppError::Conflict("a".to_string()),
+        AppError::Gone("a".to_string()),
+        AppError::ServiceUnavailable("a".to_string()),
+        AppError::Database("a".to_string()),
+        AppError::Redis("a".to_string()),
+        AppError::MessageQueue("a".to_string()),
+        AppError::Serialization("a".to_string()),
+        AppError::UrlParse("a".to_string()),
+        AppError::Internal("a".to_string()),
+    ];
+
+    for error in errors {
+        let expected = discriminant(&error);
+        let (_, rebuilt) = round_trip(error).await;
+        assert_eq!(discriminant(&rebuilt), expected, "{rebuilt:?}");
+    }
+}
+
+#[tokio::test]
+async fn client_errors_keep_their_message() {
+    let (status, error) = round_trip(AppError::NotFound("URL 'abc' not found".to_string())).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(matches!(error, AppError::NotFound(message) if message == "URL 'abc' not found"));
+}
+
+#[tokio::test]
+async fn server_errors_hide_their_details() {
+    let (status, error) = round_trip(AppError::Database("connection refused".to_string())).await;
+
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(matches!(error, AppError::Database(message) if message == "Database error occurred"));
+}
+
+#[test]
+fn unknown_codes_are_internal_errors() {
+    let error = AppError::from(shortener_core::error::ErrorBody {
+        code: "TEAPOT".to_string(),
+        message: "short and stout".to_string(),
+    });
+
+    assert!(matches!(error, AppError::Internal(message) if message == "TEAPOT: short and stout"));
+}
@@ -0,0 +1,402 @@
+//! Compatibility of the event wire format across schema versions.
+//!
+//! `tests/fixtures/events` holds a message for every schema version each
+//! event has been published with, as JSON and as the compact `MessagePack`
+//! earlier builds wrote. Fixtures are never edited: when a schema changes,
+//! add a fixture for the new version and keep decoding the old ones.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize, de::IgnoredAny};
+use serde_json::{Value, json};
+use shortener_core::messaging::{
+    AccessEvent, ClickCounted, ClickThresholdReached, Codec, Envelope, LinkEvent, UrlCreated,
+    UrlDeleted, UrlExpired, UrlPurged, UrlRestored, UrlUpdated, VersionedEvent,
+    envelope::{self, UNVERSIONED_SCHEMA_VERSION},
+    routing_keys,
+};
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/events")
+        .join(name);
+    fs::read(&path).unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display()))
+}
+
+/// Names of all fixtures, as `{event}.{version}.json`.
+fn fixture_names() -> Vec<String> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/events");
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    names
+}
+
+fn fixture_payload(name: &str) -> Value {
+    let message: Value = serde_json::from_slice(&fixture(name)).unwrap();
+    message.get("payload").cloned().unwrap_or(message)
+}
+
+fn msgpack(value: &Value) -> Vec<u8> {
+    Codec::MessagePack.encode(value).unwrap()
+}
+
+/// Decodes `name`, checks the fields of its JSON version survive decoding,
+/// then re-encodes the event with every codec and checks it decodes to the
+/// same value.
+fn assert_round_trip<T: VersionedEvent>(name: &str) {
+    let (codec, json_name) = match name.strip_suffix(".msgpack") {
+        Some(stem) => (Codec::MessagePack, format!("{stem}.json")),
+        None => (Codec::Json, name.to_string()),
+    };
+    let event: T = envelope::decode(codec, &fixture(name)).unwrap();
+
+    let decoded = serde_json::to_value(&event).unwrap();
+    let original = fixture_payload(&json_name);
+    for (field, value) in original.as_object().unwrap() {
+        assert_eq!(decoded.get(field), Some(value), "{name}: field {field}");
+    }
+
+    for codec in [Codec::Json, Codec::MessagePack] {
+        let encoded = envelope::encode(codec, "test", &event).unwrap();
+        let opened = envelope::open::<IgnoredAny>(codec, &encoded).unwrap();
+        assert_eq!(opened.event_type, T::EVENT_TYPE);
+        assert_eq!(opened.schema_version, T::SCHEMA_VERSION);
+        assert_eq!(opened.producer, "test");
+
+        let reencoded: T = envelope::decode(codec, &encoded).unwrap();
+        assert_eq!(
+            serde_json::to_value(&reencoded).unwrap(),
+            decoded,
+            "{name}: {codec:?}"
+        );
+    }
+}
+
+#[test]
+fn access_event_unversioned() {
+    assert_round_trip::<AccessEvent>("access_event.unversioned.json");
+}
+
+#[test]
+fn access_event_v1() {
+    assert_round_trip::<AccessEvent>("access_event.v1.json");
+}
+
+#[test]
+fn url_created_v1() {
+    assert_round_trip::<UrlCreated>("url_created.v1.json");
+}
+
+#[test]
+fn url_updated_v1() {
+    assert_round_trip::<UrlUpdated>("url_updated.v1.json");
+}
+
+#[test]
+fn url_deleted_v1() {
+    assert_round_trip::<UrlDeleted>("url_deleted.v1.json");
+}
+
+#[test]
+fn url_restored_v1() {
+    assert_round_trip::<UrlRestored>("url_restored.v1.json");
+}
+
+#[test]
+fn url_purged_v1() {
+    assert_round_trip::<UrlPurged>("url_purged.v1.json");
+}
+
+#[test]
+fn url_expired_v1() {
+    assert_round_trip::<UrlExpired>("url_expired.v1.json");
+}
+
+#[test]
+fn click_counted_v1() {
+    assert_round_trip::<ClickCounted>("click_counted.v1.json");
+}
+
+#[test]
+fn click_threshold_reached_v1() {
+    assert_round_trip::<ClickThresholdReached>("click_threshold_reached.v1.json");
+}
+
+#[test]
+fn every_fixture_decodes() {
+    for name in fixture_names() {
+        match name.split('.').next().unwrap() {
+            "access_event" => assert_round_trip::<AccessEvent>(&name),
+            "click_counted" => assert_round_trip::<ClickCounted>(&name),
+            "click_threshold_reached" => assert_round_trip::<ClickThresholdReached>(&name),
+            "url_created" => assert_round_trip::<UrlCreated>(&name),
+            "url_updated" => assert_round_trip::<UrlUpdated>(&name),
+            "url_deleted" => assert_round_trip::<UrlDeleted>(&name),
+            "url_restored" => assert_round_trip::<UrlRestored>(&name),
+            "url_purged" => assert_round_trip::<UrlPurged>(&name),
+            "url_expired" => assert_round_trip::<UrlExpired>(&name),
+            event => panic!("{name}: unknown event {event}"),
+        }
+    }
+}
+
+#[test]
+fn every_schema_version_has_a_fixture() {
+    let events = [
+        ("access_event", AccessEvent::SCHEMA_VERSION),
+        ("click_counted", ClickCounted::SCHEMA_VERSION),
+        (
+            "click_threshold_reached",
+            ClickThresholdReached::SCHEMA_VERSION,
+        ),
+        ("url_created", UrlCreated::SCHEMA_VERSION),
+        ("url_updated", UrlUpdated::SCHEMA_VERSION),
+        ("url_deleted", UrlDeleted::SCHEMA_VERSION),
+        ("url_restored", UrlRestored::SCHEMA_VERSION),
+        ("url_purged", UrlPurged::SCHEMA_VERSION),
+        ("url_expired", UrlExpired::SCHEMA_VERSION),
+    ];
+    let names = fixture_names();
+
+    for (event, schema_version) in events {
+        for version in 1..=schema_version {
+            let name = format!("{event}.v{version}.json");
+            assert!(names.contains(&name), "missing fixture {name}");
+        }
+    }
+}
+
+#[test]
+fn unversioned_message_opens_as_first_version() {
+    let opened: Envelope<AccessEvent> =
+        envelope::open(Codec::Json, &fixture("access_event.unversioned.json")).unwrap();
+    assert_eq!(opened.schema_version, UNVERSIONED_SCHEMA_VERSION);
+    assert!(opened.event_type.is_empty());
+}
+
+#[test]
+fn link_events_decode_by_routing_key() {
+    let cases = [
+        (routing_keys::URL_CREATED, "url_created.v1.json"),
+        (routing_keys::URL_UPDATED, "url_updated.v1.json"),
+        (routing_keys::URL_DELETED, "url_deleted.v1.json"),
+        (routing_keys::URL_RESTORED, "url_restored.v1.json"),
+        (routing_keys::URL_PURGED, "url_purged.v1.json"),
+        (routing_keys::URL_EXPIRED, "url_expired.v1.json"),
+        (
+            routing_keys::CLICK_THRESHOLD_REACHED,
+            "click_threshold_reached.v1.json",
+        ),
+    ];
+
+    for (routing_key, name) in cases {
+        let event = LinkEvent::decode(Codec::Json, routing_key, &fixture(name))
+            .unwrap()
+            .unwrap_or_else(|| panic!("{routing_key} is not a link event"));
+        assert_eq!(event.routing_key(), routing_key);
+        assert_eq!(event.code(), "abc123");
+
+        for codec in [Codec::Json, Codec::MessagePack] {
+            let encoded = event.encode(codec, "test").unwrap();
+            let reencoded = LinkEvent::decode(codec, routing_key, &encoded)
+                .unwrap()
+                .unwrap();
+            assert_eq!(reencoded.event_id(), event.event_id());
+        }
+    }
+}
+
+#[test]
+fn unknown_routing_key_is_not_a_link_event() {
+    let decoded = LinkEvent::decode(
+        Codec::Json,
+        "access.event",
+        &fixture("access_event.v1.json"),
+    )
+    .unwrap();
+    assert!(decoded.is_none());
+}
+
+#[test]
+fn other_event_type_is_rejected() {
+    assert!(envelope::decode::<UrlDeleted>(Codec::Json, &fixture("url_created.v1.json")).is_err());
+}
+
+#[test]
+fn newer_schema_with_added_fields_decodes() {
+    let mut message: Value = serde_json::from_slice(&fixture("access_event.v1.json")).unwrap();
+    message["schema_version"] = json!(AccessEvent::SCHEMA_VERSION + 1);
+    message["payload"]["device"] = json!("mobile");
+
+    let event: AccessEvent =
+        envelope::decode(Codec::Json, &serde_json::to_vec(&message).unwrap()).unwrap();
+    assert_eq!(event.code, "abc123");
+}
+
+/// An event whose schema went through two breaking changes.
+#[derive(Debug, Serialize, Deserialize)]
+struct Renamed {
+    short_code: String,
+    clicks: i64,
+}
+
+impl VersionedEvent for Renamed {
+    const EVENT_TYPE: &'static str = "Renamed";
+    const SCHEMA_VERSION: u32 = 3;
+
+    fn upcast(version: u32, mut payload: Value) -> Result<Value, shortener_core::AppError> {
+        let object = payload.as_object_mut().unwrap();
+        match version {
+            // v2 renamed `code` to `short_code`.
+            1 => {
+                let code = object.remove("code").unwrap();
+                object.insert("short_code".to_string(), code);
+            }
+            // v3 made `clicks` required.
+            2 => {
+                object.entry("clicks").or_insert(json!(0));
+            }
+            _ => unreachable!(),
+        }
+        Ok(payload)
+    }
+}
+
+#[test]
+fn older_versions_are_upcast_in_order() {
+    let v1 = json!({
+        "type": "Renamed",
+        "schema_version": 1,
+        "producer": "test",
+        "produced_at": "2024-01-01T00:00:00Z",
+        "payload": {"code": "abc123"}
+    });
+    let event: Renamed = envelope::decode(Codec::MessagePack, &msgpack(&v1)).unwrap();
+    assert_eq!(event.short_code, "abc123");
+    assert_eq!(event.clicks, 0);
+
+    let v2 = json!({
+        "type": "Renamed",
+        "schema_version": 2,
+        "producer": "test",
+        "produced_at": "2024-01-01T00:00:00Z",
+        "payload": {"short_code": "abc123", "clicks": 7}
+    });
+    let event: Renamed = envelope::decode(Codec::Json, &serde_json::to_vec(&v2).unwrap()).unwrap();
+    assert_eq!(event.clicks, 7);
+}
+
+#[test]
+fn codec_follows_content_type() {
+    let cases = [
+        (None, Codec::Json),
+        (Some("application/json"), Codec::Json),
+        (Some("application/json; charset=utf-8"), Codec::Json),
+        (Some("application/msgpack"), Codec::MessagePack),
+        (Some("application/x-msgpack"), Codec::MessagePack),
+    ];
+    for (content_type, codec) in cases {
+        assert_eq!(Codec::from_content_type(content_type).unwrap(), codec);
+        assert_eq!(
+            Codec::from_content_type(Some(codec.content_type())).unwrap(),
+            codec
+        );
+    }
+
+    assert!(Codec::from_content_type(Some("application/protobuf")).is_err());
+}
+
+#[test]
+fn mixed_format_queue_decodes_by_content_type() {
+    let event: AccessEvent =
+        envelope::decode(Codec::Json, &fixture("access_event.v1.json")).unwrap();
+    let queue = [
+        (None, fixture("access_event.unversioned.json")),
+        (
+            Some(Codec::Json.content_type()),
+            envelope::encode(Codec::Json, "test", &event).unwrap(),
+        ),
+        (
+            Some(Codec::MessagePack.content_type()),
+            envelope::encode(Codec::MessagePack, "test", &event).unwrap(),
+        ),
+    ];
+
+    for (content_type, body) in queue {
+        let codec = Codec::from_content_type(content_type).unwrap();
+        let decoded: AccessEvent = envelope::decode(codec, &body).unwrap();
+        assert_eq!(decoded.code, "abc123", "{content_type:?}");
+    }
+}
+
+#[test]
+fn codecs_are_not_interchangeable() {
+    let event: AccessEvent =
+        envelope::decode(Codec::Json, &fixture("access_event.v1.json")).unwrap();
+    let encoded = envelope::encode(Codec::MessagePack, "test", &event).unwrap();
+
+    assert!(envelope::decode::<AccessEvent>(Codec::Json, &encoded).is_err());
+}
+
+#[test]
+fn message_pack_writes_structs_as_maps() {
+    let event: AccessEvent =
+        envelope::decode(Codec::Json, &fixture("access_event.v1.json")).unwrap();
+    let encoded = envelope::encode(Codec::MessagePack, "test", &event).unwrap();
+
+    // A fixmap of the five envelope fields, with their names.
+    assert_eq!(encoded[0], 0x85);
+    assert!(encoded.windows(b"payload".len()).any(|w| w == b"payload"));
+    let event_id = event.event_id.to_string();
+    assert!(
+        encoded
+            .windows(event_id.len())
+            .any(|w| w == event_id.as_bytes())
+    );
+}
+
+#[test]
+fn compact_message_pack_of_earlier_builds_decodes() {
+    let compact = fixture("access_event.v1.msgpack");
+    // A fixarray of the five envelope fields, without their names.
+    assert_eq!(compact[0], 0x95);
+
+    let event: AccessEvent = envelope::decode(Codec::MessagePack, &compact).unwrap();
+    let expected: AccessEvent =
+        envelope::decode(Codec::Json, &fixture("access_event.v1.json")).unwrap();
+    assert_eq!(
+        serde_json::to_value(&event).unwrap(),
+        serde_json::to_value(&expected).unwrap()
+    );
+}
+
+/// The first schema of [`Renamed`], as its producer wrote it.
+#[derive(Debug, Serialize, Deserialize)]
+struct RenamedV1 {
+    code: String,
+}
+
+impl VersionedEvent for RenamedV1 {
+    const EVENT_TYPE: &'static str = "Renamed";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+#[test]
+fn message_pack_events_of_older_versions_are_upcast() {
+    let encoded = envelope::encode(
+        Codec::MessagePack,
+        "test",
+        &RenamedV1 {
+            code: "abc123".to_string(),
+        },
+    )
+    .unwrap();
+
+    let event: Renamed = envelope::decode(Codec::MessagePack, &encoded).unwrap();
+    assert_eq!(event.short_code, "abc123");
+    assert_eq!(event.clicks, 0);
+}
@@ -0,0 +1,208 @@
+//! Delivery semantics of the in-process broker, which the other backends
+//! share: queues receive the messages of their routing keys from the time
+//! they are declared, nacked messages are delivered again on request or
+//! dead-lettered until replayed, and broadcast subscriptions see every
+//! message.
+
+use std::time::Duration;
+
+use shortener_core::{
+    Broker,
+    broker::{InProcessBroker, Subscription, dead_letter_queue},
+    messaging::Codec,
+};
+
+/// Asserts nothing is delivered to `subscription` for a while.
+async fn assert_empty(subscription: &mut Subscription) {
+    let next = tokio::time::timeout(Duration::from_millis(50), subscription.next()).await;
+    assert!(next.is_err(), "unexpected delivery");
+}
+
+#[tokio::test]
+async fn queues_receive_their_routing_keys() {
+    let broker = InProcessBroker::default();
+    let mut created = broker.subscribe("created", &["url.created"]).await.unwrap();
+    let mut deleted = broker.subscribe("deleted", &["url.deleted"]).await.unwrap();
+
+    broker
+        .publish("url.created", "1", Codec::MessagePack, b"payload")
+        .await
+        .unwrap();
+
+    let delivery = created.next().await.unwrap();
+    assert_eq!(delivery.routing_key, "url.created");
+    assert_eq!(delivery.message_id.as_deref(), Some("1"));
+    assert_eq!(delivery.codec().unwrap(), Codec::MessagePack);
+    assert_eq!(delivery.payload, b"payload");
+    delivery.ack().await.unwrap();
+
+    assert_empty(&mut deleted).await;
+}
+
+#[tokio::test]
+async fn declared_queue_keeps_messages_until_subscribed() {
+    let broker = InProcessBroker::default();
+    broker
+        .publish("access.event", "dropped", Codec::Json, b"{}")
+        .await
+        .unwrap();
+    broker
+        .declare_queue("access_events", &["access.event"])
+        .await
+        .unwrap();
+    broker
+        .publish("access.event", "kept", Codec::Json, b"{}")
+        .await
+        .unwrap();
+
+    let mut subscription = broker
+        .subscribe("access_events", &["access.event"])
+        .await
+        .unwrap();
+    let delivery = subscription.next().await.unwrap();
+    assert_eq!(delivery.message_id.as_deref(), Some("kept"));
+    delivery.ack().await.unwrap();
+
+    assert_empty(&mut subscription).await;
+}
+
+#[tokio::test]
+async fn nack_with_requeue_delivers_again() {
+    let broker = InProcessBroker::default();
+    let mut subscription = broker.subscribe("queue", &["key"]).await.unwrap();
+    broker
+        .publish("key", "1", Codec::Json, b"{}")
+        .await
+        .unwrap();
+
+    subscription.next().await.unwrap().nack(true).await.unwrap();
+    let delivery = subscription.next().await.unwrap();
+    assert_eq!(delivery.message_id.as_deref(), Some("1"));
+
+    delivery.nack(false).await.unwrap();
+    assert_empty(&mut subscription).await;
+}
+
+#[tokio::test]
+async fn redelivery_wakes_a_waiting_subscription() {
+    let broker = InProcessBroker::default();
+    let mut subscription = broker.subscribe("queue", &["key"]).await.unwrap();
+    broker
+        .publish("key", "1", Codec::Json, b"{}")
+        .await
+        .unwrap();
+
+    let delivery = subscription.next().await.unwrap();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        delivery.nack(true).await.unwrap();
+    });
+
+    let delivery = tokio::time::timeout(Duration::from_secs(1), subscription.next())
+        .await
+        .expect("redelivery not received")
+        .unwrap();
+    assert_eq!(delivery.message_id.as_deref(), Some("1"));
+}
+
+#[tokio::test]
+async fn nack_without_requeue_dead_letters() {
+    let broker = InProcessBroker::default();
+    let mut subscription = broker.subscribe("queue", &["key"]).await.unwrap();
+    let mut dead_letters = broker
+        .subscribe(&dead_letter_queue("queue"), &[])
+        .await
+        .unwrap();
+    broker
+        .publish("key", "1", Codec::MessagePack, b"payload")
+        .await
+        .unwrap();
+
+    subscription
+        .next()
+        .await
+        .unwrap()
+        .nack(false)
+        .await
+        .unwrap();
+
+    let delivery = dead_letters.next().await.unwrap();
+    assert_eq!(delivery.routing_key, "key");
+    assert_eq!(delivery.message_id.as_deref(), Some("1"));
+    assert_eq!(delivery.codec().unwrap(), Codec::MessagePack);
+    assert_eq!(delivery.payload, b"payload");
+    delivery.ack().await.unwrap();
+    assert_empty(&mut subscription).await;
+}
+
+#[tokio::test]
+async fn replayed_dead_letters_return_to_their_queue_only() {
+    let broker = InProcessBroker::default();
+    let mut failing = broker.subscribe("failing", &["key"]).await.unwrap();
+    let mut other = broker.subscribe("other", &["key"]).await.unwrap();
+    broker
+        .publish("key", "1", Codec::Json, b"{}")
+        .await
+        .unwrap();
+    other.next().await.unwrap().ack().await.unwrap();
+    failing.next().await.unwrap().nack(false).await.unwrap();
+
+    assert_eq!(broker.replay_dead_letters("failing").await.unwrap(), 1);
+
+    let delivery = failing.next().await.unwrap();
+    assert_eq!(delivery.routing_key, "key");
+    assert_eq!(delivery.message_id.as_deref(), Some("1"));
+    delivery.ack().await.unwrap();
+    assert_empty(&mut other).await;
+    assert_eq!(broker.replay_dead_letters("failing").await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn subscriptions_to_a_queue_compete() {
+    let broker = InProcessBroker::default();
+    let mut first = broker.subscribe("queue", &["key"]).await.unwrap();
+    let mut second = broker.subscribe("queue", &["key"]).await.unwrap();
+
+    for id in 0..4 {
+        broker
+            .publish("key", &id.to_string(), Codec::Json, b"{}")
+            .await
+            .unwrap();
+    }
+
+    let mut received = Vec::new();
+    for subscription in [&mut first, &mut second] {
+        while let Ok(Some(delivery)) =
+            tokio::time::timeout(Duration::from_millis(50), subscription.next()).await
+        {
+            received.push(delivery.message_id.clone().unwrap());
+            delivery.ack().await.unwrap();
+        }
+    }
+    received.sort();
+    assert_eq!(received, ["0", "1", "2", "3"]);
+}
+
+#[tokio::test]
+async fn broadcast_subscriptions_each_receive_every_message() {
+    let broker = InProcessBroker::default();
+    let mut queued = broker.subscribe("queue", &["key"]).await.unwrap();
+    let mut first = broker.subscribe_broadcast(&["key"]).await.unwrap();
+    let mut second = broker.subscribe_broadcast(&["key"]).await.unwrap();
+
+    for id in 0..2 {
+        broker
+            .publish("key", &id.to_string(), Codec::Json, b"{}")
+            .await
+            .unwrap();
+    }
+
+    for subscription in [&mut queued, &mut first, &mut second] {
+        for id in ["0", "1"] {
+            let delivery = subscription.next().await.unwrap();
+            assert_eq!(delivery.message_id.as_deref(), Some(id));
+            delivery.ack().await.unwrap();
+        }
+        assert_empty(subscription).await;
+    }
+}
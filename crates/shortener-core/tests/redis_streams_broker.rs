@@ -0,0 +1,265 @@
+//! Consumer groups and dead letters of the Redis Streams broker.
+//!
+//! Needs a Redis server in `REDIS_URL`, and is skipped without one. Each test
+//! uses streams under a prefix of its own and deletes them afterwards.
+
+use std::time::Duration;
+
+use redis::{
+    AsyncCommands,
+    aio::MultiplexedConnection,
+    streams::{StreamRangeReply, StreamReadReply},
+};
+use shortener_core::{
+    Broker,
+    broker::{Delivery, RedisStreamsBroker, Subscription, dead_letter_queue},
+    config::{RedisStreamsConfig, SecretString},
+    messaging::Codec,
+};
+use uuid::Uuid;
+
+const QUEUE: &str = "queue";
+const ROUTING_KEY: &str = "key";
+
+struct Fixture {
+    broker: RedisStreamsBroker,
+    connection: MultiplexedConnection,
+    prefix: String,
+}
+
+impl Fixture {
+    /// Returns a broker with a prefix of its own, or `None` without Redis.
+    async fn new() -> Option<Self> {
+        let url = std::env::var("REDIS_URL").ok()?;
+        let prefix = format!("test:{}", Uuid::new_v4().simple());
+        let broker = RedisStreamsBroker::try_new(&RedisStreamsConfig {
+            url: SecretString::new(url.as_str()),
+            prefix: prefix.clone(),
+            max_len: 1000,
+        })
+        .await
+        .unwrap();
+        let connection = redis::Client::open(url)
+            .unwrap()
+            .get_multiplexed_async_connection()
+            .await
+            .unwrap();
+        Some(Self {
+            broker,
+            connection,
+            prefix,
+        })
+    }
+
+    fn stream(&self, name: &str) -> String {
+        format!("{}:{name}", self.prefix)
+    }
+
+    async fn publish(&self, message_id: &str) {
+        self.broker
+            .publish(ROUTING_KEY, message_id, Codec::Json, b"{}")
+            .await
+            .unwrap();
+    }
+
+    /// Number of entries delivered to the group and not yet acked.
+    async fn pending(&mut self) -> usize {
+        let (count, ..): (usize, redis::Value, redis::Value, redis::Value) = redis::cmd("XPENDING")
+            .arg(self.stream(ROUTING_KEY))
+            .arg(QUEUE)
+            .query_async(&mut self.connection)
+            .await
+            .unwrap();
+        count
+    }
+
+    async fn cleanup(mut self) {
+        let keys: Vec<String> = self
+            .connection
+            .keys(format!("{}:*", self.prefix))
+            .await
+            .unwrap();
+        if !keys.is_empty() {
+            let _: () = self.connection.del(keys).await.unwrap();
+        }
+    }
+}
+
+async fn next(subscription: &mut Subscription) -> Delivery {
+    tokio::time::timeout(Duration::from_secs(10), subscription.next())
+        .await
+        .expect("no delivery")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn queues_are_groups_receiving_entries_from_their_creation() {
+    let Some(mut fixture) = Fixture::new().await else {
+        return;
+    };
+    fixture.publish("before").await;
+
+    let mut subscription = fixture
+        .broker
+        .subscribe(QUEUE, &[ROUTING_KEY])
+        .await
+        .unwrap();
+    let groups: Vec<Vec<redis::Value>> = redis::cmd("XINFO")
+        .arg("GROUPS")
+        .arg(fixture.stream(ROUTING_KEY))
+        .query_async(&mut fixture.connection)
+        .await
+        .unwrap();
+    assert_eq!(groups.len(), 1);
+
+    fixture.publish("after").await;
+    let delivery = next(&mut subscription).await;
+    assert_eq!(delivery.routing_key, ROUTING_KEY);
+    assert_eq!(delivery.message_id.as_deref(), Some("after"));
+    assert_eq!(fixture.pending().await, 1);
+
+    delivery.ack().await.unwrap();
+    assert_eq!(fixture.pending().await, 0);
+
+    fixture.cleanup().await;
+}
+
+#[tokio::test]
+async fn stale_entries_of_other_consumers_are_claimed() {
+    let Some(mut fixture) = Fixture::new().await else {
+        return;
+    };
+    fixture
+        .broker
+        .declare_queue(QUEUE, &[ROUTING_KEY])
+        .await
+        .unwrap();
+    fixture.publish("stale").await;
+
+    // A consumer that went away read the entry and never acked it.
+    let stream = fixture.stream(ROUTING_KEY);
+    let read: StreamReadReply = redis::cmd("XREADGROUP")
+        .arg("GROUP")
+        .arg(QUEUE)
+        .arg("gone")
+        .arg("STREAMS")
+        .arg(&stream)
+        .arg(">")
+        .query_async(&mut fixture.connection)
+        .await
+        .unwrap();
+    let id = read.keys[0].ids[0].id.clone();
+    // Ages the entry past the claim timeout.
+    let _: redis::Value = redis::cmd("XCLAIM")
+        .arg(&stream)
+        .arg(QUEUE)
+        .arg("gone")
+        .arg(0)
+        .arg(&id)
+        .arg("IDLE")
+        .arg(3_600_000)
+        .query_async(&mut fixture.connection)
+        .await
+        .unwrap();
+
+    let mut subscription = fixture
+        .broker
+        .subscribe(QUEUE, &[ROUTING_KEY])
+        .await
+        .unwrap();
+    let delivery = next(&mut subscription).await;
+    assert_eq!(delivery.message_id.as_deref(), Some("stale"));
+
+    delivery.ack().await.unwrap();
+    assert_eq!(fixture.pending().await, 0);
+
+    fixture.cleanup().await;
+}
+
+#[tokio::test]
+async fn nack_with_requeue_delivers_again() {
+    let Some(mut fixture) = Fixture::new().await else {
+        return;
+    };
+    let mut subscription = fixture
+        .broker
+        .subscribe(QUEUE, &[ROUTING_KEY])
+        .await
+        .unwrap();
+    fixture.publish("1").await;
+
+    next(&mut subscription).await.nack(true).await.unwrap();
+    let delivery = next(&mut subscription).await;
+    assert_eq!(delivery.message_id.as_deref(), Some("1"));
+    assert_eq!(fixture.pending().await, 1);
+
+    delivery.ack().await.unwrap();
+    assert_eq!(fixture.pending().await, 0);
+
+    fixture.cleanup().await;
+}
+
+#[tokio::test]
+async fn nack_without_requeue_dead_letters() {
+    let Some(mut fixture) = Fixture::new().await else {
+        return;
+    };
+    let mut subscription = fixture
+        .broker
+        .subscribe(QUEUE, &[ROUTING_KEY])
+        .await
+        .unwrap();
+    fixture.publish("1").await;
+
+    next(&mut subscription).await.nack(false).await.unwrap();
+    assert_eq!(fixture.pending().await, 0);
+
+    let dead_letters: StreamRangeReply = fixture
+        .connection
+        .xrange_all(fixture.stream(&dead_letter_queue(QUEUE)))
+        .await
+        .unwrap();
+    assert_eq!(dead_letters.ids.len(), 1);
+    let entry = &dead_letters.ids[0];
+    assert_eq!(
+        entry.get::<String>("routing_key").as_deref(),
+        Some(ROUTING_KEY)
+    );
+    assert_eq!(entry.get::<String>("message_id").as_deref(), Some("1"));
+    assert_eq!(entry.get::<Vec<u8>>("payload").as_deref(), Some(&b"{}"[..]));
+
+    fixture.cleanup().await;
+}
+
+#[tokio::test]
+async fn replayed_dead_letters_return_to_their_queue_only() {
+    let Some(mut fixture) = Fixture::new().await else {
+        return;
+    };
+    let mut subscription = fixture
+        .broker
+        .subscribe(QUEUE, &[ROUTING_KEY])
+        .await
+        .unwrap();
+    let mut other = fixture
+        .broker
+        .subscribe("other", &[ROUTING_KEY])
+        .await
+        .unwrap();
+    fixture.publish("1").await;
+    next(&mut other).await.ack().await.unwrap();
+    next(&mut subscription).await.nack(false).await.unwrap();
+
+    assert_eq!(fixture.broker.replay_dead_letters(QUEUE).await.unwrap(), 1);
+
+    let delivery = next(&mut subscription).await;
+    assert_eq!(delivery.routing_key, ROUTING_KEY);
+    assert_eq!(delivery.message_id.as_deref(), Some("1"));
+    delivery.ack().await.unwrap();
+    let redelivered = tokio::time::timeout(Duration::from_millis(200), other.next()).await;
+    assert!(redelivered.is_err(), "replayed to another queue");
+    assert_eq!(fixture.broker.replay_dead_letters(QUEUE).await.unwrap(), 0);
+    assert_eq!(fixture.pending().await, 0);
+
+    fixture.cleanup().await;
+}
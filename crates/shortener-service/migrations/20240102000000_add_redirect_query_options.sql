@@ -0,0 +1,3 @@
+ALTER TABLE urls
+    ADD COLUMN forward_query BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN utm_params JSONB NOT NULL DEFAULT '{}'::jsonb;
@@ -0,0 +1,3 @@
+ALTER TABLE urls
+    ADD COLUMN redirect_type SMALLINT NOT NULL DEFAULT 307
+        CHECK (redirect_type IN (301, 302, 307, 308));
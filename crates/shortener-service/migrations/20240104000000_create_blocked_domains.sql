@@ -0,0 +1,5 @@
+CREATE TABLE blocked_domains (
+    domain TEXT PRIMARY KEY,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
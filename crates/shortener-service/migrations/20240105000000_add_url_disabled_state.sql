@@ -0,0 +1,3 @@
+ALTER TABLE urls
+    ADD COLUMN disabled_at TIMESTAMPTZ,
+    ADD COLUMN disabled_reason TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE urls
+    ADD COLUMN always_interstitial BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+ALTER TABLE urls
+    ADD COLUMN og_metadata JSONB NOT NULL DEFAULT '{}'::jsonb;
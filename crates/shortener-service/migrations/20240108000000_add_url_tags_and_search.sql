@@ -0,0 +1,16 @@
+ALTER TABLE urls
+    ADD COLUMN title TEXT,
+    ADD COLUMN description TEXT,
+    ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN destination_host TEXT GENERATED ALWAYS AS (
+        lower(substring(original_url FROM '^[A-Za-z][A-Za-z0-9+.-]*://(?:[^@/?#]*@)?([^/:?#]+)'))
+    ) STORED,
+    ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', coalesce(title, '')), 'A') ||
+        setweight(to_tsvector('simple', coalesce(description, '')), 'B') ||
+        setweight(to_tsvector('simple', original_url), 'C')
+    ) STORED;
+
+CREATE INDEX idx_urls_tags ON urls USING GIN (tags);
+CREATE INDEX idx_urls_destination_host ON urls(destination_host);
+CREATE INDEX idx_urls_search_vector ON urls USING GIN (search_vector);
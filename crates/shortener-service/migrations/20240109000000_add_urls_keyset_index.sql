@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_urls_created_at;
+
+CREATE INDEX idx_urls_created_at_id ON urls(created_at DESC, id DESC) WHERE is_active = TRUE;
@@ -0,0 +1,5 @@
+-- Set once the expiry of a link has been announced, so it is announced only once.
+ALTER TABLE urls ADD COLUMN expiry_notified_at TIMESTAMPTZ;
+
+CREATE INDEX idx_urls_pending_expiry ON urls (expires_at)
+    WHERE is_active = true AND expires_at IS NOT NULL AND expiry_notified_at IS NULL;
@@ -0,0 +1,14 @@
+-- When the link was deleted. updated_at cannot tell, as later updates of a
+-- deleted row (disabling, fetched metadata) bump it too.
+ALTER TABLE urls ADD COLUMN deleted_at TIMESTAMPTZ;
+
+UPDATE urls SET deleted_at = updated_at WHERE is_active = false;
+
+CREATE INDEX idx_urls_deleted_created_at_id ON urls (created_at DESC, id DESC) WHERE is_active = false;
+CREATE INDEX idx_urls_deleted_at ON urls (deleted_at) WHERE is_active = false;
+
+-- Codes of purged links that may not be handed out again.
+CREATE TABLE retired_codes (
+    code VARCHAR(10) PRIMARY KEY,
+    retired_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,14 @@
+-- The parser reads a destination as a single URL token, so words of its
+-- path or host did not match a search. Index them as separate words, the way
+-- the SQLite store matches them.
+DROP INDEX idx_urls_search_vector;
+
+ALTER TABLE urls
+    DROP COLUMN search_vector,
+    ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', coalesce(title, '')), 'A') ||
+        setweight(to_tsvector('simple', coalesce(description, '')), 'B') ||
+        setweight(to_tsvector('simple', regexp_replace(original_url, '[^[:alnum:]]+', ' ', 'g')), 'C')
+    ) STORED;
+
+CREATE INDEX idx_urls_search_vector ON urls USING GIN (search_vector);
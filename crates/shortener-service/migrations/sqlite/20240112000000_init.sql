@@ -0,0 +1,70 @@
+-- Schema of the Postgres migrations up to 20240112000000. Later changes are
+-- added to both directories under the same version.
+--
+-- UUIDs are stored as 16-byte blobs, timestamps as RFC 3339 text and arrays
+-- as JSON text. IDs, timestamps and destination_host are set by the store.
+
+CREATE TABLE urls (
+    id BLOB PRIMARY KEY,
+    code TEXT NOT NULL UNIQUE,
+    original_url TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    expires_at TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    forward_query BOOLEAN NOT NULL DEFAULT FALSE,
+    utm_params TEXT NOT NULL DEFAULT '{}',
+    redirect_type INTEGER NOT NULL DEFAULT 307
+        CHECK (redirect_type IN (301, 302, 307, 308)),
+    disabled_at TEXT,
+    disabled_reason TEXT,
+    always_interstitial BOOLEAN NOT NULL DEFAULT FALSE,
+    og_metadata TEXT NOT NULL DEFAULT '{}',
+    title TEXT,
+    description TEXT,
+    tags TEXT NOT NULL DEFAULT '[]',
+    destination_host TEXT,
+    expiry_notified_at TEXT
+);
+
+CREATE INDEX idx_urls_created_at_id ON urls (created_at DESC, id DESC) WHERE is_active = TRUE;
+CREATE INDEX idx_urls_destination_host ON urls (destination_host);
+CREATE INDEX idx_urls_pending_expiry ON urls (expires_at)
+    WHERE is_active = TRUE AND expires_at IS NOT NULL AND expiry_notified_at IS NULL;
+
+CREATE TABLE blocked_domains (
+    domain TEXT PRIMARY KEY,
+    reason TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE webhook_subscriptions (
+    id BLOB PRIMARY KEY,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    -- Routing keys of the events to deliver; empty means every event.
+    event_types TEXT NOT NULL DEFAULT '[]',
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE webhook_deliveries (
+    id BLOB PRIMARY KEY,
+    subscription_id BLOB NOT NULL REFERENCES webhook_subscriptions (id) ON DELETE CASCADE,
+    event_id BLOB NOT NULL,
+    event_type TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'succeeded', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TEXT NOT NULL,
+    last_status_code INTEGER,
+    last_error TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (subscription_id, event_id)
+);
+
+CREATE INDEX idx_webhook_deliveries_due ON webhook_deliveries (next_attempt_at)
+    WHERE status = 'pending';
+CREATE INDEX idx_webhook_deliveries_subscription ON webhook_deliveries (subscription_id, created_at DESC);
@@ -0,0 +1,11 @@
+ALTER TABLE urls ADD COLUMN deleted_at TEXT;
+
+UPDATE urls SET deleted_at = updated_at WHERE is_active = FALSE;
+
+CREATE INDEX idx_urls_deleted_created_at_id ON urls (created_at DESC, id DESC) WHERE is_active = FALSE;
+CREATE INDEX idx_urls_deleted_at ON urls (deleted_at) WHERE is_active = FALSE;
+
+CREATE TABLE retired_codes (
+    code TEXT PRIMARY KEY,
+    retired_at TEXT NOT NULL
+);
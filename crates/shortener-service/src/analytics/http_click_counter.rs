@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use shortener_core::AppError;
+use tracing::instrument;
+
+use super::ClickCounter;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Reads click counts from the analytics-service HTTP API.
+pub struct HttpClickCounter {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct AnalyticsResponse {
+    access_count: i64,
+}
+
+impl HttpClickCounter {
+    /// Creates a counter for the analytics-service listening at `base_url`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Internal` if the HTTP client cannot be built.
+    pub fn new(base_url: &str) -> Result<Self, AppError> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl ClickCounter for HttpClickCounter {
+    #[instrument(skip(self))]
+    async fn click_count(&self, code: &str) -> Result<i64, AppError> {
+        let response = self
+            .client
+            .get(format!("{}/api/v1/analytics/{code}", self.base_url))
+            .send()
+            .await
+            .map_err(|e| AppError::Internal(format!("Analytics request failed: {e}")))?;
+
+        // analytics-service has no entry until the first click is processed.
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(0);
+        }
+
+        let analytics: AnalyticsResponse = response
+            .error_for_status()
+            .map_err(|e| AppError::Internal(format!("Analytics request failed: {e}")))?
+            .json()
+            .await
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+
+        Ok(analytics.access_count)
+    }
+}
@@ -0,0 +1,14 @@
+mod http_click_counter;
+
+use async_trait::async_trait;
+use shortener_core::AppError;
+
+pub use http_click_counter::HttpClickCounter;
+
+/// Trait for looking up click counts of short URLs.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait ClickCounter: Send + Sync {
+    /// Returns the number of recorded clicks for `code`.
+    async fn click_count(&self, code: &str) -> Result<i64, AppError>;
+}
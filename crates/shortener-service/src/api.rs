@@ -0,0 +1,12 @@
+//! Request and response bodies of the HTTP API, defined in shortener-types
+//! so that clients do not depend on the service.
+
+pub use crate::repository::{
+    BlockedDomain, OgMetadata, RedirectType, Url, UrlState, UtmParams, WebhookDelivery,
+    WebhookSubscription,
+};
+pub use crate::routes::{
+    BlockDomainRequest, CreateUrlRequest, CreateUrlResponse, CreateWebhookRequest,
+    CreateWebhookResponse, HealthResponse, ListDeliveriesQuery, ListUrlsQuery, ReadyResponse,
+    UpdateUrlRequest,
+};
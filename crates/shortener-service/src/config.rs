@@ -2,12 +2,14 @@
 
 use saferet::SecretString;
 use serviceconf::ServiceConf;
-use shortener_core::config::{DatabaseConfig, ObservabilityConfig, RabbitMQConfig};
+use shortener_core::config::{
+    BrokerConfig, DatabaseConfig, ObservabilityConfig, RabbitMQConfig, RedisStreamsConfig,
+};
 
 /// Configuration for shortener-service.
 #[derive(Debug, Clone, ServiceConf)]
 pub struct Config {
-    /// Database connection URL.
+    /// Database connection URL: `postgres://...` or `sqlite:...`.
     #[conf(from_file)]
     pub database_url: SecretString,
 
@@ -15,19 +17,35 @@ pub struct Config {
     #[conf(default = 10)]
     pub database_max_connections: u32,
 
-    /// `RabbitMQ` connection URL.
+    /// Message broker: `rabbitmq`, `redis` (Redis Streams) or `in_process`.
+    #[conf(default = "rabbitmq".to_string())]
+    pub message_broker: String,
+
+    /// `RabbitMQ` connection URL (required by the `rabbitmq` broker).
     #[conf(from_file)]
-    pub rabbitmq_url: SecretString,
+    pub rabbitmq_url: Option<SecretString>,
 
     /// `RabbitMQ` exchange name.
     #[conf(default = "url_shortener".to_string())]
     pub rabbitmq_exchange: String,
 
-    /// `RabbitMQ` queue name.
+    /// Redis connection URL (required by the `redis` broker).
+    #[conf(from_file)]
+    pub redis_url: Option<SecretString>,
+
+    /// Prefix of the Redis stream keys used by the `redis` broker.
+    #[conf(default = "url_shortener".to_string())]
+    pub redis_stream_prefix: String,
+
+    /// Approximate number of entries kept per Redis stream.
+    #[conf(default = 100_000)]
+    pub redis_stream_max_len: usize,
+
+    /// Queue access events are delivered to.
     #[conf(default = "access_events".to_string())]
     pub rabbitmq_queue: String,
 
-    /// `RabbitMQ` routing key.
+    /// Routing key access events are published with.
     #[conf(default = "access.event".to_string())]
     pub rabbitmq_routing_key: String,
 
@@ -42,10 +60,70 @@ pub struct Config {
     /// Server port.
     #[conf(default = 8080)]
     pub server_port: u16,
+
+    /// Comma-separated domains this service answers on. Links to them are
+    /// rejected to prevent redirect loops.
+    pub short_domains: Option<String>,
+
+    /// Path to a local phishing/malware threat list (optional).
+    pub threat_feed_path: Option<String>,
+
+    /// Threat list format: `hosts` or `hash_prefix`.
+    #[conf(default = "hosts".to_string())]
+    pub threat_feed_format: String,
+
+    /// Interval in seconds between threat list reloads.
+    #[conf(default = 300)]
+    pub threat_feed_refresh_secs: u64,
+
+    /// Base URL of analytics-service, used to show click counts on preview pages (optional).
+    pub analytics_service_url: Option<String>,
+
+    /// Whether to fetch Open Graph metadata of destinations when links are created.
+    #[conf(default = true)]
+    pub og_fetch_enabled: bool,
+
+    /// `Cache-Control` max-age in seconds for permanent (301/308) redirects.
+    #[conf(default = 86400)]
+    pub permanent_redirect_max_age: u64,
+
+    /// Interval in seconds between checks for links that have expired.
+    #[conf(default = 60)]
+    pub expiry_check_secs: u64,
+
+    /// Days deleted links stay restorable before they are purged (optional;
+    /// kept until purged with shortener-admin when unset).
+    pub trash_retention_days: Option<u32>,
+
+    /// Interval in seconds between purges of the trash.
+    #[conf(default = 3600)]
+    pub trash_purge_secs: u64,
+
+    /// Whether the codes of purged links may be given to new links. When
+    /// disabled, old short URLs can never lead somewhere else.
+    #[conf(default = false)]
+    pub reuse_purged_codes: bool,
+
+    /// Queue the link events delivered to webhooks are read from.
+    #[conf(default = "webhook_events".to_string())]
+    pub webhook_queue: String,
+
+    /// Attempts made to deliver a webhook before giving up.
+    #[conf(default = 8)]
+    pub webhook_max_attempts: i32,
+
+    /// Interval in seconds between polls for due webhook deliveries.
+    #[conf(default = 5)]
+    pub webhook_poll_secs: u64,
+
+    /// Encoding of published events: `json` or `msgpack`.
+    #[conf(default = "json".to_string())]
+    pub event_codec: String,
 }
 
 impl Config {
     /// Returns the database configuration.
+    #[must_use]
     pub fn database_config(&self) -> DatabaseConfig {
         DatabaseConfig {
             url: self.database_url.clone(),
@@ -53,24 +131,59 @@ impl Config {
         }
     }
 
-    /// Returns the `RabbitMQ` configuration.
-    pub fn rabbitmq_config(&self) -> RabbitMQConfig {
-        RabbitMQConfig {
-            url: self.rabbitmq_url.clone(),
-            exchange: self.rabbitmq_exchange.clone(),
-            queue: self.rabbitmq_queue.clone(),
-            routing_key: self.rabbitmq_routing_key.clone(),
+    /// Returns the message broker configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the broker is unknown or its URL is not set.
+    pub fn broker_config(&self) -> anyhow::Result<BrokerConfig> {
+        match self.message_broker.as_str() {
+            "rabbitmq" => {
+                let Some(url) = &self.rabbitmq_url else {
+                    anyhow::bail!("RABBITMQ_URL must be set to use the rabbitmq broker");
+                };
+                Ok(BrokerConfig::RabbitMQ(RabbitMQConfig {
+                    url: url.clone(),
+                    exchange: self.rabbitmq_exchange.clone(),
+                }))
+            }
+            "redis" => {
+                let Some(url) = &self.redis_url else {
+                    anyhow::bail!("REDIS_URL must be set to use the redis broker");
+                };
+                Ok(BrokerConfig::RedisStreams(RedisStreamsConfig {
+                    url: url.clone(),
+                    prefix: self.redis_stream_prefix.clone(),
+                    max_len: self.redis_stream_max_len,
+                }))
+            }
+            "in_process" => Ok(BrokerConfig::InProcess),
+            other => anyhow::bail!("Unknown message broker '{other}'"),
         }
     }
 
     /// Returns the observability configuration.
+    #[must_use]
     pub fn observability_config(&self) -> ObservabilityConfig {
         ObservabilityConfig {
             otlp_endpoint: self.otel_exporter_endpoint.clone(),
         }
     }
 
+    /// Returns the domains listed in `short_domains`.
+    pub fn short_domains(&self) -> Vec<String> {
+        self.short_domains
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
     /// Returns the server address.
+    #[must_use]
     pub fn server_addr(&self) -> String {
         format!("{}:{}", self.server_host, self.server_port)
     }
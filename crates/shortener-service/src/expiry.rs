@@ -0,0 +1,49 @@
+//! Announces links whose expiry time has passed.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use shortener_core::messaging::{LinkEvent, UrlExpired};
+use tracing::{error, warn};
+
+use crate::{publisher::EventPublisher, repository::UrlStore};
+
+/// Expired links announced per query.
+const BATCH_SIZE: i64 = 100;
+
+/// Publishes a `url.expired` event for each link that expired, checking
+/// every `interval`.
+pub fn spawn_expiry_sweeper(
+    url_store: Arc<dyn UrlStore>,
+    publisher: Arc<dyn EventPublisher>,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+
+            loop {
+                let expired = match url_store.mark_expired(BATCH_SIZE).await {
+                    Ok(expired) => expired,
+                    Err(e) => {
+                        error!("Failed to find expired links: {:?}", e);
+                        break;
+                    }
+                };
+                let full = i64::try_from(expired.len()) == Ok(BATCH_SIZE);
+
+                for (code, expires_at) in expired {
+                    let event = LinkEvent::Expired(UrlExpired::new(code, expires_at));
+                    if let Err(e) = publisher.publish_link_event(event).await {
+                        warn!("Failed to publish link event: {:?}", e);
+                    }
+                }
+
+                if !full {
+                    break;
+                }
+            }
+        }
+    });
+}
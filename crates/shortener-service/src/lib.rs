@@ -0,0 +1,227 @@
+//! URL shortening service.
+//!
+//! The `shortener-service` binary serves [`router`] on its own; the
+//! all-in-one binary mounts [`service_routes`] next to analytics-service in
+//! one process. [`api`] holds the request and response bodies for clients.
+
+pub mod analytics;
+pub mod api;
+pub mod config;
+mod expiry;
+mod metadata;
+pub mod publisher;
+pub mod repository;
+mod routes;
+mod threat_feed;
+mod trash;
+mod validation;
+mod webhooks;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    Router,
+    routing::{delete, get, post},
+};
+use chrono::TimeDelta;
+use shortener_core::{Broker, messaging::Codec, openapi::docs_routes};
+use tracing::error;
+use utoipa::OpenApi;
+
+use analytics::ClickCounter;
+pub use config::Config;
+use metadata::{HttpMetadataFetcher, MetadataFetcher};
+use publisher::{AccessEventPublisher, EventPublisher};
+use repository::{BlocklistStore, Stores, UrlStore, WebhookStore};
+use threat_feed::{FeedFormat, ThreatFeed};
+use validation::DestinationPolicy;
+use webhooks::{DeliveryWorker, LinkEventDispatcher};
+
+/// `Cache-Control` max-age of permanent redirects when not configured.
+const DEFAULT_PERMANENT_REDIRECT_MAX_AGE: u64 = 86400;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub url_store: Arc<dyn UrlStore>,
+    pub blocklist_store: Arc<dyn BlocklistStore>,
+    pub webhook_store: Arc<dyn WebhookStore>,
+    pub destination_policy: Arc<DestinationPolicy>,
+    pub threat_feed: Option<Arc<ThreatFeed>>,
+    pub event_publisher: Arc<dyn EventPublisher>,
+    pub click_counter: Option<Arc<dyn ClickCounter>>,
+    pub metadata_fetcher: Option<Arc<dyn MetadataFetcher>>,
+    pub permanent_redirect_max_age: u64,
+}
+
+impl AppState {
+    /// State over `stores` and `event_publisher` with the default destination
+    /// policy and no threat list, click counter or metadata fetcher.
+    ///
+    /// No background tasks are started; [`build`] does that for a configured
+    /// service.
+    #[must_use]
+    pub fn new(stores: Stores, event_publisher: Arc<dyn EventPublisher>) -> Self {
+        Self {
+            url_store: stores.urls,
+            blocklist_store: stores.blocklist,
+            webhook_store: stores.webhooks,
+            destination_policy: Arc::new(DestinationPolicy::default()),
+            threat_feed: None,
+            event_publisher,
+            click_counter: None,
+            metadata_fetcher: None,
+            permanent_redirect_max_age: DEFAULT_PERMANENT_REDIRECT_MAX_AGE,
+        }
+    }
+}
+
+/// Connects to the database, publishes through `broker` and starts the
+/// background tasks (expiry sweeper, trash purger, webhook dispatcher and
+/// delivery worker, threat list refresh).
+///
+/// # Errors
+///
+/// Returns an error if the configuration is invalid or a dependency cannot
+/// be reached.
+pub async fn build(
+    config: &Config,
+    broker: Arc<dyn Broker>,
+    click_counter: Option<Arc<dyn ClickCounter>>,
+) -> anyhow::Result<AppState> {
+    let stores = repository::connect(&config.database_config()).await?;
+
+    let event_publisher: Arc<dyn EventPublisher> = Arc::new(
+        AccessEventPublisher::new(
+            Arc::clone(&broker),
+            &config.rabbitmq_queue,
+            &config.rabbitmq_routing_key,
+            config.event_codec.parse::<Codec>()?,
+        )
+        .await?,
+    );
+
+    expiry::spawn_expiry_sweeper(
+        Arc::clone(&stores.urls),
+        event_publisher.clone(),
+        Duration::from_secs(config.expiry_check_secs),
+    );
+
+    if let Some(retention_days) = config.trash_retention_days {
+        trash::spawn_trash_purger(
+            Arc::clone(&stores.urls),
+            event_publisher.clone(),
+            TimeDelta::days(i64::from(retention_days)),
+            !config.reuse_purged_codes,
+            Duration::from_secs(config.trash_purge_secs),
+        );
+    }
+
+    let dispatcher =
+        LinkEventDispatcher::new(broker, &config.webhook_queue, Arc::clone(&stores.webhooks))
+            .await?;
+    tokio::spawn(async move {
+        if let Err(e) = dispatcher.run().await {
+            error!("Webhook dispatcher error: {:?}", e);
+        }
+    });
+
+    DeliveryWorker::new(Arc::clone(&stores.webhooks), config.webhook_max_attempts)?
+        .spawn(Duration::from_secs(config.webhook_poll_secs));
+
+    let threat_feed = match &config.threat_feed_path {
+        Some(path) => {
+            let feed = Arc::new(ThreatFeed::new(
+                path.into(),
+                config.threat_feed_format.parse::<FeedFormat>()?,
+            ));
+            feed.reload().await?;
+            Arc::clone(&feed).spawn_refresh(
+                Arc::clone(&stores.urls),
+                Duration::from_secs(config.threat_feed_refresh_secs),
+            );
+            Some(feed)
+        }
+        None => None,
+    };
+
+    let destination_policy = DestinationPolicy::new(config.short_domains());
+
+    let metadata_fetcher = if config.og_fetch_enabled {
+        Some(
+            Arc::new(HttpMetadataFetcher::new(destination_policy.clone())?)
+                as Arc<dyn MetadataFetcher>,
+        )
+    } else {
+        None
+    };
+
+    Ok(AppState {
+        destination_policy: Arc::new(destination_policy),
+        threat_feed,
+        click_counter,
+        metadata_fetcher,
+        permanent_redirect_max_age: config.permanent_redirect_max_age,
+        ..AppState::new(stores, event_publisher)
+    })
+}
+
+/// Routes of the HTTP API, without the health probes.
+pub fn api_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/v1/urls",
+            get(routes::list_urls).post(routes::create_url),
+        )
+        .route(
+            "/api/v1/urls/{code}",
+            get(routes::get_url)
+                .put(routes::update_url)
+                .delete(routes::delete_url),
+        )
+        .route("/api/v1/urls/{code}/restore", post(routes::restore_url))
+        .route(
+            "/api/v1/admin/blocklist",
+            get(routes::list_blocked_domains).post(routes::block_domain),
+        )
+        .route(
+            "/api/v1/admin/blocklist/{domain}",
+            delete(routes::unblock_domain),
+        )
+        .route(
+            "/api/v1/admin/webhooks",
+            get(routes::list_webhooks).post(routes::create_webhook),
+        )
+        .route(
+            "/api/v1/admin/webhooks/{id}",
+            delete(routes::delete_webhook),
+        )
+        .route(
+            "/api/v1/admin/webhooks/{id}/deliveries",
+            get(routes::list_webhook_deliveries),
+        )
+        .route("/{code}", get(routes::redirect).post(routes::follow))
+}
+
+/// The HTTP API together with the health probes.
+pub fn service_routes() -> Router<AppState> {
+    api_routes()
+        .route("/health", get(routes::health))
+        .route("/ready", get(routes::ready))
+}
+
+/// [`service_routes`] with their [`openapi`] document and a Swagger UI.
+///
+/// Redirects read the client address, so serve it with
+/// `into_make_service_with_connect_info::<SocketAddr>()`.
+pub fn router(state: AppState) -> Router {
+    service_routes()
+        .merge(docs_routes(openapi()))
+        .with_state(state)
+}
+
+/// `OpenAPI` document of [`service_routes`].
+#[must_use]
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    routes::ApiDoc::openapi()
+}
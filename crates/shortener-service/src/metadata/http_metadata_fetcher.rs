@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{header, redirect};
+use shortener_core::AppError;
+use tracing::instrument;
+
+use super::MetadataFetcher;
+use crate::{repository::OgMetadata, validation::DestinationPolicy};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REDIRECTS: usize = 5;
+
+/// Only the document head is needed, so larger bodies are truncated.
+const MAX_BODY_BYTES: usize = 256 * 1024;
+
+/// Fetches destination pages over HTTP and reads their Open Graph tags.
+pub struct HttpMetadataFetcher {
+    client: reqwest::Client,
+}
+
+impl HttpMetadataFetcher {
+    /// Creates a fetcher whose redirects are checked against `policy`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Internal` if the HTTP client cannot be built.
+    pub fn new(policy: DestinationPolicy) -> Result<Self, AppError> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!(
+                env!("CARGO_PKG_NAME"),
+                "/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .redirect(redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS
+                    || policy.validate(attempt.url().as_str()).is_err()
+                {
+                    attempt.stop()
+                } else {
+                    attempt.follow()
+                }
+            }))
+            .build()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl MetadataFetcher for HttpMetadataFetcher {
+    #[instrument(skip(self))]
+    async fn fetch(&self, url: &str) -> Result<OgMetadata, AppError> {
+        let mut response = self
+            .client
+            .get(url)
+            .header(header::ACCEPT, "text/html")
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| AppError::Internal(format!("Metadata request failed: {e}")))?;
+
+        let is_html = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("html"));
+        if !is_html {
+            return Ok(OgMetadata::default());
+        }
+
+        let base_url = response.url().clone();
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| AppError::Internal(format!("Metadata request failed: {e}")))?
+        {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_BODY_BYTES {
+                body.truncate(MAX_BODY_BYTES);
+                break;
+            }
+        }
+
+        let mut metadata = parse_metadata(&String::from_utf8_lossy(&body));
+        metadata.image_url = metadata
+            .image_url
+            .and_then(|image| base_url.join(&image).ok())
+            .map(String::from);
+
+        Ok(metadata)
+    }
+}
+
+/// Extracts Open Graph fields from `html`, falling back to Twitter card tags,
+/// the `description` meta tag and the `<title>` element.
+fn parse_metadata(html: &str) -> OgMetadata {
+    // ASCII lower-casing keeps byte offsets valid for `html`.
+    let lower = html.to_ascii_lowercase();
+
+    let mut og = OgMetadata::default();
+    let mut fallback = OgMetadata::default();
+
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find("<meta").map(|i| pos + i) {
+        let Some(end) = lower[start..].find('>').map(|i| start + i) else {
+            break;
+        };
+        pos = end;
+
+        let attributes = parse_attributes(&html[start + "<meta".len()..end]);
+        let key = attributes
+            .iter()
+            .find(|(name, _)| name == "property" || name == "name")
+            .map(|(_, value)| value.to_ascii_lowercase());
+        let content = attributes
+            .iter()
+            .find(|(name, _)| name == "content")
+            .map(|(_, value)| decode_entities(value.trim()))
+            .filter(|value| !value.is_empty());
+
+        let (Some(key), Some(content)) = (key, content) else {
+            continue;
+        };
+
+        let slot = match key.as_str() {
+            "og:title" => &mut og.title,
+            "og:description" => &mut og.description,
+            "og:image" | "og:image:url" | "og:image:secure_url" => &mut og.image_url,
+            "twitter:title" => &mut fallback.title,
+            "twitter:description" | "description" => &mut fallback.description,
+            "twitter:image" => &mut fallback.image_url,
+            _ => continue,
+        };
+        if slot.is_none() {
+            *slot = Some(content);
+        }
+    }
+
+    if fallback.title.is_none()
+        && let Some(start) = lower.find("<title")
+        && let Some(open_end) = lower[start..].find('>').map(|i| start + i + 1)
+        && let Some(close) = lower[open_end..].find("</title").map(|i| open_end + i)
+    {
+        let title = decode_entities(html[open_end..close].trim());
+        if !title.is_empty() {
+            fallback.title = Some(title);
+        }
+    }
+
+    OgMetadata {
+        title: og.title.or(fallback.title),
+        description: og.description.or(fallback.description),
+        image_url: og.image_url.or(fallback.image_url),
+    }
+}
+
+/// Parses `name="value"` pairs of a tag, lower-casing attribute names.
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = tag.trim_start_matches('/');
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            break;
+        }
+
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let Some(after_eq) = rest.strip_prefix('=') else {
+            attributes.push((name, String::new()));
+            continue;
+        };
+        let after_eq = after_eq.trim_start();
+
+        let (value, remaining) = if let Some(quote @ ('"' | '\'')) = after_eq.chars().next() {
+            let inner = &after_eq[1..];
+            let end = inner.find(quote).unwrap_or(inner.len());
+            (&inner[..end], inner.get(end + 1..).unwrap_or_default())
+        } else {
+            let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+            (&after_eq[..end], &after_eq[end..])
+        };
+
+        attributes.push((name, value.to_string()));
+        rest = remaining;
+    }
+
+    attributes
+}
+
+/// Decodes the character references commonly found in attribute values.
+fn decode_entities(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
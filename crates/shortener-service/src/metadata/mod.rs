@@ -0,0 +1,16 @@
+mod http_metadata_fetcher;
+
+use async_trait::async_trait;
+use shortener_core::AppError;
+
+use crate::repository::OgMetadata;
+
+pub use http_metadata_fetcher::HttpMetadataFetcher;
+
+/// Trait for fetching social preview metadata of destination pages.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait MetadataFetcher: Send + Sync {
+    /// Fetch the Open Graph metadata of the page at `url`.
+    async fn fetch(&self, url: &str) -> Result<OgMetadata, AppError>;
+}
@@ -1,36 +1,42 @@
-use std::collections::HashMap;
+use std::sync::Arc;
 
 use async_trait::async_trait;
-use lapin::{
-    BasicProperties,
-    options::BasicPublishOptions,
-    types::{AMQPValue, FieldTable, LongString},
+use shortener_core::{
+    AppError, Broker,
+    messaging::{AccessEvent, Codec, LinkEvent, envelope},
 };
-use opentelemetry::{global, propagation::Injector};
-use shortener_core::{AppError, RabbitMQChannel, config::RabbitMQConfig, messaging::AccessEvent};
-use tracing::{Span, info, instrument};
-use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing::{info, instrument};
 
 use super::EventPublisher;
 
-pub struct AccessEventPublisher {
-    rabbitmq: RabbitMQChannel,
-}
-
-/// Carrier for injecting trace context into message headers.
-struct HeaderInjector(HashMap<String, String>);
+const PRODUCER: &str = env!("CARGO_PKG_NAME");
 
-impl Injector for HeaderInjector {
-    fn set(&mut self, key: &str, value: String) {
-        self.0.insert(key.to_string(), value);
-    }
+pub struct AccessEventPublisher {
+    broker: Arc<dyn Broker>,
+    routing_key: String,
+    codec: Codec,
 }
 
 impl AccessEventPublisher {
-    #[instrument(skip(config))]
-    pub async fn new(config: &RabbitMQConfig) -> Result<Self, AppError> {
-        let rabbitmq = RabbitMQChannel::try_new(config).await?;
-        Ok(Self { rabbitmq })
+    /// Declares `queue` so access events published before analytics-service
+    /// starts consuming are kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the queue cannot be declared.
+    #[instrument(skip(broker))]
+    pub async fn new(
+        broker: Arc<dyn Broker>,
+        queue: &str,
+        routing_key: &str,
+        codec: Codec,
+    ) -> Result<Self, AppError> {
+        broker.declare_queue(queue, &[routing_key]).await?;
+        Ok(Self {
+            broker,
+            routing_key: routing_key.to_string(),
+            codec,
+        })
     }
 }
 
@@ -38,39 +44,16 @@ impl AccessEventPublisher {
 impl EventPublisher for AccessEventPublisher {
     #[instrument(skip(self))]
     async fn publish(&self, event: AccessEvent) -> Result<(), AppError> {
-        let payload =
-            serde_json::to_vec(&event).map_err(|e| AppError::Serialization(e.to_string()))?;
+        let payload = envelope::encode(self.codec, PRODUCER, &event)?;
 
-        // Inject trace context into headers
-        let mut injector = HeaderInjector(HashMap::new());
-        let context = Span::current().context();
-        global::get_text_map_propagator(|propagator| {
-            propagator.inject_context(&context, &mut injector);
-        });
-
-        // Convert to FieldTable for RabbitMQ headers
-        let mut headers = FieldTable::default();
-        for (k, v) in injector.0 {
-            headers.insert(k.into(), AMQPValue::LongString(LongString::from(v)));
-        }
-
-        let properties = BasicProperties::default()
-            .with_content_type("application/json".into())
-            .with_delivery_mode(2)
-            .with_message_id(event.event_id.to_string().into())
-            .with_headers(headers);
-
-        self.rabbitmq
-            .channel
-            .basic_publish(
-                &self.rabbitmq.exchange,
-                &self.rabbitmq.routing_key,
-                BasicPublishOptions::default(),
+        self.broker
+            .publish(
+                &self.routing_key,
+                &event.event_id.to_string(),
+                self.codec,
                 &payload,
-                properties,
             )
-            .await
-            .map_err(|e| AppError::MessageQueue(e.to_string()))?;
+            .await?;
 
         info!(
             event_id = %event.event_id,
@@ -80,4 +63,27 @@ impl EventPublisher for AccessEventPublisher {
 
         Ok(())
     }
+
+    #[instrument(skip(self))]
+    async fn publish_link_event(&self, event: LinkEvent) -> Result<(), AppError> {
+        let payload = event.encode(self.codec, PRODUCER)?;
+
+        self.broker
+            .publish(
+                event.routing_key(),
+                &event.event_id().to_string(),
+                self.codec,
+                &payload,
+            )
+            .await?;
+
+        info!(
+            event_id = %event.event_id(),
+            code = %event.code(),
+            routing_key = event.routing_key(),
+            "Link event published"
+        );
+
+        Ok(())
+    }
 }
@@ -1,14 +1,20 @@
 mod access_event_publisher;
 
 use async_trait::async_trait;
-use shortener_core::{AppError, messaging::AccessEvent};
+use shortener_core::{
+    AppError,
+    messaging::{AccessEvent, LinkEvent},
+};
 
 pub use access_event_publisher::AccessEventPublisher;
 
 /// Trait for publishing events.
-#[cfg_attr(test, mockall::automock)]
+#[cfg_attr(any(test, feature = "mock"), mockall::automock)]
 #[async_trait]
 pub trait EventPublisher: Send + Sync {
     /// Publish an access event.
     async fn publish(&self, event: AccessEvent) -> Result<(), AppError>;
+
+    /// Publish a link lifecycle event under its own routing key.
+    async fn publish_link_event(&self, event: LinkEvent) -> Result<(), AppError>;
 }
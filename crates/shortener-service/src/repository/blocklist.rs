@@ -0,0 +1,9 @@
+pub use shortener_types::shortener::BlockedDomain;
+
+/// Returns `host` and its parent domains, which a blocked domain matches.
+pub(super) fn domain_candidates(host: &str) -> Vec<String> {
+    host.match_indices('.')
+        .map(|(i, _)| host[i + 1..].to_string())
+        .chain(std::iter::once(host.to_string()))
+        .collect()
+}
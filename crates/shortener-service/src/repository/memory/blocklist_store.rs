@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use shortener_core::AppError;
+use tokio::sync::Mutex;
+
+use crate::repository::{BlockedDomain, BlocklistStore, blocklist::domain_candidates};
+
+/// Blocked domains kept in process memory.
+#[derive(Default)]
+pub struct InMemoryBlocklistStore {
+    domains: Mutex<BTreeMap<String, BlockedDomain>>,
+}
+
+impl InMemoryBlocklistStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl BlocklistStore for InMemoryBlocklistStore {
+    async fn is_blocked(&self, host: &str) -> Result<bool, AppError> {
+        let domains = self.domains.lock().await;
+
+        Ok(domain_candidates(host)
+            .iter()
+            .any(|candidate| domains.contains_key(candidate)))
+    }
+
+    async fn list(&self) -> Result<Vec<BlockedDomain>, AppError> {
+        Ok(self.domains.lock().await.values().cloned().collect())
+    }
+
+    async fn add(&self, domain: &str, reason: Option<String>) -> Result<BlockedDomain, AppError> {
+        let mut domains = self.domains.lock().await;
+
+        if domains.contains_key(domain) {
+            return Err(AppError::Conflict(format!(
+                "Domain '{domain}' is already blocked"
+            )));
+        }
+
+        let blocked = BlockedDomain {
+            domain: domain.to_string(),
+            reason,
+            created_at: Utc::now(),
+        };
+        domains.insert(domain.to_string(), blocked.clone());
+
+        Ok(blocked)
+    }
+
+    async fn remove(&self, domain: &str) -> Result<(), AppError> {
+        self.domains
+            .lock()
+            .await
+            .remove(domain)
+            .map(|_| ())
+            .ok_or_else(|| AppError::NotFound(format!("Blocked domain '{domain}' not found")))
+    }
+}
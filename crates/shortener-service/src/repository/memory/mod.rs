@@ -0,0 +1,7 @@
+mod blocklist_store;
+mod url_store;
+mod webhook_store;
+
+pub use blocklist_store::InMemoryBlocklistStore;
+pub use url_store::InMemoryUrlStore;
+pub use webhook_store::InMemoryWebhookStore;
@@ -0,0 +1,401 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use shortener_core::AppError;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::repository::{
+    NewUrl, OgMetadata, Url, UrlChanges, UrlFilter, UrlState, UrlStore,
+    url::{destination_host, generate_code},
+};
+
+/// A stored link with the columns that are not part of [`Url`].
+struct UrlRecord {
+    url: Url,
+    destination_host: Option<String>,
+    expiry_notified_at: Option<DateTime<Utc>>,
+}
+
+impl UrlRecord {
+    fn matches(&self, filter: &UrlFilter) -> bool {
+        let url = &self.url;
+
+        if let Some(tag) = &filter.tag
+            && !url.tags.contains(tag)
+        {
+            return false;
+        }
+        if let Some(domain) = &filter.domain
+            && !self.destination_host.as_deref().is_some_and(|host| {
+                host == domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|rest| rest.ends_with('.'))
+            })
+        {
+            return false;
+        }
+        if filter
+            .created_from
+            .is_some_and(|from| url.created_at < from)
+            || filter.created_to.is_some_and(|to| url.created_at >= to)
+        {
+            return false;
+        }
+        if let Some(q) = &filter.q {
+            let fields = [
+                url.title.as_deref(),
+                url.description.as_deref(),
+                Some(url.original_url.as_str()),
+            ]
+            .map(|field| field.map(str::to_lowercase));
+
+            return q
+                .split_whitespace()
+                .map(|word| word.trim_matches('"').to_lowercase())
+                .filter(|word| !word.is_empty())
+                .all(|word| fields.iter().flatten().any(|field| field.contains(&word)));
+        }
+
+        true
+    }
+}
+
+/// Links kept in process memory, matching search queries like the `SQLite`
+/// store.
+#[derive(Default)]
+pub struct InMemoryUrlStore {
+    records: Mutex<Vec<UrlRecord>>,
+    retired_codes: Mutex<HashSet<String>>,
+}
+
+impl InMemoryUrlStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn not_found(code: &str) -> AppError {
+    AppError::NotFound(format!("URL with code '{code}' not found"))
+}
+
+#[async_trait]
+impl UrlStore for InMemoryUrlStore {
+    async fn ping(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    async fn create(&self, new_url: &NewUrl) -> Result<Url, AppError> {
+        let mut records = self.records.lock().await;
+        let retired = self.retired_codes.lock().await;
+
+        // Codes stay unique across deleted links, as in the database schema.
+        let code = loop {
+            let code = generate_code();
+            if !retired.contains(&code) && !records.iter().any(|record| record.url.code == code) {
+                break code;
+            }
+        };
+
+        let now = Utc::now();
+        let url = Url {
+            id: Uuid::new_v4(),
+            code,
+            original_url: new_url.original_url.clone(),
+            created_at: now,
+            updated_at: now,
+            expires_at: new_url.expires_at,
+            is_active: true,
+            forward_query: new_url.forward_query,
+            utm_params: new_url.utm_params.clone(),
+            redirect_type: new_url.redirect_type,
+            disabled_at: None,
+            disabled_reason: None,
+            always_interstitial: new_url.always_interstitial,
+            og_metadata: new_url.og_metadata.clone(),
+            title: new_url.title.clone(),
+            description: new_url.description.clone(),
+            tags: new_url.tags.clone(),
+            deleted_at: None,
+        };
+
+        records.push(UrlRecord {
+            url: url.clone(),
+            destination_host: destination_host(&url.original_url),
+            expiry_notified_at: None,
+        });
+
+        Ok(url)
+    }
+
+    async fn find_by_code(&self, code: &str) -> Result<Option<Url>, AppError> {
+        let records = self.records.lock().await;
+
+        Ok(records
+            .iter()
+            .find(|record| record.url.is_active && record.url.code == code)
+            .map(|record| record.url.clone()))
+    }
+
+    async fn find_deleted(&self, code: &str) -> Result<Option<Url>, AppError> {
+        let records = self.records.lock().await;
+
+        Ok(records
+            .iter()
+            .find(|record| !record.url.is_active && record.url.code == code)
+            .map(|record| record.url.clone()))
+    }
+
+    async fn list(
+        &self,
+        filter: &UrlFilter,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<Url>, AppError> {
+        let records = self.records.lock().await;
+
+        let is_active = filter.state == UrlState::Active;
+        let mut urls: Vec<Url> = records
+            .iter()
+            .filter(|record| record.url.is_active == is_active && record.matches(filter))
+            .filter(|record| {
+                after.is_none_or(|after| (record.url.created_at, record.url.id) < after)
+            })
+            .map(|record| record.url.clone())
+            .collect();
+        urls.sort_by_key(|url| Reverse((url.created_at, url.id)));
+        urls.truncate(usize::try_from(limit).unwrap_or(0));
+
+        Ok(urls)
+    }
+
+    async fn update(&self, code: &str, changes: &UrlChanges) -> Result<Url, AppError> {
+        let mut records = self.records.lock().await;
+
+        let record = records
+            .iter_mut()
+            .find(|record| record.url.is_active && record.url.code == code)
+            .ok_or_else(|| not_found(code))?;
+
+        let url = &mut record.url;
+        url.original_url.clone_from(&changes.original_url);
+        record.destination_host = destination_host(&changes.original_url);
+        if let Some(forward_query) = changes.forward_query {
+            url.forward_query = forward_query;
+        }
+        if let Some(utm_params) = &changes.utm_params {
+            url.utm_params = utm_params.clone();
+        }
+        if let Some(redirect_type) = changes.redirect_type {
+            url.redirect_type = redirect_type;
+        }
+        if let Some(always_interstitial) = changes.always_interstitial {
+            url.always_interstitial = always_interstitial;
+        }
+        if let Some(og_metadata) = &changes.og_metadata {
+            url.og_metadata = og_metadata.clone();
+        }
+        if changes.title.is_some() {
+            url.title.clone_from(&changes.title);
+        }
+        if changes.description.is_some() {
+            url.description.clone_from(&changes.description);
+        }
+        if let Some(tags) = &changes.tags {
+            url.tags.clone_from(tags);
+        }
+        if changes.expires_at.is_some() && changes.expires_at != url.expires_at {
+            url.expires_at = changes.expires_at;
+            record.expiry_notified_at = None;
+        }
+        url.updated_at = Utc::now();
+
+        Ok(url.clone())
+    }
+
+    async fn merge_og_metadata(&self, code: &str, fetched: &OgMetadata) -> Result<(), AppError> {
+        let mut records = self.records.lock().await;
+
+        if let Some(record) = records.iter_mut().find(|record| record.url.code == code) {
+            let og_metadata = &mut record.url.og_metadata;
+            og_metadata.title = og_metadata.title.take().or_else(|| fetched.title.clone());
+            og_metadata.description = og_metadata
+                .description
+                .take()
+                .or_else(|| fetched.description.clone());
+            og_metadata.image_url = og_metadata
+                .image_url
+                .take()
+                .or_else(|| fetched.image_url.clone());
+        }
+
+        Ok(())
+    }
+
+    async fn list_enabled_destinations(
+        &self,
+        after: &str,
+        limit: i64,
+    ) -> Result<Vec<(String, String)>, AppError> {
+        let records = self.records.lock().await;
+
+        let mut destinations: Vec<(String, String)> = records
+            .iter()
+            .map(|record| &record.url)
+            .filter(|url| url.is_active && url.disabled_at.is_none() && url.code.as_str() > after)
+            .map(|url| (url.code.clone(), url.original_url.clone()))
+            .collect();
+        destinations.sort();
+        destinations.truncate(usize::try_from(limit).unwrap_or(0));
+
+        Ok(destinations)
+    }
+
+    async fn disable(&self, codes: &[String], reason: &str) -> Result<u64, AppError> {
+        let mut records = self.records.lock().await;
+
+        let now = Utc::now();
+        let mut disabled = 0;
+        for record in records.iter_mut() {
+            let url = &mut record.url;
+            if url.disabled_at.is_none() && codes.contains(&url.code) {
+                url.disabled_at = Some(now);
+                url.disabled_reason = Some(reason.to_string());
+                url.updated_at = now;
+                disabled += 1;
+            }
+        }
+
+        Ok(disabled)
+    }
+
+    async fn list_disabled_destinations(
+        &self,
+        reason: &str,
+        after: &str,
+        limit: i64,
+    ) -> Result<Vec<(String, String)>, AppError> {
+        let records = self.records.lock().await;
+
+        let mut destinations: Vec<(String, String)> = records
+            .iter()
+            .map(|record| &record.url)
+            .filter(|url| {
+                url.is_active
+                    && url.disabled_reason.as_deref() == Some(reason)
+                    && url.code.as_str() > after
+            })
+            .map(|url| (url.code.clone(), url.original_url.clone()))
+            .collect();
+        destinations.sort();
+        destinations.truncate(usize::try_from(limit).unwrap_or(0));
+
+        Ok(destinations)
+    }
+
+    async fn enable(&self, codes: &[String], reason: &str) -> Result<u64, AppError> {
+        let mut records = self.records.lock().await;
+
+        let now = Utc::now();
+        let mut enabled = 0;
+        for record in records.iter_mut() {
+            let url = &mut record.url;
+            if url.disabled_reason.as_deref() == Some(reason) && codes.contains(&url.code) {
+                url.disabled_at = None;
+                url.disabled_reason = None;
+                url.updated_at = now;
+                enabled += 1;
+            }
+        }
+
+        Ok(enabled)
+    }
+
+    async fn mark_expired(&self, limit: i64) -> Result<Vec<(String, DateTime<Utc>)>, AppError> {
+        let mut records = self.records.lock().await;
+
+        let now = Utc::now();
+        let mut due: Vec<&mut UrlRecord> = records
+            .iter_mut()
+            .filter(|record| {
+                record.url.is_active
+                    && record.expiry_notified_at.is_none()
+                    && record
+                        .url
+                        .expires_at
+                        .is_some_and(|expires_at| expires_at <= now)
+            })
+            .collect();
+        due.sort_by_key(|record| record.url.expires_at);
+        due.truncate(usize::try_from(limit).unwrap_or(0));
+
+        Ok(due
+            .into_iter()
+            .filter_map(|record| {
+                record.expiry_notified_at = Some(now);
+                Some((record.url.code.clone(), record.url.expires_at?))
+            })
+            .collect())
+    }
+
+    async fn delete(&self, code: &str) -> Result<(), AppError> {
+        let mut records = self.records.lock().await;
+
+        let record = records
+            .iter_mut()
+            .find(|record| record.url.is_active && record.url.code == code)
+            .ok_or_else(|| not_found(code))?;
+        let now = Utc::now();
+        record.url.is_active = false;
+        record.url.deleted_at = Some(now);
+        record.url.updated_at = now;
+
+        Ok(())
+    }
+
+    async fn restore(&self, code: &str) -> Result<Url, AppError> {
+        let mut records = self.records.lock().await;
+
+        let record = records
+            .iter_mut()
+            .find(|record| !record.url.is_active && record.url.code == code)
+            .ok_or_else(|| {
+                AppError::NotFound(format!("Deleted URL with code '{code}' not found"))
+            })?;
+        record.url.is_active = true;
+        record.url.deleted_at = None;
+        record.url.updated_at = Utc::now();
+
+        Ok(record.url.clone())
+    }
+
+    async fn purge_deleted(
+        &self,
+        deleted_before: DateTime<Utc>,
+        retire_codes: bool,
+    ) -> Result<Vec<String>, AppError> {
+        let mut records = self.records.lock().await;
+        let mut retired = self.retired_codes.lock().await;
+
+        let mut purged = Vec::new();
+        records.retain(|record| {
+            let purge = record
+                .url
+                .deleted_at
+                .is_some_and(|deleted_at| deleted_at < deleted_before);
+            if purge {
+                if retire_codes {
+                    retired.insert(record.url.code.clone());
+                }
+                purged.push(record.url.code.clone());
+            }
+            !purge
+        });
+
+        Ok(purged)
+    }
+}
@@ -0,0 +1,245 @@
+use std::cmp::Reverse;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use shortener_core::AppError;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::repository::{DueDelivery, WebhookDelivery, WebhookStore, WebhookSubscription};
+
+#[derive(Default)]
+struct State {
+    subscriptions: Vec<WebhookSubscription>,
+    deliveries: Vec<WebhookDelivery>,
+}
+
+/// Webhook subscriptions and deliveries kept in process memory.
+#[derive(Default)]
+pub struct InMemoryWebhookStore {
+    state: Mutex<State>,
+}
+
+impl InMemoryWebhookStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl WebhookStore for InMemoryWebhookStore {
+    async fn create(
+        &self,
+        url: &str,
+        secret: &str,
+        event_types: &[String],
+    ) -> Result<WebhookSubscription, AppError> {
+        let subscription = WebhookSubscription {
+            id: Uuid::new_v4(),
+            url: url.to_string(),
+            secret: secret.to_string(),
+            event_types: event_types.to_vec(),
+            is_active: true,
+            created_at: Utc::now(),
+        };
+        self.state
+            .lock()
+            .await
+            .subscriptions
+            .push(subscription.clone());
+
+        Ok(subscription)
+    }
+
+    async fn list(&self) -> Result<Vec<WebhookSubscription>, AppError> {
+        Ok(self.state.lock().await.subscriptions.clone())
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), AppError> {
+        let mut state = self.state.lock().await;
+
+        let len = state.subscriptions.len();
+        state
+            .subscriptions
+            .retain(|subscription| subscription.id != id);
+        if state.subscriptions.len() == len {
+            return Err(AppError::NotFound(format!(
+                "Webhook subscription '{id}' not found"
+            )));
+        }
+        state
+            .deliveries
+            .retain(|delivery| delivery.subscription_id != id);
+
+        Ok(())
+    }
+
+    async fn enqueue(
+        &self,
+        event_id: Uuid,
+        event_type: &str,
+        payload: &serde_json::Value,
+    ) -> Result<u64, AppError> {
+        let mut state = self.state.lock().await;
+        let State {
+            subscriptions,
+            deliveries,
+        } = &mut *state;
+
+        let now = Utc::now();
+        let mut queued = 0;
+        for subscription in subscriptions.iter().filter(|subscription| {
+            subscription.is_active
+                && (subscription.event_types.is_empty()
+                    || subscription.event_types.iter().any(|t| t == event_type))
+        }) {
+            if deliveries.iter().any(|delivery| {
+                delivery.subscription_id == subscription.id && delivery.event_id == event_id
+            }) {
+                continue;
+            }
+
+            deliveries.push(WebhookDelivery {
+                id: Uuid::new_v4(),
+                subscription_id: subscription.id,
+                event_id,
+                event_type: event_type.to_string(),
+                payload: payload.clone(),
+                status: "pending".to_string(),
+                attempts: 0,
+                next_attempt_at: now,
+                last_status_code: None,
+                last_error: None,
+                created_at: now,
+                updated_at: now,
+            });
+            queued += 1;
+        }
+
+        Ok(queued)
+    }
+
+    async fn claim_due(
+        &self,
+        limit: i64,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<DueDelivery>, AppError> {
+        let mut state = self.state.lock().await;
+        let State {
+            subscriptions,
+            deliveries,
+        } = &mut *state;
+
+        let now = Utc::now();
+        let mut due: Vec<&mut WebhookDelivery> = deliveries
+            .iter_mut()
+            .filter(|delivery| delivery.status == "pending" && delivery.next_attempt_at <= now)
+            .collect();
+        due.sort_by_key(|delivery| delivery.next_attempt_at);
+        due.truncate(usize::try_from(limit).unwrap_or(0));
+
+        Ok(due
+            .into_iter()
+            .filter_map(|delivery| {
+                let subscription = subscriptions
+                    .iter()
+                    .find(|subscription| subscription.id == delivery.subscription_id)?;
+
+                delivery.attempts += 1;
+                delivery.next_attempt_at = lease_until;
+                delivery.updated_at = now;
+
+                Some(DueDelivery {
+                    id: delivery.id,
+                    event_id: delivery.event_id,
+                    event_type: delivery.event_type.clone(),
+                    payload: delivery.payload.clone(),
+                    attempts: delivery.attempts,
+                    url: subscription.url.clone(),
+                    secret: subscription.secret.clone(),
+                })
+            })
+            .collect())
+    }
+
+    async fn record_success(&self, id: Uuid, status_code: i16) -> Result<(), AppError> {
+        let mut state = self.state.lock().await;
+
+        if let Some(delivery) = state.deliveries.iter_mut().find(|d| d.id == id) {
+            delivery.status = "succeeded".to_string();
+            delivery.last_status_code = Some(status_code);
+            delivery.last_error = None;
+            delivery.updated_at = Utc::now();
+        }
+
+        Ok(())
+    }
+
+    async fn record_failure(
+        &self,
+        id: Uuid,
+        status_code: Option<i16>,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), AppError> {
+        let mut state = self.state.lock().await;
+
+        if let Some(delivery) = state.deliveries.iter_mut().find(|d| d.id == id) {
+            match retry_at {
+                Some(retry_at) => {
+                    delivery.status = "pending".to_string();
+                    delivery.next_attempt_at = retry_at;
+                }
+                None => delivery.status = "failed".to_string(),
+            }
+            delivery.last_status_code = status_code;
+            delivery.last_error = Some(error.to_string());
+            delivery.updated_at = Utc::now();
+        }
+
+        Ok(())
+    }
+
+    async fn retry_failed(&self, subscription_id: Option<Uuid>) -> Result<u64, AppError> {
+        let mut state = self.state.lock().await;
+
+        let now = Utc::now();
+        let mut requeued = 0;
+        for delivery in state.deliveries.iter_mut().filter(|delivery| {
+            delivery.status == "failed"
+                && subscription_id.is_none_or(|id| delivery.subscription_id == id)
+        }) {
+            delivery.status = "pending".to_string();
+            delivery.attempts = 0;
+            delivery.next_attempt_at = now;
+            delivery.updated_at = now;
+            requeued += 1;
+        }
+
+        Ok(requeued)
+    }
+
+    async fn deliveries(
+        &self,
+        subscription_id: Uuid,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, AppError> {
+        let state = self.state.lock().await;
+
+        let mut deliveries: Vec<WebhookDelivery> = state
+            .deliveries
+            .iter()
+            .filter(|delivery| {
+                delivery.subscription_id == subscription_id
+                    && after.is_none_or(|after| (delivery.created_at, delivery.id) < after)
+            })
+            .cloned()
+            .collect();
+        deliveries.sort_by_key(|delivery| Reverse((delivery.created_at, delivery.id)));
+        deliveries.truncate(usize::try_from(limit).unwrap_or(0));
+
+        Ok(deliveries)
+    }
+}
@@ -1,3 +1,223 @@
-mod url_repository;
+//! Storage of links, blocked domains and webhooks.
+//!
+//! Each store is a trait with a Postgres and an `SQLite` implementation;
+//! [`connect`] picks them by the scheme of `DATABASE_URL`. The in-memory
+//! implementations back tests that run without a database.
 
-pub use url_repository::{Url, UrlRepository};
+mod blocklist;
+mod memory;
+mod postgres;
+mod sqlite;
+mod url;
+mod webhook;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use shortener_core::{AppError, config::DatabaseConfig};
+use uuid::Uuid;
+
+pub use blocklist::BlockedDomain;
+pub use memory::{InMemoryBlocklistStore, InMemoryUrlStore, InMemoryWebhookStore};
+pub use url::{NewUrl, OgMetadata, RedirectType, Url, UrlChanges, UrlFilter, UrlState, UtmParams};
+pub use webhook::{DueDelivery, WebhookDelivery, WebhookSubscription};
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait UrlStore: Send + Sync {
+    /// Checks that the database can be reached.
+    async fn ping(&self) -> Result<(), AppError>;
+
+    /// Stores a link under a random code that is neither taken nor retired.
+    async fn create(&self, new_url: &NewUrl) -> Result<Url, AppError>;
+
+    async fn find_by_code(&self, code: &str) -> Result<Option<Url>, AppError>;
+
+    /// Returns the link `code` if it is in the trash.
+    async fn find_deleted(&self, code: &str) -> Result<Option<Url>, AppError>;
+
+    /// Returns up to `limit` links matching `filter`, newest first, starting
+    /// after the `(created_at, id)` key `after`.
+    async fn list(
+        &self,
+        filter: &UrlFilter,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<Url>, AppError>;
+
+    async fn update(&self, code: &str, changes: &UrlChanges) -> Result<Url, AppError>;
+
+    /// Fills Open Graph fields of `code` that are not set yet with `fetched`.
+    async fn merge_og_metadata(&self, code: &str, fetched: &OgMetadata) -> Result<(), AppError>;
+
+    /// Returns up to `limit` `(code, original_url)` pairs of links that are
+    /// neither deleted nor disabled, ordered by code and starting after `after`.
+    async fn list_enabled_destinations(
+        &self,
+        after: &str,
+        limit: i64,
+    ) -> Result<Vec<(String, String)>, AppError>;
+
+    /// Disables the links with the given codes so they no longer redirect.
+    ///
+    /// Returns the number of links that were not already disabled.
+    async fn disable(&self, codes: &[String], reason: &str) -> Result<u64, AppError>;
+
+    /// Returns up to `limit` `(code, original_url)` pairs of active links
+    /// disabled for `reason`, ordered by code and starting after `after`.
+    async fn list_disabled_destinations(
+        &self,
+        reason: &str,
+        after: &str,
+        limit: i64,
+    ) -> Result<Vec<(String, String)>, AppError>;
+
+    /// Re-enables the links with the given codes that were disabled for
+    /// `reason`.
+    ///
+    /// Returns the number of links re-enabled.
+    async fn enable(&self, codes: &[String], reason: &str) -> Result<u64, AppError>;
+
+    /// Marks up to `limit` links whose expiry has passed as announced.
+    ///
+    /// Returns the `(code, expires_at)` pairs of the marked links; each
+    /// expiry is returned once, unless the link gets a new expiry time.
+    async fn mark_expired(&self, limit: i64) -> Result<Vec<(String, DateTime<Utc>)>, AppError>;
+
+    /// Moves the active link `code` to the trash.
+    async fn delete(&self, code: &str) -> Result<(), AppError>;
+
+    /// Takes the deleted link `code` out of the trash.
+    async fn restore(&self, code: &str) -> Result<Url, AppError>;
+
+    /// Permanently removes the links deleted before `deleted_before`.
+    ///
+    /// Returns the codes of the removed links. They are retired when
+    /// `retire_codes` is set and can be taken by new links otherwise.
+    async fn purge_deleted(
+        &self,
+        deleted_before: DateTime<Utc>,
+        retire_codes: bool,
+    ) -> Result<Vec<String>, AppError>;
+}
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait BlocklistStore: Send + Sync {
+    /// Returns `true` if `host` or any of its parent domains is blocked.
+    async fn is_blocked(&self, host: &str) -> Result<bool, AppError>;
+
+    async fn list(&self) -> Result<Vec<BlockedDomain>, AppError>;
+
+    async fn add(&self, domain: &str, reason: Option<String>) -> Result<BlockedDomain, AppError>;
+
+    async fn remove(&self, domain: &str) -> Result<(), AppError>;
+}
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait WebhookStore: Send + Sync {
+    async fn create(
+        &self,
+        url: &str,
+        secret: &str,
+        event_types: &[String],
+    ) -> Result<WebhookSubscription, AppError>;
+
+    async fn list(&self) -> Result<Vec<WebhookSubscription>, AppError>;
+
+    /// Deletes the subscription `id` together with its delivery log.
+    async fn delete(&self, id: Uuid) -> Result<(), AppError>;
+
+    /// Queues `payload` for every active subscription to `event_type`.
+    ///
+    /// Events already queued for a subscription are skipped, so redelivered
+    /// messages are not sent twice. Returns the number of queued deliveries.
+    async fn enqueue(
+        &self,
+        event_id: Uuid,
+        event_type: &str,
+        payload: &serde_json::Value,
+    ) -> Result<u64, AppError>;
+
+    /// Claims up to `limit` pending deliveries that are due and counts the
+    /// attempt.
+    ///
+    /// Claimed deliveries are not due again until `lease_until`, so another
+    /// worker retries them if this one stops before recording the outcome.
+    async fn claim_due(
+        &self,
+        limit: i64,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<DueDelivery>, AppError>;
+
+    async fn record_success(&self, id: Uuid, status_code: i16) -> Result<(), AppError>;
+
+    /// Records a failed attempt, retrying at `retry_at` or giving up when it
+    /// is `None`.
+    async fn record_failure(
+        &self,
+        id: Uuid,
+        status_code: Option<i16>,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), AppError>;
+
+    /// Makes the deliveries that were given up due again with no attempts
+    /// counted, for `subscription_id` or, when `None`, every subscription.
+    ///
+    /// Returns the number of requeued deliveries.
+    async fn retry_failed(&self, subscription_id: Option<Uuid>) -> Result<u64, AppError>;
+
+    /// Returns up to `limit` deliveries of `subscription_id`, newest first,
+    /// starting after the `(created_at, id)` key `after`.
+    async fn deliveries(
+        &self,
+        subscription_id: Uuid,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, AppError>;
+}
+
+/// The stores of one database.
+#[derive(Clone)]
+pub struct Stores {
+    pub urls: Arc<dyn UrlStore>,
+    pub blocklist: Arc<dyn BlocklistStore>,
+    pub webhooks: Arc<dyn WebhookStore>,
+}
+
+impl Stores {
+    /// Empty stores kept in process memory.
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self {
+            urls: Arc::new(InMemoryUrlStore::new()),
+            blocklist: Arc::new(InMemoryBlocklistStore::new()),
+            webhooks: Arc::new(InMemoryWebhookStore::new()),
+        }
+    }
+}
+
+/// Connects to the database at `config.url` and applies its migrations.
+///
+/// `postgres://` and `postgresql://` URLs use Postgres, `sqlite:` URLs `SQLite`.
+///
+/// # Errors
+///
+/// Returns `AppError::Database` if the scheme is not supported, or the
+/// database cannot be reached or migrated.
+pub async fn connect(config: &DatabaseConfig) -> Result<Stores, AppError> {
+    let url = config.url.expose();
+
+    if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        postgres::connect(url, config.max_connections).await
+    } else if url.starts_with("sqlite:") {
+        sqlite::connect(url, config.max_connections).await
+    } else {
+        Err(AppError::Database(
+            "DATABASE_URL must start with postgres://, postgresql:// or sqlite:".to_string(),
+        ))
+    }
+}
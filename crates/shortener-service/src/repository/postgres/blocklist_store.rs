@@ -0,0 +1,97 @@
+use async_trait::async_trait;
+use shortener_core::AppError;
+use sqlx::PgPool;
+use tracing::instrument;
+
+use crate::repository::{BlockedDomain, BlocklistStore, blocklist::domain_candidates};
+
+pub struct PostgresBlocklistStore {
+    pool: PgPool,
+}
+
+impl PostgresBlocklistStore {
+    #[must_use]
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl BlocklistStore for PostgresBlocklistStore {
+    #[instrument(skip(self))]
+    async fn is_blocked(&self, host: &str) -> Result<bool, AppError> {
+        let candidates = domain_candidates(host);
+
+        let blocked = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM blocked_domains WHERE domain = ANY($1)) as "blocked!"
+            "#,
+            &candidates
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(blocked)
+    }
+
+    #[instrument(skip(self))]
+    async fn list(&self) -> Result<Vec<BlockedDomain>, AppError> {
+        let domains = sqlx::query_as!(
+            BlockedDomain,
+            r#"
+            SELECT domain, reason, created_at
+            FROM blocked_domains
+            ORDER BY domain
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(domains)
+    }
+
+    #[instrument(skip(self))]
+    async fn add(&self, domain: &str, reason: Option<String>) -> Result<BlockedDomain, AppError> {
+        let blocked = sqlx::query_as!(
+            BlockedDomain,
+            r#"
+            INSERT INTO blocked_domains (domain, reason)
+            VALUES ($1, $2)
+            ON CONFLICT (domain) DO NOTHING
+            RETURNING domain, reason, created_at
+            "#,
+            domain,
+            reason
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?
+        .ok_or_else(|| AppError::Conflict(format!("Domain '{domain}' is already blocked")))?;
+
+        Ok(blocked)
+    }
+
+    #[instrument(skip(self))]
+    async fn remove(&self, domain: &str) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM blocked_domains
+            WHERE domain = $1
+            "#,
+            domain
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "Blocked domain '{domain}' not found"
+            )));
+        }
+
+        Ok(())
+    }
+}
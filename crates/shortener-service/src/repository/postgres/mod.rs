@@ -0,0 +1,33 @@
+mod blocklist_store;
+mod url_store;
+mod webhook_store;
+
+use std::sync::Arc;
+
+use shortener_core::AppError;
+use sqlx::postgres::PgPoolOptions;
+
+use blocklist_store::PostgresBlocklistStore;
+use url_store::PostgresUrlStore;
+use webhook_store::PostgresWebhookStore;
+
+use super::Stores;
+
+pub(super) async fn connect(url: &str, max_connections: u32) -> Result<Stores, AppError> {
+    let pool = PgPoolOptions::new()
+        .max_connections(max_connections)
+        .connect(url)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+    Ok(Stores {
+        urls: Arc::new(PostgresUrlStore::new(pool.clone())),
+        blocklist: Arc::new(PostgresBlocklistStore::new(pool.clone())),
+        webhooks: Arc::new(PostgresWebhookStore::new(pool)),
+    })
+}
@@ -0,0 +1,461 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use shortener_core::AppError;
+use sqlx::{PgPool, Postgres, QueryBuilder, types::Json};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::repository::{
+    NewUrl, OgMetadata, RedirectType, Url, UrlChanges, UrlFilter, UrlState, UrlStore, UtmParams,
+    url::{CODE_ATTEMPTS, generate_code},
+};
+
+/// Columns selected into [`Url`] by queries built at runtime.
+const URL_COLUMNS: &str = "id, code, original_url, created_at, updated_at, expires_at, is_active, \
+     forward_query, utm_params, redirect_type, disabled_at, disabled_reason, \
+     always_interstitial, og_metadata, title, description, tags, deleted_at";
+
+pub struct PostgresUrlStore {
+    pool: PgPool,
+}
+
+impl PostgresUrlStore {
+    #[must_use]
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UrlStore for PostgresUrlStore {
+    #[instrument(skip(self))]
+    async fn ping(&self) -> Result<(), AppError> {
+        sqlx::query!(r#"SELECT 1 as "one!""#)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn create(&self, new_url: &NewUrl) -> Result<Url, AppError> {
+        for _ in 0..CODE_ATTEMPTS {
+            let url = sqlx::query_as!(
+                Url,
+                r#"
+                INSERT INTO urls (
+                    code, original_url, forward_query, utm_params, redirect_type,
+                    always_interstitial, og_metadata, title, description, tags, expires_at
+                )
+                SELECT $1::VARCHAR, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11
+                WHERE NOT EXISTS (SELECT 1 FROM retired_codes WHERE code = $1)
+                RETURNING id, code, original_url, created_at, updated_at, expires_at, is_active,
+                          forward_query, utm_params as "utm_params: UtmParams",
+                          redirect_type as "redirect_type: RedirectType",
+                          disabled_at, disabled_reason, always_interstitial,
+                          og_metadata as "og_metadata: OgMetadata",
+                          title, description, tags, deleted_at
+                "#,
+                generate_code(),
+                new_url.original_url,
+                new_url.forward_query,
+                Json(&new_url.utm_params) as _,
+                new_url.redirect_type as _,
+                new_url.always_interstitial,
+                Json(&new_url.og_metadata) as _,
+                new_url.title,
+                new_url.description,
+                &new_url.tags,
+                new_url.expires_at
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+            if let Some(url) = url {
+                return Ok(url);
+            }
+        }
+
+        Err(AppError::Internal(
+            "No unretired code found for the new link".to_string(),
+        ))
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_code(&self, code: &str) -> Result<Option<Url>, AppError> {
+        let url = sqlx::query_as!(
+            Url,
+            r#"
+            SELECT id, code, original_url, created_at, updated_at, expires_at, is_active,
+                   forward_query, utm_params as "utm_params: UtmParams",
+                   redirect_type as "redirect_type: RedirectType",
+                   disabled_at, disabled_reason, always_interstitial,
+                   og_metadata as "og_metadata: OgMetadata",
+                   title, description, tags, deleted_at
+            FROM urls
+            WHERE code = $1 AND is_active = true
+            "#,
+            code
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(url)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_deleted(&self, code: &str) -> Result<Option<Url>, AppError> {
+        let url = sqlx::query_as!(
+            Url,
+            r#"
+            SELECT id, code, original_url, created_at, updated_at, expires_at, is_active,
+                   forward_query, utm_params as "utm_params: UtmParams",
+                   redirect_type as "redirect_type: RedirectType",
+                   disabled_at, disabled_reason, always_interstitial,
+                   og_metadata as "og_metadata: OgMetadata",
+                   title, description, tags, deleted_at
+            FROM urls
+            WHERE code = $1 AND is_active = false
+            "#,
+            code
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(url)
+    }
+
+    #[instrument(skip(self))]
+    async fn list(
+        &self,
+        filter: &UrlFilter,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<Url>, AppError> {
+        let is_active = filter.state == UrlState::Active;
+        let mut query = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {URL_COLUMNS} FROM urls WHERE is_active = {is_active}"
+        ));
+
+        if let Some(tag) = &filter.tag {
+            query
+                .push(" AND tags @> ARRAY[")
+                .push_bind(tag)
+                .push("]::TEXT[]");
+        }
+        if let Some(domain) = &filter.domain {
+            query
+                .push(" AND (destination_host = ")
+                .push_bind(domain)
+                .push(" OR right(destination_host, char_length(")
+                .push_bind(domain)
+                .push(") + 1) = '.' || ")
+                .push_bind(domain)
+                .push(")");
+        }
+        if let Some(created_from) = filter.created_from {
+            query.push(" AND created_at >= ").push_bind(created_from);
+        }
+        if let Some(created_to) = filter.created_to {
+            query.push(" AND created_at < ").push_bind(created_to);
+        }
+        if let Some(q) = &filter.q {
+            query
+                .push(" AND search_vector @@ websearch_to_tsquery('simple', ")
+                .push_bind(q)
+                .push(")");
+        }
+
+        if let Some((created_at, id)) = after {
+            query
+                .push(" AND (created_at, id) < (")
+                .push_bind(created_at)
+                .push(", ")
+                .push_bind(id)
+                .push(")");
+        }
+
+        query
+            .push(" ORDER BY created_at DESC, id DESC LIMIT ")
+            .push_bind(limit);
+
+        let urls = query
+            .build_query_as::<Url>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(urls)
+    }
+
+    #[instrument(skip(self))]
+    async fn update(&self, code: &str, changes: &UrlChanges) -> Result<Url, AppError> {
+        let url = sqlx::query_as!(
+            Url,
+            r#"
+            UPDATE urls
+            SET original_url = $2,
+                forward_query = COALESCE($3, forward_query),
+                utm_params = COALESCE($4, utm_params),
+                redirect_type = COALESCE($5, redirect_type),
+                always_interstitial = COALESCE($6, always_interstitial),
+                og_metadata = COALESCE($7, og_metadata),
+                title = COALESCE($8, title),
+                description = COALESCE($9, description),
+                tags = COALESCE($10, tags),
+                expiry_notified_at = CASE
+                    WHEN expires_at IS DISTINCT FROM COALESCE($11, expires_at) THEN NULL
+                    ELSE expiry_notified_at
+                END,
+                expires_at = COALESCE($11, expires_at),
+                updated_at = NOW()
+            WHERE code = $1 AND is_active = true
+            RETURNING id, code, original_url, created_at, updated_at, expires_at, is_active,
+                      forward_query, utm_params as "utm_params: UtmParams",
+                      redirect_type as "redirect_type: RedirectType",
+                      disabled_at, disabled_reason, always_interstitial,
+                      og_metadata as "og_metadata: OgMetadata",
+                      title, description, tags, deleted_at
+            "#,
+            code,
+            changes.original_url,
+            changes.forward_query,
+            changes.utm_params.as_ref().map(Json) as _,
+            changes.redirect_type as _,
+            changes.always_interstitial,
+            changes.og_metadata.as_ref().map(Json) as _,
+            changes.title,
+            changes.description,
+            changes.tags.as_deref(),
+            changes.expires_at
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound(format!("URL with code '{code}' not found")))?;
+
+        Ok(url)
+    }
+
+    #[instrument(skip(self))]
+    async fn merge_og_metadata(&self, code: &str, fetched: &OgMetadata) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            UPDATE urls
+            SET og_metadata = $2 || og_metadata
+            WHERE code = $1
+            "#,
+            code,
+            Json(fetched) as _
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn list_enabled_destinations(
+        &self,
+        after: &str,
+        limit: i64,
+    ) -> Result<Vec<(String, String)>, AppError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT code, original_url
+            FROM urls
+            WHERE is_active = true AND disabled_at IS NULL AND code > $1
+            ORDER BY code
+            LIMIT $2
+            "#,
+            after,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.code, row.original_url))
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn disable(&self, codes: &[String], reason: &str) -> Result<u64, AppError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE urls
+            SET disabled_at = NOW(), disabled_reason = $2, updated_at = NOW()
+            WHERE code = ANY($1) AND disabled_at IS NULL
+            "#,
+            codes,
+            reason
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip(self))]
+    async fn list_disabled_destinations(
+        &self,
+        reason: &str,
+        after: &str,
+        limit: i64,
+    ) -> Result<Vec<(String, String)>, AppError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT code, original_url
+            FROM urls
+            WHERE is_active = true AND disabled_reason = $1 AND code > $2
+            ORDER BY code
+            LIMIT $3
+            "#,
+            reason,
+            after,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.code, row.original_url))
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn enable(&self, codes: &[String], reason: &str) -> Result<u64, AppError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE urls
+            SET disabled_at = NULL, disabled_reason = NULL, updated_at = NOW()
+            WHERE code = ANY($1) AND disabled_reason = $2
+            "#,
+            codes,
+            reason
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip(self))]
+    async fn mark_expired(&self, limit: i64) -> Result<Vec<(String, DateTime<Utc>)>, AppError> {
+        let rows = sqlx::query!(
+            r#"
+            UPDATE urls
+            SET expiry_notified_at = NOW()
+            WHERE id IN (
+                SELECT id
+                FROM urls
+                WHERE is_active = true
+                  AND expires_at <= NOW()
+                  AND expiry_notified_at IS NULL
+                ORDER BY expires_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING code, expires_at as "expires_at!"
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.code, row.expires_at))
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn delete(&self, code: &str) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE urls
+            SET is_active = false, deleted_at = NOW(), updated_at = NOW()
+            WHERE code = $1 AND is_active = true
+            "#,
+            code
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "URL with code '{code}' not found"
+            )));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn restore(&self, code: &str) -> Result<Url, AppError> {
+        let url = sqlx::query_as!(
+            Url,
+            r#"
+            UPDATE urls
+            SET is_active = true, deleted_at = NULL, updated_at = NOW()
+            WHERE code = $1 AND is_active = false
+            RETURNING id, code, original_url, created_at, updated_at, expires_at, is_active,
+                      forward_query, utm_params as "utm_params: UtmParams",
+                      redirect_type as "redirect_type: RedirectType",
+                      disabled_at, disabled_reason, always_interstitial,
+                      og_metadata as "og_metadata: OgMetadata",
+                      title, description, tags, deleted_at
+            "#,
+            code
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound(format!("Deleted URL with code '{code}' not found")))?;
+
+        Ok(url)
+    }
+
+    #[instrument(skip(self))]
+    async fn purge_deleted(
+        &self,
+        deleted_before: DateTime<Utc>,
+        retire_codes: bool,
+    ) -> Result<Vec<String>, AppError> {
+        let purged = sqlx::query_scalar!(
+            r#"
+            WITH purged AS (
+                DELETE FROM urls
+                WHERE is_active = false AND deleted_at < $1
+                RETURNING code
+            ),
+            retired AS (
+                INSERT INTO retired_codes (code)
+                SELECT code FROM purged WHERE $2
+                ON CONFLICT (code) DO NOTHING
+            )
+            SELECT code as "code!" FROM purged
+            "#,
+            deleted_before,
+            retire_codes
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(purged)
+    }
+}
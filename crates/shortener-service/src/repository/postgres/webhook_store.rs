@@ -0,0 +1,243 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use shortener_core::AppError;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::repository::{DueDelivery, WebhookDelivery, WebhookStore, WebhookSubscription};
+
+pub struct PostgresWebhookStore {
+    pool: PgPool,
+}
+
+impl PostgresWebhookStore {
+    #[must_use]
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl WebhookStore for PostgresWebhookStore {
+    #[instrument(skip(self, secret))]
+    async fn create(
+        &self,
+        url: &str,
+        secret: &str,
+        event_types: &[String],
+    ) -> Result<WebhookSubscription, AppError> {
+        let subscription = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            INSERT INTO webhook_subscriptions (url, secret, event_types)
+            VALUES ($1, $2, $3)
+            RETURNING id, url, secret, event_types, is_active, created_at
+            "#,
+            url,
+            secret,
+            event_types
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(subscription)
+    }
+
+    #[instrument(skip(self))]
+    async fn list(&self) -> Result<Vec<WebhookSubscription>, AppError> {
+        let subscriptions = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            SELECT id, url, secret, event_types, is_active, created_at
+            FROM webhook_subscriptions
+            ORDER BY created_at, id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(subscriptions)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete(&self, id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM webhook_subscriptions
+            WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "Webhook subscription '{id}' not found"
+            )));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, payload))]
+    async fn enqueue(
+        &self,
+        event_id: Uuid,
+        event_type: &str,
+        payload: &serde_json::Value,
+    ) -> Result<u64, AppError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries (subscription_id, event_id, event_type, payload)
+            SELECT id, $1, $2, $3
+            FROM webhook_subscriptions
+            WHERE is_active = true
+              AND (cardinality(event_types) = 0 OR $2 = ANY(event_types))
+            ON CONFLICT (subscription_id, event_id) DO NOTHING
+            "#,
+            event_id,
+            event_type,
+            payload
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_due(
+        &self,
+        limit: i64,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<DueDelivery>, AppError> {
+        let deliveries = sqlx::query_as!(
+            DueDelivery,
+            r#"
+            UPDATE webhook_deliveries d
+            SET attempts = d.attempts + 1, next_attempt_at = $2, updated_at = NOW()
+            FROM webhook_subscriptions s
+            WHERE s.id = d.subscription_id
+              AND d.id IN (
+                  SELECT id
+                  FROM webhook_deliveries
+                  WHERE status = 'pending' AND next_attempt_at <= NOW()
+                  ORDER BY next_attempt_at
+                  LIMIT $1
+                  FOR UPDATE SKIP LOCKED
+              )
+            RETURNING d.id, d.event_id, d.event_type, d.payload, d.attempts, s.url, s.secret
+            "#,
+            limit,
+            lease_until
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(deliveries)
+    }
+
+    #[instrument(skip(self))]
+    async fn record_success(&self, id: Uuid, status_code: i16) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'succeeded', last_status_code = $2, last_error = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            id,
+            status_code
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn record_failure(
+        &self,
+        id: Uuid,
+        status_code: Option<i16>,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = CASE WHEN $4::TIMESTAMPTZ IS NULL THEN 'failed' ELSE 'pending' END,
+                next_attempt_at = COALESCE($4, next_attempt_at),
+                last_status_code = $2,
+                last_error = $3,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            id,
+            status_code,
+            error,
+            retry_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn retry_failed(&self, subscription_id: Option<Uuid>) -> Result<u64, AppError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'pending', attempts = 0, next_attempt_at = NOW(), updated_at = NOW()
+            WHERE status = 'failed' AND ($1::UUID IS NULL OR subscription_id = $1)
+            "#,
+            subscription_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip(self))]
+    async fn deliveries(
+        &self,
+        subscription_id: Uuid,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, AppError> {
+        let (after_created_at, after_id) = after.unzip();
+
+        let deliveries = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT id, subscription_id, event_id, event_type, payload, status, attempts,
+                   next_attempt_at, last_status_code, last_error, created_at, updated_at
+            FROM webhook_deliveries
+            WHERE subscription_id = $1
+              AND ($2::TIMESTAMPTZ IS NULL OR (created_at, id) < ($2, $3))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $4
+            "#,
+            subscription_id,
+            after_created_at,
+            after_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(deliveries)
+    }
+}
@@ -0,0 +1,100 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use shortener_core::AppError;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use tracing::instrument;
+
+use crate::repository::{BlockedDomain, BlocklistStore, blocklist::domain_candidates};
+
+pub struct SqliteBlocklistStore {
+    pool: SqlitePool,
+}
+
+impl SqliteBlocklistStore {
+    #[must_use]
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl BlocklistStore for SqliteBlocklistStore {
+    #[instrument(skip(self))]
+    async fn is_blocked(&self, host: &str) -> Result<bool, AppError> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT EXISTS(SELECT 1 FROM blocked_domains WHERE domain IN (",
+        );
+        let mut candidates = query.separated(", ");
+        for candidate in domain_candidates(host) {
+            candidates.push_bind(candidate);
+        }
+        query.push("))");
+
+        let blocked = query
+            .build_query_scalar::<bool>()
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(blocked)
+    }
+
+    #[instrument(skip(self))]
+    async fn list(&self) -> Result<Vec<BlockedDomain>, AppError> {
+        let domains = sqlx::query_as::<_, BlockedDomain>(
+            r"
+            SELECT domain, reason, created_at
+            FROM blocked_domains
+            ORDER BY domain
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(domains)
+    }
+
+    #[instrument(skip(self))]
+    async fn add(&self, domain: &str, reason: Option<String>) -> Result<BlockedDomain, AppError> {
+        let blocked = sqlx::query_as::<_, BlockedDomain>(
+            r"
+            INSERT INTO blocked_domains (domain, reason, created_at)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT (domain) DO NOTHING
+            RETURNING domain, reason, created_at
+            ",
+        )
+        .bind(domain)
+        .bind(reason)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?
+        .ok_or_else(|| AppError::Conflict(format!("Domain '{domain}' is already blocked")))?;
+
+        Ok(blocked)
+    }
+
+    #[instrument(skip(self))]
+    async fn remove(&self, domain: &str) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"
+            DELETE FROM blocked_domains
+            WHERE domain = ?1
+            ",
+        )
+        .bind(domain)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "Blocked domain '{domain}' not found"
+            )));
+        }
+
+        Ok(())
+    }
+}
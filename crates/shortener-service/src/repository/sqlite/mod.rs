@@ -0,0 +1,50 @@
+mod blocklist_store;
+mod url_store;
+mod webhook_store;
+
+use std::{str::FromStr, sync::Arc};
+
+use shortener_core::AppError;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+
+use blocklist_store::SqliteBlocklistStore;
+use url_store::SqliteUrlStore;
+use webhook_store::SqliteWebhookStore;
+
+use super::Stores;
+
+pub(super) async fn connect(url: &str, max_connections: u32) -> Result<Stores, AppError> {
+    let options = SqliteConnectOptions::from_str(url)
+        .map_err(|e| AppError::Database(e.to_string()))?
+        .create_if_missing(true);
+
+    // An in-memory database lives as long as its connections, and shared
+    // cache connections to it fail on lock contention instead of waiting.
+    // Keep a single connection open for the lifetime of the pool.
+    let pool = if url.contains(":memory:") || url.contains("mode=memory") {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await
+    } else {
+        SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options.journal_mode(SqliteJournalMode::Wal))
+            .await
+    }
+    .map_err(|e| AppError::Database(e.to_string()))?;
+
+    sqlx::migrate!("./migrations/sqlite")
+        .run(&pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+    Ok(Stores {
+        urls: Arc::new(SqliteUrlStore::new(pool.clone())),
+        blocklist: Arc::new(SqliteBlocklistStore::new(pool.clone())),
+        webhooks: Arc::new(SqliteWebhookStore::new(pool)),
+    })
+}
@@ -0,0 +1,541 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use shortener_core::AppError;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool, types::Json};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::repository::{
+    NewUrl, OgMetadata, RedirectType, Url, UrlChanges, UrlFilter, UrlState, UrlStore, UtmParams,
+    url::{CODE_ATTEMPTS, destination_host, generate_code},
+};
+
+/// Columns selected into [`UrlRow`].
+const URL_COLUMNS: &str = "id, code, original_url, created_at, updated_at, expires_at, is_active, \
+     forward_query, utm_params, redirect_type, disabled_at, disabled_reason, \
+     always_interstitial, og_metadata, title, description, tags, deleted_at";
+
+/// A row of `urls`, which stores the tags as a JSON array.
+#[derive(sqlx::FromRow)]
+struct UrlRow {
+    id: Uuid,
+    code: String,
+    original_url: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+    is_active: bool,
+    forward_query: bool,
+    utm_params: Json<UtmParams>,
+    redirect_type: RedirectType,
+    disabled_at: Option<DateTime<Utc>>,
+    disabled_reason: Option<String>,
+    always_interstitial: bool,
+    og_metadata: Json<OgMetadata>,
+    title: Option<String>,
+    description: Option<String>,
+    tags: Json<Vec<String>>,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<UrlRow> for Url {
+    fn from(row: UrlRow) -> Self {
+        Self {
+            id: row.id,
+            code: row.code,
+            original_url: row.original_url,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            expires_at: row.expires_at,
+            is_active: row.is_active,
+            forward_query: row.forward_query,
+            utm_params: row.utm_params.0,
+            redirect_type: row.redirect_type,
+            disabled_at: row.disabled_at,
+            disabled_reason: row.disabled_reason,
+            always_interstitial: row.always_interstitial,
+            og_metadata: row.og_metadata.0,
+            title: row.title,
+            description: row.description,
+            tags: row.tags.0,
+            deleted_at: row.deleted_at,
+        }
+    }
+}
+
+/// Escapes the wildcards of a `LIKE ... ESCAPE '\'` pattern.
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+pub struct SqliteUrlStore {
+    pool: SqlitePool,
+}
+
+impl SqliteUrlStore {
+    #[must_use]
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UrlStore for SqliteUrlStore {
+    #[instrument(skip(self))]
+    async fn ping(&self) -> Result<(), AppError> {
+        sqlx::query("SELECT 1")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn create(&self, new_url: &NewUrl) -> Result<Url, AppError> {
+        for _ in 0..CODE_ATTEMPTS {
+            let now = Utc::now();
+
+            let row = sqlx::query_as::<_, UrlRow>(&format!(
+                r"
+                INSERT INTO urls (
+                    id, code, original_url, created_at, updated_at, forward_query, utm_params,
+                    redirect_type, always_interstitial, og_metadata, title, description, tags,
+                    expires_at, destination_host
+                )
+                SELECT ?1, ?2, ?3, ?4, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14
+                WHERE NOT EXISTS (SELECT 1 FROM retired_codes WHERE code = ?2)
+                RETURNING {URL_COLUMNS}
+                "
+            ))
+            .bind(Uuid::new_v4())
+            .bind(generate_code())
+            .bind(&new_url.original_url)
+            .bind(now)
+            .bind(new_url.forward_query)
+            .bind(Json(&new_url.utm_params))
+            .bind(new_url.redirect_type)
+            .bind(new_url.always_interstitial)
+            .bind(Json(&new_url.og_metadata))
+            .bind(&new_url.title)
+            .bind(&new_url.description)
+            .bind(Json(&new_url.tags))
+            .bind(new_url.expires_at)
+            .bind(destination_host(&new_url.original_url))
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+            if let Some(row) = row {
+                return Ok(row.into());
+            }
+        }
+
+        Err(AppError::Internal(
+            "No unretired code found for the new link".to_string(),
+        ))
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_code(&self, code: &str) -> Result<Option<Url>, AppError> {
+        let row = sqlx::query_as::<_, UrlRow>(&format!(
+            r"
+            SELECT {URL_COLUMNS}
+            FROM urls
+            WHERE code = ?1 AND is_active = TRUE
+            "
+        ))
+        .bind(code)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(row.map(Url::from))
+    }
+
+    #[instrument(skip(self))]
+    async fn find_deleted(&self, code: &str) -> Result<Option<Url>, AppError> {
+        let row = sqlx::query_as::<_, UrlRow>(&format!(
+            r"
+            SELECT {URL_COLUMNS}
+            FROM urls
+            WHERE code = ?1 AND is_active = FALSE
+            "
+        ))
+        .bind(code)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(row.map(Url::from))
+    }
+
+    #[instrument(skip(self))]
+    async fn list(
+        &self,
+        filter: &UrlFilter,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<Url>, AppError> {
+        let is_active = filter.state == UrlState::Active;
+        let mut query = QueryBuilder::<Sqlite>::new(format!(
+            "SELECT {URL_COLUMNS} FROM urls WHERE is_active = {is_active}"
+        ));
+
+        if let Some(tag) = &filter.tag {
+            query
+                .push(" AND EXISTS (SELECT 1 FROM json_each(tags) WHERE value = ")
+                .push_bind(tag)
+                .push(")");
+        }
+        if let Some(domain) = &filter.domain {
+            query
+                .push(" AND (destination_host = ")
+                .push_bind(domain)
+                .push(" OR substr(destination_host, -(length(")
+                .push_bind(domain)
+                .push(") + 1)) = '.' || ")
+                .push_bind(domain)
+                .push(")");
+        }
+        if let Some(created_from) = filter.created_from {
+            query.push(" AND created_at >= ").push_bind(created_from);
+        }
+        if let Some(created_to) = filter.created_to {
+            query.push(" AND created_at < ").push_bind(created_to);
+        }
+        if let Some(q) = &filter.q {
+            for word in q.split_whitespace().map(|word| word.trim_matches('"')) {
+                if word.is_empty() {
+                    continue;
+                }
+                let pattern = format!("%{}%", escape_like(word));
+                query
+                    .push(" AND (title LIKE ")
+                    .push_bind(pattern.clone())
+                    .push(" ESCAPE '\\' OR description LIKE ")
+                    .push_bind(pattern.clone())
+                    .push(" ESCAPE '\\' OR original_url LIKE ")
+                    .push_bind(pattern)
+                    .push(" ESCAPE '\\')");
+            }
+        }
+
+        if let Some((created_at, id)) = after {
+            query
+                .push(" AND (created_at, id) < (")
+                .push_bind(created_at)
+                .push(", ")
+                .push_bind(id)
+                .push(")");
+        }
+
+        query
+            .push(" ORDER BY created_at DESC, id DESC LIMIT ")
+            .push_bind(limit);
+
+        let rows = query
+            .build_query_as::<UrlRow>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rows.into_iter().map(Url::from).collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn update(&self, code: &str, changes: &UrlChanges) -> Result<Url, AppError> {
+        let row = sqlx::query_as::<_, UrlRow>(&format!(
+            r"
+            UPDATE urls
+            SET original_url = ?2,
+                destination_host = ?3,
+                forward_query = COALESCE(?4, forward_query),
+                utm_params = COALESCE(?5, utm_params),
+                redirect_type = COALESCE(?6, redirect_type),
+                always_interstitial = COALESCE(?7, always_interstitial),
+                og_metadata = COALESCE(?8, og_metadata),
+                title = COALESCE(?9, title),
+                description = COALESCE(?10, description),
+                tags = COALESCE(?11, tags),
+                expiry_notified_at = CASE
+                    WHEN expires_at IS NOT COALESCE(?12, expires_at) THEN NULL
+                    ELSE expiry_notified_at
+                END,
+                expires_at = COALESCE(?12, expires_at),
+                updated_at = ?13
+            WHERE code = ?1 AND is_active = TRUE
+            RETURNING {URL_COLUMNS}
+            "
+        ))
+        .bind(code)
+        .bind(&changes.original_url)
+        .bind(destination_host(&changes.original_url))
+        .bind(changes.forward_query)
+        .bind(changes.utm_params.as_ref().map(Json))
+        .bind(changes.redirect_type)
+        .bind(changes.always_interstitial)
+        .bind(changes.og_metadata.as_ref().map(Json))
+        .bind(&changes.title)
+        .bind(&changes.description)
+        .bind(changes.tags.as_ref().map(Json))
+        .bind(changes.expires_at)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound(format!("URL with code '{code}' not found")))?;
+
+        Ok(row.into())
+    }
+
+    #[instrument(skip(self))]
+    async fn merge_og_metadata(&self, code: &str, fetched: &OgMetadata) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+            UPDATE urls
+            SET og_metadata = json_patch(?2, og_metadata)
+            WHERE code = ?1
+            ",
+        )
+        .bind(code)
+        .bind(Json(fetched))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn list_enabled_destinations(
+        &self,
+        after: &str,
+        limit: i64,
+    ) -> Result<Vec<(String, String)>, AppError> {
+        let rows = sqlx::query_as::<_, (String, String)>(
+            r"
+            SELECT code, original_url
+            FROM urls
+            WHERE is_active = TRUE AND disabled_at IS NULL AND code > ?1
+            ORDER BY code
+            LIMIT ?2
+            ",
+        )
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rows)
+    }
+
+    #[instrument(skip(self))]
+    async fn disable(&self, codes: &[String], reason: &str) -> Result<u64, AppError> {
+        if codes.is_empty() {
+            return Ok(0);
+        }
+
+        let now = Utc::now();
+        let mut query = QueryBuilder::<Sqlite>::new("UPDATE urls SET disabled_at = ");
+        query
+            .push_bind(now)
+            .push(", disabled_reason = ")
+            .push_bind(reason)
+            .push(", updated_at = ")
+            .push_bind(now)
+            .push(" WHERE disabled_at IS NULL AND code IN (");
+        let mut separated = query.separated(", ");
+        for code in codes {
+            separated.push_bind(code);
+        }
+        query.push(")");
+
+        let result = query
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip(self))]
+    async fn list_disabled_destinations(
+        &self,
+        reason: &str,
+        after: &str,
+        limit: i64,
+    ) -> Result<Vec<(String, String)>, AppError> {
+        let rows = sqlx::query_as::<_, (String, String)>(
+            r"
+            SELECT code, original_url
+            FROM urls
+            WHERE is_active = TRUE AND disabled_reason = ?1 AND code > ?2
+            ORDER BY code
+            LIMIT ?3
+            ",
+        )
+        .bind(reason)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rows)
+    }
+
+    #[instrument(skip(self))]
+    async fn enable(&self, codes: &[String], reason: &str) -> Result<u64, AppError> {
+        if codes.is_empty() {
+            return Ok(0);
+        }
+
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "UPDATE urls SET disabled_at = NULL, disabled_reason = NULL, updated_at = ",
+        );
+        query
+            .push_bind(Utc::now())
+            .push(" WHERE disabled_reason = ")
+            .push_bind(reason)
+            .push(" AND code IN (");
+        let mut separated = query.separated(", ");
+        for code in codes {
+            separated.push_bind(code);
+        }
+        query.push(")");
+
+        let result = query
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip(self))]
+    async fn mark_expired(&self, limit: i64) -> Result<Vec<(String, DateTime<Utc>)>, AppError> {
+        // Writers are serialized by SQLite, so no row locks are needed.
+        let rows = sqlx::query_as::<_, (String, DateTime<Utc>)>(
+            r"
+            UPDATE urls
+            SET expiry_notified_at = ?1
+            WHERE id IN (
+                SELECT id
+                FROM urls
+                WHERE is_active = TRUE
+                  AND expires_at <= ?1
+                  AND expiry_notified_at IS NULL
+                ORDER BY expires_at
+                LIMIT ?2
+            )
+            RETURNING code, expires_at
+            ",
+        )
+        .bind(Utc::now())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rows)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete(&self, code: &str) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"
+            UPDATE urls
+            SET is_active = FALSE, deleted_at = ?2, updated_at = ?2
+            WHERE code = ?1 AND is_active = TRUE
+            ",
+        )
+        .bind(code)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "URL with code '{code}' not found"
+            )));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn restore(&self, code: &str) -> Result<Url, AppError> {
+        let row = sqlx::query_as::<_, UrlRow>(&format!(
+            r"
+            UPDATE urls
+            SET is_active = TRUE, deleted_at = NULL, updated_at = ?2
+            WHERE code = ?1 AND is_active = FALSE
+            RETURNING {URL_COLUMNS}
+            "
+        ))
+        .bind(code)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound(format!("Deleted URL with code '{code}' not found")))?;
+
+        Ok(row.into())
+    }
+
+    #[instrument(skip(self))]
+    async fn purge_deleted(
+        &self,
+        deleted_before: DateTime<Utc>,
+        retire_codes: bool,
+    ) -> Result<Vec<String>, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if retire_codes {
+            sqlx::query(
+                r"
+                INSERT INTO retired_codes (code, retired_at)
+                SELECT code, ?2
+                FROM urls
+                WHERE is_active = FALSE AND deleted_at < ?1
+                ON CONFLICT (code) DO NOTHING
+                ",
+            )
+            .bind(deleted_before)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        let purged = sqlx::query_scalar(
+            r"
+            DELETE FROM urls
+            WHERE is_active = FALSE AND deleted_at < ?1
+            RETURNING code
+            ",
+        )
+        .bind(deleted_before)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(purged)
+    }
+}
@@ -0,0 +1,303 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use shortener_core::AppError;
+use sqlx::{SqlitePool, types::Json};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::repository::{DueDelivery, WebhookDelivery, WebhookStore, WebhookSubscription};
+
+/// A row of `webhook_subscriptions`, which stores the event types as a JSON array.
+#[derive(sqlx::FromRow)]
+struct SubscriptionRow {
+    id: Uuid,
+    url: String,
+    secret: String,
+    event_types: Json<Vec<String>>,
+    is_active: bool,
+    created_at: DateTime<Utc>,
+}
+
+impl From<SubscriptionRow> for WebhookSubscription {
+    fn from(row: SubscriptionRow) -> Self {
+        Self {
+            id: row.id,
+            url: row.url,
+            secret: row.secret,
+            event_types: row.event_types.0,
+            is_active: row.is_active,
+            created_at: row.created_at,
+        }
+    }
+}
+
+pub struct SqliteWebhookStore {
+    pool: SqlitePool,
+}
+
+impl SqliteWebhookStore {
+    #[must_use]
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl WebhookStore for SqliteWebhookStore {
+    #[instrument(skip(self, secret))]
+    async fn create(
+        &self,
+        url: &str,
+        secret: &str,
+        event_types: &[String],
+    ) -> Result<WebhookSubscription, AppError> {
+        let row = sqlx::query_as::<_, SubscriptionRow>(
+            r"
+            INSERT INTO webhook_subscriptions (id, url, secret, event_types, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            RETURNING id, url, secret, event_types, is_active, created_at
+            ",
+        )
+        .bind(Uuid::new_v4())
+        .bind(url)
+        .bind(secret)
+        .bind(Json(event_types))
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(row.into())
+    }
+
+    #[instrument(skip(self))]
+    async fn list(&self) -> Result<Vec<WebhookSubscription>, AppError> {
+        let rows = sqlx::query_as::<_, SubscriptionRow>(
+            r"
+            SELECT id, url, secret, event_types, is_active, created_at
+            FROM webhook_subscriptions
+            ORDER BY created_at, id
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rows.into_iter().map(WebhookSubscription::from).collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn delete(&self, id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"
+            DELETE FROM webhook_subscriptions
+            WHERE id = ?1
+            ",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "Webhook subscription '{id}' not found"
+            )));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, payload))]
+    async fn enqueue(
+        &self,
+        event_id: Uuid,
+        event_type: &str,
+        payload: &serde_json::Value,
+    ) -> Result<u64, AppError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let subscription_ids = sqlx::query_scalar::<_, Uuid>(
+            r"
+            SELECT id
+            FROM webhook_subscriptions
+            WHERE is_active = TRUE
+              AND (json_array_length(event_types) = 0
+                   OR EXISTS (SELECT 1 FROM json_each(event_types) WHERE value = ?1))
+            ",
+        )
+        .bind(event_type)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // Delivery IDs are generated here, as SQLite has no UUID function.
+        let now = Utc::now();
+        let mut queued = 0;
+        for subscription_id in subscription_ids {
+            let result = sqlx::query(
+                r"
+                INSERT INTO webhook_deliveries (
+                    id, subscription_id, event_id, event_type, payload,
+                    next_attempt_at, created_at, updated_at
+                )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, ?6)
+                ON CONFLICT (subscription_id, event_id) DO NOTHING
+                ",
+            )
+            .bind(Uuid::new_v4())
+            .bind(subscription_id)
+            .bind(event_id)
+            .bind(event_type)
+            .bind(Json(payload))
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+            queued += result.rows_affected();
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(queued)
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_due(
+        &self,
+        limit: i64,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<DueDelivery>, AppError> {
+        // Writers are serialized by SQLite, so no row locks are needed.
+        let deliveries = sqlx::query_as::<_, DueDelivery>(
+            r"
+            UPDATE webhook_deliveries
+            SET attempts = attempts + 1, next_attempt_at = ?2, updated_at = ?3
+            WHERE id IN (
+                SELECT id
+                FROM webhook_deliveries
+                WHERE status = 'pending' AND next_attempt_at <= ?3
+                ORDER BY next_attempt_at
+                LIMIT ?1
+            )
+            RETURNING id, event_id, event_type, payload, attempts,
+                      (SELECT url FROM webhook_subscriptions s WHERE s.id = subscription_id) AS url,
+                      (SELECT secret FROM webhook_subscriptions s WHERE s.id = subscription_id) AS secret
+            ",
+        )
+        .bind(limit)
+        .bind(lease_until)
+        .bind(Utc::now())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(deliveries)
+    }
+
+    #[instrument(skip(self))]
+    async fn record_success(&self, id: Uuid, status_code: i16) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+            UPDATE webhook_deliveries
+            SET status = 'succeeded', last_status_code = ?2, last_error = NULL, updated_at = ?3
+            WHERE id = ?1
+            ",
+        )
+        .bind(id)
+        .bind(status_code)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn record_failure(
+        &self,
+        id: Uuid,
+        status_code: Option<i16>,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+            UPDATE webhook_deliveries
+            SET status = CASE WHEN ?4 IS NULL THEN 'failed' ELSE 'pending' END,
+                next_attempt_at = COALESCE(?4, next_attempt_at),
+                last_status_code = ?2,
+                last_error = ?3,
+                updated_at = ?5
+            WHERE id = ?1
+            ",
+        )
+        .bind(id)
+        .bind(status_code)
+        .bind(error)
+        .bind(retry_at)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn retry_failed(&self, subscription_id: Option<Uuid>) -> Result<u64, AppError> {
+        let now = Utc::now();
+        let result = sqlx::query(
+            r"
+            UPDATE webhook_deliveries
+            SET status = 'pending', attempts = 0, next_attempt_at = ?2, updated_at = ?2
+            WHERE status = 'failed' AND (?1 IS NULL OR subscription_id = ?1)
+            ",
+        )
+        .bind(subscription_id)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip(self))]
+    async fn deliveries(
+        &self,
+        subscription_id: Uuid,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, AppError> {
+        let (after_created_at, after_id) = after.unzip();
+
+        let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+            r"
+            SELECT id, subscription_id, event_id, event_type, payload, status, attempts,
+                   next_attempt_at, last_status_code, last_error, created_at, updated_at
+            FROM webhook_deliveries
+            WHERE subscription_id = ?1
+              AND (?2 IS NULL OR (created_at, id) < (?2, ?3))
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?4
+            ",
+        )
+        .bind(subscription_id)
+        .bind(after_created_at)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(deliveries)
+    }
+}
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use rand::Rng;
+
+pub use shortener_types::shortener::{OgMetadata, RedirectType, Url, UrlState, UtmParams};
+
+const CODE_CHARSET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const CODE_LENGTH: usize = 6;
+
+/// Codes drawn before giving up on creating a link whose code is neither
+/// taken nor retired.
+pub(super) const CODE_ATTEMPTS: usize = 5;
+
+/// Attributes of a short URL to be created.
+#[derive(Debug, Clone, Default)]
+pub struct NewUrl {
+    pub original_url: String,
+    pub forward_query: bool,
+    pub utm_params: UtmParams,
+    pub redirect_type: RedirectType,
+    pub always_interstitial: bool,
+    pub og_metadata: OgMetadata,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Changes applied to an existing short URL. `None` keeps the current value.
+#[derive(Debug, Clone, Default)]
+pub struct UrlChanges {
+    pub original_url: String,
+    pub forward_query: Option<bool>,
+    pub utm_params: Option<UtmParams>,
+    pub redirect_type: Option<RedirectType>,
+    pub always_interstitial: Option<bool>,
+    pub og_metadata: Option<OgMetadata>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Filters applied when listing short URLs. `None` fields match every link.
+#[derive(Debug, Clone, Default)]
+pub struct UrlFilter {
+    /// Links in this state; active ones by default.
+    pub state: UrlState,
+    /// Only links carrying this tag.
+    pub tag: Option<String>,
+    /// Only links whose destination host is this domain or one of its subdomains.
+    pub domain: Option<String>,
+    /// Only links created at or after this instant.
+    pub created_from: Option<DateTime<Utc>>,
+    /// Only links created before this instant.
+    pub created_to: Option<DateTime<Utc>>,
+    /// Full-text query over title, description and destination, in
+    /// `websearch_to_tsquery` syntax. The `SQLite` store matches links
+    /// containing every word of the query instead.
+    pub q: Option<String>,
+}
+
+/// Generates a random short code.
+pub(super) fn generate_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..CODE_LENGTH)
+        .map(|_| {
+            let idx = rng.gen_range(0..CODE_CHARSET.len());
+            CODE_CHARSET[idx] as char
+        })
+        .collect()
+}
+
+/// Returns the lowercased host of `url`, which [`UrlFilter::domain`] matches.
+///
+/// Mirrors the `destination_host` column of the Postgres schema, so both
+/// stores filter by domain alike.
+pub(super) fn destination_host(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    let mut scheme_chars = scheme.chars();
+    if !scheme_chars.next()?.is_ascii_alphabetic()
+        || !scheme_chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '-'))
+    {
+        return None;
+    }
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority
+        .split_once('@')
+        .map_or(authority, |(_, host)| host)
+        .split(':')
+        .next()
+        .unwrap_or_default();
+
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
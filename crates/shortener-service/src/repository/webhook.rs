@@ -0,0 +1,16 @@
+use uuid::Uuid;
+
+pub use shortener_types::shortener::{WebhookDelivery, WebhookSubscription};
+
+/// A delivery claimed for sending, with the endpoint it goes to.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DueDelivery {
+    pub id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    /// Attempts made so far, including the claimed one.
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
@@ -0,0 +1,81 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use shortener_core::{AppError, error::ErrorResponse};
+use tracing::instrument;
+
+use crate::{AppState, repository::BlockedDomain, validation::normalize_host};
+
+pub use shortener_types::shortener::BlockDomainRequest;
+
+/// Lists the blocked destination domains.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/blocklist",
+    tag = "blocklist",
+    responses((status = 200, description = "Every blocked domain", body = Vec<BlockedDomain>))
+)]
+#[instrument(skip(state))]
+pub async fn list_blocked_domains(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<BlockedDomain>>, AppError> {
+    let domains = state.blocklist_store.list().await?;
+    Ok(Json(domains))
+}
+
+/// Rejects new destinations on a domain and its subdomains.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/blocklist",
+    tag = "blocklist",
+    request_body = BlockDomainRequest,
+    responses(
+        (status = 201, description = "The domain was blocked", body = BlockedDomain),
+        (status = 400, description = "The domain is invalid", body = ErrorResponse),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn block_domain(
+    State(state): State<AppState>,
+    Json(req): Json<BlockDomainRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let domain = parse_domain(&req.domain)?;
+
+    let blocked = state.blocklist_store.add(&domain, req.reason).await?;
+
+    Ok((StatusCode::CREATED, Json(blocked)))
+}
+
+/// Removes a domain from the blocklist.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/blocklist/{domain}",
+    tag = "blocklist",
+    params(("domain" = String, Path, description = "Blocked domain")),
+    responses(
+        (status = 204, description = "The domain was unblocked"),
+        (status = 400, description = "The domain is invalid", body = ErrorResponse),
+        (status = 404, description = "The domain is not blocked", body = ErrorResponse),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn unblock_domain(
+    State(state): State<AppState>,
+    Path(domain): Path<String>,
+) -> Result<StatusCode, AppError> {
+    state
+        .blocklist_store
+        .remove(&parse_domain(&domain)?)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Normalizes `raw` to the form destination hosts are compared in.
+fn parse_domain(raw: &str) -> Result<String, AppError> {
+    url::Host::parse(&normalize_host(raw))
+        .map(|host| host.to_string())
+        .map_err(|e| AppError::BadRequest(format!("Invalid domain '{raw}': {e}")))
+}
@@ -1,54 +1,54 @@
 use axum::{Json, extract::State, http::StatusCode};
-use serde::Serialize;
 use tracing::instrument;
 
 use crate::AppState;
 
-#[derive(Serialize)]
-pub struct HealthResponse {
-    status: &'static str,
-    service_name: &'static str,
-}
-
-#[derive(Serialize)]
-pub struct ReadyResponse {
-    status: &'static str,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    database: Option<&'static str>,
-}
+pub use shortener_types::shortener::{HealthResponse, ReadyResponse};
 
 /// Liveness probe - プロセスが生きているか確認
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses((status = 200, description = "The process is running", body = HealthResponse))
+)]
 #[instrument]
 pub async fn health() -> Json<HealthResponse> {
     Json(HealthResponse {
-        status: "ok",
-        service_name: env!("CARGO_PKG_NAME"),
+        status: "ok".to_string(),
+        service_name: env!("CARGO_PKG_NAME").to_string(),
     })
 }
 
 /// Readiness probe - トラフィックを受け入れられるか確認
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "The database is reachable", body = ReadyResponse),
+        (status = 503, description = "The database is unreachable", body = ReadyResponse),
+    )
+)]
 #[instrument(skip(state))]
 pub async fn ready(State(state): State<AppState>) -> (StatusCode, Json<ReadyResponse>) {
     // Check database connection
-    let db_ok = sqlx::query!(r#"SELECT 1 as "one!""#)
-        .fetch_one(state.url_repository.pool())
-        .await
-        .is_ok();
+    let db_ok = state.url_store.ping().await.is_ok();
 
     if db_ok {
         (
             StatusCode::OK,
             Json(ReadyResponse {
-                status: "ready",
-                database: Some("ok"),
+                status: "ready".to_string(),
+                database: Some("ok".to_string()),
             }),
         )
     } else {
         (
             StatusCode::SERVICE_UNAVAILABLE,
             Json(ReadyResponse {
-                status: "not ready",
-                database: Some("unavailable"),
+                status: "not ready".to_string(),
+                database: Some("unavailable".to_string()),
             }),
         )
     }
@@ -1,7 +1,20 @@
+mod blocklist;
 mod health;
+mod openapi;
+mod preview;
 mod redirect;
 mod urls;
+mod webhooks;
 
-pub use health::{health, ready};
-pub use redirect::redirect;
-pub use urls::{create_url, delete_url, get_url, list_urls, update_url};
+pub use blocklist::{BlockDomainRequest, block_domain, list_blocked_domains, unblock_domain};
+pub use health::{HealthResponse, ReadyResponse, health, ready};
+pub use openapi::ApiDoc;
+pub use redirect::{follow, redirect};
+pub use urls::{
+    CreateUrlRequest, CreateUrlResponse, ListUrlsQuery, UpdateUrlRequest, create_url, delete_url,
+    get_url, list_urls, restore_url, update_url,
+};
+pub use webhooks::{
+    CreateWebhookRequest, CreateWebhookResponse, ListDeliveriesQuery, create_webhook,
+    delete_webhook, list_webhook_deliveries, list_webhooks,
+};
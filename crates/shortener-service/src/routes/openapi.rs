@@ -0,0 +1,36 @@
+use utoipa::OpenApi;
+
+use super::{blocklist, health, redirect, urls, webhooks};
+
+/// `OpenAPI` document of [`crate::service_routes`].
+#[derive(OpenApi)]
+#[openapi(
+    info(description = "URL shortening service"),
+    paths(
+        urls::create_url,
+        urls::list_urls,
+        urls::get_url,
+        urls::update_url,
+        urls::delete_url,
+        urls::restore_url,
+        blocklist::list_blocked_domains,
+        blocklist::block_domain,
+        blocklist::unblock_domain,
+        webhooks::list_webhooks,
+        webhooks::create_webhook,
+        webhooks::delete_webhook,
+        webhooks::list_webhook_deliveries,
+        redirect::redirect,
+        redirect::follow,
+        health::health,
+        health::ready,
+    ),
+    tags(
+        (name = "urls", description = "Short links"),
+        (name = "blocklist", description = "Destination domains rejected for new links"),
+        (name = "webhooks", description = "Subscriptions to link events"),
+        (name = "redirect", description = "Following short links"),
+        (name = "health", description = "Liveness and readiness probes"),
+    )
+)]
+pub struct ApiDoc;
@@ -0,0 +1,159 @@
+use std::fmt::Write as _;
+
+use axum::{
+    http::header,
+    response::{Html, IntoResponse, Response},
+};
+
+use crate::repository::Url;
+
+/// Renders the interstitial page shown before following `url`.
+///
+/// `continue_action` is the address the continue form is posted to, which
+/// performs the actual redirect, and `clicks` is `None` when the count could
+/// not be retrieved.
+pub fn render_preview(
+    url: &Url,
+    destination: &str,
+    continue_action: &str,
+    clicks: Option<i64>,
+) -> Response {
+    let clicks = clicks.map_or_else(|| "unavailable".to_string(), |c| c.to_string());
+    let host = url::Url::parse(destination)
+        .ok()
+        .and_then(|d| d.host_str().map(String::from))
+        .unwrap_or_else(|| destination.to_string());
+
+    let mut body = String::new();
+    let _ = write!(
+        body,
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="robots" content="noindex">
+<title>Link preview: {code}</title>
+</head>
+<body>
+<main>
+<h1>You are leaving for another site</h1>
+"#,
+        code = escape_html(&url.code),
+    );
+
+    if url.always_interstitial {
+        body.push_str(
+            "<p><strong>This link has been flagged for review. \
+             Make sure you trust the destination before continuing.</strong></p>\n",
+        );
+    }
+
+    let _ = write!(
+        body,
+        r#"<dl>
+<dt>Destination</dt><dd><code>{destination}</code></dd>
+<dt>Created</dt><dd>{created_at}</dd>
+<dt>Clicks</dt><dd>{clicks}</dd>
+</dl>
+<form method="post" action="{continue_action}">
+<button type="submit">Continue to {host}</button>
+</form>
+</main>
+</body>
+</html>
+"#,
+        destination = escape_html(destination),
+        created_at = url.created_at.format("%Y-%m-%d %H:%M UTC"),
+        continue_action = escape_html(continue_action),
+        host = escape_html(&host),
+    );
+
+    (
+        [
+            (header::CACHE_CONTROL, "private, no-store"),
+            (header::REFERRER_POLICY, "no-referrer"),
+        ],
+        Html(body),
+    )
+        .into_response()
+}
+
+/// Renders the Open Graph tagged page served to link unfurlers.
+///
+/// Clients that do follow HTML redirects are sent on to `destination`.
+pub fn render_social_preview(url: &Url, destination: &str) -> Response {
+    let og = &url.og_metadata;
+    let title = og.title.as_deref().unwrap_or(destination);
+
+    let mut head = String::new();
+    let _ = writeln!(
+        head,
+        r#"<meta property="og:title" content="{}">"#,
+        escape_html(title)
+    );
+    let _ = writeln!(
+        head,
+        r#"<meta property="og:url" content="{}">"#,
+        escape_html(destination)
+    );
+    if let Some(description) = &og.description {
+        let _ = writeln!(
+            head,
+            r#"<meta property="og:description" content="{}">"#,
+            escape_html(description)
+        );
+    }
+    let card = if let Some(image_url) = &og.image_url {
+        let _ = writeln!(
+            head,
+            r#"<meta property="og:image" content="{}">"#,
+            escape_html(image_url)
+        );
+        "summary_large_image"
+    } else {
+        "summary"
+    };
+    let _ = writeln!(head, r#"<meta name="twitter:card" content="{card}">"#);
+
+    let body = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+{head}<meta http-equiv="refresh" content="0; url={destination}">
+</head>
+<body>
+<p><a href="{destination}">{title}</a></p>
+</body>
+</html>
+"#,
+        title = escape_html(title),
+        destination = escape_html(destination),
+    );
+
+    (
+        [
+            (header::CACHE_CONTROL, "public, max-age=300"),
+            (header::VARY, "User-Agent"),
+        ],
+        Html(body),
+    )
+        .into_response()
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -1,57 +1,656 @@
 use std::net::SocketAddr;
 
 use axum::{
-    extract::{ConnectInfo, Path, State},
-    http::HeaderMap,
-    response::{IntoResponse, Redirect},
+    extract::{ConnectInfo, Path, RawQuery, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
 };
-use shortener_core::{AppError, messaging::AccessEvent};
-use tracing::{Instrument, Span, info, info_span, instrument};
+use chrono::Utc;
+use shortener_core::{AppError, error::ErrorResponse, messaging::AccessEvent};
+use tracing::{Instrument, Span, info, info_span, instrument, warn};
 
-use crate::AppState;
+use super::preview::{render_preview, render_social_preview};
+use crate::{
+    AppState,
+    repository::{RedirectType, Url},
+};
+
+const CODE_PLACEHOLDER: &str = "{code}";
+
+/// Path suffix requesting the preview page, as in `/{code}+`.
+const PREVIEW_SUFFIX: char = '+';
+
+/// Query parameter requesting the preview page when given without a value,
+/// as in `?preview`. With a value it is forwarded like any other parameter.
+const PREVIEW_PARAM: &str = "preview";
 
+/// `Sec-Fetch-Site` values of requests sent by this service's own pages or
+/// typed by the user.
+const FIRST_PARTY_FETCH_SITES: &[&str] = &["same-origin", "none"];
+
+/// Lower-cased `User-Agent` fragments of link unfurlers, which get an Open
+/// Graph page instead of a redirect.
+const CRAWLER_USER_AGENTS: &[&str] = &[
+    "slackbot",
+    "twitterbot",
+    "facebookexternalhit",
+    "facebookcatalog",
+    "linkedinbot",
+    "discordbot",
+    "telegrambot",
+    "whatsapp",
+    "skypeuripreview",
+    "embedly",
+    "pinterestbot",
+    "redditbot",
+    "mastodon",
+    "bluesky",
+];
+
+/// Redirects to the destination of a link.
+///
+/// The query string is forwarded when the link is configured to. Link
+/// unfurlers get an Open Graph page, and `/{code}+`, `?preview` and
+/// `always_interstitial` links show a preview page instead of redirecting.
+#[utoipa::path(
+    get,
+    path = "/{code}",
+    tag = "redirect",
+    params(("code" = String, Path, description = "Short code of the link")),
+    responses(
+        (status = 200, description = "Preview or Open Graph page", content_type = "text/html", body = String),
+        (status = 301, description = "Redirect of a `moved_permanently` link",
+            headers(("Location" = String, description = "Destination URL"))),
+        (status = 302, description = "Redirect of a `found` link",
+            headers(("Location" = String, description = "Destination URL"))),
+        (status = 307, description = "Redirect of a `temporary` link",
+            headers(("Location" = String, description = "Destination URL"))),
+        (status = 308, description = "Redirect of a `permanent` link",
+            headers(("Location" = String, description = "Destination URL"))),
+        (status = 404, description = "No active link has this code", body = ErrorResponse),
+        (status = 410, description = "The link has expired or was disabled", body = ErrorResponse),
+    )
+)]
 #[instrument(skip(state, headers))]
 pub async fn redirect(
     State(state): State<AppState>,
     Path(code): Path<String>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> Result<Response, AppError> {
+    let (code, preview_suffix) = match code.strip_suffix(PREVIEW_SUFFIX).map(str::to_string) {
+        Some(code) => (code, true),
+        None => (code, false),
+    };
+
+    let mut visitor_params = parse_query(query.as_deref());
+    let preview_requested = take_flag(&mut visitor_params, PREVIEW_PARAM) || preview_suffix;
+
+    let url = find_redirectable(&state, &code).await?;
+    let destination = build_destination(&url, visitor_params.clone())?;
+
+    if preview_requested || url.always_interstitial {
+        return Ok(preview(&state, &url, &destination, &visitor_params).await);
+    }
+
+    let user_agent = header_value(&headers, header::USER_AGENT);
+
+    // Unfurl requests are not clicks, so no access event is published.
+    if user_agent.as_deref().is_some_and(is_crawler) {
+        return Ok(render_social_preview(&url, &destination));
+    }
+
+    publish_access_event(&state, &code, user_agent, &headers, addr);
+
+    info!(code = %code, destination = %destination, "Redirecting");
+
+    let cache_control = cache_control(&url, state.permanent_redirect_max_age);
+
+    // Crawlers get a different response from the same URL, so caches must
+    // key on the user agent.
+    Ok((
+        redirect_status(url.redirect_type),
+        [
+            (header::LOCATION, destination),
+            (header::CACHE_CONTROL, cache_control),
+            (header::VARY, "User-Agent".to_string()),
+        ],
+    )
+        .into_response())
+}
+
+/// Follows a link from its preview page.
+///
+/// The preview page submits this form to continue to the destination, which
+/// is the only way past the interstitial of an `always_interstitial` link.
+/// Forms submitted from other sites get the preview page again.
+#[utoipa::path(
+    post,
+    path = "/{code}",
+    tag = "redirect",
+    params(("code" = String, Path, description = "Short code of the link")),
+    responses(
+        (status = 200, description = "Preview page, for forms submitted from other sites",
+            content_type = "text/html", body = String),
+        (status = 303, description = "Redirect to the destination",
+            headers(("Location" = String, description = "Destination URL"))),
+        (status = 404, description = "No active link has this code", body = ErrorResponse),
+        (status = 410, description = "The link has expired or was disabled", body = ErrorResponse),
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn follow(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    RawQuery(query): RawQuery,
     headers: HeaderMap,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
-) -> Result<impl IntoResponse, AppError> {
+) -> Result<Response, AppError> {
+    let visitor_params = parse_query(query.as_deref());
+
+    let url = find_redirectable(&state, &code).await?;
+    let destination = build_destination(&url, visitor_params.clone())?;
+
+    let first_party = headers
+        .get("sec-fetch-site")
+        .and_then(|v| v.to_str().ok())
+        .is_none_or(|site| FIRST_PARTY_FETCH_SITES.contains(&site));
+    if !first_party {
+        return Ok(preview(&state, &url, &destination, &visitor_params).await);
+    }
+
+    let user_agent = header_value(&headers, header::USER_AGENT);
+    publish_access_event(&state, &code, user_agent, &headers, addr);
+
+    info!(code = %code, destination = %destination, "Following link from preview");
+
+    Ok((
+        StatusCode::SEE_OTHER,
+        [
+            (header::LOCATION, destination),
+            (header::CACHE_CONTROL, "private, no-store".to_string()),
+        ],
+    )
+        .into_response())
+}
+
+/// Returns the link `code` if it may be redirected to.
+async fn find_redirectable(state: &AppState, code: &str) -> Result<Url, AppError> {
     let url = state
-        .url_repository
-        .find_by_code(&code)
+        .url_store
+        .find_by_code(code)
         .await?
         .ok_or_else(|| AppError::NotFound(format!("URL with code '{code}' not found")))?;
 
-    let user_agent = headers
-        .get("user-agent")
-        .and_then(|v| v.to_str().ok())
-        .map(String::from);
+    if url.disabled_at.is_some() {
+        return Err(AppError::Gone(format!(
+            "URL with code '{code}' has been disabled"
+        )));
+    }
 
-    let referer = headers
-        .get("referer")
-        .and_then(|v| v.to_str().ok())
-        .map(String::from);
+    if url
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Err(AppError::Gone(format!(
+            "URL with code '{code}' has expired"
+        )));
+    }
+
+    Ok(url)
+}
+
+/// Renders the preview page of `url` with its click count, if available.
+async fn preview(
+    state: &AppState,
+    url: &Url,
+    destination: &str,
+    visitor_params: &[(String, String)],
+) -> Response {
+    let clicks = match &state.click_counter {
+        Some(counter) => counter
+            .click_count(&url.code)
+            .await
+            .inspect_err(|e| warn!("Failed to fetch click count: {:?}", e))
+            .ok(),
+        None => None,
+    };
+
+    render_preview(
+        url,
+        destination,
+        &continue_action(&url.code, visitor_params),
+        clicks,
+    )
+}
+
+/// Publishes a click on `code` in the background.
+fn publish_access_event(
+    state: &AppState,
+    code: &str,
+    user_agent: Option<String>,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+) {
+    let referer = header_value(headers, header::REFERER);
 
     let publisher = state.event_publisher.clone();
-    let code_clone = code.clone();
+    let code = code.to_string();
     let ip = addr.ip().to_string();
 
     // Create a span for the background task that follows from the current span
     let current_span = Span::current();
-    let span = info_span!(parent: &current_span, "publish_access_event", code = %code_clone);
+    let span = info_span!(parent: &current_span, "publish_access_event", code = %code);
 
     tokio::spawn(
         async move {
-            let event = AccessEvent::new(code_clone, user_agent, Some(ip), referer);
+            let event = AccessEvent::new(code, user_agent, Some(ip), referer);
             if let Err(e) = publisher.publish(event).await {
                 tracing::warn!("Failed to publish access event: {:?}", e);
             }
         }
         .instrument(span),
     );
+}
+
+fn header_value(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+}
+
+fn parse_query(query: Option<&str>) -> Vec<(String, String)> {
+    query
+        .map(|q| {
+            url::form_urlencoded::parse(q.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn is_crawler(user_agent: &str) -> bool {
+    let user_agent = user_agent.to_ascii_lowercase();
+    CRAWLER_USER_AGENTS.iter().any(|c| user_agent.contains(c))
+}
+
+/// Returns the `Cache-Control` value of a redirect to `url`.
+///
+/// Permanent redirects may be cached for `max_age` seconds, but never past the
+/// link's expiry, after which the link must answer `410 Gone`.
+fn cache_control(url: &Url, max_age: u64) -> String {
+    if !url.redirect_type.is_permanent() {
+        return "private, no-store".to_string();
+    }
+    let max_age = match url.expires_at {
+        Some(expires_at) => {
+            let remaining = (expires_at - Utc::now()).num_seconds();
+            max_age.min(u64::try_from(remaining).unwrap_or(0))
+        }
+        None => max_age,
+    };
+    if max_age == 0 {
+        "private, no-store".to_string()
+    } else {
+        format!("public, max-age={max_age}")
+    }
+}
+
+/// Removes every valueless `name` parameter from `params`, returning whether
+/// one was present. `name` parameters with a value are kept.
+fn take_flag(params: &mut Vec<(String, String)>, name: &str) -> bool {
+    let len = params.len();
+    params.retain(|(key, value)| key != name || !value.is_empty());
+    params.len() != len
+}
+
+/// Returns the address the preview page posts to in order to follow `code`,
+/// keeping the visitor's query parameters.
+fn continue_action(code: &str, visitor_params: &[(String, String)]) -> String {
+    if visitor_params.is_empty() {
+        return format!("/{code}");
+    }
+
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    query.extend_pairs(visitor_params);
+    format!("/{code}?{}", query.finish())
+}
+
+fn redirect_status(redirect_type: RedirectType) -> StatusCode {
+    match redirect_type {
+        RedirectType::MovedPermanently => StatusCode::MOVED_PERMANENTLY,
+        RedirectType::Found => StatusCode::FOUND,
+        RedirectType::Temporary => StatusCode::TEMPORARY_REDIRECT,
+        RedirectType::Permanent => StatusCode::PERMANENT_REDIRECT,
+    }
+}
+
+/// Builds the redirect target for `url` from the visitor's query parameters.
+///
+/// Query parameters are merged with the following precedence, lowest first:
+/// parameters already on the destination, forwarded visitor parameters (only
+/// when `forward_query` is enabled) and the link's UTM tags. A key set by a
+/// higher-precedence source replaces every value of that key from lower ones
+/// and is appended after the remaining parameters, so the result only depends
+/// on the inputs.
+fn build_destination(url: &Url, visitor_params: Vec<(String, String)>) -> Result<String, AppError> {
+    let forwarded = if url.forward_query {
+        visitor_params
+    } else {
+        Vec::new()
+    };
+
+    if forwarded.is_empty() && url.utm_params.is_empty() {
+        return Ok(url.original_url.clone());
+    }
+
+    let mut destination =
+        url::Url::parse(&url.original_url).map_err(|e| AppError::UrlParse(e.to_string()))?;
+
+    let mut params: Vec<(String, String)> = destination.query_pairs().into_owned().collect();
+
+    merge_params(&mut params, forwarded);
+    merge_params(
+        &mut params,
+        url.utm_params
+            .pairs()
+            .map(|(name, value)| (name.to_string(), value.replace(CODE_PLACEHOLDER, &url.code)))
+            .collect(),
+    );
+
+    destination.query_pairs_mut().clear().extend_pairs(&params);
+
+    Ok(destination.into())
+}
+
+/// Overrides `params` with `overrides`, keeping every value of a repeated key.
+fn merge_params(params: &mut Vec<(String, String)>, overrides: Vec<(String, String)>) {
+    for (key, _) in &overrides {
+        params.retain(|(k, _)| k != key);
+    }
+    params.extend(overrides);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::http::HeaderValue;
+
+    use super::*;
+    use crate::{
+        publisher::MockEventPublisher,
+        repository::{NewUrl, OgMetadata, Stores, UtmParams},
+    };
+
+    async fn state_with_link(redirect_type: RedirectType) -> (AppState, String) {
+        state_with_url(NewUrl {
+            original_url: "https://example.com/".to_string(),
+            redirect_type,
+            ..NewUrl::default()
+        })
+        .await
+    }
+
+    async fn state_with_url(new_url: NewUrl) -> (AppState, String) {
+        let mut event_publisher = MockEventPublisher::new();
+        event_publisher.expect_publish().returning(|_| Ok(()));
+
+        let state = AppState::new(Stores::in_memory(), Arc::new(event_publisher));
+        let url = state.url_store.create(&new_url).await.unwrap();
+        (state, url.code)
+    }
+
+    async fn get(state: AppState, code: String, user_agent: &'static str) -> Response {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::USER_AGENT, HeaderValue::from_static(user_agent));
+        let addr = SocketAddr::from(([127, 0, 0, 1], 40000));
+
+        redirect(
+            State(state),
+            Path(code),
+            RawQuery(None),
+            headers,
+            ConnectInfo(addr),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn social_previews_vary_on_user_agent() {
+        let (state, code) = state_with_link(RedirectType::Permanent).await;
+
+        let response = get(state, code, "Slackbot-LinkExpanding 1.0").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::VARY], "User-Agent");
+    }
+
+    #[tokio::test]
+    async fn cacheable_redirects_vary_on_user_agent() {
+        let (state, code) = state_with_link(RedirectType::Permanent).await;
+
+        let response = get(state, code, "Mozilla/5.0").await;
+
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert!(
+            response.headers()[header::CACHE_CONTROL]
+                .to_str()
+                .unwrap()
+                .starts_with("public")
+        );
+        assert_eq!(response.headers()[header::VARY], "User-Agent");
+    }
+
+    #[tokio::test]
+    async fn each_redirect_type_sets_its_status_and_cache_control() {
+        let cases = [
+            (
+                RedirectType::MovedPermanently,
+                StatusCode::MOVED_PERMANENTLY,
+                "public, max-age=86400",
+            ),
+            (RedirectType::Found, StatusCode::FOUND, "private, no-store"),
+            (
+                RedirectType::Temporary,
+                StatusCode::TEMPORARY_REDIRECT,
+                "private, no-store",
+            ),
+            (
+                RedirectType::Permanent,
+                StatusCode::PERMANENT_REDIRECT,
+                "public, max-age=86400",
+            ),
+        ];
+
+        for (redirect_type, status, cache_control) in cases {
+            let (state, code) = state_with_link(redirect_type).await;
+
+            let response = get(state, code, "Mozilla/5.0").await;
+
+            assert_eq!(response.status(), status, "{redirect_type:?}");
+            assert_eq!(
+                response.headers()[header::CACHE_CONTROL],
+                cache_control,
+                "{redirect_type:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn permanent_redirects_are_not_cached_past_expiry() {
+        let (state, code) = state_with_url(NewUrl {
+            original_url: "https://example.com/".to_string(),
+            redirect_type: RedirectType::Permanent,
+            expires_at: Some(Utc::now() + chrono::Duration::minutes(10)),
+            ..NewUrl::default()
+        })
+        .await;
+
+        let response = get(state, code, "Mozilla/5.0").await;
+
+        let cache_control = response.headers()[header::CACHE_CONTROL].to_str().unwrap();
+        let max_age: u64 = cache_control
+            .strip_prefix("public, max-age=")
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((590..=600).contains(&max_age), "{cache_control}");
+    }
+
+    #[test]
+    fn expired_permanent_links_are_not_cached() {
+        let mut url = link("https://example.com/", false, UtmParams::default());
+        url.redirect_type = RedirectType::Permanent;
+        url.expires_at = Some(Utc::now() - chrono::Duration::seconds(1));
+
+        assert_eq!(cache_control(&url, 86400), "private, no-store");
+    }
+
+    fn link(original_url: &str, forward_query: bool, utm_params: UtmParams) -> Url {
+        let now = Utc::now();
+        Url {
+            id: uuid::Uuid::new_v4(),
+            code: "abc123".to_string(),
+            original_url: original_url.to_string(),
+            created_at: now,
+            updated_at: now,
+            expires_at: None,
+            is_active: true,
+            forward_query,
+            utm_params,
+            redirect_type: RedirectType::default(),
+            disabled_at: None,
+            disabled_reason: None,
+            always_interstitial: false,
+            og_metadata: OgMetadata::default(),
+            title: None,
+            description: None,
+            tags: Vec::new(),
+            deleted_at: None,
+        }
+    }
+
+    fn utm(source: &str, campaign: &str) -> UtmParams {
+        UtmParams {
+            utm_source: Some(source.to_string()),
+            utm_campaign: Some(campaign.to_string()),
+            ..UtmParams::default()
+        }
+    }
+
+    #[test]
+    fn build_destination_merges_params_by_precedence() {
+        let cases = [
+            (
+                "no params keeps the destination as is",
+                link("https://example.com/a?x=1", true, UtmParams::default()),
+                "",
+                "https://example.com/a?x=1",
+            ),
+            (
+                "visitor params are dropped without forward_query",
+                link("https://example.com/a?x=1", false, UtmParams::default()),
+                "y=2",
+                "https://example.com/a?x=1",
+            ),
+            (
+                "visitor params are appended with forward_query",
+                link("https://example.com/a?x=1", true, UtmParams::default()),
+                "y=2",
+                "https://example.com/a?x=1&y=2",
+            ),
+            (
+                "visitor params replace destination params",
+                link("https://example.com/a?x=1&y=1", true, UtmParams::default()),
+                "x=2",
+                "https://example.com/a?y=1&x=2",
+            ),
+            (
+                "repeated visitor keys replace every destination value",
+                link(
+                    "https://example.com/a?k=1&k=2&z=0",
+                    true,
+                    UtmParams::default(),
+                ),
+                "k=3&k=4",
+                "https://example.com/a?z=0&k=3&k=4",
+            ),
+            (
+                "link UTM tags win over visitor UTM tags",
+                link("https://example.com/a", true, utm("news", "spring")),
+                "utm_source=visitor&q=1",
+                "https://example.com/a?q=1&utm_source=news&utm_campaign=spring",
+            ),
+            (
+                "link UTM tags are added without forward_query",
+                link(
+                    "https://example.com/a?utm_source=old",
+                    false,
+                    utm("news", "spring"),
+                ),
+                "utm_source=visitor",
+                "https://example.com/a?utm_source=news&utm_campaign=spring",
+            ),
+            (
+                "{code} is replaced with the short code",
+                link("https://example.com/a", false, utm("sl-{code}", "{code}")),
+                "",
+                "https://example.com/a?utm_source=sl-abc123&utm_campaign=abc123",
+            ),
+        ];
+
+        for (name, url, query, expected) in cases {
+            let destination = build_destination(&url, parse_query(Some(query))).unwrap();
+            assert_eq!(destination, expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn only_a_valueless_preview_param_requests_the_preview() {
+        let cases = [
+            ("preview", true, ""),
+            ("preview=&a=1", true, "a=1"),
+            ("preview=full&a=1", false, "preview=full&a=1"),
+            ("a=1", false, "a=1"),
+        ];
+
+        for (query, requested, rest) in cases {
+            let mut params = parse_query(Some(query));
+            assert_eq!(take_flag(&mut params, PREVIEW_PARAM), requested, "{query}");
+            assert_eq!(params, parse_query(Some(rest)), "{query}");
+        }
+    }
+
+    #[tokio::test]
+    async fn preview_param_with_a_value_is_forwarded() {
+        let mut event_publisher = MockEventPublisher::new();
+        event_publisher.expect_publish().returning(|_| Ok(()));
+        let state = AppState::new(Stores::in_memory(), Arc::new(event_publisher));
+        let new_url = NewUrl {
+            original_url: "https://example.com/".to_string(),
+            forward_query: true,
+            ..NewUrl::default()
+        };
+        let code = state.url_store.create(&new_url).await.unwrap().code;
 
-    info!(code = %code, original_url = %url.original_url, "Redirecting");
+        let response = redirect(
+            State(state),
+            Path(code),
+            RawQuery(Some("preview=full".to_string())),
+            HeaderMap::new(),
+            ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))),
+        )
+        .await
+        .unwrap();
 
-    Ok(Redirect::temporary(&url.original_url))
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://example.com/?preview=full"
+        );
+    }
 }
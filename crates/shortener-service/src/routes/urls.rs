@@ -4,49 +4,154 @@ use axum::{
     http::StatusCode,
     response::IntoResponse,
 };
-use serde::{Deserialize, Serialize};
-use shortener_core::AppError;
-use tracing::instrument;
+use chrono::{DateTime, Utc};
+use shortener_core::{
+    AppError, Page,
+    error::ErrorResponse,
+    messaging::{LinkEvent, UrlCreated, UrlDeleted, UrlRestored, UrlUpdated},
+    pagination::{clamp_limit, decode_cursor},
+};
+use tracing::{Instrument, Span, info_span, instrument, warn};
+
+use crate::{
+    AppState,
+    repository::{NewUrl, Url, UrlChanges, UrlFilter},
+    validation::normalize_host,
+};
 
-use crate::{AppState, repository::Url};
+pub use shortener_types::shortener::{
+    CreateUrlRequest, CreateUrlResponse, ListUrlsQuery, UpdateUrlRequest,
+};
 
-#[derive(Debug, Deserialize)]
-pub struct CreateUrlRequest {
-    pub url: String,
+/// Lower-cases and trims `tags`, dropping empty and duplicate entries.
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut tags: Vec<String> = tags
+        .into_iter()
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
 }
 
-#[derive(Debug, Serialize)]
-pub struct CreateUrlResponse {
-    pub code: String,
-    pub short_url: String,
-    pub original_url: String,
+/// Rejects expiry times that have already passed.
+fn validate_expires_at(expires_at: Option<DateTime<Utc>>) -> Result<(), AppError> {
+    if expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Err(AppError::BadRequest(
+            "expires_at must be in the future".to_string(),
+        ));
+    }
+    Ok(())
 }
 
-#[derive(Debug, Deserialize)]
-pub struct UpdateUrlRequest {
-    pub url: String,
+/// Publishes `event` in the background so the response does not wait for the broker.
+fn publish_link_event(state: &AppState, event: LinkEvent) {
+    let publisher = state.event_publisher.clone();
+
+    let current_span = Span::current();
+    let span = info_span!(
+        parent: &current_span,
+        "publish_link_event",
+        routing_key = event.routing_key()
+    );
+
+    tokio::spawn(
+        async move {
+            if let Err(e) = publisher.publish_link_event(event).await {
+                warn!("Failed to publish link event: {:?}", e);
+            }
+        }
+        .instrument(span),
+    );
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ListUrlsQuery {
-    #[serde(default = "default_limit")]
-    pub limit: i64,
-    #[serde(default)]
-    pub offset: i64,
+/// Fills the Open Graph fields of `url` that are not set from its
+/// destination page, in the background.
+fn fetch_og_metadata(state: &AppState, url: &Url) {
+    let Some(fetcher) = state.metadata_fetcher.clone() else {
+        return;
+    };
+    let url_store = state.url_store.clone();
+    let code = url.code.clone();
+    let original_url = url.original_url.clone();
+
+    let current_span = Span::current();
+    let span = info_span!(parent: &current_span, "fetch_og_metadata", code = %code);
+
+    tokio::spawn(
+        async move {
+            match fetcher.fetch(&original_url).await {
+                Ok(metadata) if !metadata.is_empty() => {
+                    if let Err(e) = url_store.merge_og_metadata(&code, &metadata).await {
+                        warn!("Failed to store Open Graph metadata: {:?}", e);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to fetch Open Graph metadata: {:?}", e),
+            }
+        }
+        .instrument(span),
+    );
 }
 
-fn default_limit() -> i64 {
-    20
+/// Rejects destinations that violate the static policy, are on the blocklist
+/// or are listed in the threat feed.
+async fn validate_destination(state: &AppState, raw: &str) -> Result<(), AppError> {
+    let url = state.destination_policy.validate(raw)?;
+
+    if let Some(threat_feed) = &state.threat_feed {
+        threat_feed.check(&url)?;
+    }
+
+    if let Some(host) = url.host_str().map(normalize_host)
+        && state.blocklist_store.is_blocked(&host).await?
+    {
+        return Err(AppError::BadRequest(format!("Host '{host}' is blocked")));
+    }
+
+    Ok(())
 }
 
+/// Creates a short URL.
+#[utoipa::path(
+    post,
+    path = "/api/v1/urls",
+    tag = "urls",
+    request_body = CreateUrlRequest,
+    responses(
+        (status = 201, description = "The link was created", body = CreateUrlResponse),
+        (status = 400, description = "The destination or a field is invalid", body = ErrorResponse),
+    )
+)]
 #[instrument(skip(state))]
 pub async fn create_url(
     State(state): State<AppState>,
     Json(req): Json<CreateUrlRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    url::Url::parse(&req.url).map_err(|e| AppError::UrlParse(e.to_string()))?;
+    validate_destination(&state, &req.url).await?;
+    validate_expires_at(req.expires_at)?;
+
+    let new_url = NewUrl {
+        original_url: req.url,
+        forward_query: req.forward_query,
+        utm_params: req.utm_params,
+        redirect_type: req.redirect_type,
+        always_interstitial: req.always_interstitial,
+        og_metadata: req.og_metadata,
+        title: req.title,
+        description: req.description,
+        tags: normalize_tags(req.tags),
+        expires_at: req.expires_at,
+    };
+    let url = state.url_store.create(&new_url).await?;
+
+    publish_link_event(
+        &state,
+        LinkEvent::Created(UrlCreated::new(url.code.clone(), url.original_url.clone())),
+    );
 
-    let url = state.url_repository.create(&req.url).await?;
+    fetch_og_metadata(&state, &url);
 
     let response = CreateUrlResponse {
         short_url: format!("/{}", url.code),
@@ -57,45 +162,327 @@ pub async fn create_url(
     Ok((StatusCode::CREATED, Json(response)))
 }
 
+/// Lists active links, or those in the trash, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/urls",
+    tag = "urls",
+    params(ListUrlsQuery),
+    responses(
+        (status = 200, description = "A page of links", body = Page<Url>),
+        (status = 400, description = "The cursor is invalid", body = ErrorResponse),
+    )
+)]
 #[instrument(skip(state))]
 pub async fn list_urls(
     State(state): State<AppState>,
     Query(query): Query<ListUrlsQuery>,
-) -> Result<Json<Vec<Url>>, AppError> {
-    let urls = state.url_repository.list(query.limit, query.offset).await?;
-    Ok(Json(urls))
+) -> Result<Json<Page<Url>>, AppError> {
+    let filter = UrlFilter {
+        state: query.state,
+        tag: query
+            .tag
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty()),
+        domain: query
+            .domain
+            .as_deref()
+            .map(normalize_host)
+            .filter(|d| !d.is_empty()),
+        created_from: query.created_from,
+        created_to: query.created_to,
+        q: query.q.filter(|q| !q.trim().is_empty()),
+    };
+    let after = query.cursor.as_deref().map(decode_cursor).transpose()?;
+    let limit = clamp_limit(query.limit);
+
+    let urls = state
+        .url_store
+        .list(&filter, after, i64::try_from(limit + 1).unwrap_or(i64::MAX))
+        .await?;
+
+    Ok(Json(Page::from_overfetch(urls, limit, |url| {
+        (url.created_at, url.id)
+    })))
 }
 
+/// Returns an active link.
+#[utoipa::path(
+    get,
+    path = "/api/v1/urls/{code}",
+    tag = "urls",
+    params(("code" = String, Path, description = "Short code of the link")),
+    responses(
+        (status = 200, description = "The link", body = Url),
+        (status = 404, description = "No active link has this code", body = ErrorResponse),
+    )
+)]
 #[instrument(skip(state))]
 pub async fn get_url(
     State(state): State<AppState>,
     Path(code): Path<String>,
 ) -> Result<Json<Url>, AppError> {
     let url = state
-        .url_repository
+        .url_store
         .find_by_code(&code)
         .await?
         .ok_or_else(|| AppError::NotFound(format!("URL with code '{code}' not found")))?;
     Ok(Json(url))
 }
 
+/// Changes the destination and settings of an active link.
+#[utoipa::path(
+    put,
+    path = "/api/v1/urls/{code}",
+    tag = "urls",
+    params(("code" = String, Path, description = "Short code of the link")),
+    request_body = UpdateUrlRequest,
+    responses(
+        (status = 200, description = "The updated link", body = Url),
+        (status = 400, description = "The destination or a field is invalid", body = ErrorResponse),
+        (status = 404, description = "No active link has this code", body = ErrorResponse),
+    )
+)]
 #[instrument(skip(state))]
 pub async fn update_url(
     State(state): State<AppState>,
     Path(code): Path<String>,
     Json(req): Json<UpdateUrlRequest>,
 ) -> Result<Json<Url>, AppError> {
-    url::Url::parse(&req.url).map_err(|e| AppError::UrlParse(e.to_string()))?;
+    validate_destination(&state, &req.url).await?;
+    validate_expires_at(req.expires_at)?;
+
+    let previous = state
+        .url_store
+        .find_by_code(&code)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("URL with code '{code}' not found")))?;
+    let destination_changed = previous.original_url != req.url;
+
+    // Metadata of the old destination does not describe the new one; the
+    // fields not given are fetched again.
+    let og_metadata = if destination_changed {
+        Some(req.og_metadata.unwrap_or_default())
+    } else {
+        req.og_metadata
+    };
+
+    let changes = UrlChanges {
+        original_url: req.url,
+        forward_query: req.forward_query,
+        utm_params: req.utm_params,
+        redirect_type: req.redirect_type,
+        always_interstitial: req.always_interstitial,
+        og_metadata,
+        title: req.title,
+        description: req.description,
+        tags: req.tags.map(normalize_tags),
+        expires_at: req.expires_at,
+    };
+    let url = state.url_store.update(&code, &changes).await?;
+
+    publish_link_event(
+        &state,
+        LinkEvent::Updated(UrlUpdated::new(url.code.clone(), url.original_url.clone())),
+    );
+
+    if destination_changed {
+        fetch_og_metadata(&state, &url);
+    }
 
-    let url = state.url_repository.update(&code, &req.url).await?;
     Ok(Json(url))
 }
 
+/// Moves a link to the trash; its code stops redirecting.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/urls/{code}",
+    tag = "urls",
+    params(("code" = String, Path, description = "Short code of the link")),
+    responses(
+        (status = 204, description = "The link was deleted"),
+        (status = 404, description = "No active link has this code", body = ErrorResponse),
+    )
+)]
 #[instrument(skip(state))]
 pub async fn delete_url(
     State(state): State<AppState>,
     Path(code): Path<String>,
 ) -> Result<StatusCode, AppError> {
-    state.url_repository.delete(&code).await?;
+    state.url_store.delete(&code).await?;
+
+    publish_link_event(&state, LinkEvent::Deleted(UrlDeleted::new(code)));
+
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Takes a deleted link out of the trash; its code redirects again.
+///
+/// The destination is checked again as on creation, since the policy,
+/// blocklist or threat feed may have changed while the link was deleted.
+#[utoipa::path(
+    post,
+    path = "/api/v1/urls/{code}/restore",
+    tag = "urls",
+    params(("code" = String, Path, description = "Short code of the link")),
+    responses(
+        (status = 200, description = "The restored link", body = Url),
+        (status = 400, description = "The destination is no longer allowed", body = ErrorResponse),
+        (status = 404, description = "No deleted link has this code", body = ErrorResponse),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn restore_url(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+) -> Result<Json<Url>, AppError> {
+    let deleted =
+        state.url_store.find_deleted(&code).await?.ok_or_else(|| {
+            AppError::NotFound(format!("Deleted URL with code '{code}' not found"))
+        })?;
+    validate_destination(&state, &deleted.original_url).await?;
+
+    let url = state.url_store.restore(&code).await?;
+
+    publish_link_event(
+        &state,
+        LinkEvent::Restored(UrlRestored::new(url.code.clone(), url.original_url.clone())),
+    );
+
+    Ok(Json(url))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::*;
+    use crate::{
+        metadata::MockMetadataFetcher,
+        publisher::MockEventPublisher,
+        repository::{OgMetadata, Stores},
+    };
+
+    fn state(fetcher: MockMetadataFetcher) -> AppState {
+        let mut event_publisher = MockEventPublisher::new();
+        event_publisher
+            .expect_publish_link_event()
+            .returning(|_| Ok(()));
+
+        let mut state = AppState::new(Stores::in_memory(), Arc::new(event_publisher));
+        state.metadata_fetcher = Some(Arc::new(fetcher));
+        state
+    }
+
+    async fn create(state: &AppState, original_url: &str) -> Url {
+        let new_url = NewUrl {
+            original_url: original_url.to_string(),
+            og_metadata: OgMetadata {
+                title: Some("Old title".to_string()),
+                image_url: Some("https://old.example.com/card.png".to_string()),
+                ..OgMetadata::default()
+            },
+            ..NewUrl::default()
+        };
+        state.url_store.create(&new_url).await.unwrap()
+    }
+
+    async fn update(state: &AppState, code: &str, req: UpdateUrlRequest) -> Url {
+        let Json(url) = update_url(State(state.clone()), Path(code.to_string()), Json(req))
+            .await
+            .unwrap();
+        url
+    }
+
+    #[tokio::test]
+    async fn changing_the_destination_refetches_metadata() {
+        let mut fetcher = MockMetadataFetcher::new();
+        fetcher
+            .expect_fetch()
+            .withf(|url| url == "https://new.example.com/")
+            .times(1)
+            .returning(|_| {
+                Ok(OgMetadata {
+                    title: Some("New title".to_string()),
+                    description: Some("Fetched".to_string()),
+                    image_url: None,
+                })
+            });
+        let state = state(fetcher);
+        let url = create(&state, "https://old.example.com/").await;
+
+        let req = UpdateUrlRequest {
+            url: "https://new.example.com/".to_string(),
+            og_metadata: Some(OgMetadata {
+                description: Some("Given".to_string()),
+                ..OgMetadata::default()
+            }),
+            ..UpdateUrlRequest::default()
+        };
+        let updated = update(&state, &url.code, req).await;
+        assert!(updated.og_metadata.image_url.is_none());
+
+        let mut og_metadata = OgMetadata::default();
+        for _ in 0..100 {
+            let stored = state.url_store.find_by_code(&url.code).await.unwrap();
+            og_metadata = stored.unwrap().og_metadata;
+            if og_metadata.title.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(og_metadata.title.as_deref(), Some("New title"));
+        assert_eq!(og_metadata.description.as_deref(), Some("Given"));
+        assert!(og_metadata.image_url.is_none());
+    }
+
+    #[tokio::test]
+    async fn keeping_the_destination_keeps_metadata() {
+        let mut fetcher = MockMetadataFetcher::new();
+        fetcher.expect_fetch().never();
+        let state = state(fetcher);
+        let url = create(&state, "https://old.example.com/").await;
+
+        let req = UpdateUrlRequest {
+            url: "https://old.example.com/".to_string(),
+            title: Some("Renamed".to_string()),
+            ..UpdateUrlRequest::default()
+        };
+        let updated = update(&state, &url.code, req).await;
+
+        assert_eq!(updated.og_metadata, url.og_metadata);
+    }
+
+    #[tokio::test]
+    async fn restoring_checks_the_destination_again() {
+        let state = state(MockMetadataFetcher::new());
+        let url = create(&state, "https://old.example.com/").await;
+        state.url_store.delete(&url.code).await.unwrap();
+        state
+            .blocklist_store
+            .add("old.example.com", None)
+            .await
+            .unwrap();
+
+        let err = restore_url(State(state.clone()), Path(url.code.clone()))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, AppError::BadRequest(message) if message == "Host 'old.example.com' is blocked"),
+            "{err:?}"
+        );
+        let deleted = state.url_store.find_deleted(&url.code).await.unwrap();
+        assert!(deleted.is_some());
+
+        state
+            .blocklist_store
+            .remove("old.example.com")
+            .await
+            .unwrap();
+        let Json(restored) = restore_url(State(state.clone()), Path(url.code.clone()))
+            .await
+            .unwrap();
+        assert!(restored.deleted_at.is_none());
+    }
+}
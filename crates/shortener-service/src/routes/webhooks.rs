@@ -0,0 +1,154 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use rand::Rng;
+use shortener_core::{
+    AppError, Page,
+    error::ErrorResponse,
+    messaging::routing_keys,
+    pagination::{clamp_limit, decode_cursor},
+};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{
+    AppState,
+    repository::{WebhookDelivery, WebhookSubscription},
+};
+
+pub use shortener_types::shortener::{
+    CreateWebhookRequest, CreateWebhookResponse, ListDeliveriesQuery,
+};
+
+const SECRET_BYTES: usize = 32;
+const MIN_SECRET_LEN: usize = 16;
+
+/// Lists the webhook subscriptions, without their secrets.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/webhooks",
+    tag = "webhooks",
+    responses((status = 200, description = "Every subscription", body = Vec<WebhookSubscription>))
+)]
+#[instrument(skip(state))]
+pub async fn list_webhooks(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<WebhookSubscription>>, AppError> {
+    let subscriptions = state.webhook_store.list().await?;
+    Ok(Json(subscriptions))
+}
+
+/// Subscribes an endpoint to link events.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/webhooks",
+    tag = "webhooks",
+    request_body = CreateWebhookRequest,
+    responses(
+        (status = 201, description = "The subscription was created", body = CreateWebhookResponse),
+        (status = 400, description = "The URL, secret or an event type is invalid", body = ErrorResponse),
+    )
+)]
+#[instrument(skip(state, req))]
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    Json(req): Json<CreateWebhookRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    state.destination_policy.validate(&req.url)?;
+
+    if let Some(unknown) = req
+        .event_types
+        .iter()
+        .find(|t| !routing_keys::LINK_EVENTS.contains(&t.as_str()))
+    {
+        return Err(AppError::BadRequest(format!(
+            "Unknown event type '{unknown}'"
+        )));
+    }
+
+    let secret = match req.secret {
+        Some(secret) if secret.len() < MIN_SECRET_LEN => {
+            return Err(AppError::BadRequest(format!(
+                "secret must be at least {MIN_SECRET_LEN} characters"
+            )));
+        }
+        Some(secret) => secret,
+        None => generate_secret(),
+    };
+
+    let mut event_types = req.event_types;
+    event_types.sort();
+    event_types.dedup();
+
+    let subscription = state
+        .webhook_store
+        .create(&req.url, &secret, &event_types)
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateWebhookResponse {
+            subscription,
+            secret,
+        }),
+    ))
+}
+
+/// Deletes a subscription together with its deliveries.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = Uuid, Path, description = "ID of the subscription")),
+    responses(
+        (status = 204, description = "The subscription was deleted"),
+        (status = 404, description = "No subscription has this ID", body = ErrorResponse),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    state.webhook_store.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Lists the deliveries of a subscription, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/webhooks/{id}/deliveries",
+    tag = "webhooks",
+    params(("id" = Uuid, Path, description = "ID of the subscription"), ListDeliveriesQuery),
+    responses(
+        (status = 200, description = "A page of deliveries", body = Page<WebhookDelivery>),
+        (status = 400, description = "The cursor is invalid", body = ErrorResponse),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn list_webhook_deliveries(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ListDeliveriesQuery>,
+) -> Result<Json<Page<WebhookDelivery>>, AppError> {
+    let after = query.cursor.as_deref().map(decode_cursor).transpose()?;
+    let limit = clamp_limit(query.limit);
+
+    let deliveries = state
+        .webhook_store
+        .deliveries(id, after, i64::try_from(limit + 1).unwrap_or(i64::MAX))
+        .await?;
+
+    Ok(Json(Page::from_overfetch(deliveries, limit, |delivery| {
+        (delivery.created_at, delivery.id)
+    })))
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    rand::thread_rng().fill(&mut bytes);
+    hex::encode(bytes)
+}
@@ -0,0 +1,645 @@
+//! Local phishing/malware threat list checks.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    net::IpAddr,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use sha2::{Digest, Sha256};
+use shortener_core::AppError;
+use tokio::sync::Mutex;
+use tracing::{debug, error, info, instrument, warn};
+use url::{Host, Url};
+
+use crate::repository::UrlStore;
+
+const SCAN_BATCH_SIZE: i64 = 1000;
+
+/// `disabled_reason` of links disabled by the feed. Only links disabled for
+/// this reason are re-enabled once their destination leaves the list.
+const DISABLED_REASON: &str = "Destination listed in threat feed";
+
+/// Hostnames that hosts-format lists map to themselves.
+const HOSTS_FILE_SELF_ENTRIES: &[&str] = &[
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "ip6-localhost",
+    "ip6-loopback",
+];
+
+/// Layout of the threat list file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    /// One entry per line: hosts-file lines (`0.0.0.0 evil.example`), bare
+    /// hostnames or full URLs.
+    Hosts,
+    /// One hex-encoded SHA-256 hash (32 bytes) or hash prefix (4 to 31
+    /// bytes) per line, computed over Safe Browsing style host/path
+    /// expressions. A prefix hit only counts once the full hash of the same
+    /// expression is listed too, so prefixes without one are dropped on load.
+    HashPrefix,
+}
+
+impl FromStr for FeedFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hosts" => Ok(Self::Hosts),
+            "hash_prefix" => Ok(Self::HashPrefix),
+            other => Err(AppError::Internal(format!(
+                "Unknown threat feed format '{other}'"
+            ))),
+        }
+    }
+}
+
+/// Parsed contents of a threat list.
+#[derive(Debug, Default)]
+pub struct ThreatList {
+    hosts: HashSet<String>,
+    urls: HashSet<String>,
+    full_hashes: HashSet<[u8; 32]>,
+    hash_prefixes: HashSet<Vec<u8>>,
+    prefix_lengths: BTreeSet<usize>,
+}
+
+impl ThreatList {
+    /// Parses `contents`, skipping blank lines, comments and malformed entries.
+    #[must_use]
+    pub fn parse(contents: &str, format: FeedFormat) -> Self {
+        let mut list = Self::default();
+
+        for line in contents.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() || line.starts_with('!') {
+                continue;
+            }
+
+            match format {
+                FeedFormat::Hosts => list.add_hosts_line(line),
+                FeedFormat::HashPrefix => match hex::decode(line).map(<[u8; 32]>::try_from) {
+                    Ok(Ok(full_hash)) => {
+                        list.full_hashes.insert(full_hash);
+                    }
+                    Ok(Err(prefix)) if (4..32).contains(&prefix.len()) => {
+                        list.prefix_lengths.insert(prefix.len());
+                        list.hash_prefixes.insert(prefix);
+                    }
+                    _ => warn!(entry = %line, "Skipping invalid hash prefix"),
+                },
+            }
+        }
+
+        list.drop_unconfirmed_prefixes();
+        list
+    }
+
+    /// Drops hash prefixes no full hash of the list starts with, as they can
+    /// never confirm a match.
+    fn drop_unconfirmed_prefixes(&mut self) {
+        let full_hashes = &self.full_hashes;
+        let before = self.hash_prefixes.len();
+        self.hash_prefixes.retain(|prefix| {
+            let confirmed = full_hashes.iter().any(|hash| hash.starts_with(prefix));
+            if !confirmed {
+                debug!(prefix = %hex::encode(prefix), "Dropping hash prefix without a full hash");
+            }
+            confirmed
+        });
+
+        let dropped = before - self.hash_prefixes.len();
+        if dropped > 0 {
+            warn!(dropped, "Skipping hash prefixes without a full hash");
+        }
+        self.prefix_lengths = self.hash_prefixes.iter().map(Vec::len).collect();
+    }
+
+    fn add_hosts_line(&mut self, line: &str) {
+        let mut tokens = line.split_whitespace().peekable();
+
+        // Hosts-file lines start with the address the names resolve to.
+        if tokens
+            .peek()
+            .is_some_and(|t| t.parse::<IpAddr>().is_ok() && line.contains(char::is_whitespace))
+        {
+            tokens.next();
+        }
+
+        for token in tokens {
+            if token.contains("://") {
+                if let Ok(url) = Url::parse(token) {
+                    self.urls.insert(url_key(url));
+                } else {
+                    warn!(entry = %token, "Skipping invalid URL");
+                }
+            } else if !HOSTS_FILE_SELF_ENTRIES.contains(&token) {
+                self.hosts
+                    .insert(token.trim_end_matches('.').to_ascii_lowercase());
+            }
+        }
+    }
+
+    /// Returns the number of entries in the list.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.hosts.len() + self.urls.len() + self.full_hashes.len() + self.hash_prefixes.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if `url` matches an entry of the list.
+    ///
+    /// A hash prefix hit is confirmed against the full hashes of the list, so
+    /// a URL sharing only a prefix with a listed expression does not match.
+    #[must_use]
+    pub fn matches(&self, url: &Url) -> bool {
+        if let Some(host) = url.host_str() {
+            let host = host.trim_end_matches('.');
+            let mut candidate = Some(host);
+            while let Some(h) = candidate {
+                if self.hosts.contains(h) {
+                    return true;
+                }
+                candidate = h.split_once('.').map(|(_, parent)| parent);
+            }
+        }
+
+        if self.urls.contains(&url_key(url.clone())) {
+            return true;
+        }
+
+        if self.full_hashes.is_empty() && self.hash_prefixes.is_empty() {
+            return false;
+        }
+
+        let mut unconfirmed = false;
+        for expression in expressions(url) {
+            let digest: [u8; 32] = Sha256::digest(expression.as_bytes()).into();
+            if self.full_hashes.contains(&digest) {
+                return true;
+            }
+            unconfirmed |= self
+                .prefix_lengths
+                .iter()
+                .any(|&len| self.hash_prefixes.contains(&digest[..len]));
+        }
+
+        if unconfirmed {
+            debug!(%url, "Hash prefix hit without a matching full hash");
+        }
+
+        false
+    }
+}
+
+/// Returns the form URL entries are compared in.
+fn url_key(mut url: Url) -> String {
+    url.set_fragment(None);
+    url.into()
+}
+
+/// Returns the host-suffix/path-prefix expressions looked up for `url`,
+/// following the Safe Browsing URL hashing scheme.
+fn expressions(url: &Url) -> Vec<String> {
+    let Some(host) = url.host_str() else {
+        return Vec::new();
+    };
+    let host = host.trim_end_matches('.');
+
+    let mut hosts = vec![host.to_string()];
+    if let Some(Host::Domain(_)) = url.host() {
+        let labels: Vec<&str> = host.split('.').collect();
+        let start = labels.len().saturating_sub(5).max(1);
+        for i in start..labels.len().saturating_sub(1) {
+            hosts.push(labels[i..].join("."));
+        }
+    }
+
+    let path = url.path();
+    let mut paths = Vec::new();
+    if let Some(query) = url.query() {
+        paths.push(format!("{path}?{query}"));
+    }
+    paths.push(path.to_string());
+
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    let mut prefix = String::from("/");
+    let directories = segments.len() - 1;
+    for segment in std::iter::once("").chain(segments.into_iter().take(directories.min(3))) {
+        if !segment.is_empty() {
+            prefix.push_str(segment);
+            prefix.push('/');
+        }
+        if !paths.contains(&prefix) {
+            paths.push(prefix.clone());
+        }
+    }
+
+    hosts
+        .iter()
+        .flat_map(|h| paths.iter().map(move |p| format!("{h}{p}")))
+        .collect()
+}
+
+/// Threat list loaded from a local file and refreshed periodically.
+pub struct ThreatFeed {
+    path: PathBuf,
+    format: FeedFormat,
+    list: RwLock<Arc<ThreatList>>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl ThreatFeed {
+    #[must_use]
+    pub fn new(path: PathBuf, format: FeedFormat) -> Self {
+        Self {
+            path,
+            format,
+            list: RwLock::new(Arc::new(ThreatList::default())),
+            modified: Mutex::new(None),
+        }
+    }
+
+    fn current(&self) -> Arc<ThreatList> {
+        Arc::clone(
+            &self
+                .list
+                .read()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        )
+    }
+
+    /// Rejects `url` if it is on the threat list.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::BadRequest` if the destination is listed.
+    pub fn check(&self, url: &Url) -> Result<(), AppError> {
+        if self.current().matches(url) {
+            return Err(AppError::BadRequest(format!(
+                "URL '{url}' is listed as phishing or malware"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reloads the file if it changed since the last load.
+    ///
+    /// Returns `true` if a new list was loaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Internal` if the file cannot be read.
+    #[instrument(skip(self), fields(path = %self.path.display()))]
+    pub async fn reload(&self) -> Result<bool, AppError> {
+        let metadata = tokio::fs::metadata(&self.path)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to stat threat feed: {e}")))?;
+        let modified = metadata.modified().ok();
+
+        let mut last_modified = self.modified.lock().await;
+        if modified.is_some() && *last_modified == modified {
+            return Ok(false);
+        }
+
+        let contents = tokio::fs::read_to_string(&self.path)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to read threat feed: {e}")))?;
+        let list = ThreatList::parse(&contents, self.format);
+
+        info!(entries = list.len(), "Threat feed loaded");
+
+        *self
+            .list
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = Arc::new(list);
+        *last_modified = modified;
+
+        Ok(true)
+    }
+
+    /// Disables every enabled link whose destination is on the current list.
+    ///
+    /// Returns the number of links disabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Database` if reading or updating links fails.
+    #[instrument(skip(self, url_store))]
+    pub async fn disable_listed(&self, url_store: &dyn UrlStore) -> Result<u64, AppError> {
+        let list = self.current();
+        if list.is_empty() {
+            return Ok(0);
+        }
+
+        let mut disabled = 0;
+        let mut after = String::new();
+        loop {
+            let batch = url_store
+                .list_enabled_destinations(&after, SCAN_BATCH_SIZE)
+                .await?;
+            let Some((last_code, _)) = batch.last() else {
+                break;
+            };
+            after.clone_from(last_code);
+
+            let listed: Vec<String> = batch
+                .into_iter()
+                .filter(|(_, destination)| {
+                    Url::parse(destination).is_ok_and(|url| list.matches(&url))
+                })
+                .map(|(code, _)| code)
+                .collect();
+
+            if !listed.is_empty() {
+                disabled += url_store.disable(&listed, DISABLED_REASON).await?;
+            }
+        }
+
+        if disabled > 0 {
+            warn!(disabled, "Disabled links listed in threat feed");
+        }
+
+        Ok(disabled)
+    }
+
+    /// Re-enables links disabled by the feed whose destination is no longer
+    /// on the current list.
+    ///
+    /// Returns the number of links re-enabled. Links disabled for any other
+    /// reason are left alone.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Database` if reading or updating links fails.
+    #[instrument(skip(self, url_store))]
+    pub async fn enable_delisted(&self, url_store: &dyn UrlStore) -> Result<u64, AppError> {
+        let list = self.current();
+
+        let mut enabled = 0;
+        let mut after = String::new();
+        loop {
+            let batch = url_store
+                .list_disabled_destinations(DISABLED_REASON, &after, SCAN_BATCH_SIZE)
+                .await?;
+            let Some((last_code, _)) = batch.last() else {
+                break;
+            };
+            after.clone_from(last_code);
+
+            let delisted: Vec<String> = batch
+                .into_iter()
+                .filter(|(_, destination)| {
+                    Url::parse(destination).is_ok_and(|url| !list.matches(&url))
+                })
+                .map(|(code, _)| code)
+                .collect();
+
+            if !delisted.is_empty() {
+                enabled += url_store.enable(&delisted, DISABLED_REASON).await?;
+            }
+        }
+
+        if enabled > 0 {
+            info!(enabled, "Re-enabled links no longer listed in threat feed");
+        }
+
+        Ok(enabled)
+    }
+
+    /// Brings the disabled state of links in line with the current list.
+    async fn apply(&self, url_store: &dyn UrlStore) {
+        if let Err(e) = self.disable_listed(url_store).await {
+            error!("Failed to disable listed links: {:?}", e);
+        }
+        if let Err(e) = self.enable_delisted(url_store).await {
+            error!("Failed to re-enable delisted links: {:?}", e);
+        }
+    }
+
+    /// Applies the loaded list to existing links, then periodically reloads
+    /// the file, disabling newly listed links and re-enabling links that
+    /// left the list.
+    pub fn spawn_refresh(self: Arc<Self>, url_store: Arc<dyn UrlStore>, interval: Duration) {
+        tokio::spawn(async move {
+            self.apply(url_store.as_ref()).await;
+
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            loop {
+                ticker.tick().await;
+
+                match self.reload().await {
+                    Ok(true) => self.apply(url_store.as_ref()).await,
+                    Ok(false) => {}
+                    Err(e) => error!("Failed to refresh threat feed: {:?}", e),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{InMemoryUrlStore, MockUrlStore, NewUrl};
+
+    fn url(raw: &str) -> Url {
+        Url::parse(raw).unwrap()
+    }
+
+    fn hash(expression: &str) -> String {
+        hex::encode(Sha256::digest(expression.as_bytes()))
+    }
+
+    fn feed(list: ThreatList) -> ThreatFeed {
+        let feed = ThreatFeed::new(PathBuf::from("threats.txt"), FeedFormat::Hosts);
+        *feed.list.write().unwrap() = Arc::new(list);
+        feed
+    }
+
+    #[test]
+    fn hosts_match_listed_domains_and_subdomains() {
+        let list = ThreatList::parse(
+            "# comment\n\
+             0.0.0.0 evil.example\n\
+             127.0.0.1 localhost\n\
+             Phish.Example.\n\
+             https://files.example/payload.exe#top\n",
+            FeedFormat::Hosts,
+        );
+
+        assert_eq!(list.len(), 3);
+        for (raw, listed) in [
+            ("http://evil.example/", true),
+            ("https://login.evil.example/account", true),
+            ("http://EVIL.example./", true),
+            ("http://phish.example/", true),
+            ("http://notevil.example/", false),
+            ("http://evil.example.com/", false),
+            ("http://localhost/", false),
+            ("https://files.example/payload.exe", true),
+            ("https://files.example/payload.exe#other", true),
+            ("https://files.example/readme.txt", false),
+        ] {
+            assert_eq!(list.matches(&url(raw)), listed, "{raw}");
+        }
+    }
+
+    #[test]
+    fn hash_prefix_hit_needs_the_full_hash() {
+        let full = hash("evil.example/");
+        let prefix = &full[..8];
+
+        let prefix_only = ThreatList::parse(prefix, FeedFormat::HashPrefix);
+        assert!(!prefix_only.matches(&url("http://evil.example/")));
+
+        let confirmed = ThreatList::parse(&format!("{prefix}\n{full}"), FeedFormat::HashPrefix);
+        assert!(confirmed.matches(&url("http://evil.example/")));
+        assert!(confirmed.matches(&url("https://www.evil.example/a/b?c=d")));
+        assert!(!confirmed.matches(&url("http://good.example/")));
+
+        let other = ThreatList::parse(
+            &format!("{prefix}\n{}", hash("evil.example/other/")),
+            FeedFormat::HashPrefix,
+        );
+        assert!(!other.matches(&url("http://evil.example/")));
+    }
+
+    #[test]
+    fn hash_list_skips_invalid_entries() {
+        let list = ThreatList::parse(
+            &format!("zz\n010203\n{}00\n{}", hash("a/"), hash("b/")),
+            FeedFormat::HashPrefix,
+        );
+        assert_eq!(list.len(), 1);
+    }
+
+    #[test]
+    fn hash_list_drops_prefixes_without_a_full_hash() {
+        let full = hash("evil.example/");
+        let list = ThreatList::parse(
+            &format!("{}\n{}\n{full}", &full[..8], &hash("other.example/")[..8]),
+            FeedFormat::HashPrefix,
+        );
+
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.prefix_lengths, BTreeSet::from([4]));
+    }
+
+    #[tokio::test]
+    async fn disable_pass_disables_only_confirmed_matches() {
+        let list = ThreatList::parse(
+            &format!(
+                "{}\n{}\n{}",
+                hash("evil.example/"),
+                hash("malware.example/"),
+                &hash("almost.example/")[..8]
+            ),
+            FeedFormat::HashPrefix,
+        );
+
+        let mut store = MockUrlStore::new();
+        store
+            .expect_list_enabled_destinations()
+            .returning(|after, _| {
+                Ok(if after.is_empty() {
+                    vec![
+                        ("a".to_string(), "https://evil.example/login".to_string()),
+                        ("b".to_string(), "https://almost.example/".to_string()),
+                        ("c".to_string(), "https://cdn.malware.example/x".to_string()),
+                        ("d".to_string(), "https://good.example/".to_string()),
+                    ]
+                } else {
+                    Vec::new()
+                })
+            });
+        store
+            .expect_disable()
+            .withf(|codes, _| codes == ["a".to_string(), "c".to_string()])
+            .times(1)
+            .returning(|codes, _| Ok(codes.len() as u64));
+
+        assert_eq!(feed(list).disable_listed(&store).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn disable_pass_skips_disable_when_nothing_is_listed() {
+        let mut store = MockUrlStore::new();
+        store
+            .expect_list_enabled_destinations()
+            .returning(|after, _| {
+                Ok(if after.is_empty() {
+                    vec![("a".to_string(), "https://good.example/".to_string())]
+                } else {
+                    Vec::new()
+                })
+            });
+        store.expect_disable().never();
+
+        let list = ThreatList::parse("evil.example", FeedFormat::Hosts);
+        assert_eq!(feed(list).disable_listed(&store).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn enable_pass_re_enables_only_delisted_links() {
+        let list = ThreatList::parse("evil.example", FeedFormat::Hosts);
+
+        let mut store = MockUrlStore::new();
+        store
+            .expect_list_disabled_destinations()
+            .withf(|reason, _, _| reason == DISABLED_REASON)
+            .returning(|_, after, _| {
+                Ok(if after.is_empty() {
+                    vec![
+                        ("a".to_string(), "https://evil.example/login".to_string()),
+                        ("b".to_string(), "https://cleaned.example/".to_string()),
+                    ]
+                } else {
+                    Vec::new()
+                })
+            });
+        store
+            .expect_enable()
+            .withf(|codes, reason| codes == ["b".to_string()] && reason == DISABLED_REASON)
+            .times(1)
+            .returning(|codes, _| Ok(codes.len() as u64));
+
+        assert_eq!(feed(list).enable_delisted(&store).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn links_leave_and_rejoin_the_feed() {
+        let store = InMemoryUrlStore::new();
+        let url = store
+            .create(&NewUrl {
+                original_url: "https://evil.example/".to_string(),
+                ..NewUrl::default()
+            })
+            .await
+            .unwrap();
+
+        let listed = feed(ThreatList::parse("evil.example", FeedFormat::Hosts));
+        assert_eq!(listed.disable_listed(&store).await.unwrap(), 1);
+        let disabled = store.find_by_code(&url.code).await.unwrap().unwrap();
+        assert_eq!(disabled.disabled_reason.as_deref(), Some(DISABLED_REASON));
+        assert!(disabled.updated_at > url.updated_at);
+
+        let delisted = feed(ThreatList::parse("other.example", FeedFormat::Hosts));
+        assert_eq!(delisted.enable_delisted(&store).await.unwrap(), 1);
+        let enabled = store.find_by_code(&url.code).await.unwrap().unwrap();
+        assert!(enabled.disabled_at.is_none());
+        assert!(enabled.disabled_reason.is_none());
+        assert!(enabled.updated_at > disabled.updated_at);
+    }
+}
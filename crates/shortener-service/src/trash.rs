@@ -0,0 +1,47 @@
+//! Purges links that have been in the trash for longer than the retention.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use shortener_core::messaging::{LinkEvent, UrlPurged};
+use tracing::{error, info, warn};
+
+use crate::{publisher::EventPublisher, repository::UrlStore};
+
+/// Permanently removes links deleted more than `retention` ago, checking
+/// every `interval`, and publishes a `url.purged` event for each. Their
+/// codes are retired when `retire_codes` is set.
+pub fn spawn_trash_purger(
+    url_store: Arc<dyn UrlStore>,
+    publisher: Arc<dyn EventPublisher>,
+    retention: TimeDelta,
+    retire_codes: bool,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+
+            let deleted_before = Utc::now() - retention;
+            let purged = match url_store.purge_deleted(deleted_before, retire_codes).await {
+                Ok(purged) => purged,
+                Err(e) => {
+                    error!("Failed to purge deleted links: {:?}", e);
+                    continue;
+                }
+            };
+            if !purged.is_empty() {
+                info!(purged = purged.len(), "Purged links from the trash");
+            }
+
+            for code in purged {
+                let event = LinkEvent::Purged(UrlPurged::new(code));
+                if let Err(e) = publisher.publish_link_event(event).await {
+                    warn!("Failed to publish link event: {:?}", e);
+                }
+            }
+        }
+    });
+}
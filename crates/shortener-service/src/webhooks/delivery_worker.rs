@@ -0,0 +1,347 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use reqwest::{header, redirect};
+use shortener_core::AppError;
+use tokio::task::JoinSet;
+use tracing::{Instrument, error, info, info_span, warn};
+
+use super::{EVENT_HEADER, ID_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER, sign};
+use crate::repository::{DueDelivery, WebhookStore};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Deliveries claimed per poll.
+const BATCH_SIZE: i64 = 32;
+
+/// Time a claimed delivery stays reserved for the worker sending it.
+const LEASE_SECS: i64 = 60;
+
+const INITIAL_RETRY_SECS: i64 = 10;
+const MAX_RETRY_SECS: i64 = 3600;
+
+/// Longest error message, in characters, kept in the delivery log.
+const MAX_ERROR_LEN: usize = 500;
+
+/// Sends queued webhook deliveries.
+#[derive(Clone)]
+pub struct DeliveryWorker {
+    store: Arc<dyn WebhookStore>,
+    client: reqwest::Client,
+    max_attempts: i32,
+}
+
+impl DeliveryWorker {
+    /// Creates a worker giving up on a delivery after `max_attempts` attempts.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Internal` if the HTTP client cannot be built.
+    pub fn new(store: Arc<dyn WebhookStore>, max_attempts: i32) -> Result<Self, AppError> {
+        // Redirects are not followed, so endpoints cannot bounce requests to
+        // hosts that were never validated.
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!(
+                env!("CARGO_PKG_NAME"),
+                "/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .redirect(redirect::Policy::none())
+            .build()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(Self {
+            store,
+            client,
+            max_attempts,
+        })
+    }
+
+    /// Polls for due deliveries every `interval` and sends them.
+    pub fn spawn(self, interval: Duration) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+
+                // Keep claiming while full batches come back, so a backlog is
+                // drained without waiting for the next tick.
+                loop {
+                    match self.send_due().await {
+                        Ok(true) => {}
+                        Ok(false) => break,
+                        Err(e) => {
+                            error!("Failed to send webhook deliveries: {:?}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Sends one batch of due deliveries, returning whether the batch was full.
+    async fn send_due(&self) -> Result<bool, AppError> {
+        let lease_until = Utc::now() + TimeDelta::seconds(LEASE_SECS);
+        let deliveries = self.store.claim_due(BATCH_SIZE, lease_until).await?;
+        let full = i64::try_from(deliveries.len()) == Ok(BATCH_SIZE);
+
+        let mut tasks = JoinSet::new();
+        for delivery in deliveries {
+            let worker = self.clone();
+            let span = info_span!(
+                "send_webhook",
+                delivery_id = %delivery.id,
+                event_type = %delivery.event_type,
+                attempt = delivery.attempts,
+            );
+            tasks.spawn(async move { worker.send(delivery).await }.instrument(span));
+        }
+
+        while let Some(result) = tasks.join_next().await {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!("Failed to record webhook delivery: {:?}", e),
+                Err(e) => error!("Webhook delivery task failed: {:?}", e),
+            }
+        }
+
+        Ok(full)
+    }
+
+    /// Sends `delivery` and records the outcome.
+    async fn send(&self, delivery: DueDelivery) -> Result<(), AppError> {
+        let body = serde_json::to_vec(&delivery.payload)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        let timestamp = Utc::now().timestamp();
+
+        let result = self
+            .client
+            .post(&delivery.url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(ID_HEADER, delivery.event_id.to_string())
+            .header(EVENT_HEADER, &delivery.event_type)
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign(&delivery.secret, timestamp, &body))
+            .body(body)
+            .send()
+            .await;
+
+        let (status_code, error) = match result {
+            Ok(response) if response.status().is_success() => {
+                info!(status = %response.status(), "Webhook delivered");
+                return self
+                    .store
+                    .record_success(delivery.id, response_status(&response))
+                    .await;
+            }
+            Ok(response) => (
+                Some(response_status(&response)),
+                format!("Endpoint responded with {}", response.status()),
+            ),
+            Err(e) => (None, truncate(e.to_string())),
+        };
+
+        let retry_at = (delivery.attempts < self.max_attempts)
+            .then(|| Utc::now() + retry_delay(delivery.attempts));
+        if let Some(retry_at) = retry_at {
+            warn!(%retry_at, %error, "Webhook delivery failed, retrying");
+        } else {
+            warn!(%error, "Webhook delivery failed, giving up");
+        }
+
+        self.store
+            .record_failure(delivery.id, status_code, &error, retry_at)
+            .await
+    }
+}
+
+fn response_status(response: &reqwest::Response) -> i16 {
+    i16::try_from(response.status().as_u16()).unwrap_or(i16::MAX)
+}
+
+/// Delay before the attempt following `attempts` attempts: 10 seconds,
+/// doubling per attempt and capped at one hour.
+fn retry_delay(attempts: i32) -> TimeDelta {
+    let exponent = u32::try_from(attempts.saturating_sub(1))
+        .unwrap_or(0)
+        .min(16);
+    TimeDelta::seconds((INITIAL_RETRY_SECS << exponent).min(MAX_RETRY_SECS))
+}
+
+fn truncate(mut message: String) -> String {
+    if let Some((end, _)) = message.char_indices().nth(MAX_ERROR_LEN) {
+        message.truncate(end);
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{
+        Router,
+        body::Bytes,
+        extract::State,
+        http::{HeaderMap, StatusCode},
+        routing::post,
+    };
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::repository::MockWebhookStore;
+
+    const SECRET: &str = "whsec_test";
+
+    /// Requests received by [`Endpoint`], as headers and body.
+    type Received = Arc<Mutex<Vec<(HeaderMap, Vec<u8>)>>>;
+    /// Statuses [`Endpoint`] answers with, `200 OK` once drained.
+    type Statuses = Arc<Mutex<VecDeque<StatusCode>>>;
+
+    /// Stub webhook endpoint answering with the queued statuses in turn.
+    struct Endpoint {
+        url: String,
+        received: Received,
+    }
+
+    impl Endpoint {
+        async fn start(statuses: &[StatusCode]) -> Self {
+            let statuses = Statuses::new(Mutex::new(statuses.iter().copied().collect()));
+            let received = Received::default();
+
+            let app = Router::new()
+                .route("/hook", post(respond))
+                .with_state((statuses, received.clone()));
+
+            let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+                .await
+                .unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+            Self {
+                url: format!("http://{addr}/hook"),
+                received,
+            }
+        }
+    }
+
+    async fn respond(
+        State((statuses, received)): State<(Statuses, Received)>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> StatusCode {
+        received.lock().unwrap().push((headers, body.to_vec()));
+        statuses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or(StatusCode::OK)
+    }
+
+    fn delivery(url: &str, attempts: i32) -> DueDelivery {
+        DueDelivery {
+            id: Uuid::nil(),
+            event_id: Uuid::from_u128(1),
+            event_type: "clicks.threshold_reached".to_string(),
+            payload: json!({ "type": "clicks.threshold_reached", "data": { "code": "abc" } }),
+            attempts,
+            url: url.to_string(),
+            secret: SECRET.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_delivery_is_retried_until_it_succeeds() {
+        let endpoint = Endpoint::start(&[StatusCode::INTERNAL_SERVER_ERROR]).await;
+
+        let mut store = MockWebhookStore::new();
+        store
+            .expect_record_failure()
+            .withf(|_, status_code, error, retry_at| {
+                let expected = Utc::now() + TimeDelta::seconds(INITIAL_RETRY_SECS);
+                *status_code == Some(500)
+                    && error.contains("500")
+                    && retry_at.is_some_and(|at| (at - expected).num_seconds().abs() <= 1)
+            })
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        store
+            .expect_record_success()
+            .withf(|_, status_code| *status_code == 200)
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let worker = DeliveryWorker::new(Arc::new(store), 5).unwrap();
+        worker.send(delivery(&endpoint.url, 1)).await.unwrap();
+        worker.send(delivery(&endpoint.url, 2)).await.unwrap();
+
+        let received = endpoint.received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        for (headers, body) in received.iter() {
+            assert_eq!(headers[ID_HEADER], Uuid::from_u128(1).to_string());
+            assert_eq!(headers[EVENT_HEADER], "clicks.threshold_reached");
+
+            let timestamp = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+            assert_eq!(headers[SIGNATURE_HEADER], sign(SECRET, timestamp, body));
+        }
+    }
+
+    #[tokio::test]
+    async fn delivery_is_given_up_after_max_attempts() {
+        let endpoint = Endpoint::start(&[StatusCode::SERVICE_UNAVAILABLE]).await;
+
+        let mut store = MockWebhookStore::new();
+        store
+            .expect_record_failure()
+            .withf(|_, status_code, _, retry_at| *status_code == Some(503) && retry_at.is_none())
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+
+        let worker = DeliveryWorker::new(Arc::new(store), 3).unwrap();
+        worker.send(delivery(&endpoint.url, 3)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn unreachable_endpoint_is_retried_without_status() {
+        let mut store = MockWebhookStore::new();
+        store
+            .expect_record_failure()
+            .withf(|_, status_code, _, retry_at| status_code.is_none() && retry_at.is_some())
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+
+        // Nothing listens on port 9 of the loopback address.
+        let worker = DeliveryWorker::new(Arc::new(store), 3).unwrap();
+        worker
+            .send(delivery("http://127.0.0.1:9/hook", 1))
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_an_hour() {
+        let delays: Vec<_> = [1, 2, 3, 4, 9, 10, 100]
+            .into_iter()
+            .map(|attempts| retry_delay(attempts).num_seconds())
+            .collect();
+
+        assert_eq!(delays, [10, 20, 40, 80, 2560, 3600, 3600]);
+    }
+
+    #[test]
+    fn truncate_keeps_char_boundaries() {
+        let message = "é".repeat(MAX_ERROR_LEN + 10);
+
+        assert_eq!(truncate(message).chars().count(), MAX_ERROR_LEN);
+        assert_eq!(truncate("short".to_string()), "short");
+    }
+}
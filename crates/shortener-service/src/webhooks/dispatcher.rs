@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use opentelemetry::trace::SpanKind;
+use serde_json::json;
+use shortener_core::{
+    AppError, Broker,
+    broker::Delivery,
+    messaging::{LinkEvent, routing_keys},
+};
+use tracing::{Instrument, error, info, info_span, instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::repository::WebhookStore;
+
+/// Queues a webhook delivery for each link event and matching subscription.
+pub struct LinkEventDispatcher {
+    broker: Arc<dyn Broker>,
+    queue: String,
+    store: Arc<dyn WebhookStore>,
+}
+
+impl LinkEventDispatcher {
+    /// Declares `queue` bound to the link event routing keys.
+    #[instrument(skip(broker, store))]
+    pub async fn new(
+        broker: Arc<dyn Broker>,
+        queue: &str,
+        store: Arc<dyn WebhookStore>,
+    ) -> Result<Self, AppError> {
+        broker
+            .declare_queue(queue, routing_keys::LINK_EVENTS)
+            .await?;
+
+        Ok(Self {
+            broker,
+            queue: queue.to_string(),
+            store,
+        })
+    }
+
+    /// Consumes link events until the broker closes the subscription.
+    pub async fn run(self) -> Result<(), AppError> {
+        let mut subscription = self
+            .broker
+            .subscribe(&self.queue, routing_keys::LINK_EVENTS)
+            .await?;
+
+        info!(queue = %self.queue, "Started consuming link events");
+
+        while let Some(delivery) = subscription.next().await {
+            let span = info_span!(
+                "dispatch_link_event",
+                otel.kind = ?SpanKind::Consumer,
+                messaging.system = self.broker.system(),
+                messaging.destination = %self.queue,
+                routing_key = %delivery.routing_key,
+            );
+            span.set_parent(delivery.trace_context());
+
+            self.handle(delivery).instrument(span).await;
+        }
+
+        Ok(())
+    }
+
+    async fn handle(&self, delivery: Delivery) {
+        let event = delivery
+            .codec()
+            .and_then(|codec| LinkEvent::decode(codec, &delivery.routing_key, &delivery.payload));
+        match event {
+            Ok(Some(event)) => {
+                let payload = json!({
+                    "type": event.routing_key(),
+                    "data": event,
+                });
+
+                match self
+                    .store
+                    .enqueue(event.event_id(), event.routing_key(), &payload)
+                    .await
+                {
+                    Ok(queued) => info!(
+                        event_id = %event.event_id(),
+                        queued,
+                        "Queued webhook deliveries"
+                    ),
+                    Err(e) => {
+                        error!("Failed to queue webhook deliveries: {:?}", e);
+
+                        if let Err(e) = delivery.nack(true).await {
+                            error!("Failed to nack message: {:?}", e);
+                        }
+                        return;
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!(
+                    "Failed to deserialize link event, dead-lettering it: {:?}",
+                    e
+                );
+
+                if let Err(e) = delivery.nack(false).await {
+                    error!("Failed to nack message: {:?}", e);
+                }
+                return;
+            }
+        }
+
+        if let Err(e) = delivery.ack().await {
+            error!("Failed to ack message: {:?}", e);
+        }
+    }
+}
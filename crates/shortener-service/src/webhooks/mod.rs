@@ -0,0 +1,58 @@
+//! Outbound webhooks for link events.
+//!
+//! [`LinkEventDispatcher`] consumes link events from the exchange and queues
+//! a delivery per matching subscription; [`DeliveryWorker`] sends queued
+//! deliveries and retries failed ones with exponential backoff.
+
+mod delivery_worker;
+mod dispatcher;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+pub use delivery_worker::DeliveryWorker;
+pub use dispatcher::LinkEventDispatcher;
+
+/// Header carrying the event ID, identical across retries.
+pub const ID_HEADER: &str = "x-webhook-id";
+/// Header carrying the routing key of the event.
+pub const EVENT_HEADER: &str = "x-webhook-event";
+/// Header carrying the Unix time the request was signed at.
+pub const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+/// Header carrying `sha256=` and the hex HMAC-SHA256 of `{timestamp}.{body}`.
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// Returns the signature header value of `body` sent at `timestamp`.
+#[must_use]
+pub(crate) fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_matches_known_vector() {
+        let body = br#"{"type":"clicks.threshold_reached"}"#;
+
+        assert_eq!(
+            sign("whsec_test", 1_700_000_000, body),
+            "sha256=2703912eaadc0e88cb7989301646406f54f7a00eb96742b3afd0bcb9ed9381ea"
+        );
+    }
+
+    #[test]
+    fn signature_covers_timestamp_and_body() {
+        let signature = sign("whsec_test", 1_700_000_000, b"{}");
+
+        assert_ne!(sign("whsec_test", 1_700_000_001, b"{}"), signature);
+        assert_ne!(sign("whsec_test", 1_700_000_000, b"[]"), signature);
+        assert_ne!(sign("whsec_other", 1_700_000_000, b"{}"), signature);
+    }
+}
@@ -0,0 +1,75 @@
+//! Checks that the Postgres and `SQLite` migrations end in the same schema.
+//!
+//! Needs a Postgres database in `DATABASE_URL`, and is skipped without one.
+
+use std::collections::BTreeSet;
+
+use sqlx::{PgPool, sqlite::SqlitePoolOptions};
+
+/// Postgres columns without an `SQLite` counterpart: the `SQLite` store
+/// searches with `LIKE` instead of a text search vector.
+const POSTGRES_ONLY: &[(&str, &str)] = &[("urls", "search_vector")];
+
+/// `(table, column)` pairs of the Postgres schema.
+async fn postgres_columns(url: &str) -> BTreeSet<(String, String)> {
+    let pool = PgPool::connect(url).await.unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    sqlx::query_as::<_, (String, String)>(
+        "SELECT table_name::text, column_name::text FROM information_schema.columns
+         WHERE table_schema = current_schema() AND table_name NOT LIKE '\\_sqlx%'",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .filter(|(table, column)| !POSTGRES_ONLY.contains(&(table.as_str(), column.as_str())))
+    .collect()
+}
+
+/// `(table, column)` pairs of the `SQLite` schema.
+async fn sqlite_columns() -> BTreeSet<(String, String)> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations/sqlite")
+        .run(&pool)
+        .await
+        .unwrap();
+
+    sqlx::query_as::<_, (String, String)>(
+        "SELECT m.name, p.name FROM sqlite_schema AS m, pragma_table_info(m.name) AS p
+         WHERE m.type = 'table' AND m.name NOT LIKE '\\_sqlx%' ESCAPE '\\'",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .collect()
+}
+
+#[tokio::test]
+async fn postgres_and_sqlite_schemas_have_the_same_columns() {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        return;
+    };
+    if !url.starts_with("postgres") {
+        return;
+    }
+
+    let postgres = postgres_columns(&url).await;
+    let sqlite = sqlite_columns().await;
+
+    assert_eq!(
+        postgres.difference(&sqlite).collect::<Vec<_>>(),
+        Vec::<&(String, String)>::new(),
+        "columns missing from the SQLite migrations"
+    );
+    assert_eq!(
+        sqlite.difference(&postgres).collect::<Vec<_>>(),
+        Vec::<&(String, String)>::new(),
+        "columns missing from the Postgres migrations"
+    );
+}
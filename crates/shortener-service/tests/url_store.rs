@@ -0,0 +1,550 @@
+//! Behaviour every `UrlStore` shares, checked against the in-memory and
+//! `SQLite` stores, and against Postgres when `DATABASE_URL` points at one.
+//!
+//! Postgres may hold links of other runs, so every link of a test lives under
+//! a destination domain of its own and every listing is scoped to it.
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::{TimeDelta, Utc};
+use shortener_core::{
+    AppError,
+    config::{DatabaseConfig, SecretString},
+};
+use shortener_service::repository::{
+    self, InMemoryUrlStore, NewUrl, OgMetadata, RedirectType, UrlChanges, UrlFilter, UrlState,
+    UrlStore, UtmParams,
+};
+use uuid::Uuid;
+
+/// The stores under test, by name.
+async fn stores() -> Vec<(&'static str, Arc<dyn UrlStore>)> {
+    let mut stores: Vec<(&'static str, Arc<dyn UrlStore>)> = vec![
+        ("memory", Arc::new(InMemoryUrlStore::new())),
+        ("sqlite", connect("sqlite::memory:").await),
+    ];
+    if let Ok(url) = std::env::var("DATABASE_URL")
+        && url.starts_with("postgres")
+    {
+        stores.push(("postgres", connect(&url).await));
+    }
+    stores
+}
+
+async fn connect(url: &str) -> Arc<dyn UrlStore> {
+    let config = DatabaseConfig {
+        url: SecretString::new(url),
+        max_connections: 2,
+    };
+    repository::connect(&config).await.unwrap().urls
+}
+
+/// Returns a destination domain no other test uses.
+fn unique_domain() -> String {
+    format!("t{}.test", Uuid::new_v4().simple())
+}
+
+/// Creates a link to `https://{host}/{path}`.
+async fn create(
+    store: &dyn UrlStore,
+    host: &str,
+    path: &str,
+    title: Option<&str>,
+    tags: &[&str],
+) -> String {
+    let new_url = NewUrl {
+        original_url: format!("https://{host}/{path}"),
+        title: title.map(str::to_string),
+        tags: tags.iter().map(|tag| (*tag).to_string()).collect(),
+        ..NewUrl::default()
+    };
+    let code = store.create(&new_url).await.unwrap().code;
+    // Keeps creation times apart for the date range filter.
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    code
+}
+
+/// Returns the codes of the links matching `filter`, newest first.
+async fn list(store: &dyn UrlStore, filter: UrlFilter) -> Vec<String> {
+    store
+        .list(&filter, None, 100)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|url| url.code)
+        .collect()
+}
+
+#[tokio::test]
+async fn tag_filter_matches_links_carrying_the_tag() {
+    for (name, store) in stores().await {
+        let store = store.as_ref();
+        let domain = unique_domain();
+        let both = create(store, &domain, "a", None, &["docs", "rust"]).await;
+        let rust = create(store, &domain, "b", None, &["rust"]).await;
+        create(store, &domain, "c", None, &["rusty"]).await;
+        create(store, &domain, "d", None, &[]).await;
+
+        let filter = |tag: &str| UrlFilter {
+            domain: Some(domain.clone()),
+            tag: Some(tag.to_string()),
+            ..UrlFilter::default()
+        };
+        assert_eq!(
+            list(store, filter("rust")).await,
+            [rust.as_str(), both.as_str()],
+            "{name}"
+        );
+        assert_eq!(list(store, filter("docs")).await, [both.as_str()], "{name}");
+        assert!(list(store, filter("Rust")).await.is_empty(), "{name}");
+    }
+}
+
+#[tokio::test]
+async fn domain_filter_matches_the_domain_and_its_subdomains() {
+    for (name, store) in stores().await {
+        let store = store.as_ref();
+        let domain = unique_domain();
+        let apex = create(store, &domain, "", None, &[]).await;
+        let sub = create(store, &format!("www.{domain}"), "", None, &[]).await;
+        let upper = create(
+            store,
+            &format!("API.{}", domain.to_uppercase()),
+            "",
+            None,
+            &[],
+        )
+        .await;
+        create(store, &format!("not{domain}"), "", None, &[]).await;
+        create(store, "example.com", &domain, None, &[]).await;
+
+        let filter = |domain: &str| UrlFilter {
+            domain: Some(domain.to_string()),
+            ..UrlFilter::default()
+        };
+        assert_eq!(
+            list(store, filter(&domain)).await,
+            [upper.as_str(), sub.as_str(), apex.as_str()],
+            "{name}"
+        );
+        assert_eq!(
+            list(store, filter(&format!("www.{domain}"))).await,
+            [sub.as_str()],
+            "{name}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn created_range_includes_its_start_and_excludes_its_end() {
+    for (name, store) in stores().await {
+        let store = store.as_ref();
+        let domain = unique_domain();
+        let mut links = Vec::new();
+        for path in ["a", "b", "c", "d"] {
+            let code = create(store, &domain, path, None, &[]).await;
+            links.push(store.find_by_code(&code).await.unwrap().unwrap());
+        }
+
+        let filter = UrlFilter {
+            domain: Some(domain.clone()),
+            created_from: Some(links[1].created_at),
+            created_to: Some(links[3].created_at),
+            ..UrlFilter::default()
+        };
+        assert_eq!(
+            list(store, filter).await,
+            [links[2].code.as_str(), links[1].code.as_str()],
+            "{name}"
+        );
+
+        let open_ended = UrlFilter {
+            domain: Some(domain.clone()),
+            created_from: Some(links[2].created_at),
+            ..UrlFilter::default()
+        };
+        assert_eq!(
+            list(store, open_ended).await,
+            [links[3].code.as_str(), links[2].code.as_str()],
+            "{name}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn search_matches_every_word_across_title_description_and_destination() {
+    for (name, store) in stores().await {
+        let store = store.as_ref();
+        let domain = unique_domain();
+        let workshop = create(store, &domain, "events", Some("Kubernetes workshop"), &[]).await;
+        let described = store
+            .create(&NewUrl {
+                original_url: format!("https://{domain}/talks"),
+                description: Some("A workshop on tracing".to_string()),
+                ..NewUrl::default()
+            })
+            .await
+            .unwrap()
+            .code;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let path = create(store, &domain, "kubernetes", Some("Slides"), &[]).await;
+        create(store, &domain, "other", Some("Gardening"), &[]).await;
+
+        let filter = |q: &str| UrlFilter {
+            domain: Some(domain.clone()),
+            q: Some(q.to_string()),
+            ..UrlFilter::default()
+        };
+        assert_eq!(
+            list(store, filter("workshop")).await,
+            [described.as_str(), workshop.as_str()],
+            "{name}"
+        );
+        assert_eq!(
+            list(store, filter("kubernetes")).await,
+            [path.as_str(), workshop.as_str()],
+            "{name}"
+        );
+        assert_eq!(
+            list(store, filter("KUBERNETES workshop")).await,
+            [workshop.as_str()],
+            "{name}"
+        );
+        assert!(list(store, filter("cooking")).await.is_empty(), "{name}");
+    }
+}
+
+#[tokio::test]
+async fn filters_combine() {
+    for (name, store) in stores().await {
+        let store = store.as_ref();
+        let domain = unique_domain();
+        let matching = create(store, &domain, "a", Some("Release notes"), &["docs"]).await;
+        create(store, &domain, "b", Some("Release notes"), &["blog"]).await;
+        create(store, &domain, "c", Some("Roadmap"), &["docs"]).await;
+
+        let filter = UrlFilter {
+            domain: Some(domain.clone()),
+            tag: Some("docs".to_string()),
+            q: Some("release".to_string()),
+            ..UrlFilter::default()
+        };
+        assert_eq!(list(store, filter).await, [matching.as_str()], "{name}");
+    }
+}
+
+#[tokio::test]
+async fn created_links_are_found_with_every_field() {
+    for (name, store) in stores().await {
+        let domain = unique_domain();
+        let new_url = NewUrl {
+            original_url: format!("https://{domain}/landing?a=1"),
+            forward_query: true,
+            utm_params: UtmParams {
+                utm_source: Some("newsletter".to_string()),
+                utm_campaign: Some("{code}".to_string()),
+                ..UtmParams::default()
+            },
+            redirect_type: RedirectType::Permanent,
+            always_interstitial: true,
+            og_metadata: OgMetadata {
+                title: Some("Landing".to_string()),
+                ..OgMetadata::default()
+            },
+            title: Some("Title".to_string()),
+            description: Some("Description".to_string()),
+            tags: vec!["b".to_string(), "a".to_string()],
+            expires_at: Some(Utc::now() + TimeDelta::days(1)),
+        };
+
+        let created = store.create(&new_url).await.unwrap();
+        let found = store.find_by_code(&created.code).await.unwrap().unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&found).unwrap(),
+            serde_json::to_value(&created).unwrap(),
+            "{name}"
+        );
+        assert_eq!(found.original_url, new_url.original_url, "{name}");
+        assert_eq!(found.utm_params, new_url.utm_params, "{name}");
+        assert_eq!(found.redirect_type, RedirectType::Permanent, "{name}");
+        assert_eq!(found.tags, ["b", "a"], "{name}");
+        assert!(found.is_active && found.deleted_at.is_none(), "{name}");
+        assert!(
+            store.find_by_code("nope!").await.unwrap().is_none(),
+            "{name}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn updates_change_only_the_given_fields() {
+    for (name, store) in stores().await {
+        let domain = unique_domain();
+        let created = store
+            .create(&NewUrl {
+                original_url: format!("https://{domain}/old"),
+                title: Some("Kept".to_string()),
+                tags: vec!["kept".to_string()],
+                ..NewUrl::default()
+            })
+            .await
+            .unwrap();
+
+        let updated = store
+            .update(
+                &created.code,
+                &UrlChanges {
+                    original_url: format!("https://www.{domain}/new"),
+                    redirect_type: Some(RedirectType::Found),
+                    description: Some("Added".to_string()),
+                    ..UrlChanges::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            updated.original_url,
+            format!("https://www.{domain}/new"),
+            "{name}"
+        );
+        assert_eq!(updated.redirect_type, RedirectType::Found, "{name}");
+        assert_eq!(updated.description.as_deref(), Some("Added"), "{name}");
+        assert_eq!(updated.title.as_deref(), Some("Kept"), "{name}");
+        assert_eq!(updated.tags, ["kept"], "{name}");
+        assert!(updated.updated_at > created.updated_at, "{name}");
+
+        // The domain filter follows the new destination.
+        let filter = UrlFilter {
+            domain: Some(format!("www.{domain}")),
+            ..UrlFilter::default()
+        };
+        assert_eq!(
+            list(store.as_ref(), filter).await,
+            [created.code.as_str()],
+            "{name}"
+        );
+
+        let missing = store.update("nope!", &UrlChanges::default()).await;
+        assert!(matches!(missing, Err(AppError::NotFound(_))), "{name}");
+    }
+}
+
+#[tokio::test]
+async fn list_pages_by_creation_key() {
+    for (name, store) in stores().await {
+        let store = store.as_ref();
+        let domain = unique_domain();
+        let mut codes = Vec::new();
+        for path in ["a", "b", "c", "d", "e"] {
+            codes.push(create(store, &domain, path, None, &[]).await);
+        }
+        codes.reverse();
+
+        let filter = UrlFilter {
+            domain: Some(domain.clone()),
+            ..UrlFilter::default()
+        };
+        let mut listed = Vec::new();
+        let mut after = None;
+        loop {
+            let page = store.list(&filter, after, 2).await.unwrap();
+            let Some(last) = page.last() else {
+                break;
+            };
+            after = Some((last.created_at, last.id));
+            listed.extend(page.into_iter().map(|url| url.code));
+        }
+
+        assert_eq!(listed, codes, "{name}");
+    }
+}
+
+#[tokio::test]
+async fn deleted_links_move_to_the_trash_until_restored_or_purged() {
+    for (name, store) in stores().await {
+        let store = store.as_ref();
+        let domain = unique_domain();
+        let kept = create(store, &domain, "kept", None, &[]).await;
+        let code = create(store, &domain, "trash", None, &[]).await;
+
+        store.delete(&code).await.unwrap();
+        assert!(store.find_by_code(&code).await.unwrap().is_none(), "{name}");
+        let deleted = store.find_deleted(&code).await.unwrap().unwrap();
+        assert!(!deleted.is_active && deleted.deleted_at.is_some(), "{name}");
+        let trash = UrlFilter {
+            state: UrlState::Deleted,
+            domain: Some(domain.clone()),
+            ..UrlFilter::default()
+        };
+        assert_eq!(list(store, trash.clone()).await, [code.as_str()], "{name}");
+        assert!(
+            matches!(store.delete(&code).await, Err(AppError::NotFound(_))),
+            "{name}"
+        );
+
+        let restored = store.restore(&code).await.unwrap();
+        assert!(
+            restored.is_active && restored.deleted_at.is_none(),
+            "{name}"
+        );
+        assert!(store.find_deleted(&code).await.unwrap().is_none(), "{name}");
+        assert!(
+            matches!(store.restore(&code).await, Err(AppError::NotFound(_))),
+            "{name}"
+        );
+
+        store.delete(&code).await.unwrap();
+        let deleted_at = store
+            .find_deleted(&code)
+            .await
+            .unwrap()
+            .unwrap()
+            .deleted_at
+            .unwrap();
+        assert!(
+            !store
+                .purge_deleted(deleted_at, true)
+                .await
+                .unwrap()
+                .contains(&code),
+            "{name}"
+        );
+        let purged = store
+            .purge_deleted(deleted_at + TimeDelta::milliseconds(1), true)
+            .await
+            .unwrap();
+        assert!(purged.contains(&code), "{name}");
+        assert!(!purged.contains(&kept), "{name}");
+        assert!(store.find_deleted(&code).await.unwrap().is_none(), "{name}");
+        assert!(list(store, trash).await.is_empty(), "{name}");
+        assert!(store.find_by_code(&kept).await.unwrap().is_some(), "{name}");
+    }
+}
+
+#[tokio::test]
+async fn disabled_links_are_listed_by_reason_until_enabled() {
+    for (name, store) in stores().await {
+        let store = store.as_ref();
+        let domain = unique_domain();
+        let code = create(store, &domain, "", None, &[]).await;
+        let reason = format!("Test {domain}");
+        let codes = [code.clone()];
+        // Starts the scan just before `code`.
+        let before = &code[..code.len() - 1];
+
+        let enabled = store.list_enabled_destinations(before, 1).await.unwrap();
+        assert_eq!(enabled[0].0, code, "{name}");
+
+        assert_eq!(store.disable(&codes, &reason).await.unwrap(), 1, "{name}");
+        assert_eq!(store.disable(&codes, &reason).await.unwrap(), 0, "{name}");
+        let disabled = store.find_by_code(&code).await.unwrap().unwrap();
+        assert!(disabled.disabled_at.is_some(), "{name}");
+        assert_eq!(
+            disabled.disabled_reason.as_deref(),
+            Some(reason.as_str()),
+            "{name}"
+        );
+        let enabled = store.list_enabled_destinations(before, 1).await.unwrap();
+        assert!(enabled.first().is_none_or(|(c, _)| *c != code), "{name}");
+        assert_eq!(
+            store
+                .list_disabled_destinations(&reason, "", 10)
+                .await
+                .unwrap(),
+            [(code.clone(), format!("https://{domain}/"))],
+            "{name}"
+        );
+
+        assert_eq!(store.enable(&codes, "Other").await.unwrap(), 0, "{name}");
+        assert_eq!(store.enable(&codes, &reason).await.unwrap(), 1, "{name}");
+        let enabled = store.find_by_code(&code).await.unwrap().unwrap();
+        assert!(enabled.disabled_at.is_none(), "{name}");
+        assert!(enabled.disabled_reason.is_none(), "{name}");
+        assert!(
+            store
+                .list_disabled_destinations(&reason, "", 10)
+                .await
+                .unwrap()
+                .is_empty(),
+            "{name}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn expired_links_are_announced_once_per_expiry() {
+    for (name, store) in stores().await {
+        let domain = unique_domain();
+        let created = store
+            .create(&NewUrl {
+                original_url: format!("https://{domain}/"),
+                expires_at: Some(Utc::now() - TimeDelta::seconds(1)),
+                ..NewUrl::default()
+            })
+            .await
+            .unwrap();
+        let code = created.code.clone();
+        let expires_at = created.expires_at.unwrap();
+
+        let marked = store.mark_expired(1000).await.unwrap();
+        assert!(marked.contains(&(code.clone(), expires_at)), "{name}");
+        let marked = store.mark_expired(1000).await.unwrap();
+        assert!(marked.iter().all(|(c, _)| *c != code), "{name}");
+
+        let changes = UrlChanges {
+            original_url: created.original_url.clone(),
+            expires_at: Some(expires_at + TimeDelta::milliseconds(1)),
+            ..UrlChanges::default()
+        };
+        let updated = store.update(&code, &changes).await.unwrap();
+        let marked = store.mark_expired(1000).await.unwrap();
+        assert!(
+            marked.contains(&(code.clone(), updated.expires_at.unwrap())),
+            "{name}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn fetched_metadata_fills_only_missing_fields() {
+    for (name, store) in stores().await {
+        let domain = unique_domain();
+        let created = store
+            .create(&NewUrl {
+                original_url: format!("https://{domain}/"),
+                og_metadata: OgMetadata {
+                    title: Some("Mine".to_string()),
+                    ..OgMetadata::default()
+                },
+                ..NewUrl::default()
+            })
+            .await
+            .unwrap();
+
+        let fetched = OgMetadata {
+            title: Some("Fetched".to_string()),
+            description: Some("Fetched description".to_string()),
+            image_url: None,
+        };
+        store
+            .merge_og_metadata(&created.code, &fetched)
+            .await
+            .unwrap();
+
+        let og_metadata = store
+            .find_by_code(&created.code)
+            .await
+            .unwrap()
+            .unwrap()
+            .og_metadata;
+        assert_eq!(og_metadata.title.as_deref(), Some("Mine"), "{name}");
+        assert_eq!(
+            og_metadata.description.as_deref(),
+            Some("Fetched description"),
+            "{name}"
+        );
+        assert!(og_metadata.image_url.is_none(), "{name}");
+    }
+}
@@ -0,0 +1,191 @@
+//! Request and response bodies of the analytics-service HTTP API.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Page;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct Analytics {
+    pub code: String,
+    pub access_count: i64,
+    pub last_accessed_at: Option<DateTime<Utc>>,
+}
+
+/// Period a click ranking covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum RankWindow {
+    /// The current and previous 23 UTC hours.
+    #[serde(rename = "24h")]
+    Day,
+    /// The current and previous 167 UTC hours.
+    #[serde(rename = "7d")]
+    Week,
+    /// Since the first click.
+    #[serde(rename = "all")]
+    All,
+}
+
+/// Position of a code in a click ranking.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RankedCode {
+    pub rank: usize,
+    pub code: String,
+    pub access_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ListAnalyticsQuery {
+    /// Page size, at most 100.
+    #[serde(default = "default_limit")]
+    #[cfg_attr(feature = "openapi", param(default = 20))]
+    pub limit: usize,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+fn default_limit() -> usize {
+    20
+}
+
+impl Default for ListAnalyticsQuery {
+    fn default() -> Self {
+        Self {
+            limit: default_limit(),
+            cursor: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct TopAnalyticsQuery {
+    #[serde(default = "default_window")]
+    #[cfg_attr(feature = "openapi", param(default = "all"))]
+    pub window: RankWindow,
+    /// Number of codes, at most 100.
+    #[serde(default = "default_top_limit")]
+    #[cfg_attr(feature = "openapi", param(default = 10))]
+    pub limit: usize,
+}
+
+fn default_window() -> RankWindow {
+    RankWindow::All
+}
+
+fn default_top_limit() -> usize {
+    10
+}
+
+impl Default for TopAnalyticsQuery {
+    fn default() -> Self {
+        Self {
+            window: default_window(),
+            limit: default_top_limit(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TopAnalyticsResponse {
+    pub window: RankWindow,
+    pub items: Vec<RankedCode>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AnalyticsListResponse {
+    #[serde(flatten)]
+    pub page: Page<Analytics>,
+    /// Number of codes with analytics, across all pages.
+    pub total: usize,
+}
+
+/// Output format of an event export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+impl ExportFormat {
+    #[must_use]
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Ndjson => "application/x-ndjson",
+            Self::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ndjson => "ndjson",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ExportQuery {
+    pub format: ExportFormat,
+    /// Start of the exported range, inclusive. Defaults to the first event.
+    pub from: Option<DateTime<Utc>>,
+    /// End of the exported range, exclusive. Defaults to the time of the request.
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// A processed click as seen by subscribers. Visitor details are left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Click {
+    pub event_id: Uuid,
+    pub code: String,
+    pub accessed_at: DateTime<Utc>,
+    /// All-time count of the code including this click.
+    pub access_count: i64,
+}
+
+/// Message delivered to a subscriber.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamMessage {
+    Click(Click),
+    /// The subscriber fell behind and `skipped` clicks, counted across all
+    /// codes, were dropped.
+    Lagged {
+        skipped: u64,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HealthResponse {
+    pub status: String,
+    pub service_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ReadyResponse {
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redis: Option<String>,
+}
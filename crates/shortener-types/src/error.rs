@@ -0,0 +1,155 @@
+//! Errors of the services, as handled internally and as sent to clients.
+
+#[cfg(feature = "axum")]
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("Resource not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid input: {0}")]
+    BadRequest(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Gone: {0}")]
+    Gone(String),
+
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    #[error("Database error: {0}")]
+    Database(String),
+
+    #[error("Redis error: {0}")]
+    Redis(String),
+
+    #[error("Message queue error: {0}")]
+    MessageQueue(String),
+
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    #[error("URL parse error: {0}")]
+    UrlParse(String),
+
+    #[error("Internal server error: {0}")]
+    Internal(String),
+}
+
+/// Body of every error response.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorBody {
+    /// Machine-readable error kind, such as `NOT_FOUND`.
+    #[cfg_attr(feature = "openapi", schema(example = "NOT_FOUND"))]
+    pub code: String,
+    pub message: String,
+}
+
+#[cfg(feature = "axum")]
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, code, message) = match &self {
+            Self::NotFound(msg) => (StatusCode::NOT_FOUND, "NOT_FOUND", msg.clone()),
+            Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, "BAD_REQUEST", msg.clone()),
+            Self::Conflict(msg) => (StatusCode::CONFLICT, "CONFLICT", msg.clone()),
+            Self::Gone(msg) => (StatusCode::GONE, "GONE", msg.clone()),
+            Self::ServiceUnavailable(msg) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "SERVICE_UNAVAILABLE",
+                msg.clone(),
+            ),
+            Self::Database(e) => {
+                tracing::error!("Database error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "DATABASE_ERROR",
+                    "Database error occurred".to_string(),
+                )
+            }
+            Self::Redis(e) => {
+                tracing::error!("Redis error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "REDIS_ERROR",
+                    "Redis error occurred".to_string(),
+                )
+            }
+            Self::MessageQueue(e) => {
+                tracing::error!("Message queue error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "MQ_ERROR",
+                    "Message queue error occurred".to_string(),
+                )
+            }
+            Self::Serialization(e) => {
+                tracing::error!("Serialization error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "SERIALIZATION_ERROR",
+                    "Serialization error".to_string(),
+                )
+            }
+            Self::UrlParse(e) => (StatusCode::BAD_REQUEST, "INVALID_URL", e.clone()),
+            Self::Internal(e) => {
+                tracing::error!("Internal error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "INTERNAL_ERROR",
+                    "Internal server error".to_string(),
+                )
+            }
+        };
+
+        let body = Json(ErrorResponse {
+            error: ErrorBody {
+                code: code.to_string(),
+                message,
+            },
+        });
+
+        (status, body).into_response()
+    }
+}
+
+/// Rebuilds the error a service reported, for clients of the HTTP APIs.
+///
+/// Server-side failures only carry the generic message of the response.
+/// Unknown codes become `AppError::Internal`.
+impl From<ErrorBody> for AppError {
+    fn from(body: ErrorBody) -> Self {
+        let ErrorBody { code, message } = body;
+        match code.as_str() {
+            "NOT_FOUND" => Self::NotFound(message),
+            "BAD_REQUEST" => Self::BadRequest(message),
+            "CONFLICT" => Self::Conflict(message),
+            "GONE" => Self::Gone(message),
+            "SERVICE_UNAVAILABLE" => Self::ServiceUnavailable(message),
+            "DATABASE_ERROR" => Self::Database(message),
+            "REDIS_ERROR" => Self::Redis(message),
+            "MQ_ERROR" => Self::MessageQueue(message),
+            "SERIALIZATION_ERROR" => Self::Serialization(message),
+            "INVALID_URL" => Self::UrlParse(message),
+            "INTERNAL_ERROR" => Self::Internal(message),
+            _ => Self::Internal(format!("{code}: {message}")),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, AppError>;
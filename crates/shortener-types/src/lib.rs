@@ -0,0 +1,15 @@
+//! Request and response bodies of the HTTP APIs and their error type.
+//!
+//! Shared by the services and their clients, so clients do not build the
+//! services. The `openapi`, `sqlx` and `axum` features add what the services
+//! need on top.
+
+pub mod analytics;
+pub mod error;
+pub mod pagination;
+pub mod shortener;
+#[cfg(feature = "sqlx")]
+mod sqlx_json;
+
+pub use error::{AppError, Result};
+pub use pagination::Page;
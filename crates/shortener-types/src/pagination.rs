@@ -0,0 +1,40 @@
+//! Pages of list endpoints and their opaque cursors.
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize};
+
+/// A page of results in a stable order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor of the following page, `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from up to `limit + 1` items fetched after the previous
+    /// cursor. The extra item only signals that another page follows; the
+    /// next cursor is the sort key returned by `key` for the last kept item.
+    pub fn from_overfetch<K: Serialize>(
+        mut items: Vec<T>,
+        limit: usize,
+        key: impl FnOnce(&T) -> K,
+    ) -> Self {
+        let next_cursor = if items.len() > limit {
+            items.truncate(limit);
+            items.last().map(|item| encode_cursor(&key(item)))
+        } else {
+            None
+        };
+
+        Self { items, next_cursor }
+    }
+}
+
+/// Encodes a sort key as an opaque cursor.
+pub fn encode_cursor<K: Serialize>(key: &K) -> String {
+    // Serializing plain keys to JSON cannot fail.
+    let json = serde_json::to_vec(key).unwrap_or_default();
+    URL_SAFE_NO_PAD.encode(json)
+}
@@ -0,0 +1,336 @@
+//! Request and response bodies of the shortener-service HTTP API.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[allow(clippy::struct_field_names)]
+pub struct Url {
+    pub id: Uuid,
+    pub code: String,
+    pub original_url: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub is_active: bool,
+    pub forward_query: bool,
+    pub utm_params: UtmParams,
+    pub redirect_type: RedirectType,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub disabled_reason: Option<String>,
+    pub always_interstitial: bool,
+    pub og_metadata: OgMetadata,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    /// When the link was deleted; set while it is in the trash.
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Whether a link is in use or in the trash.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum UrlState {
+    /// Not deleted.
+    #[default]
+    Active,
+    /// Deleted but not purged yet, so it can be restored.
+    Deleted,
+}
+
+/// HTTP status used when redirecting to the destination, stored as the status code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::Type))]
+#[serde(rename_all = "snake_case")]
+#[repr(i16)]
+pub enum RedirectType {
+    /// 301 Moved Permanently.
+    MovedPermanently = 301,
+    /// 302 Found.
+    Found = 302,
+    /// 307 Temporary Redirect.
+    #[default]
+    Temporary = 307,
+    /// 308 Permanent Redirect.
+    Permanent = 308,
+}
+
+impl RedirectType {
+    /// Returns `true` if clients and caches may reuse the redirect.
+    #[must_use]
+    pub fn is_permanent(self) -> bool {
+        matches!(self, Self::MovedPermanently | Self::Permanent)
+    }
+}
+
+/// UTM tags merged into the destination URL on redirect.
+///
+/// Values may contain the `{code}` placeholder, which is replaced with the
+/// short code of the link being followed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(deny_unknown_fields)]
+#[allow(clippy::struct_field_names)]
+pub struct UtmParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utm_source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utm_medium: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utm_campaign: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utm_term: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utm_content: Option<String>,
+}
+
+impl UtmParams {
+    /// Returns the configured tags as `(name, value)` pairs in a fixed order.
+    pub fn pairs(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            ("utm_source", &self.utm_source),
+            ("utm_medium", &self.utm_medium),
+            ("utm_campaign", &self.utm_campaign),
+            ("utm_term", &self.utm_term),
+            ("utm_content", &self.utm_content),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.as_deref().map(|v| (name, v)))
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pairs().next().is_none()
+    }
+}
+
+/// Open Graph fields served to link unfurlers instead of a redirect.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(deny_unknown_fields)]
+pub struct OgMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_url: Option<String>,
+}
+
+impl OgMetadata {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.image_url.is_none()
+    }
+}
+
+#[cfg(feature = "sqlx")]
+crate::sqlx_json::json_column!(UtmParams, OgMetadata);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateUrlRequest {
+    pub url: String,
+    #[serde(default)]
+    pub forward_query: bool,
+    #[serde(default)]
+    pub utm_params: UtmParams,
+    #[serde(default)]
+    pub redirect_type: RedirectType,
+    #[serde(default)]
+    pub always_interstitial: bool,
+    /// Social preview fields; fetched from the destination when omitted.
+    #[serde(default)]
+    pub og_metadata: OgMetadata,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateUrlResponse {
+    pub code: String,
+    /// Path of the short URL on this service, as in `/{code}`.
+    pub short_url: String,
+    pub original_url: String,
+}
+
+/// New destination and settings of a link; omitted settings are kept.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UpdateUrlRequest {
+    pub url: String,
+    pub forward_query: Option<bool>,
+    pub utm_params: Option<UtmParams>,
+    pub redirect_type: Option<RedirectType>,
+    pub always_interstitial: Option<bool>,
+    /// Social preview fields. When the destination changes, the fields not
+    /// given are fetched from the new destination.
+    pub og_metadata: Option<OgMetadata>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ListUrlsQuery {
+    /// Page size, at most 100.
+    #[serde(default = "default_limit")]
+    #[cfg_attr(feature = "openapi", param(default = 20))]
+    pub limit: usize,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    /// `deleted` lists the links in the trash instead of the active ones.
+    #[serde(default)]
+    pub state: UrlState,
+    /// Only links with this tag.
+    pub tag: Option<String>,
+    /// Only links to this host or its subdomains.
+    pub domain: Option<String>,
+    /// Only links created at or after this time.
+    pub created_from: Option<DateTime<Utc>>,
+    /// Only links created before this time.
+    pub created_to: Option<DateTime<Utc>>,
+    /// Words that must all appear in the title, description or destination.
+    pub q: Option<String>,
+}
+
+fn default_limit() -> usize {
+    20
+}
+
+impl Default for ListUrlsQuery {
+    fn default() -> Self {
+        Self {
+            limit: default_limit(),
+            cursor: None,
+            state: UrlState::default(),
+            tag: None,
+            domain: None,
+            created_from: None,
+            created_to: None,
+            q: None,
+        }
+    }
+}
+
+/// A destination domain rejected by the service, including its subdomains.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct BlockedDomain {
+    pub domain: String,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BlockDomainRequest {
+    /// Blocked together with its subdomains.
+    pub domain: String,
+    pub reason: Option<String>,
+}
+
+/// An endpoint that receives link events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct WebhookSubscription {
+    pub id: Uuid,
+    pub url: String,
+    /// Key of the payload signatures; only returned when the subscription is created.
+    #[serde(skip_serializing, default)]
+    pub secret: String,
+    /// Routing keys of the delivered events; empty means every event.
+    pub event_types: Vec<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One event queued for, or sent to, one subscription.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub subscription_id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    /// `pending`, `succeeded` or `failed`.
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_status_code: Option<i16>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    /// Signing key; generated when omitted.
+    pub secret: Option<String>,
+    /// Routing keys to deliver; every event when empty.
+    #[serde(default)]
+    pub event_types: Vec<String>,
+}
+
+/// A new subscription, including the secret that is not returned again.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateWebhookResponse {
+    #[serde(flatten)]
+    pub subscription: WebhookSubscription,
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ListDeliveriesQuery {
+    /// Page size, at most 100.
+    #[serde(default = "default_limit")]
+    #[cfg_attr(feature = "openapi", param(default = 20))]
+    pub limit: usize,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+impl Default for ListDeliveriesQuery {
+    fn default() -> Self {
+        Self {
+            limit: default_limit(),
+            cursor: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HealthResponse {
+    pub status: String,
+    pub service_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ReadyResponse {
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+}
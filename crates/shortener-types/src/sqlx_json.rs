@@ -0,0 +1,37 @@
+//! Columns holding a type as JSON.
+
+/// Decodes each of the given types from a JSON column, as
+/// [`sqlx::types::Json`] of the type does.
+///
+/// Binding goes through `Json(&value)` as usual; only decoding is needed to
+/// read the types as plain fields of a row.
+macro_rules! json_column {
+    ($($ty:ty),+ $(,)?) => {$(
+        impl<DB: sqlx::Database> sqlx::Type<DB> for $ty
+        where
+            sqlx::types::Json<$ty>: sqlx::Type<DB>,
+        {
+            fn type_info() -> DB::TypeInfo {
+                <sqlx::types::Json<$ty> as sqlx::Type<DB>>::type_info()
+            }
+
+            fn compatible(ty: &DB::TypeInfo) -> bool {
+                <sqlx::types::Json<$ty> as sqlx::Type<DB>>::compatible(ty)
+            }
+        }
+
+        impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for $ty
+        where
+            sqlx::types::Json<$ty>: sqlx::Decode<'r, DB>,
+        {
+            fn decode(
+                value: <DB as sqlx::Database>::ValueRef<'r>,
+            ) -> Result<Self, sqlx::error::BoxDynError> {
+                <sqlx::types::Json<$ty> as sqlx::Decode<'r, DB>>::decode(value)
+                    .map(|json| json.0)
+            }
+        }
+    )+};
+}
+
+pub(crate) use json_column;
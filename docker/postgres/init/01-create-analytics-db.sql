@@ -0,0 +1,2 @@
+-- Database for analytics-service's durable access event storage.
+CREATE DATABASE analytics OWNER urlshortener;